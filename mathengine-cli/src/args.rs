@@ -0,0 +1,332 @@
+use std::fmt;
+
+use mathengine::{Locale, Radix};
+
+pub const USAGE: &str = "\
+Usage: mathengine [OPTIONS] <EXPRESSION>...
+       mathengine repl
+       mathengine -
+       mathengine run <FILE>
+       mathengine watch <FILE>
+       mathengine units [DIMENSION]
+       mathengine convert <VALUE> <FROM> [to] <TO>
+       mathengine table <VALUE>...
+       mathengine rates [update]
+
+Evaluate a math expression, with units and conversions.
+The words of the expression are joined with spaces, so quoting is optional:
+
+  mathengine \"2 + 3 * 4\"
+  mathengine 10 ft to m
+
+Separate expressions with `;` to evaluate them in turn, printing each result.
+Later ones see the variables and functions earlier ones define:
+
+  mathengine \"x = 5m; x * 2; x to cm\"
+
+Commands:
+  repl             Evaluate lines as they're typed, keeping variables between
+                   them. The default when run in a terminal without arguments
+  -                Evaluate each line of stdin, printing a result per line.
+                   The default when input is piped in without arguments
+  run <FILE>       Run a file of expressions and definitions, like `x = 2`,
+                   printing each result, or only `print(...)` when it's used.
+                   `#` starts a comment
+  watch <FILE>     Run a file, and again whenever it's saved, for a live
+                   calculation sheet. Ctrl-C stops watching
+  units [DIMENSION]
+                   List the units of each dimension, or just one like
+                   `length`, with the other names they can be written as
+  convert <VALUE> <FROM> [to] <TO>
+                   Convert a value between units, like `convert 10 m ft`
+  table <VALUE>... Show each value in every unit of its dimension, like
+                   `table 1mi 100m` for a line each of `1mi = 1.609344km = ...`
+  rates [update]   Show the cached exchange rates currency conversions use,
+                   or fetch current ones from MATHENGINE_RATES_URL
+                   (open.er-api.com by default) with curl. Rates a day old
+                   are still used, with a warning
+
+Options:
+  --precision <N>  Round results to N decimal places
+  --sig-figs <N>   Round results to N significant figures, or `auto` for as
+                   many as the numbers they're worked out from have, like
+                   7.5 for `2.50 * 3.0`
+  --money          Print currency results as money, like `$1,234.56` or
+                   `1.234,56 €` with --locale de
+  --base <BASE>    Print integer results in hex, bin, oct or dec, whatever
+                   base they were written in
+  --locale <TAG>   Read and print numbers with a locale's separators, and
+                   accept its unit names, like `--locale de` for `1,5 Fuß`.
+                   With a decimal comma, `;` separates arguments and items
+  --strict         Fail on mismatched or missing units, like `10m + 2` or
+                   `5m + 3C`, rather than assuming what was meant
+  --no-strict      Assume what was meant, even with `strict = true` in the
+                   config file
+  --json           Print each result as a JSON object with its input, value,
+                   unit, dimension and any error
+  --time           Show how long lexing, parsing and evaluating each input
+                   took, on stderr
+  --explain        Show the steps each input is evaluated in, like
+                   `5 * 4 → 20` or `1m + 50cm → 1m + 0.5m → 1.5m`, before
+                   its result
+  --tokens         Show the tokens each input lexes into before its result
+  --ast            Show the syntax tree each input parses into before its
+                   result, as a JSON document with --json
+  --raw            Print only the number of each result, without its unit or
+                   colors, for capturing in shell variables
+  --quiet          Leave out the REPL's banner and prompt, printing only
+                   results
+  --no-color       Print without colors, as when NO_COLOR is set
+  -h, --help       Print this help
+  -V, --version    Print the version
+  --               Treat everything after as the expression, like `-- -5 + 2`
+
+Files:
+  ~/.config/mathengine/config.toml
+                   Defaults for options the command line leaves out, like
+                   `precision = 4`, `locale = \"de-DE\"`, `strict = true` and
+                   `units = \"units.txt\"` for a file of `klick = km` lines
+  ~/.cache/mathengine/rates.json
+                   Exchange rates saved by `rates update`
+  ~/.local/state/mathengine/history
+                   Lines typed into the REPL, recalled in later sessions
+
+Exit status:
+  0  success
+  1  input, a file or the config file couldn't be read
+  2  the command line didn't make sense
+  3  the expression didn't lex, like `2 $ 3`
+  4  the expression didn't parse, like `2 + * 3`
+  5  the expression didn't evaluate, like `1 / 0`
+  6  the expression's units didn't check";
+
+// The most digits FormatOptions rounds to
+const MAX_DIGITS: usize = 1100;
+
+/// What the command line asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Version,
+    Repl,
+    Stdin,
+    Run { path: String },
+    Watch { path: String },
+    Units { dimension: Option<String> },
+    Rates { update: bool },
+    Convert { amount: String, from: String, to: String },
+    Table { values: Vec<String> },
+    Evaluate { expression: String },
+}
+
+/// How results are shown, whatever the command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    pub json: bool,
+    /// Decimal places to round results to
+    pub precision: Option<usize>,
+    /// Significant figures to round results to
+    pub sig_figs: Option<usize>,
+    /// Round results to the significant figures their numbers justify
+    pub track_figures: bool,
+    /// The locale tag expressions are read and displayed with
+    pub locale: Option<String>,
+    /// Check units before evaluating, failing on mismatches. `None` leaves
+    /// it to the config file
+    pub strict: Option<bool>,
+    /// Show currency results as money
+    pub money: bool,
+    /// The base integer results are printed in, or the one they came in
+    pub base: Option<Base>,
+    /// Show how long each input took to evaluate
+    pub time: bool,
+    /// Show the steps each input is evaluated in
+    pub explain: bool,
+    /// Show the tokens of each input
+    pub tokens: bool,
+    /// Show the syntax tree of each input
+    pub ast: bool,
+    /// Never color output, even on a terminal
+    pub no_color: bool,
+    /// Print results as bare numbers
+    pub raw: bool,
+    /// Don't show the REPL's banner and prompt
+    pub quiet: bool,
+}
+
+/// A base to print integers in, from `--base`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Base {
+    Decimal,
+    Radix(Radix),
+}
+
+/// A parsed command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub command: Command,
+    pub options: Options,
+}
+
+/// A command line that doesn't make sense, shown above the usage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Read the arguments after the program name. `terminal` says whether
+/// stdin is a terminal, where no arguments at all starts the REPL rather
+/// than reading stdin.
+pub fn parse<I: IntoIterator<Item = String>>(args: I, terminal: bool) -> Result<Args, UsageError> {
+    let mut options = Options::default();
+    let mut words = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !is_option(&arg) {
+            words.push(arg);
+            continue;
+        }
+        // Options taking a value accept `--option value` and `--option=value`
+        let (arg, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg, None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next());
+        match arg.as_str() {
+            "--precision" => options.precision = Some(digits(&arg, value())?),
+            "--sig-figs" => match value() {
+                Some(value) if value == "auto" => options.track_figures = true,
+                value => options.sig_figs = Some(digits(&arg, value)?),
+            },
+            "--base" => options.base = Some(base(value())?),
+            "--locale" => options.locale = Some(locale(value())?),
+            "-h" | "--help" => return Ok(Args { command: Command::Help, options }),
+            "-V" | "--version" => return Ok(Args { command: Command::Version, options }),
+            "--strict" => options.strict = Some(true),
+            "--no-strict" => options.strict = Some(false),
+            "--money" => options.money = true,
+            "--json" => options.json = true,
+            "--time" => options.time = true,
+            "--explain" => options.explain = true,
+            "--tokens" => options.tokens = true,
+            "--ast" => options.ast = true,
+            "--no-color" => options.no_color = true,
+            "--raw" => options.raw = true,
+            "--quiet" => options.quiet = true,
+            "--" => words.extend(args.by_ref()),
+            _ => return Err(UsageError(format!("unknown option '{}'", arg))),
+        }
+    }
+
+    if options.raw && options.json {
+        return Err(UsageError("--raw and --json can't be used together".to_string()));
+    }
+
+    let command = match words.first().map(String::as_str) {
+        None if terminal => Command::Repl,
+        None => Command::Stdin,
+        Some("repl") => subcommand(Command::Repl, &words, 0)?,
+        Some("-") => subcommand(Command::Stdin, &words, 0)?,
+        Some("run") => {
+            let path = argument(&words, 1, "a file to run")?;
+            subcommand(Command::Run { path }, &words, 1)?
+        }
+        Some("watch") => {
+            let path = argument(&words, 1, "a file to watch")?;
+            subcommand(Command::Watch { path }, &words, 1)?
+        }
+        Some("units") => {
+            let dimension = words.get(1).cloned();
+            subcommand(Command::Units { dimension }, &words, 1)?
+        }
+        Some("rates") => match words.get(1).map(String::as_str) {
+            None => Command::Rates { update: false },
+            Some("update") => subcommand(Command::Rates { update: true }, &words, 1)?,
+            Some(other) => return Err(UsageError(format!("unknown rates command '{}', expected update", other))),
+        },
+        Some("convert") => {
+            // `to` between the units is optional
+            let mut words = words.clone();
+            if words.len() == 5 && words[3].eq_ignore_ascii_case("to") {
+                words.remove(3);
+            }
+            let command = Command::Convert {
+                amount: argument(&words, 1, "a value to convert")?,
+                from: argument(&words, 2, "a unit to convert from")?,
+                to: argument(&words, 3, "a unit to convert to")?,
+            };
+            subcommand(command, &words, 3)?
+        }
+        Some("table") => {
+            argument(&words, 1, "a value to convert, like 1mi")?;
+            Command::Table { values: words[1..].to_vec() }
+        }
+        _ => Command::Evaluate {
+            expression: words.join(" "),
+        },
+    };
+    Ok(Args { command, options })
+}
+
+// Rounding digits, as many as results can be rounded to
+pub fn digits(option: &str, value: Option<String>) -> Result<usize, UsageError> {
+    let Some(value) = value else {
+        return Err(UsageError(format!("{} needs a number of digits", option)));
+    };
+    match value.parse::<usize>() {
+        Ok(digits) if digits <= MAX_DIGITS => Ok(digits),
+        _ => Err(UsageError(format!(
+            "{} takes a whole number up to {}, not '{}'",
+            option, MAX_DIGITS, value
+        ))),
+    }
+}
+
+fn locale(value: Option<String>) -> Result<String, UsageError> {
+    match value {
+        Some(tag) if Locale::from_tag(&tag).is_some() => Ok(tag),
+        Some(tag) => Err(UsageError(format!("unknown locale '{}'", tag))),
+        None => Err(UsageError("--locale needs a tag, like de-DE".to_string())),
+    }
+}
+
+fn base(value: Option<String>) -> Result<Base, UsageError> {
+    match value.as_deref().map(str::to_ascii_lowercase).as_deref() {
+        Some("hex") => Ok(Base::Radix(Radix::Hexadecimal)),
+        Some("bin") => Ok(Base::Radix(Radix::Binary)),
+        Some("oct") => Ok(Base::Radix(Radix::Octal)),
+        Some("dec") => Ok(Base::Decimal),
+        Some(_) => Err(UsageError(format!(
+            "--base takes hex, bin, oct or dec, not '{}'",
+            value.unwrap_or_default()
+        ))),
+        None => Err(UsageError("--base needs hex, bin, oct or dec".to_string())),
+    }
+}
+
+// A subcommand, if it was given no more than `arguments` arguments
+fn subcommand(command: Command, words: &[String], arguments: usize) -> Result<Command, UsageError> {
+    match words.get(arguments + 1) {
+        Some(extra) => Err(UsageError(format!("unexpected argument '{}' for {}", extra, words[0]))),
+        None => Ok(command),
+    }
+}
+
+// The subcommand argument at `index`, which is required
+fn argument(words: &[String], index: usize, what: &str) -> Result<String, UsageError> {
+    match words.get(index) {
+        Some(word) => Ok(word.clone()),
+        None => Err(UsageError(format!("{} needs {}", words[0], what))),
+    }
+}
+
+// Options start with a letter or a second dash, so expressions like `-5 + 2`
+// and `-(3)` are read as expressions
+fn is_option(arg: &str) -> bool {
+    let mut chars = arg.chars();
+    chars.next() == Some('-') && matches!(chars.next(), Some(c) if c == '-' || c.is_alphabetic())
+}
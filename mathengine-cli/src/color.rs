@@ -0,0 +1,48 @@
+use std::io::IsTerminal;
+
+/// The styles output is painted in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    /// The number in a result
+    Number,
+    /// The unit after it
+    Unit,
+    /// The `Error:` label
+    Error,
+    /// The carets under the part of the input an error is about
+    Caret,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Number => "1",
+            Style::Unit => "36",
+            Style::Error => "1;31",
+            Style::Caret => "31",
+        }
+    }
+}
+
+/// Whether to color a stream: only terminals are, unless `NO_COLOR` is set
+/// (see <https://no-color.org>) or color was turned off.
+pub fn enabled(no_color: bool, terminal: bool) -> bool {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    terminal && !no_color && !no_color_env
+}
+
+/// Whether to color stdout and stderr, in that order.
+pub fn streams(no_color: bool) -> (bool, bool) {
+    (
+        enabled(no_color, std::io::stdout().is_terminal()),
+        enabled(no_color, std::io::stderr().is_terminal()),
+    )
+}
+
+/// `text` in `style`, or as it is when `color` is off.
+pub fn paint(text: &str, style: Style, color: bool) -> String {
+    if !color || text.is_empty() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{}\x1b[0m", style.code(), text)
+}
@@ -0,0 +1,213 @@
+//! Settings read from `~/.config/mathengine/config.toml`, which fill in for
+//! options the command line leaves out:
+//!
+//! ```toml
+//! precision = 4
+//! locale = "de-DE"
+//! strict = true
+//! units = "~/.config/mathengine/units.txt"
+//! ```
+//!
+//! The units file has a line like `klick = km` for each extra unit name.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mathengine::{Locale, UnitRegistry};
+
+use crate::args;
+
+/// What the config file sets. Anything it leaves out is `None` or `false`.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Decimal places to round results to
+    pub precision: Option<usize>,
+    /// The locale tag to read and display expressions with, like `de-DE`
+    pub locale: Option<String>,
+    /// Check units before evaluating
+    pub strict: bool,
+    /// Extra unit names, from the units file
+    pub units: UnitRegistry,
+}
+
+/// A config or units file that couldn't be read, or a line of one that
+/// doesn't make sense.
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    line: Option<usize>,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// Where the config file is looked for: under `$XDG_CONFIG_HOME`, or
+/// `~/.config` without it.
+pub fn path() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("mathengine").join("config.toml"))
+}
+
+/// Where the REPL keeps its history between sessions: under
+/// `$XDG_STATE_HOME`, or `~/.local/state` without it.
+pub fn history_path() -> Option<PathBuf> {
+    base_dir("XDG_STATE_HOME", ".local/state").map(|dir| dir.join("mathengine").join("history"))
+}
+
+/// Where `rates update` caches exchange rates: under `$XDG_CACHE_HOME`, or
+/// `~/.cache` without it.
+pub fn rates_path() -> Option<PathBuf> {
+    base_dir("XDG_CACHE_HOME", ".cache").map(|dir| dir.join("mathengine").join("rates.json"))
+}
+
+fn base_dir(variable: &str, under_home: &str) -> Option<PathBuf> {
+    match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").filter(|home| !home.is_empty()).map(|home| PathBuf::from(home).join(under_home)),
+    }
+}
+
+/// Read the config file. Having none is the same as an empty one.
+pub fn load() -> Result<Config, ConfigError> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+    match fs::read_to_string(&path) {
+        Ok(source) => parse(&source, &path),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(ConfigError { path, line: None, message: err.to_string() }),
+    }
+}
+
+// The `key = value` lines of a config file. Only the TOML this needs is
+// understood: strings, whole numbers, booleans and `#` comments
+fn parse(source: &str, path: &Path) -> Result<Config, ConfigError> {
+    let mut config = Config::default();
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: String| ConfigError { path: path.to_path_buf(), line: Some(number + 1), message };
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(error("expected `key = value`".to_string()));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match key {
+            "precision" => {
+                let digits = args::digits(key, Some(value.to_string())).map_err(|err| error(err.0))?;
+                config.precision = Some(digits);
+            }
+            "locale" => {
+                let tag = string(value).ok_or_else(|| error("locale takes a quoted tag, like \"de-DE\"".to_string()))?;
+                if Locale::from_tag(&tag).is_none() {
+                    return Err(error(format!("unknown locale '{}'", tag)));
+                }
+                config.locale = Some(tag);
+            }
+            "strict" => {
+                config.strict = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(error(format!("strict takes true or false, not '{}'", value))),
+                };
+            }
+            "units" => {
+                let file = string(value).ok_or_else(|| error("units takes a quoted path".to_string()))?;
+                config.units = units(&resolve(&file, path))?;
+            }
+            _ => return Err(error(format!("unknown setting '{}'", key))),
+        }
+    }
+    Ok(config)
+}
+
+// The aliases in a units file, a `name = unit` line each
+fn units(path: &Path) -> Result<UnitRegistry, ConfigError> {
+    let source = fs::read_to_string(path).map_err(|err| ConfigError {
+        path: path.to_path_buf(),
+        line: None,
+        message: err.to_string(),
+    })?;
+    let mut registry = UnitRegistry::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.split_once('=').map(|(alias, unit)| (alias.trim(), unit.trim())) {
+            Some((alias, unit)) if !alias.is_empty() && !unit.is_empty() => {
+                registry = registry.with_alias(alias, unit);
+            }
+            _ => {
+                return Err(ConfigError {
+                    path: path.to_path_buf(),
+                    line: Some(number + 1),
+                    message: "expected `name = unit`".to_string(),
+                })
+            }
+        }
+    }
+    Ok(registry)
+}
+
+// A path from the config file, where `~` is the home directory and relative
+// paths are beside the config file
+fn resolve(file: &str, config: &Path) -> PathBuf {
+    if let Some(rest) = file.strip_prefix("~/") {
+        if let Some(home) = env::var_os("HOME") {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+    match config.parent() {
+        Some(dir) => dir.join(file),
+        None => PathBuf::from(file),
+    }
+}
+
+// A `#` outside a string starts a comment
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => {
+                chars.next();
+            }
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// A double-quoted TOML string, with its escapes
+fn string(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut string = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => string.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                't' => '\t',
+                _ => return None,
+            }),
+            '"' => return None,
+            c => string.push(c),
+        }
+    }
+    Some(string)
+}
@@ -0,0 +1,30 @@
+use std::process::ExitCode;
+
+use mathengine::{MathEngine, Value};
+
+use crate::exit;
+use crate::output::Output;
+
+/// Convert `amount` from one unit to another, through the engine like any
+/// other expression so currencies, unit aliases and the locale all apply.
+pub fn run(engine: &MathEngine, amount: &str, from: &str, to: &str, output: &Output) -> ExitCode {
+    // The amount can be worked out, like `2+3`, but not have a unit of its own
+    if !matches!(engine.evaluate(amount), Ok(Value::Number(_))) {
+        eprintln!("error: convert needs a number to convert, not '{}'", amount);
+        return ExitCode::from(exit::USAGE);
+    }
+
+    // Scaling the unit rather than writing `-5m` keeps negative amounts,
+    // which can't be negated once they have a unit, working
+    let input = format!("(({}) * 1{}) to {}", amount, from, to);
+    match engine.evaluate(&input) {
+        Ok(value) => {
+            output.value(engine, &input, &value);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            output.error(&input, &err, None);
+            ExitCode::from(exit::for_error(&err))
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use mathengine_lexer::{Lexer, Span};
+use mathengine_parser::{Expression, Parser, Statement, Versioned};
+use serde_json::json;
+
+/// The tokens of `input`, one per line with the characters each came from.
+pub fn tokens_text(input: &str) -> String {
+    match Lexer::new(input).tokenize_with_spans() {
+        Ok(tokens) => tokens
+            .iter()
+            .map(|(token, span)| format!("  {:<8}{:?}", span.to_string(), token))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(err) => format!("  {}", err),
+    }
+}
+
+/// The syntax tree of `input`, pretty-printed.
+pub fn ast_text(input: &str) -> String {
+    match parse(input) {
+        Ok(statement) => format!("{:#?}", statement)
+            .lines()
+            .map(|line| format!("  {}", line))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(err) => format!("  {}", err),
+    }
+}
+
+/// The tokens of `input` as JSON, or null when it doesn't lex.
+pub fn tokens_json(input: &str) -> serde_json::Value {
+    match Lexer::new(input).tokenize_with_spans() {
+        Ok(tokens) => tokens
+            .iter()
+            .map(|(token, span)| json!({ "token": format!("{:?}", token), "span": span_json(*span) }))
+            .collect(),
+        Err(_) => json!(null),
+    }
+}
+
+/// The syntax tree of `input` in the versioned document format, or null
+/// when it doesn't parse. Definitions and equations wrap their expressions
+/// in an object named for the statement.
+pub fn ast_json(input: &str) -> serde_json::Value {
+    let Ok(statement) = parse(input) else {
+        return json!(null);
+    };
+    let content = match &statement {
+        Statement::Expression(expr) => expression_json(expr),
+        Statement::Solve { equation, variable } => json!({ "Solve": {
+            "left": expression_json(&equation.left),
+            "right": expression_json(&equation.right),
+            "variable": variable,
+        }}),
+        Statement::Assign { name, value } => json!({ "Assign": {
+            "name": name,
+            "value": expression_json(value),
+        }}),
+        Statement::Define { name, params, body } => json!({ "Define": {
+            "name": name,
+            "params": params,
+            "body": expression_json(body),
+        }}),
+    };
+    json!({ "version": mathengine_parser::FORMAT_VERSION, "content": content })
+}
+
+fn parse(input: &str) -> Result<Statement, mathengine_parser::ParseError> {
+    let lexer = Lexer::new(input);
+    Parser::new(lexer.tokens()).parse_statement()
+}
+
+fn expression_json(expr: &Expression) -> serde_json::Value {
+    // The document's content, without the version around it
+    let document = serde_json::to_value(Versioned(expr)).unwrap_or_default();
+    document.get("content").cloned().unwrap_or_default()
+}
+
+fn span_json(span: Span) -> serde_json::Value {
+    json!({ "start": span.start, "end": span.end })
+}
+
@@ -0,0 +1,35 @@
+//! Exit statuses, one for each kind of failure so scripts can tell them
+//! apart. Success is 0.
+
+use std::io;
+
+use mathengine::Error;
+
+/// Reading input or a file failed
+pub const FAILURE: u8 = 1;
+/// The command line didn't make sense
+pub const USAGE: u8 = 2;
+pub const LEXER: u8 = 3;
+pub const PARSER: u8 = 4;
+pub const EVALUATION: u8 = 5;
+/// Dimension checking rejected the expression
+pub const TYPE: u8 = 6;
+
+/// The status for an expression that failed with `err`.
+pub fn for_error(err: &Error) -> u8 {
+    match err {
+        Error::Lexer(_) => LEXER,
+        Error::Parser(_) => PARSER,
+        Error::Evaluator(_) => EVALUATION,
+        Error::Type(_) => TYPE,
+    }
+}
+
+/// The status for failing to write results. A reader that stopped early,
+/// like `head`, isn't a failure.
+pub fn for_write_error(err: &io::Error) -> u8 {
+    match err.kind() {
+        io::ErrorKind::BrokenPipe => 0,
+        _ => FAILURE,
+    }
+}
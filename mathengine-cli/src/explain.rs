@@ -0,0 +1,152 @@
+use mathengine::{DimensionType, EvalObserver, Expression, FormatOptions, Operation, Value};
+
+/// Collects the steps an expression is evaluated in, a line like
+/// `5 * 4 → 20` for each operation, function call and list, with the units
+/// its values were converted to first, like `1m + 50cm → 1m + 0.5m → 1.5m`.
+pub struct Steps {
+    // How values are displayed, like results
+    format: FormatOptions,
+    pub steps: Vec<String>,
+}
+
+impl Steps {
+    pub fn new(format: FormatOptions) -> Self {
+        Self { format, steps: Vec::new() }
+    }
+}
+
+impl EvalObserver for Steps {
+    fn on_node(&mut self, node: &Expression, inputs: &[Value], output: &Value) {
+        let Some(mut step) = self.describe(node, inputs) else {
+            return;
+        };
+        if let Some(converted) = converted(node, inputs).and_then(|inputs| self.describe(node, &inputs)) {
+            step = format!("{} → {}", step, converted);
+        }
+        // Lists of literals evaluate to themselves, which isn't a step
+        let output = output.format(&self.format);
+        if step != output {
+            self.steps.push(format!("{} → {}", step, output));
+        }
+    }
+}
+
+impl Steps {
+    // How `node` reads applied to `inputs`, unless it isn't a step
+    fn describe(&self, node: &Expression, inputs: &[Value]) -> Option<String> {
+        let input = |i: usize| inputs.get(i).map(|value| value.format(&self.format)).unwrap_or_default();
+        let step = match node {
+            // Literals and variables are where steps start, not steps
+            Expression::Number(_)
+            | Expression::Decimal { .. }
+            | Expression::Integer(_)
+            | Expression::Bool(_)
+            | Expression::UnitValue { .. }
+            | Expression::Unit(_) => return None,
+            Expression::Binary { op: Operation::Convert, right, .. } => match &**right {
+                Expression::Unit(unit) => format!("{} to {}", input(0), unit),
+                _ => return None,
+            },
+            // The percentage is its number, divided by a hundred as it's applied
+            Expression::Binary { op: op @ (Operation::Add | Operation::Subtract), right, .. }
+                if matches!(**right, Expression::Percent(_)) =>
+            {
+                format!("{} {} {}%", input(0), symbol(op), input(1))
+            }
+            Expression::Binary { op: Operation::Of, left, .. } if matches!(**left, Expression::Percent(_)) => {
+                format!("{}% of {}", input(0), input(1))
+            }
+            Expression::Binary { op, .. } => format!("{} {} {}", input(0), symbol(op), input(1)),
+            Expression::Unary { op: Operation::Not, .. } => format!("not {}", input(0)),
+            Expression::Unary { .. } => format!("-{}", input(0)),
+            Expression::Factorial { .. } => format!("{}!", input(0)),
+            Expression::Percent(_) => format!("{}%", input(0)),
+            // Only the condition is an input; the branch it chose is evaluated
+            // as its own steps
+            Expression::FunctionCall { name, .. } if name == "if" => format!("if({}, …)", input(0)),
+            Expression::FunctionCall { name, .. } => format!("{}({})", name, list(&self.format, inputs)),
+            Expression::List(_) => format!("[{}]", list(&self.format, inputs)),
+        };
+        Some(step)
+    }
+}
+
+// The inputs of `node` in the units it converted them to before combining
+// them, if it converted any
+fn converted(node: &Expression, inputs: &[Value]) -> Option<Vec<Value>> {
+    match (node, inputs) {
+        (Expression::Binary { op, .. }, [Value::UnitValue(left), Value::UnitValue(right)]) => {
+            if left.unit() == right.unit()
+                || left.signature() != right.signature()
+                || left.dimension() == DimensionType::Unknown
+            {
+                return None;
+            }
+            let (left, right) = match (op, left.dimension()) {
+                // Products of derived units are in the left side's units
+                (Operation::Multiply | Operation::Divide, DimensionType::Derived) => {
+                    (left.clone(), right.convert_to(left.unit()).ok()?)
+                }
+                // Sums, comparisons and products within a dimension are
+                // worked out in its base unit
+                _ => (left.in_base_units(), right.in_base_units()),
+            };
+            Some(vec![Value::UnitValue(left), Value::UnitValue(right)])
+        }
+        // Aggregates work in the unit of their first value
+        (Expression::FunctionCall { name, .. }, _) if is_aggregate(name) => match inputs {
+            [Value::List(items)] => Some(vec![Value::List(in_first_unit(items)?)]),
+            items => in_first_unit(items),
+        },
+        _ => None,
+    }
+}
+
+fn is_aggregate(name: &str) -> bool {
+    matches!(name, "sum" | "mean" | "median" | "stdev" | "min" | "max")
+}
+
+fn in_first_unit(items: &[Value]) -> Option<Vec<Value>> {
+    let first = items.iter().find_map(|item| match item {
+        Value::UnitValue(uv) => Some(uv),
+        _ => None,
+    })?;
+    let unit = first.canonical_unit_name();
+    let mut changed = false;
+    let items = items
+        .iter()
+        .map(|item| match item {
+            Value::UnitValue(uv) if uv.canonical_unit_name() != unit => {
+                changed = true;
+                uv.convert_to(&unit).map(Value::UnitValue).ok()
+            }
+            other => Some(other.clone()),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    changed.then_some(items)
+}
+
+fn list(format: &FormatOptions, values: &[Value]) -> String {
+    values.iter().map(|value| value.format(format)).collect::<Vec<_>>().join(", ")
+}
+
+fn symbol(op: &Operation) -> &'static str {
+    match op {
+        Operation::Add => "+",
+        Operation::Subtract => "-",
+        Operation::Multiply => "*",
+        Operation::Divide => "/",
+        Operation::Power => "^",
+        Operation::Convert => "to",
+        Operation::Of => "of",
+        Operation::And => "and",
+        Operation::Or => "or",
+        Operation::Not => "not",
+        Operation::Less => "<",
+        Operation::LessEqual => "<=",
+        Operation::Greater => ">",
+        Operation::GreaterEqual => ">=",
+        Operation::Equal => "==",
+        Operation::NotEqual => "!=",
+    }
+}
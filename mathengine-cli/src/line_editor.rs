@@ -0,0 +1,279 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::PathBuf;
+
+// The most lines of history kept in a history file
+const HISTORY_LIMIT: usize = 1000;
+
+/// What reading a line ended with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadLine {
+    Line(String),
+    /// Ctrl-C, which drops the line being typed
+    Interrupted,
+    /// Ctrl-D on an empty line, or the end of piped input
+    Eof,
+}
+
+/// Reads lines from the terminal with cursor movement and history.
+///
+/// Left and right (or Ctrl-B and Ctrl-F) move along the line, Home and End
+/// (or Ctrl-A and Ctrl-E) jump to its ends, and up and down step through
+/// earlier lines. When stdin isn't a terminal, lines are read as they come.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    history: Vec<String>,
+    // Where history is kept between sessions
+    history_file: Option<PathBuf>,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start with the history in `path`, and add each new line to it. A file
+    /// that isn't there yet is created with the first line.
+    pub fn with_history_file(path: PathBuf) -> Self {
+        let mut history: Vec<String> = fs::read_to_string(&path)
+            .map(|saved| saved.lines().map(String::from).collect())
+            .unwrap_or_default();
+        if history.len() > HISTORY_LIMIT {
+            history.drain(..history.len() - HISTORY_LIMIT);
+            // Losing history isn't worth stopping for
+            let _ = fs::write(&path, history.join("\n") + "\n");
+        }
+        Self { history, history_file: Some(path) }
+    }
+
+    /// Remember `line` for the up arrow, unless it repeats the last one.
+    pub fn add_history(&mut self, line: &str) {
+        if !line.trim().is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+            if let Some(path) = &self.history_file {
+                let _ = append_line(path, line);
+            }
+        }
+    }
+
+    /// Read a line after showing `prompt`, which is left out for piped input.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<ReadLine> {
+        if io::stdin().is_terminal() {
+            let mut stdout = io::stdout();
+            write!(stdout, "{}", prompt)?;
+            stdout.flush()?;
+            if let Some(_raw) = RawMode::enable() {
+                return Editing::new(prompt, &self.history).run();
+            }
+        }
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(ReadLine::Eof);
+        }
+        Ok(ReadLine::Line(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+fn append_line(path: &PathBuf, line: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+// The line being typed, and where in the history it's looking
+struct Editing<'a> {
+    prompt: &'a str,
+    history: &'a [String],
+    line: Vec<char>,
+    cursor: usize,
+    // Index into the history being shown, or its length for the new line
+    recalled: usize,
+    // The new line, kept while the history is being looked through
+    draft: Vec<char>,
+}
+
+impl<'a> Editing<'a> {
+    fn new(prompt: &'a str, history: &'a [String]) -> Self {
+        Self {
+            prompt,
+            history,
+            line: Vec::new(),
+            cursor: 0,
+            recalled: history.len(),
+            draft: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> io::Result<ReadLine> {
+        let mut input = io::stdin().lock().bytes();
+        let mut next = || input.next().transpose();
+        loop {
+            let Some(byte) = next()? else {
+                return Ok(ReadLine::Eof);
+            };
+            match byte {
+                b'\r' | b'\n' => {
+                    println!("\r");
+                    return Ok(ReadLine::Line(self.line.iter().collect()));
+                }
+                // Ctrl-C
+                3 => {
+                    println!("^C\r");
+                    return Ok(ReadLine::Interrupted);
+                }
+                // Ctrl-D ends the input on an empty line, and deletes otherwise
+                4 if self.line.is_empty() => {
+                    println!("\r");
+                    return Ok(ReadLine::Eof);
+                }
+                4 => self.delete(),
+                // Backspace, which terminals send as DEL or Ctrl-H
+                127 | 8 => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.delete();
+                    }
+                }
+                1 => self.cursor = 0,
+                5 => self.cursor = self.line.len(),
+                2 => self.cursor = self.cursor.saturating_sub(1),
+                6 => self.cursor = (self.cursor + 1).min(self.line.len()),
+                // Ctrl-U clears everything before the cursor
+                21 => {
+                    self.line.drain(..self.cursor);
+                    self.cursor = 0;
+                }
+                0x1b => match (next()?, next()?) {
+                    (Some(b'[' | b'O'), Some(b'A')) => self.recall(-1),
+                    (Some(b'[' | b'O'), Some(b'B')) => self.recall(1),
+                    (Some(b'[' | b'O'), Some(b'C')) => self.cursor = (self.cursor + 1).min(self.line.len()),
+                    (Some(b'[' | b'O'), Some(b'D')) => self.cursor = self.cursor.saturating_sub(1),
+                    (Some(b'[' | b'O'), Some(b'H')) => self.cursor = 0,
+                    (Some(b'[' | b'O'), Some(b'F')) => self.cursor = self.line.len(),
+                    // Home, Delete and End as `ESC [ 1 ~`, `ESC [ 3 ~` and `ESC [ 4 ~`
+                    (Some(b'['), Some(key @ b'1'..=b'8')) if next()? == Some(b'~') => match key {
+                        b'1' | b'7' => self.cursor = 0,
+                        b'3' => self.delete(),
+                        b'4' | b'8' => self.cursor = self.line.len(),
+                        _ => {}
+                    },
+                    _ => {}
+                },
+                byte => {
+                    if let Some(c) = read_char(byte, &mut next)? {
+                        if !c.is_control() {
+                            self.line.insert(self.cursor, c);
+                            self.cursor += 1;
+                        }
+                    }
+                }
+            }
+            self.redraw()?;
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.line.len() {
+            self.line.remove(self.cursor);
+        }
+    }
+
+    // Step `by` lines through the history, back to the draft past its end
+    fn recall(&mut self, by: isize) {
+        let Some(to) = self.recalled.checked_add_signed(by).filter(|to| *to <= self.history.len()) else {
+            return;
+        };
+        if self.recalled == self.history.len() {
+            self.draft = self.line.clone();
+        }
+        self.recalled = to;
+        self.line = match self.history.get(to) {
+            Some(line) => line.chars().collect(),
+            None => self.draft.clone(),
+        };
+        self.cursor = self.line.len();
+    }
+
+    fn redraw(&self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        let line: String = self.line.iter().collect();
+        write!(stdout, "\r{}{}\x1b[K", self.prompt, line)?;
+        let back = self.line.len() - self.cursor;
+        if back > 0 {
+            write!(stdout, "\x1b[{}D", back)?;
+        }
+        stdout.flush()
+    }
+}
+
+// The character `first` starts, reading the rest of its UTF-8 bytes
+fn read_char(
+    first: u8,
+    next: &mut impl FnMut() -> io::Result<Option<u8>>,
+) -> io::Result<Option<char>> {
+    let width = match first {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Ok(None),
+    };
+    let mut bytes = vec![first];
+    for _ in 1..width {
+        match next()? {
+            Some(byte) => bytes.push(byte),
+            None => return Ok(None),
+        }
+    }
+    Ok(std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()))
+}
+
+// The terminal reading keys as they're pressed rather than a line at a time,
+// until dropped
+struct RawMode {
+    #[cfg(unix)]
+    original: libc::termios,
+}
+
+impl RawMode {
+    #[cfg(unix)]
+    fn enable() -> Option<Self> {
+        // SAFETY: termios is plain data, filled in by tcgetattr before use
+        unsafe {
+            let mut original = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return None;
+            }
+            let mut raw = original;
+            // Keys arrive one at a time, unechoed, and Ctrl-C is read rather
+            // than sent as a signal
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) != 0 {
+                return None;
+            }
+            Some(Self { original })
+        }
+    }
+
+    // Other terminals keep their own line editing
+    #[cfg(not(unix))]
+    fn enable() -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `enable`
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.original);
+        }
+    }
+}
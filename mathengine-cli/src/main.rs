@@ -1,42 +1,155 @@
-use mathengine::{Error, Value, evaluate_expression};
-
-fn main() {
-    let expressions = vec![
-        // Test unit arithmetic
-        "1m + 1m + 100cm", // Should be 3m
-        "10ft + 2m",       // Should convert to base (meters)
-        "5m - 200cm",      // Should be 3m
-        "100in - 1ft",     // Should convert to base (meters)
-        "(1m + 2m) to cm",
-        "(1m + 2m) to yards",
-        "1 meter to centimeters",
-        "(2m * 2)",
-        "(2m * 2) + (10cm + 10)",
-    ];
-
-    for e in expressions {
-        println!("\nExpression: {}", e);
-
-        match evaluate_expression(e) {
-            Ok(value) => print_result(value),
-            Err(err) => print_error(err),
+use std::io::{BufRead, IsTerminal};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use mathengine::{FormatOptions, Locale, MathEngine, Session};
+
+mod args;
+mod color;
+mod config;
+mod convert;
+mod debug;
+mod exit;
+mod explain;
+mod line_editor;
+mod output;
+mod rates;
+mod repl;
+mod script;
+mod table;
+mod units;
+mod watch;
+
+use args::{Command, Options};
+use config::Config;
+use output::Output;
+
+fn main() -> ExitCode {
+    let args = match args::parse(std::env::args().skip(1), std::io::stdin().is_terminal()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {}\nRun 'mathengine --help' for usage", err);
+            return ExitCode::from(exit::USAGE);
         }
+    };
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(exit::FAILURE);
+        }
+    };
+    let output = Output::new(&args.options);
+    let engine = engine(&args.options, config);
+
+    match args.command {
+        Command::Help => output::print(args::USAGE),
+        Command::Version => output::print(format!("mathengine {}", env!("CARGO_PKG_VERSION"))),
+        Command::Repl => {
+            if let Err(err) = repl::run(Session::with_engine(engine), &output) {
+                eprintln!("error: {}", err);
+                return ExitCode::from(exit::FAILURE);
+            }
+        }
+        Command::Stdin => return evaluate_stdin(Session::with_engine(engine), &output),
+        Command::Run { path } => return script::run(&path, &mut Session::with_engine(engine), &output),
+        Command::Watch { path } => watch::run(&path, &engine, &output),
+        Command::Rates { update: true } => return rates::update(&rates::JsonApi::from_env()),
+        Command::Rates { update: false } => return rates::show(&engine, &output),
+        Command::Units { dimension } => return units::list(&engine, dimension.as_deref(), &output),
+        Command::Convert { amount, from, to } => return convert::run(&engine, &amount, &from, &to, &output),
+        Command::Table { values } => return table::run(&engine, &values, &output),
+        Command::Evaluate { expression } => return evaluate(Session::with_engine(engine), &expression, &output),
     }
+    ExitCode::SUCCESS
 }
 
-fn print_result(value: Value) {
-    match value {
-        Value::Number(n) => println!("Result: {}", n),
-        Value::UnitValue(uv) => println!("UNIT RESULT: {}", uv),
+// Evaluate the `;` separated parts of `expression` in turn, in one session so
+// later parts see what earlier ones define, printing each result. The first
+// that fails stops the rest
+fn evaluate(mut session: Session, expression: &str, output: &Output) -> ExitCode {
+    let parts: Vec<&str> = split_expressions(expression).map(str::trim).filter(|part| !part.is_empty()).collect();
+    for (i, part) in parts.iter().enumerate() {
+        match output.eval(&mut session, part) {
+            Ok(Some(value)) => output.value(session.engine(), part, &value),
+            Ok(None) => {}
+            Err(err) => {
+                let location = (parts.len() > 1).then(|| format!("expression {}", i + 1));
+                output.error(part, &err, location.as_deref());
+                return ExitCode::from(exit::for_error(&err));
+            }
+        }
     }
+    ExitCode::SUCCESS
 }
 
-fn print_error(err: Error) {
-    // The Display implementation already formats errors nicely
-    eprintln!("Error: {}", err);
+// The engine evaluating and formatting with the command line's options,
+// and the config file's where the command line doesn't say
+fn engine(options: &Options, config: Config) -> MathEngine {
+    let tag = options.locale.as_ref().or(config.locale.as_ref());
+    // Tags were checked when they were read
+    let locale = tag.and_then(|tag| Locale::from_tag(tag)).unwrap_or_default();
+    let units = match tag {
+        Some(tag) => config.units.with_language(tag),
+        None => config.units,
+    };
+    let mut builder = MathEngine::builder();
+    if let Some(rates) = rates::Rates::load() {
+        builder = builder.rates(Arc::new(rates::CachedRates::new(rates)));
+    }
+    builder
+        .strict(options.strict.unwrap_or(config.strict))
+        .units(units)
+        .input_locale(locale)
+        .format_options(FormatOptions {
+            decimal_places: options.precision.or(config.precision),
+            significant_figures: options.sig_figs,
+            money: options.money,
+            locale,
+            ..FormatOptions::default()
+        })
+        .build()
+}
 
-    // If you want to get the source error for more detail:
-    if let Some(source) = std::error::Error::source(&err) {
-        eprintln!("  Caused by: {}", source);
+// Print a result per line of stdin, and each error with the line it's from.
+// The lines share a session, like the parts of an expression, so later ones
+// see what earlier ones define. Failed lines don't stop the rest, but the
+// first sets the exit status
+fn evaluate_stdin(mut session: Session, output: &Output) -> ExitCode {
+    let mut status = None;
+    for (i, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::from(exit::FAILURE);
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match output.eval(&mut session, &line) {
+            Ok(Some(value)) => output.value(session.engine(), &line, &value),
+            Ok(None) => {}
+            Err(err) => {
+                output.error(&line, &err, Some(&format!("line {}", i + 1)));
+                status.get_or_insert(exit::for_error(&err));
+            }
+        }
     }
+    ExitCode::from(status.unwrap_or(0))
+}
+
+// The parts of `expression` between `;`s outside brackets, which separate
+// arguments and list items in locales with a decimal comma
+fn split_expressions(expression: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0usize;
+    expression.split(move |c| {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        c == ';' && depth == 0
+    })
 }
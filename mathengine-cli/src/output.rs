@@ -0,0 +1,298 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use mathengine::{Error, MathEngine, Number, Session, Significant, Timings, Value};
+use serde_json::{json, Map};
+
+use crate::args::{Base, Options};
+use crate::color::{self, paint, Style};
+use crate::debug;
+use crate::exit;
+use crate::explain::Steps;
+
+/// Prints results and errors the way the command line asked for.
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    json: bool,
+    // Bare numbers, without units
+    raw: bool,
+    base: Option<Base>,
+    quiet: bool,
+    // Show how long each input took
+    time: bool,
+    // Show the steps each input was evaluated in, kept from evaluating it
+    // until its result is shown
+    explain: bool,
+    steps: RefCell<Option<Vec<String>>>,
+    // Round each result to the significant figures evaluating it found,
+    // likewise kept until it's shown
+    track_figures: Cell<bool>,
+    figures: Cell<Option<usize>>,
+    // Show how each input lexed and parsed, ahead of its result
+    tokens: bool,
+    ast: bool,
+    // Whether results and errors are colored
+    color_stdout: bool,
+    color_stderr: bool,
+}
+
+impl Output {
+    pub fn new(options: &Options) -> Self {
+        let (color_stdout, color_stderr) = color::streams(options.no_color);
+        Self {
+            json: options.json,
+            raw: options.raw,
+            base: options.base,
+            quiet: options.quiet,
+            time: options.time,
+            explain: options.explain,
+            steps: RefCell::default(),
+            track_figures: Cell::new(options.track_figures),
+            figures: Cell::default(),
+            tokens: options.tokens,
+            ast: options.ast,
+            color_stdout,
+            color_stderr,
+        }
+    }
+
+    /// Whether output is JSON rather than text.
+    pub fn json(&self) -> bool {
+        self.json
+    }
+
+    /// Whether to leave out everything but results, like the REPL's prompt.
+    pub fn quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Round results to the significant figures their numbers have, or stop,
+    /// like the REPL's `:set sig-figs auto`.
+    pub fn track_figures(&self, track: bool) {
+        self.track_figures.set(track);
+    }
+
+    /// This output, showing the steps of each evaluation too.
+    pub fn explaining(&self) -> Self {
+        Self { explain: true, ..self.clone() }
+    }
+
+    /// Evaluate `input` in `session`, showing how long it took or the steps
+    /// it took if asked to.
+    pub fn eval(&self, session: &mut Session, input: &str) -> Result<Option<Value>, Error> {
+        if self.explain {
+            let mut steps = Steps::new(session.engine().format_options().clone());
+            let result = session.eval_with_observer(input, &mut steps);
+            self.steps.replace(Some(steps.steps));
+            return result;
+        }
+        if self.track_figures.get() {
+            let result = session.eval_significant(input);
+            self.figures.set(result.as_ref().ok().and_then(|result| result.as_ref()?.figures));
+            return result.map(|result| result.map(|result| result.value));
+        }
+        if !self.time {
+            return session.eval(input);
+        }
+        let (result, timings) = session.eval_timed(input);
+        self.timings(&timings);
+        result
+    }
+
+    /// Show how long evaluating an input took, if asked to. Timings go to
+    /// stderr so they don't mix with results.
+    pub fn timings(&self, timings: &Timings) {
+        if self.time {
+            eprintln!(
+                "time: lex {:?}, parse {:?}, eval {:?}, total {:?}",
+                timings.lex,
+                timings.parse,
+                timings.eval,
+                timings.total()
+            );
+        }
+    }
+
+    /// Show the value `input` evaluated to.
+    pub fn value(&self, engine: &MathEngine, input: &str, value: &Value) {
+        let value = &match self.base {
+            Some(base) => in_base(value, base),
+            None => value.clone(),
+        };
+        let figures = self.figures.take();
+        let format = |value: &Value| Significant { value: value.clone(), figures }.format(engine.format_options());
+        if self.raw {
+            self.print_debug(input);
+            print(raw(value, &format));
+            return;
+        }
+        if !self.json {
+            self.print_debug(input);
+            print(self.paint_value(&format(value), value));
+            return;
+        }
+        let info = value.unit_info();
+        self.print_json(
+            input,
+            json!({
+                "input": input,
+                "value": plain(value),
+                "unit": info.as_ref().map(|info| &info.unit),
+                "dimension": info.as_ref().map(|info| info.dimension.name()),
+                "formatted": format(value),
+                "error": null,
+            }),
+        );
+    }
+
+    /// Show why `input` failed. `location` says where it came from, like a
+    /// line of a file, when there's more than one input.
+    pub fn error(&self, input: &str, err: &Error, location: Option<&str>) {
+        if !self.json {
+            self.print_debug(input);
+            let location = location.map(|at| format!("{}: ", at)).unwrap_or_default();
+            // Underline the part of the input that failed
+            let color = self.color_stderr;
+            let mut lines: Vec<String> = err.render(input).lines().map(String::from).collect();
+            if lines.len() > 1 {
+                let carets = lines.len() - 1;
+                lines[carets] = paint(&lines[carets], Style::Caret, color);
+            }
+            eprintln!("{}{} {}", location, paint("Error:", Style::Error, color), lines.join("\n"));
+            return;
+        }
+        let diagnostic = err.diagnostic(input);
+        let mut error = Map::new();
+        error.insert("code".to_string(), json!(diagnostic.code));
+        error.insert("message".to_string(), json!(diagnostic.message));
+        error.insert(
+            "span".to_string(),
+            match diagnostic.labels.first() {
+                Some((span, _)) => json!({ "start": span.start, "end": span.end }),
+                None => json!(null),
+            },
+        );
+        if let Some(location) = location {
+            error.insert("location".to_string(), json!(location));
+        }
+        self.print_json(
+            input,
+            json!({
+                "input": input,
+                "value": null,
+                "unit": null,
+                "dimension": null,
+                "formatted": null,
+                "error": error,
+            }),
+        );
+    }
+
+    // A formatted result with its number and unit in their colors
+    fn paint_value(&self, formatted: &str, value: &Value) -> String {
+        let color = self.color_stdout;
+        let unit = value.unit_info().map(|info| info.unit);
+        match unit.as_deref().and_then(|unit| formatted.strip_suffix(unit).map(|number| (number, unit))) {
+            Some((number, unit)) => format!("{}{}", paint(number, Style::Number, color), paint(unit, Style::Unit, color)),
+            None => paint(formatted, Style::Number, color),
+        }
+    }
+
+    fn print_debug(&self, input: &str) {
+        if self.tokens {
+            print(format!("tokens:\n{}", debug::tokens_text(input)));
+        }
+        if self.ast {
+            print(format!("ast:\n{}", debug::ast_text(input)));
+        }
+        if let Some(steps) = self.steps.take().filter(|steps| !steps.is_empty()) {
+            print("steps:");
+            for step in steps {
+                print(format!("  {}", step));
+            }
+        }
+    }
+
+    fn print_json(&self, input: &str, mut object: serde_json::Value) {
+        if self.tokens {
+            object["tokens"] = debug::tokens_json(input);
+        }
+        if self.ast {
+            object["ast"] = debug::ast_json(input);
+        }
+        if let Some(steps) = self.steps.take() {
+            object["steps"] = json!(steps);
+        }
+        print(object);
+    }
+}
+
+/// Write a line of output to stdout. When writing fails the program ends,
+/// successfully if whoever was reading has stopped, like `head`.
+pub fn print(line: impl Display) {
+    if let Err(err) = writeln!(io::stdout().lock(), "{}", line) {
+        std::process::exit(write_failed(&err).into());
+    }
+}
+
+/// The status to exit with when writing output failed, reporting anything
+/// but a closed pipe.
+pub fn write_failed(err: &io::Error) -> u8 {
+    let status = exit::for_write_error(err);
+    if status != 0 {
+        eprintln!("error: can't write output: {}", err);
+    }
+    status
+}
+
+// Integers, in lists too, shown in `base`. Other values are left alone
+fn in_base(value: &Value, base: Base) -> Value {
+    let integer = match value {
+        Value::Number(number) => number.as_integer(),
+        Value::Radix { value, .. } => Some(*value),
+        Value::List(items) => return Value::List(items.iter().map(|item| in_base(item, base)).collect()),
+        _ => None,
+    };
+    match (integer, base) {
+        (Some(integer), Base::Radix(radix)) => Value::Radix { value: integer, radix },
+        (Some(integer), Base::Decimal) => Value::Number(Number::Integer(integer)),
+        (None, _) => value.clone(),
+    }
+}
+
+// The value's number without its unit, and list items a line each
+fn raw(value: &Value, format: &dyn Fn(&Value) -> String) -> String {
+    match value {
+        Value::UnitValue(unit_value) => format(&Value::Number(Number::Float(unit_value.value()))),
+        Value::List(items) => items.iter().map(|item| raw(item, format)).collect::<Vec<_>>().join("\n"),
+        value => format(value),
+    }
+}
+
+// The value as JSON: numbers and unit values as their magnitude, which
+// `unit` goes with, and lists item by item
+fn plain(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Number(number) => number_json(number),
+        Value::UnitValue(unit_value) => json!(unit_value.value()),
+        Value::Bool(b) => json!(b),
+        Value::List(items) => items.iter().map(plain).collect(),
+        Value::Radix { value, .. } => integer_json(*value),
+    }
+}
+
+fn number_json(number: &Number) -> serde_json::Value {
+    match number.as_integer() {
+        Some(integer) => integer_json(integer),
+        None => json!(number.to_f64()),
+    }
+}
+
+// Integers too large for JSON parsers to read exactly are written as floats
+fn integer_json(integer: i128) -> serde_json::Value {
+    match i64::try_from(integer) {
+        Ok(integer) => json!(integer),
+        Err(_) => json!(integer as f64),
+    }
+}
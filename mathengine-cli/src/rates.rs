@@ -0,0 +1,283 @@
+//! Exchange rates for currency conversions, fetched by `rates update` and
+//! kept in a cache so conversions work offline.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, ExitCode};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use mathengine::{MathEngine, RateError, RateProvider};
+use serde_json::{json, Value as Json};
+
+use crate::config;
+use crate::exit;
+use crate::output::{self, Output};
+
+// Where rates are fetched from, unless MATHENGINE_RATES_URL says otherwise
+const DEFAULT_URL: &str = "https://open.er-api.com/v6/latest/USD";
+
+// Rates older than this are still used, with a warning
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Somewhere exchange rates can be fetched from.
+pub trait RateSource {
+    /// Where the rates come from, for showing with them.
+    fn name(&self) -> String;
+
+    /// The current rates.
+    fn fetch(&self) -> Result<Rates, String>;
+}
+
+/// Fetches what's at a URL.
+pub trait Fetch {
+    /// The body of a successful response.
+    fn get(&self, url: &str) -> Result<Vec<u8>, String>;
+}
+
+/// Fetches with the `curl` command, so the CLI needs no HTTP client of its
+/// own.
+pub struct Curl;
+
+impl Fetch for Curl {
+    fn get(&self, url: &str) -> Result<Vec<u8>, String> {
+        let response = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location", "--max-time", "30", url])
+            .output()
+            .map_err(|err| format!("can't run curl: {}", err))?;
+        if !response.status.success() {
+            return Err(String::from_utf8_lossy(&response.stderr).trim().to_string());
+        }
+        Ok(response.stdout)
+    }
+}
+
+/// An API answering with JSON like `{"base_code": "USD", "rates": {"EUR":
+/// 0.92, ...}}`, as open.er-api.com does, fetched with `F`.
+pub struct JsonApi<F = Curl> {
+    url: String,
+    fetcher: F,
+}
+
+impl JsonApi {
+    /// The API at MATHENGINE_RATES_URL, or open.er-api.com, fetched with `curl`.
+    pub fn from_env() -> Self {
+        let url = env::var("MATHENGINE_RATES_URL").unwrap_or_else(|_| DEFAULT_URL.to_string());
+        Self::new(url, Curl)
+    }
+}
+
+impl<F: Fetch> JsonApi<F> {
+    /// The API at `url`, fetched with `fetcher`.
+    pub fn new(url: impl Into<String>, fetcher: F) -> Self {
+        Self { url: url.into(), fetcher }
+    }
+}
+
+impl<F: Fetch> RateSource for JsonApi<F> {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    fn fetch(&self) -> Result<Rates, String> {
+        let response = self.fetcher.get(&self.url)?;
+        let body: Json = serde_json::from_slice(&response).map_err(|err| format!("unexpected response: {}", err))?;
+        let base = body["base_code"].as_str().ok_or("unexpected response: no base_code")?;
+        let rates = body["rates"]
+            .as_object()
+            .ok_or("unexpected response: no rates")?
+            .iter()
+            .filter_map(|(code, rate)| Some((code.to_lowercase(), rate.as_f64()?)))
+            .collect();
+        Ok(Rates {
+            base: base.to_lowercase(),
+            rates,
+            source: self.name(),
+            fetched: SystemTime::now(),
+        })
+    }
+}
+
+/// Rates against one base currency, as fetched and cached.
+#[derive(Debug, Clone)]
+pub struct Rates {
+    /// The currency the rates are against, lowercase
+    pub base: String,
+    /// How many of each currency one unit of `base` buys, by lowercase code
+    pub rates: HashMap<String, f64>,
+    pub source: String,
+    pub fetched: SystemTime,
+}
+
+impl Rates {
+    /// How many of `to` one `from` buys, going through the base currency.
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        let against_base = |code: &str| match code == self.base {
+            true => Some(1.0),
+            false => self.rates.get(code).copied(),
+        };
+        Some(against_base(to)? / against_base(from).filter(|rate| *rate != 0.0)?)
+    }
+
+    /// How long ago the rates were fetched.
+    pub fn age(&self) -> Duration {
+        SystemTime::now().duration_since(self.fetched).unwrap_or_default()
+    }
+
+    /// The cached rates, if `rates update` has saved any that can be read.
+    pub fn load() -> Option<Rates> {
+        let text = fs::read_to_string(config::rates_path()?).ok()?;
+        let cache: Json = serde_json::from_str(&text).ok()?;
+        Some(Rates {
+            base: cache["base"].as_str()?.to_string(),
+            rates: cache["rates"]
+                .as_object()?
+                .iter()
+                .filter_map(|(code, rate)| Some((code.clone(), rate.as_f64()?)))
+                .collect(),
+            source: cache["source"].as_str().unwrap_or_default().to_string(),
+            fetched: UNIX_EPOCH + Duration::from_secs(cache["fetched"].as_u64()?),
+        })
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let fetched = self.fetched.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let cache = json!({
+            "source": self.source,
+            "fetched": fetched,
+            "base": self.base,
+            "rates": self.rates,
+        });
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, cache.to_string()).map_err(|err| err.to_string())
+    }
+}
+
+/// Cached rates for converting, which warn once if they're out of date.
+#[derive(Debug)]
+pub struct CachedRates {
+    rates: Rates,
+    warned: AtomicBool,
+}
+
+impl CachedRates {
+    pub fn new(rates: Rates) -> Self {
+        Self { rates, warned: AtomicBool::new(false) }
+    }
+}
+
+impl RateProvider for CachedRates {
+    fn rate(&self, from: &str, to: &str) -> Result<f64, RateError> {
+        let age = self.rates.age();
+        if age > STALE_AFTER && !self.warned.swap(true, Ordering::Relaxed) {
+            eprintln!(
+                "warning: exchange rates are {} old, run 'mathengine rates update' for current ones",
+                describe_age(age)
+            );
+        }
+        self.rates.rate(from, to).ok_or(RateError::Missing)
+    }
+}
+
+/// Fetch rates from `source` and cache them for later conversions.
+pub fn update(source: &dyn RateSource) -> ExitCode {
+    let Some(path) = config::rates_path() else {
+        eprintln!("error: nowhere to cache rates, set HOME or XDG_CACHE_HOME");
+        return ExitCode::from(exit::FAILURE);
+    };
+    let rates = match source.fetch() {
+        Ok(rates) => rates,
+        Err(err) => {
+            eprintln!("error: can't fetch rates from {}: {}", source.name(), err);
+            return ExitCode::from(exit::FAILURE);
+        }
+    };
+    if let Err(err) = rates.save(&path) {
+        eprintln!("error: can't cache rates in {}: {}", path.display(), err);
+        return ExitCode::from(exit::FAILURE);
+    }
+    output::print(format!("Fetched {} rates against {} from {}", rates.rates.len(), rates.base.to_uppercase(), rates.source));
+    ExitCode::SUCCESS
+}
+
+/// Show the cached rates of the currencies the engine converts, and how old
+/// they are.
+pub fn show(engine: &MathEngine, output: &Output) -> ExitCode {
+    let Some(rates) = Rates::load() else {
+        eprintln!("error: no exchange rates are cached, run 'mathengine rates update'");
+        return ExitCode::from(exit::FAILURE);
+    };
+    let listed: Vec<(&str, f64)> = engine
+        .capabilities()
+        .currencies
+        .into_iter()
+        .filter_map(|code| Some((code, rates.rate(&rates.base, code)?)))
+        .collect();
+    if output.json() {
+        let fetched = rates.fetched.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let listed: serde_json::Map<_, _> = listed.iter().map(|(code, rate)| (code.to_string(), json!(rate))).collect();
+        output::print(json!({ "base": rates.base, "fetched": fetched, "source": rates.source, "rates": listed }));
+        return ExitCode::SUCCESS;
+    }
+    output::print(format!(
+        "Rates against {}, fetched {} ago from {}:",
+        rates.base.to_uppercase(),
+        describe_age(rates.age()),
+        rates.source
+    ));
+    for (code, rate) in listed {
+        output::print(format!("  {}  {}", code.to_uppercase(), rate));
+    }
+    ExitCode::SUCCESS
+}
+
+// An age like `3 days` or `5 minutes`, in its largest whole unit
+fn describe_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    let (amount, unit) = match seconds {
+        0..=119 => (seconds, "second"),
+        120..=7_199 => (seconds / 60, "minute"),
+        7_200..=172_799 => (seconds / 3_600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Answers every request with the same response
+    struct Fake(Result<&'static str, &'static str>);
+
+    impl Fetch for Fake {
+        fn get(&self, _url: &str) -> Result<Vec<u8>, String> {
+            self.0.map(|body| body.as_bytes().to_vec()).map_err(str::to_string)
+        }
+    }
+
+    #[test]
+    fn test_json_api() {
+        let api = JsonApi::new("https://rates.test", Fake(Ok(r#"{"base_code": "USD", "rates": {"USD": 1, "EUR": 0.5, "GBP": 0.25, "XYZ": null}}"#)));
+        let rates = api.fetch().unwrap();
+        assert_eq!((rates.base.as_str(), rates.source.as_str()), ("usd", "https://rates.test"));
+        assert_eq!(rates.rates.len(), 3);
+        assert_eq!(rates.rate("usd", "eur"), Some(0.5));
+        // Other pairs go through the base currency
+        assert_eq!(rates.rate("eur", "gbp"), Some(0.5));
+        assert_eq!(rates.rate("usd", "xyz"), None);
+
+        for (response, error) in [
+            (Err("couldn't connect"), "couldn't connect"),
+            (Ok(r#"{"rates": {}}"#), "unexpected response: no base_code"),
+            (Ok(r#"{"base_code": "USD"}"#), "unexpected response: no rates"),
+        ] {
+            assert_eq!(JsonApi::new("https://rates.test", Fake(response)).fetch().unwrap_err(), error);
+        }
+        assert!(JsonApi::new("https://rates.test", Fake(Ok("<html>"))).fetch().unwrap_err().starts_with("unexpected response: "));
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::{self, IsTerminal};
+
+use mathengine::Session;
+
+use crate::args;
+use crate::config;
+use crate::line_editor::{LineEditor, ReadLine};
+use crate::output::Output;
+use crate::units;
+
+const PROMPT: &str = "> ";
+
+const BANNER: &str = "\
+mathengine - type an expression, `x = 2` to define a variable, `:help` for commands, or `exit` to leave";
+
+const HELP: &str = "\
+Commands:
+  :set precision <N>   Round results to N decimal places, or `off`
+  :set sig-figs <N>    Round results to N significant figures, `auto` for as
+                       many as their numbers have, or `off`
+  :explain <EXPR>      Evaluate an expression, showing each step it takes
+  :vars                List the variables and functions defined so far
+  :history             List the lines entered so far, with the `_1`, `_2`, ...
+                       that stand for their results
+  :clear               Forget every variable and function defined so far,
+                       and the history
+  :units [DIMENSION]   List the units, or only those of one dimension
+  :help                Show this list
+  exit, quit           Leave";
+
+/// Evaluate lines as they're typed, keeping variables and functions
+/// between them, until the input ends or `exit` is entered.
+pub fn run(mut session: Session, output: &Output) -> io::Result<()> {
+    // Only what's typed is worth recalling in later sessions
+    let terminal = io::stdin().is_terminal();
+    let mut editor = match config::history_path() {
+        Some(path) if terminal => LineEditor::with_history_file(path),
+        _ => LineEditor::new(),
+    };
+    if terminal && !output.quiet() {
+        println!("{}", BANNER);
+    }
+    let prompt = if output.quiet() { "" } else { PROMPT };
+    loop {
+        let line = match editor.read_line(prompt)? {
+            ReadLine::Line(line) => line,
+            ReadLine::Interrupted => continue,
+            ReadLine::Eof => return Ok(()),
+        };
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+        editor.add_history(input);
+        if matches!(input, "exit" | "quit") {
+            return Ok(());
+        }
+        if let Some(command) = input.strip_prefix(':') {
+            if let Err(err) = run_command(&mut session, command, output) {
+                eprintln!("error: {}", err);
+            }
+            continue;
+        }
+
+        match output.eval(&mut session, input) {
+            Ok(Some(value)) => output.value(session.engine(), input, &value),
+            Ok(None) => {}
+            Err(err) => output.error(input, &err, None),
+        }
+    }
+}
+
+// Run a `:` command, which looks at or changes the session rather than
+// evaluating anything
+fn run_command(session: &mut Session, command: &str, output: &Output) -> Result<(), String> {
+    if let Some(expression) = command.strip_prefix("explain ") {
+        let (output, expression) = (output.explaining(), expression.trim());
+        match output.eval(session, expression) {
+            Ok(Some(value)) => output.value(session.engine(), expression, &value),
+            Ok(None) => {}
+            Err(err) => output.error(expression, &err, None),
+        }
+        return Ok(());
+    }
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words.as_slice() {
+        ["help"] => println!("{}", HELP),
+        ["vars"] => list_definitions(session),
+        ["history"] => list_history(session),
+        ["clear"] => session.clear(),
+        ["units"] => {
+            units::list(session.engine(), None, output);
+        }
+        ["units", dimension] => {
+            units::list(session.engine(), Some(dimension), output);
+        }
+        ["set", "sig-figs", "auto"] => output.track_figures(true),
+        ["set", option, value] => {
+            let mut options = session.engine().format_options().clone();
+            let digits = match *value {
+                "off" => None,
+                value => Some(args::digits(option, Some(value.to_string())).map_err(|err| err.0)?),
+            };
+            match *option {
+                "precision" => options.decimal_places = digits,
+                "sig-figs" => {
+                    options.significant_figures = digits;
+                    output.track_figures(false);
+                }
+                _ => return Err(format!("unknown setting '{}', expected precision or sig-figs", option)),
+            }
+            session.set_format_options(options);
+        }
+        _ => return Err(format!("unknown command ':{}', see :help", command)),
+    }
+    Ok(())
+}
+
+fn list_definitions(session: &Session) {
+    let context = session.engine().context();
+    // Results in the history are listed by `:history`
+    let mut variables: Vec<_> = context.variables.iter().filter(|(name, _)| !is_recalled(name)).collect();
+    variables.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in variables {
+        println!("{} = {}", name, session.engine().format(value));
+    }
+    let mut functions: Vec<_> = context.functions.iter().collect();
+    functions.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, function) in functions {
+        println!("{}({})", name, function.params.join(", "));
+    }
+}
+
+fn list_history(session: &Session) {
+    for (i, entry) in session.history().iter().enumerate() {
+        match &entry.result {
+            Some(value) => println!("_{}  {} → {}", i + 1, entry.input, session.engine().format(value)),
+            None => println!("_{}  {}", i + 1, entry.input),
+        }
+    }
+}
+
+// A name like `_3`, for a result in the history
+fn is_recalled(name: &str) -> bool {
+    name.strip_prefix('_').is_some_and(|number| number.chars().all(|c| c.is_ascii_digit()))
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::process::ExitCode;
+
+use mathengine::Session;
+
+use crate::exit;
+use crate::output::Output;
+
+/// Run a file of expressions and definitions a line at a time, in one session
+/// so later lines see what earlier ones define.
+///
+/// `#` starts a comment. Each line's result is printed, unless the file uses
+/// `print(...)`, in which case only what's printed is shown. The first line
+/// that fails stops the script.
+pub fn run(path: &str, session: &mut Session, output: &Output) -> ExitCode {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("error: can't read {}: {}", path, err);
+            return ExitCode::from(exit::FAILURE);
+        }
+    };
+
+    let lines: Vec<(usize, &str)> = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, strip_comment(line).trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    let prints_only = lines.iter().any(|(_, line)| print_argument(line).is_some());
+
+    for (number, line) in lines {
+        let printed = print_argument(line);
+        let source = printed.unwrap_or(line);
+        match output.eval(session, source) {
+            Ok(Some(value)) if printed.is_some() || !prints_only => {
+                output.value(session.engine(), line, &value)
+            }
+            Ok(_) => {}
+            Err(err) => {
+                output.error(source, &err, Some(&format!("{}:{}", path, number)));
+                return ExitCode::from(exit::for_error(&err));
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once('#').map_or(line, |(code, _)| code)
+}
+
+// The expression in a line that's all `print(...)`
+fn print_argument(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix("print")?.trim_start().strip_prefix('(')?.strip_suffix(')')?;
+    // The parentheses have to enclose the whole argument, unlike in
+    // `print(1) + (2)`
+    let mut depth = 0usize;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            _ => {}
+        }
+    }
+    Some(inner)
+}
@@ -0,0 +1,48 @@
+use std::process::ExitCode;
+
+use mathengine::{MathEngine, Value};
+use serde_json::json;
+
+use crate::exit;
+use crate::output::{self, Output};
+
+/// Show each of `values` in every unit of its dimension, a line each. The
+/// values are expressions, like `1mi` or `"3ft + 2in"`, and all have to be
+/// of the first one's dimension.
+pub fn run(engine: &MathEngine, values: &[String], output: &Output) -> ExitCode {
+    let mut converted = Vec::with_capacity(values.len());
+    for input in values {
+        match engine.evaluate(input) {
+            Ok(Value::UnitValue(value)) => converted.push(value),
+            Ok(value) => {
+                eprintln!("error: table needs values with units, not '{}'", engine.format(&value));
+                return ExitCode::from(exit::USAGE);
+            }
+            Err(err) => {
+                output.error(input, &err, None);
+                return ExitCode::from(exit::for_error(&err));
+            }
+        }
+    }
+
+    let dimension = converted[0].dimension();
+    if let Some(other) = converted.iter().find(|value| value.dimension() != dimension) {
+        eprintln!("error: can't show {} in a table of {} units", other, dimension.name());
+        return ExitCode::from(exit::USAGE);
+    }
+    let table = match engine.units().conversion_table(dimension, &converted) {
+        Ok(table) => table,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(exit::EVALUATION);
+        }
+    };
+
+    if output.json() {
+        let rows: Vec<Vec<f64>> = table.rows.iter().map(|row| row.iter().map(|value| value.value()).collect()).collect();
+        output::print(json!({ "dimension": dimension.name(), "units": table.units, "rows": rows }));
+        return ExitCode::SUCCESS;
+    }
+    output::print(table.format(engine.format_options()));
+    ExitCode::SUCCESS
+}
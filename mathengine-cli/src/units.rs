@@ -0,0 +1,60 @@
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use mathengine::{DimensionType, MathEngine, UnitNames};
+use serde_json::json;
+
+use crate::exit;
+use crate::output::{self, Output};
+
+/// List the units the engine knows, with their other spellings, grouped by
+/// dimension. `dimension` narrows the list to one, like `length`.
+pub fn list(engine: &MathEngine, dimension: Option<&str>, output: &Output) -> ExitCode {
+    let capabilities = engine.capabilities();
+    let dimensions: Vec<_> = match dimension {
+        Some(name) => match capabilities.dimensions.iter().find(|d| d.name().eq_ignore_ascii_case(name)) {
+            Some(dimension) => vec![*dimension],
+            None => {
+                let known: Vec<_> = capabilities.dimensions.iter().map(|d| d.name()).collect();
+                eprintln!("error: unknown dimension '{}', expected one of: {}", name, known.join(", "));
+                return ExitCode::from(exit::USAGE);
+            }
+        },
+        None => capabilities.dimensions.clone(),
+    };
+
+    let units = |dimension| capabilities.units.iter().filter(move |unit| unit.dimension == dimension);
+    if output.json() {
+        let listed: Vec<_> = dimensions
+            .iter()
+            .flat_map(|dimension| units(*dimension))
+            .map(|unit| json!({ "unit": unit.unit, "dimension": unit.dimension.name(), "aliases": unit.aliases }))
+            .collect();
+        output::print(serde_json::Value::from(listed));
+        return ExitCode::SUCCESS;
+    }
+
+    let listed = dimensions.iter().map(|dimension| (*dimension, units(*dimension).collect()));
+    match write_list(&mut io::stdout().lock(), listed) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => ExitCode::from(output::write_failed(&err)),
+    }
+}
+
+// Each dimension's units in a column, with their other names beside them
+fn write_list<'a>(
+    out: &mut impl Write,
+    dimensions: impl Iterator<Item = (DimensionType, Vec<&'a UnitNames>)>,
+) -> io::Result<()> {
+    for (i, (dimension, listed)) in dimensions.enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{}:", dimension.name())?;
+        let width = listed.iter().map(|unit| unit.unit.chars().count()).max().unwrap_or(0);
+        for unit in listed {
+            writeln!(out, "  {:width$}  {}", unit.unit, unit.aliases.join(", "), width = width)?;
+        }
+    }
+    Ok(())
+}
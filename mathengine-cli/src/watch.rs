@@ -0,0 +1,42 @@
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use mathengine::{MathEngine, Session};
+
+use crate::output::Output;
+use crate::script;
+
+// How often the file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Run a file like `run`, then again each time it's saved, until
+/// interrupted. Each run starts from a fresh session, so deleted lines leave
+/// nothing behind. On a terminal the screen is cleared between runs;
+/// otherwise they're separated by a blank line.
+pub fn run(path: &str, engine: &MathEngine, output: &Output) -> ! {
+    let clear = io::stdout().is_terminal();
+    let mut last = None;
+    loop {
+        let modified = modified(path);
+        if last != Some(modified) {
+            if clear {
+                print!("\x1b[2J\x1b[H");
+                let _ = io::stdout().flush();
+            } else if last.is_some() {
+                println!();
+            }
+            last = Some(modified);
+            script::run(path, &mut Session::with_engine(engine.clone()), output);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// When the file was last changed, or `None` while it can't be read, like
+// during an editor's save
+fn modified(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
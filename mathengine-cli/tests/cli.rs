@@ -0,0 +1,205 @@
+//! Runs the `mathengine` binary the way a shell would, checking what it
+//! prints and the status it exits with.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Each run gets its own config, cache and state directories, so neither the
+// user's files nor other tests' leak in. `files` are written into the
+// `mathengine` directory they share, by name
+fn directory(files: &[(&str, &str)]) -> PathBuf {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let dir = env::temp_dir().join(format!("mathengine-cli-{}-{}", std::process::id(), run));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("mathengine")).unwrap();
+    for (name, contents) in files {
+        fs::write(dir.join("mathengine").join(name), contents).unwrap();
+    }
+    dir
+}
+
+fn run_with_config(config: Option<&str>, args: &[&str]) -> Output {
+    let files: Vec<(&str, &str)> = config.map(|config| ("config.toml", config)).into_iter().collect();
+    run_with_files(&files, args)
+}
+
+fn run_with_files(files: &[(&str, &str)], args: &[&str]) -> Output {
+    let dir = directory(files);
+    let output = Command::new(env!("CARGO_BIN_EXE_mathengine"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", &dir)
+        .env("XDG_CACHE_HOME", &dir)
+        .env("XDG_STATE_HOME", &dir)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    let _ = fs::remove_dir_all(&dir);
+    output
+}
+
+fn run(args: &[&str]) -> Output {
+    run_with_config(None, args)
+}
+
+// Run with `input` piped to stdin, like `echo ... | mathengine`
+fn run_with_stdin(input: &str, args: &[&str]) -> Output {
+    let dir = directory(&[]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mathengine"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", &dir)
+        .env("XDG_CACHE_HOME", &dir)
+        .env("XDG_STATE_HOME", &dir)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let _ = fs::remove_dir_all(&dir);
+    output
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).trim_end().to_string()
+}
+
+fn status(output: &Output) -> i32 {
+    output.status.code().unwrap()
+}
+
+#[test]
+fn test_words_are_joined() {
+    assert_eq!(stdout(&run(&["2 + 3 * 4"])), "14");
+    assert_eq!(stdout(&run(&["10", "ft", "to", "m"])), "3.048m");
+    assert_eq!(stdout(&run(&["x = 5m; x * 2; x to cm"])), "5m\n10m\n500cm");
+    // Negative numbers aren't options, and `--` ends them
+    assert_eq!(stdout(&run(&["-5", "+", "2"])), "-3");
+    assert_eq!(stdout(&run(&["--", "--5"])), "5");
+}
+
+#[test]
+fn test_stdin_lines_share_a_session() {
+    let output = run_with_stdin("x = 3\nx * 2\n\nf(n) = n + x\nf(1)\n", &[]);
+    assert_eq!((status(&output), stdout(&output)), (0, "3\n6\n4".to_string()));
+    // A failed line is reported with its number, and the rest still run
+    let output = run_with_stdin("y = 2\n1 / 0\ny\n", &["-"]);
+    assert_eq!((status(&output), stdout(&output)), (5, "2\n2".to_string()));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("line 2: "));
+}
+
+#[test]
+fn test_options() {
+    assert_eq!(stdout(&run(&["--precision", "2", "10m to ft"])), "32.81ft");
+    assert_eq!(stdout(&run(&["--precision=2", "10m to ft"])), "32.81ft");
+    assert_eq!(stdout(&run(&["--raw", "10 ft to m"])), "3.048");
+    assert_eq!(stdout(&run(&["--base", "hex", "255 + 1"])), "0x100");
+    assert_eq!(stdout(&run(&["--sig-figs", "auto", "1.20m * 3"])), "3.60m");
+    assert_eq!(stdout(&run(&["--explain", "1m + 50cm"])), "steps:\n  1m + 50cm → 1m + 0.5m → 1.5m\n1.5m");
+}
+
+#[test]
+fn test_locale() {
+    assert_eq!(stdout(&run(&["--locale", "de", "2 Fuß + 0,5 Fuß"])), "0,762m");
+    assert_eq!(stdout(&run(&["--locale=de-DE", "--precision", "1", "1,25 + 1"])), "2,3");
+    assert_eq!(status(&run(&["--locale", "xx", "1"])), 2);
+}
+
+#[test]
+fn test_exit_statuses() {
+    let cases: [(&[&str], i32); 9] = [
+        (&["1 + 1"], 0),
+        (&["--bogus", "1"], 2),
+        (&["--precision", "many", "1"], 2),
+        (&["--precision"], 2),
+        (&["--raw", "--json", "1"], 2),
+        (&["2 $ 3"], 3),
+        (&["2 + * 3"], 4),
+        (&["1 / 0"], 5),
+        (&["--strict", "10m + 2"], 6),
+    ];
+    for (args, expected) in cases {
+        let output = run(args);
+        assert_eq!(status(&output), expected, "{:?}", args);
+        if expected != 0 {
+            assert!(output.stdout.is_empty(), "{:?}", args);
+            assert!(!output.stderr.is_empty(), "{:?}", args);
+        }
+    }
+}
+
+#[test]
+fn test_config_fills_in_options() {
+    let config = "precision = 3\nlocale = \"de-DE\"\nstrict = true\n";
+    assert_eq!(stdout(&run_with_config(Some(config), &["1 / 3"])), "0,333");
+    assert_eq!(status(&run_with_config(Some(config), &["10m + 2"])), 6);
+}
+
+#[test]
+fn test_command_line_overrides_config() {
+    let config = "precision = 3\nlocale = \"de-DE\"\nstrict = true\n";
+    assert_eq!(stdout(&run_with_config(Some(config), &["--precision", "1", "1 / 3"])), "0,3");
+    assert_eq!(stdout(&run_with_config(Some(config), &["--locale", "en", "1.5 + 1"])), "2.5");
+    let output = run_with_config(Some(config), &["--no-strict", "10m + 2"]);
+    assert_eq!((status(&output), stdout(&output)), (0, "12m".to_string()));
+    // The last of the two wins
+    assert_eq!(status(&run_with_config(None, &["--no-strict", "--strict", "10m + 2"])), 6);
+}
+
+#[test]
+fn test_bad_config() {
+    let output = run_with_config(Some("precision = lots\n"), &["1 + 1"]);
+    assert_eq!(status(&output), 1);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("config.toml:1"));
+}
+
+#[test]
+fn test_convert() {
+    assert_eq!(stdout(&run(&["convert", "10", "ft", "m"])), "3.048m");
+    assert_eq!(stdout(&run(&["convert", "-40", "F", "to", "C"])), "-40C");
+    assert_eq!(stdout(&run(&["--locale", "de", "convert", "2,5", "m", "cm"])), "250cm");
+    assert_eq!(stdout(&run(&["convert", "2+3", "ft", "in"])), "60in");
+    assert_eq!(status(&run(&["convert", "2m", "m", "ft"])), 2);
+    assert_eq!(status(&run(&["convert", "10", "m", "C"])), 5);
+
+    // Currencies go through the cached rates, and units through the aliases
+    let fetched = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let rates = format!(r#"{{"source": "test", "fetched": {}, "base": "usd", "rates": {{"usd": 1.0, "eur": 0.5}}}}"#, fetched);
+    let files = [
+        ("config.toml", "units = \"units.toml\"\n"),
+        ("units.toml", "span = ft\n"),
+        ("rates.json", rates.as_str()),
+    ];
+    assert_eq!(stdout(&run_with_files(&files, &["convert", "10", "USD", "EUR"])), "5eur");
+    assert_eq!(stdout(&run_with_files(&files, &["convert", "2", "span", "in"])), "24in");
+}
+
+#[test]
+fn test_closed_stdout_is_not_an_error() {
+    for args in [&["units"][..], &["2 + 2"], &["--json", "units"]] {
+        let dir = directory(&[]);
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mathengine"))
+            .args(args)
+            .env("XDG_CONFIG_HOME", &dir)
+            .env("XDG_CACHE_HOME", &dir)
+            .env("XDG_STATE_HOME", &dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // Like `| head -0`, the reader is gone before anything is written
+        drop(child.stdout.take());
+        let output = child.wait_with_output().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(status(&output), 0, "{:?}", args);
+        assert!(output.stderr.is_empty(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+}
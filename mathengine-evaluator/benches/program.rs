@@ -0,0 +1,43 @@
+//! Times running each formula many times with a changing variable, walking
+//! the tree against running it compiled. Run with `cargo bench -p
+//! mathengine-evaluator`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mathengine_evaluator::{compile, evaluate_with_context, EvaluationContext};
+use mathengine_lexer::Lexer;
+use mathengine_parser::Parser;
+
+const RUNS: i128 = 100_000;
+
+fn time(mut run: impl FnMut(&EvaluationContext)) -> Duration {
+    let mut context = EvaluationContext::default();
+    let started = Instant::now();
+    for x in 0..RUNS {
+        context.set_variable("x", x);
+        run(&context);
+    }
+    started.elapsed() / RUNS as u32
+}
+
+fn main() {
+    let formulas = [
+        "x^2 + 2 * x - 1",
+        "(x * 3.5 + 2m) to cm",
+        "x * 1 usd + 5 usd",
+        "if(x > 50000, sqrt(x), x / 2) + max(x, 3, 7)",
+        "mean([x, x + 1, x + 2]) * 10%",
+    ];
+    for source in formulas {
+        let ast = Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+        let program = compile(&ast).unwrap();
+        let tree = time(|context| {
+            black_box(evaluate_with_context(&ast, context).unwrap());
+        });
+        let compiled = time(|context| {
+            black_box(program.run(context).unwrap());
+        });
+        println!("{:<48} tree {:>8.2?}  compiled {:>8.2?}", source, tree, compiled);
+    }
+}
@@ -0,0 +1,455 @@
+//! Compile expressions to a flat instruction sequence for repeated evaluation.
+//!
+//! Walking the tree re-parses unit names and re-discovers the shape of the
+//! expression on every call. A [`Program`] does that work once, so evaluating
+//! the same formula many times with different variables only runs a tight
+//! loop over a stack.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use mathengine_lexer::{Operation, Span};
+use mathengine_parser::{
+    Expression,
+    types::{ConversionError, Number, UnitValue, Value},
+};
+
+use crate::{
+    EvalError, EvaluationContext, TIMEOUT_CHECK_INTERVAL, apply_binary, apply_percentage,
+    apply_unary, call_function, convert, currency, functions, hundred, is_fixed_unit, is_provided,
+    money_in, number_literal, percentage_operands, pop, variable,
+};
+
+/// A compiled expression, ready to run against any [`EvaluationContext`].
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{compile, EvaluationContext};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::Parser;
+///
+/// let tokens = Lexer::new("x^2 + 1").tokenize().unwrap();
+/// let program = compile(&Parser::new(tokens).parse().unwrap()).unwrap();
+///
+/// let mut context = EvaluationContext::default();
+/// for x in 1..=3 {
+///     context.set_variable("x", x as i128);
+///     println!("{}", program.run(&context).unwrap());
+/// }
+/// # assert_eq!(program.run(&context).unwrap().to_string(), "10");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    // Source span of the node each instruction came from, for error reporting
+    spans: Vec<Option<Span>>,
+    // Room for the most values the stack holds at once
+    stack: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    // A value known at compile time
+    Push(Value),
+    // An amount of money, kept in the number mode it runs in
+    Money(UnitValue),
+    // A decimal literal, whose representation depends on the number mode
+    Literal { value: f64, digits: Option<Arc<str>> },
+    // A value in a unit outside the unit tables and not a currency, which
+    // is an error unless a dimension in the context provides it
+    Unit {
+        value: f64,
+        unit: Arc<str>,
+        error: ConversionError,
+    },
+    Load(String),
+    Binary(Operation),
+    // Percent-scaled `+`, `-` and `of`, with the raw percentage on the stack
+    Percentage(Operation),
+    Unary(Operation),
+    Percent,
+    Factorial,
+    Convert(Expression),
+    Call { name: String, args: usize },
+    List(usize),
+    // Pop the condition and jump to the target when it is false
+    JumpUnless(usize),
+    Jump(usize),
+}
+
+impl Instruction {
+    fn is_operation(&self) -> bool {
+        !matches!(
+            self,
+            Instruction::Push(_)
+                | Instruction::Money(_)
+                | Instruction::Literal { .. }
+                | Instruction::Unit { .. }
+                | Instruction::Load(_)
+                | Instruction::JumpUnless(_)
+                | Instruction::Jump(_)
+        )
+    }
+}
+
+// Compilation mirrors evaluation: visit children, then emit the instruction
+// combining them. `if` emits its jumps as placeholders and patches them once
+// the branch they skip has been emitted.
+enum Task<'a> {
+    Visit(&'a Expression),
+    Emit(Instruction, Option<Span>),
+    JumpUnless,
+    Jump,
+    Patch,
+}
+
+/// Compile an expression into a [`Program`].
+///
+/// Compilation fails only for malformed `if` calls; everything else is
+/// reported when the program runs, just like [`evaluate`](crate::evaluate).
+pub fn compile(expr: &Expression) -> Result<Program, EvalError> {
+    let mut instructions = Vec::new();
+    let mut spans = Vec::new();
+    let mut tasks = vec![Task::Visit(expr)];
+    // Indices of emitted jumps still waiting for their target
+    let mut pending = Vec::new();
+
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Visit(expr) => match expr {
+                Expression::Number(_)
+                | Expression::Decimal { .. }
+                | Expression::Integer(_)
+                | Expression::Bool(_)
+                | Expression::UnitValue { .. }
+                | Expression::Unit(_) => tasks.push(Task::Emit(leaf(expr), None)),
+                Expression::Binary {
+                    op: Operation::Convert,
+                    left,
+                    right,
+                    span,
+                } => {
+                    tasks.push(Task::Emit(Instruction::Convert((**right).clone()), Some(*span)));
+                    tasks.push(Task::Visit(left));
+                }
+                Expression::Binary {
+                    op,
+                    left,
+                    right,
+                    span,
+                } => {
+                    let instruction = match percentage_operands(op, left, right) {
+                        Some(_) => Instruction::Percentage(op.clone()),
+                        None => Instruction::Binary(op.clone()),
+                    };
+                    let (left, right) = percentage_operands(op, left, right).unwrap_or((left, right));
+                    tasks.push(Task::Emit(instruction, Some(*span)));
+                    tasks.push(Task::Visit(right));
+                    tasks.push(Task::Visit(left));
+                }
+                Expression::Unary { op, operand, span } => {
+                    tasks.push(Task::Emit(Instruction::Unary(op.clone()), Some(*span)));
+                    tasks.push(Task::Visit(operand));
+                }
+                Expression::Percent(operand) => {
+                    tasks.push(Task::Emit(Instruction::Percent, None));
+                    tasks.push(Task::Visit(operand));
+                }
+                Expression::Factorial { operand, span } => {
+                    tasks.push(Task::Emit(Instruction::Factorial, Some(*span)));
+                    tasks.push(Task::Visit(operand));
+                }
+                Expression::FunctionCall { name, args, span } if name == "if" => {
+                    let [condition, then, otherwise] = args.as_slice() else {
+                        let error = EvalError::WrongArgumentCount {
+                            function: name.clone(),
+                            expected: 3,
+                            found: args.len(),
+                        };
+                        return Err(error.at(Some(*span)));
+                    };
+                    tasks.push(Task::Patch);
+                    tasks.push(Task::Visit(otherwise));
+                    tasks.push(Task::Patch);
+                    tasks.push(Task::Jump);
+                    tasks.push(Task::Visit(then));
+                    tasks.push(Task::JumpUnless);
+                    tasks.push(Task::Visit(condition));
+                }
+                Expression::FunctionCall { name, args, span } => {
+                    let call = Instruction::Call {
+                        name: name.clone(),
+                        args: args.len(),
+                    };
+                    tasks.push(Task::Emit(call, Some(*span)));
+                    tasks.extend(args.iter().rev().map(Task::Visit));
+                }
+                Expression::List(items) => {
+                    tasks.push(Task::Emit(Instruction::List(items.len()), None));
+                    tasks.extend(items.iter().rev().map(Task::Visit));
+                }
+            },
+            Task::Emit(instruction, span) => {
+                instructions.push(instruction);
+                spans.push(span);
+            }
+            Task::JumpUnless => {
+                pending.push(instructions.len());
+                instructions.push(Instruction::JumpUnless(0));
+                spans.push(None);
+            }
+            Task::Jump => {
+                // The conditional jump skips past this one into the else branch
+                let skip_then = pending.pop().expect("jump emitted without a condition");
+                pending.push(instructions.len());
+                instructions.push(Instruction::Jump(0));
+                spans.push(None);
+                pending.push(skip_then);
+            }
+            Task::Patch => {
+                let target = instructions.len();
+                match pending.pop().map(|index| &mut instructions[index]) {
+                    Some(Instruction::JumpUnless(to) | Instruction::Jump(to)) => *to = target,
+                    _ => unreachable!("patch without a pending jump"),
+                }
+            }
+        }
+    }
+
+    let stack = stack_size(&instructions);
+    Ok(Program {
+        instructions,
+        spans,
+        stack,
+    })
+}
+
+// How deep the stack gets, going through the instructions in order. Both
+// branches of an `if` are counted, so this can be more than is needed but
+// never less
+fn stack_size(instructions: &[Instruction]) -> usize {
+    let mut depth = 0usize;
+    let mut deepest = 0;
+    for instruction in instructions {
+        depth = match instruction {
+            Instruction::Push(_)
+            | Instruction::Money(_)
+            | Instruction::Literal { .. }
+            | Instruction::Unit { .. }
+            | Instruction::Load(_) => depth + 1,
+            Instruction::Binary(_) | Instruction::Percentage(_) | Instruction::JumpUnless(_) => depth.saturating_sub(1),
+            Instruction::Call { args: len, .. } | Instruction::List(len) => (depth + 1).saturating_sub(*len),
+            _ => depth,
+        };
+        deepest = deepest.max(depth);
+    }
+    deepest
+}
+
+fn leaf(expr: &Expression) -> Instruction {
+    match expr {
+        Expression::Number(n) => Instruction::Literal { value: *n, digits: None },
+        Expression::Decimal { value, digits } => Instruction::Literal {
+            value: *value,
+            digits: Some(digits.clone()),
+        },
+        Expression::Integer(i) => Instruction::Push(Value::Number(Number::from(*i))),
+        Expression::Bool(b) => Instruction::Push(Value::Bool(*b)),
+        // Units are looked up once here rather than on every run
+        Expression::UnitValue { value, unit } => match UnitValue::new(*value, unit.clone()) {
+            Ok(unit_value) => Instruction::Push(Value::UnitValue(unit_value)),
+            Err(ConversionError::UnknownUnit(name)) if currency::is_currency(&name) => {
+                Instruction::Money(UnitValue::named(*value, unit.clone()))
+            }
+            Err(ConversionError::UnknownUnit(name)) if is_fixed_unit(&name) => {
+                Instruction::Push(Value::UnitValue(UnitValue::named(*value, unit.clone())))
+            }
+            // Whether this is a unit of a provided dimension depends on the
+            // context the program runs in
+            Err(error) => Instruction::Unit {
+                value: *value,
+                unit: unit.clone(),
+                error,
+            },
+        },
+        Expression::Unit(name) => Instruction::Load(name.clone()),
+        _ => unreachable!("only leaf expressions compile to a single instruction"),
+    }
+}
+
+impl Program {
+    /// Run the program with the options and variables in `context`.
+    ///
+    /// Operation limits, timeouts and cancellation apply; node and depth limits are about
+    /// the shape of the tree and aren't checked once it's compiled.
+    pub fn run(&self, context: &EvaluationContext) -> Result<Value, EvalError> {
+        let limits = &context.limits;
+        let started = limits.timeout.map(|_| Instant::now());
+        let mut values: Vec<Value> = Vec::with_capacity(self.stack);
+        let mut operations = 0usize;
+        let mut steps = 0usize;
+        let mut pc = 0;
+
+        while let Some(instruction) = self.instructions.get(pc) {
+            let pc_before = pc;
+            pc += 1;
+            steps += 1;
+            if let (Some(timeout), Some(started)) = (limits.timeout, started) {
+                if steps % TIMEOUT_CHECK_INTERVAL == 0 && started.elapsed() > timeout {
+                    return Err(EvalError::Timeout { limit: timeout });
+                }
+            }
+            if let Some(cancel) = &limits.cancel {
+                if steps % TIMEOUT_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
+                    return Err(EvalError::Cancelled);
+                }
+            }
+            // Count the same operations the tree evaluator does: everything
+            // that combines values, but not loads or control flow
+            if let (Some(limit), true) = (limits.max_operations, instruction.is_operation()) {
+                operations += 1;
+                if operations > limit {
+                    return Err(EvalError::OperationLimitExceeded { limit });
+                }
+            }
+
+            let result = Self::step(instruction, &mut values, &mut pc, context);
+            if let Some(value) = result.map_err(|e| e.at(self.spans[pc_before]))? {
+                values.push(value);
+            }
+        }
+
+        Ok(pop(&mut values))
+    }
+
+    // Execute one instruction, returning the value it produces or `None` for
+    // control flow that only moves the program counter
+    fn step(
+        instruction: &Instruction,
+        values: &mut Vec<Value>,
+        pc: &mut usize,
+        context: &EvaluationContext,
+    ) -> Result<Option<Value>, EvalError> {
+        let mode = context.number_mode;
+        let result = match instruction {
+            Instruction::Push(value) => value.clone(),
+            Instruction::Money(amount) => Value::UnitValue(money_in(amount.clone(), mode)),
+            Instruction::Literal { value, digits } => Value::Number(number_literal(*value, digits.as_deref(), mode)),
+            Instruction::Unit { value, unit, error } => match error {
+                ConversionError::UnknownUnit(name) if is_provided(name, context) => {
+                    Value::UnitValue(UnitValue::named(*value, unit.clone()))
+                }
+                error => return Err(error.clone().into()),
+            },
+            Instruction::Load(name) => variable(name, context)?,
+            Instruction::Binary(op) => {
+                let right = pop(values);
+                let left = pop(values);
+                apply_binary(op, left, right, context)?
+            }
+            Instruction::Percentage(op) => {
+                let right = pop(values);
+                let left = pop(values);
+                apply_percentage(op, left, right, context)?
+            }
+            Instruction::Unary(op) => apply_unary(op, pop(values))?,
+            Instruction::Percent => apply_binary(&Operation::Divide, pop(values), hundred(), context)?,
+            Instruction::Factorial => functions::factorial(pop(values), context.gamma_factorial)?,
+            Instruction::Convert(target) => convert(pop(values), target, context)?,
+            Instruction::Call { name, args } => {
+                let args = values.split_off(values.len() - args);
+                match context.functions.get(name) {
+                    Some(function) => call_function(name, function, args, context)?,
+                    None => functions::call(name, args, mode)?,
+                }
+            }
+            Instruction::List(len) => Value::List(values.split_off(values.len() - len)),
+            Instruction::JumpUnless(target) => {
+                if !pop(values).is_truthy() {
+                    *pc = *target;
+                }
+                return Ok(None);
+            }
+            Instruction::Jump(target) => {
+                *pc = *target;
+                return Ok(None);
+            }
+        };
+        Ok(Some(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluate_with_context;
+    use crate::tests::parse;
+
+    #[test]
+    fn test_compiled_program_matches_tree_evaluation() {
+        let mut context = EvaluationContext::default();
+        context.set_variable("x", 4i128);
+        let sources = [
+            "x^2 + 2 * x - 1",
+            "(1m + 50cm) to cm",
+            "200 + 10%",
+            "10% of x",
+            "if(x > 3, if(x > 10, 1, 2), 1 / 0)",
+            "mean([x, 5, 9])",
+            "-3! + sqrt(9 m^2) * 2",
+            "not x < 2 and true",
+            "1 / 0",
+            "y + 1",
+            "2 * (x - 4)! + sqrt(1, 2)",
+        ];
+        for source in sources {
+            // Parse with spans so the errors are compared position and all
+            let tokens = mathengine_lexer::Lexer::new(source).tokenize_with_spans().unwrap();
+            let ast = mathengine_parser::Parser::with_spans(tokens).parse().unwrap();
+            let tree = evaluate_with_context(&ast, &context).map(|v| v.to_string());
+            let compiled = compile(&ast).unwrap().run(&context).map(|v| v.to_string());
+            assert_eq!(tree, compiled, "{}", source);
+        }
+    }
+    #[test]
+    fn test_units_are_resolved_once() {
+        #[derive(Debug)]
+        struct Sizes;
+
+        impl crate::DimensionProvider for Sizes {
+            fn name(&self) -> &str {
+                "size"
+            }
+
+            fn units(&self) -> Vec<String> {
+                vec!["eu".to_string()]
+            }
+
+            fn convert(&self, value: f64, _from: &str, _to: &str) -> Option<f64> {
+                Some(value)
+            }
+        }
+
+        let program = compile(&parse("2m + 5 USD * 0")).unwrap();
+        assert!(program.instructions.iter().all(|instruction| !matches!(instruction, Instruction::Unit { .. })));
+        assert!(program.run(&EvaluationContext::default()).is_err());
+        // A unit a provider might have is looked up in the context it runs in
+        let program = compile(&parse("42eu")).unwrap();
+        let sizes = EvaluationContext {
+            dimensions: vec![Arc::new(Sizes)],
+            ..EvaluationContext::default()
+        };
+        assert_eq!(program.run(&sizes).unwrap().to_string(), "42eu");
+        assert!(matches!(program.run(&EvaluationContext::default()), Err(EvalError::UnknownUnit { .. })));
+    }
+
+    #[test]
+    fn test_stack_is_sized_up_front() {
+        for (source, stack) in [("1", 1), ("1 + 2 * 3", 3), ("max(1, 2, 3) + 4", 3), ("[1, [2, 3]]", 3)] {
+            assert_eq!(compile(&parse(source)).unwrap().stack, stack, "{}", source);
+        }
+        // Only one branch of an `if` runs, but both are counted
+        assert_eq!(compile(&parse("if(1 > 0, 2, 3) + 4")).unwrap().stack, 3);
+    }
+}
@@ -0,0 +1,778 @@
+//! Static dimensional analysis of expression trees.
+//!
+//! [`check`] works out the dimension an expression evaluates to without
+//! evaluating it, so unit mismatches like `5m + 3kg` or `1m to C` can be
+//! reported while the expression is still being typed.
+
+use std::fmt;
+
+use mathengine_lexer::{Operation, Span};
+use mathengine_parser::{
+    Expression,
+    types::{DimensionType, UnitValue},
+};
+
+use crate::{comparison, percentage_operands};
+
+/// The dimension of the value an expression evaluates to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DimensionSignature {
+    /// A plain number
+    Dimensionless,
+    /// A boolean, from a comparison or logical operator
+    Boolean,
+    /// A value in `dimension` raised to `exponent`, like `m^2` for an area
+    Quantity {
+        dimension: DimensionType,
+        exponent: i32,
+    },
+    /// A value in units of several dimensions, each raised to its power,
+    /// like `[(Length, 1), (Time, -1)]` for a speed. Dimensions are listed
+    /// once, in the order [`DimensionType`] has them
+    Derived(Vec<(DimensionType, i32)>),
+    /// A list whose items all have the given signature
+    List(Box<DimensionSignature>),
+    /// Only known once evaluated: variables, unknown functions, and
+    /// expressions whose dimension depends on runtime values
+    Unresolved,
+}
+
+impl DimensionSignature {
+    fn quantity(dimension: DimensionType, exponent: i32) -> DimensionSignature {
+        if exponent == 0 {
+            DimensionSignature::Dimensionless
+        } else {
+            DimensionSignature::Quantity {
+                dimension,
+                exponent,
+            }
+        }
+    }
+
+    // Signature of `unit` as written, e.g. "cm^2" or "km/h"
+    fn of_unit(unit: &str) -> DimensionSignature {
+        let unit = UnitValue::named(1.0, unit.to_string());
+        match unit.dimension() {
+            DimensionType::Derived => DimensionSignature::Derived(unit.signature()),
+            dimension => DimensionSignature::quantity(dimension, unit.exponent()),
+        }
+    }
+
+    // The signature of a product of powers of dimensions, which may have
+    // cancelled down to one dimension or none
+    fn product(mut dimensions: Vec<(DimensionType, i32)>) -> DimensionSignature {
+        dimensions.retain(|(_, exponent)| *exponent != 0);
+        dimensions.sort_by_key(|(dimension, _)| *dimension as u8);
+        match dimensions.as_slice() {
+            [] => DimensionSignature::Dimensionless,
+            [(dimension, exponent)] => DimensionSignature::quantity(*dimension, *exponent),
+            _ => DimensionSignature::Derived(dimensions),
+        }
+    }
+
+    // The powers of the dimensions a quantity is in, if they can be
+    // combined with other dimensions
+    fn dimensions(&self) -> Option<Vec<(DimensionType, i32)>> {
+        match self {
+            DimensionSignature::Quantity {
+                dimension,
+                exponent,
+            } if dimension.is_linear() => Some(vec![(*dimension, *exponent)]),
+            DimensionSignature::Derived(dimensions) => Some(dimensions.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DimensionSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DimensionSignature::Dimensionless => write!(f, "dimensionless"),
+            DimensionSignature::Boolean => write!(f, "boolean"),
+            DimensionSignature::Quantity {
+                dimension,
+                exponent,
+            } => {
+                let name = match dimension {
+                    DimensionType::Unknown => "unknown unit",
+                    known => known.name(),
+                };
+                if *exponent == 1 {
+                    write!(f, "{}", name)
+                } else {
+                    write!(f, "{}^{}", name, exponent)
+                }
+            }
+            DimensionSignature::Derived(dimensions) => {
+                for (i, (dimension, exponent)) in dimensions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "*")?;
+                    }
+                    match exponent {
+                        1 => write!(f, "{}", dimension.name())?,
+                        _ => write!(f, "{}^{}", dimension.name(), exponent)?,
+                    }
+                }
+                Ok(())
+            }
+            DimensionSignature::List(items) => write!(f, "list of {}", items),
+            DimensionSignature::Unresolved => write!(f, "unresolved"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    IncompatibleDimensions {
+        operation: String,
+        left: DimensionSignature,
+        right: DimensionSignature,
+    },
+    InvalidConversion {
+        from: DimensionSignature,
+        to_unit: String,
+    },
+    InvalidOperand {
+        operation: String,
+        operand: DimensionSignature,
+    },
+    /// An error found in the sub-expression at `span` in the source
+    Spanned {
+        span: Span,
+        error: Box<TypeError>,
+    },
+}
+
+impl TypeError {
+    /// The source span of the sub-expression that failed to check, when the
+    /// expression was parsed with spans.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            TypeError::Spanned { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, without its location.
+    pub fn without_span(&self) -> &TypeError {
+        match self {
+            TypeError::Spanned { error, .. } => error.without_span(),
+            other => other,
+        }
+    }
+
+    fn at(self, span: Option<Span>) -> TypeError {
+        match span {
+            Some(span) if !span.is_empty() && self.span().is_none() => TypeError::Spanned {
+                span,
+                error: Box::new(self),
+            },
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::IncompatibleDimensions {
+                operation,
+                left,
+                right,
+            } => {
+                write!(f, "Cannot {} {} and {}", operation, left, right)
+            }
+            TypeError::InvalidConversion { from, to_unit } => {
+                write!(f, "Cannot convert {} to '{}'", from, to_unit)
+            }
+            TypeError::InvalidOperand { operation, operand } => {
+                write!(f, "Cannot apply '{}' to {}", operation, operand)
+            }
+            TypeError::Spanned { span, error } => {
+                write!(f, "{} at position {}", error, span)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+// Post-order traversal on an explicit stack, like evaluation, so deeply
+// nested input can't overflow the call stack
+enum Task<'a> {
+    Visit(&'a Expression),
+    Apply(&'a Expression),
+}
+
+/// Work out the dimension of an expression without evaluating it.
+///
+/// Checking follows the evaluator's rules: a plain number takes the unit of
+/// the other operand in `10m + 5`, quantities combine only within a dimension,
+/// and conversions must stay within one. Variables are [`Unresolved`], and
+/// so is anything that depends on them.
+///
+/// [`Unresolved`]: DimensionSignature::Unresolved
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{check, DimensionSignature};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{types::DimensionType, Parser};
+///
+/// let parse = |input| Parser::new(Lexer::new(input).tokenize().unwrap()).parse().unwrap();
+///
+/// assert_eq!(
+///     check(&parse("3m * 4ft")).unwrap(),
+///     DimensionSignature::Quantity { dimension: DimensionType::Length, exponent: 2 }
+/// );
+/// assert!(check(&parse("5m + 3kg")).is_err());
+/// assert!(check(&parse("1m to C")).is_err());
+/// ```
+pub fn check(expr: &Expression) -> Result<DimensionSignature, TypeError> {
+    let mut tasks = vec![Task::Visit(expr)];
+    let mut signatures: Vec<DimensionSignature> = Vec::new();
+
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Visit(expr) => {
+                tasks.push(Task::Apply(expr));
+                match expr {
+                    Expression::Binary {
+                        op: Operation::Convert,
+                        left,
+                        ..
+                    } => tasks.push(Task::Visit(left)),
+                    Expression::Binary { left, right, .. } => {
+                        tasks.push(Task::Visit(right));
+                        tasks.push(Task::Visit(left));
+                    }
+                    Expression::Unary { operand, .. }
+                    | Expression::Factorial { operand, .. }
+                    | Expression::Percent(operand) => tasks.push(Task::Visit(operand)),
+                    Expression::FunctionCall { args: items, .. } | Expression::List(items) => {
+                        tasks.extend(items.iter().rev().map(Task::Visit))
+                    }
+                    _ => {}
+                }
+            }
+            Task::Apply(expr) => {
+                let signature = apply(expr, &mut signatures).map_err(|e| e.at(expr.span()))?;
+                signatures.push(signature);
+            }
+        }
+    }
+
+    Ok(signatures.pop().expect("checked expression left no signature"))
+}
+
+fn apply(
+    expr: &Expression,
+    signatures: &mut Vec<DimensionSignature>,
+) -> Result<DimensionSignature, TypeError> {
+    let signature = match expr {
+        Expression::Number(_) | Expression::Decimal { .. } | Expression::Integer(_) => DimensionSignature::Dimensionless,
+        Expression::Bool(_) => DimensionSignature::Boolean,
+        Expression::UnitValue { unit, .. } => DimensionSignature::of_unit(unit),
+        Expression::Unit(_) => DimensionSignature::Unresolved,
+        // Scaling by a hundredth keeps the dimension
+        Expression::Percent(_) => pop(signatures),
+        Expression::Binary {
+            op: Operation::Convert,
+            right,
+            ..
+        } => convert(pop(signatures), right)?,
+        Expression::Binary {
+            op, left, right, ..
+        } => {
+            let r = pop(signatures);
+            let l = pop(signatures);
+            // Adding a percentage scales the left side, keeping its dimension
+            if let Operation::Add | Operation::Subtract = op {
+                if percentage_operands(op, left, right).is_some() {
+                    return Ok(l);
+                }
+            }
+            if let Operation::Of = op {
+                if percentage_operands(op, left, right).is_none() {
+                    return Err(TypeError::InvalidOperand {
+                        operation: "of".to_string(),
+                        operand: l,
+                    });
+                }
+            }
+            binary(op, l, r, integer_literal(right))?
+        }
+        Expression::Unary { op, .. } => {
+            let operand = pop(signatures);
+            match op {
+                Operation::Not => DimensionSignature::Boolean,
+                _ => unary("negate", operand, |s| match s {
+                    DimensionSignature::Boolean => None,
+                    other => Some(other),
+                })?,
+            }
+        }
+        Expression::Factorial { .. } => unary("factorial", pop(signatures), |s| match s {
+            DimensionSignature::Dimensionless | DimensionSignature::Unresolved => Some(s),
+            _ => None,
+        })?,
+        Expression::FunctionCall { name, args, .. } => {
+            let args = signatures.split_off(signatures.len() - args.len());
+            function(name, args)?
+        }
+        Expression::List(items) => {
+            let items = signatures.split_off(signatures.len() - items.len());
+            DimensionSignature::List(Box::new(common("combine", items)?))
+        }
+    };
+    Ok(signature)
+}
+
+// Children are checked before their parent, so the stack always holds enough
+// signatures here
+fn pop(signatures: &mut Vec<DimensionSignature>) -> DimensionSignature {
+    signatures.pop().expect("operand missing from signature stack")
+}
+
+fn binary(
+    op: &Operation,
+    left: DimensionSignature,
+    right: DimensionSignature,
+    exponent: Option<i32>,
+) -> Result<DimensionSignature, TypeError> {
+    use DimensionSignature::*;
+
+    // Lists broadcast, so check the operation on their items
+    match (left, right) {
+        (List(l), List(r)) => Ok(List(Box::new(binary(op, *l, *r, exponent)?))),
+        (List(l), r) => Ok(List(Box::new(binary(op, *l, r, exponent)?))),
+        (l, List(r)) => Ok(List(Box::new(binary(op, l, *r, exponent)?))),
+        (l, r) => scalar(op, l, r, exponent),
+    }
+}
+
+fn scalar(
+    op: &Operation,
+    left: DimensionSignature,
+    right: DimensionSignature,
+    exponent: Option<i32>,
+) -> Result<DimensionSignature, TypeError> {
+    use DimensionSignature::*;
+
+    let incompatible = |left: &DimensionSignature, right: &DimensionSignature| {
+        TypeError::IncompatibleDimensions {
+            operation: operation_name(op).to_string(),
+            left: left.clone(),
+            right: right.clone(),
+        }
+    };
+
+    if matches!(op, Operation::And | Operation::Or) {
+        return Ok(Boolean);
+    }
+    if comparison::is_comparison(op) {
+        return match (left, right) {
+            (Boolean, Boolean) if matches!(op, Operation::Equal | Operation::NotEqual) => {
+                Ok(Boolean)
+            }
+            (l @ Boolean, r) | (l, r @ Boolean) => Err(incompatible(&l, &r)),
+            (l, r) => additive(&l, &r)
+                .map(|_| Boolean)
+                .ok_or_else(|| incompatible(&l, &r)),
+        };
+    }
+    if let (operand @ Boolean, _) | (_, operand @ Boolean) = (&left, &right) {
+        return Err(TypeError::InvalidOperand {
+            operation: operation_name(op).to_string(),
+            operand: operand.clone(),
+        });
+    }
+
+    match op {
+        // Amounts in currencies or units of provided dimensions convert with
+        // the rates and providers given at evaluation, if at all
+        Operation::Add | Operation::Subtract if unknown(&left) && left == right => Ok(left),
+        Operation::Add | Operation::Subtract => {
+            additive(&left, &right).ok_or_else(|| incompatible(&left, &right))
+        }
+        Operation::Multiply | Operation::Of => {
+            multiplicative(&left, &right, 1).ok_or_else(|| incompatible(&left, &right))
+        }
+        Operation::Divide => {
+            multiplicative(&left, &right, -1).ok_or_else(|| incompatible(&left, &right))
+        }
+        Operation::Power => match (left, right) {
+            (_, r @ Quantity { .. }) => Err(TypeError::InvalidOperand {
+                operation: operation_name(op).to_string(),
+                operand: r,
+            }),
+            // Only literal integer powers of a unit are known statically, and
+            // ones too large for an exponent are left to evaluation to reject
+            (Quantity { dimension, exponent: base }, _) => match exponent.and_then(|power| base.checked_mul(power)) {
+                Some(exponent) => Ok(DimensionSignature::quantity(dimension, exponent)),
+                None => Ok(Unresolved),
+            },
+            (Derived(dimensions), _) => {
+                let dimensions: Option<Vec<_>> = exponent.and_then(|power| {
+                    dimensions
+                        .into_iter()
+                        .map(|(dimension, base)| Some((dimension, base.checked_mul(power)?)))
+                        .collect()
+                });
+                Ok(dimensions.map_or(Unresolved, DimensionSignature::product))
+            }
+            (l, _) => Ok(l),
+        },
+        _ => Ok(Unresolved),
+    }
+}
+
+// Adding and comparing need both sides in the same dimension; a plain number
+// takes the unit of the other side
+fn additive(left: &DimensionSignature, right: &DimensionSignature) -> Option<DimensionSignature> {
+    use DimensionSignature::*;
+
+    match (left, right) {
+        (Unresolved, other) | (other, Unresolved) => Some(other.clone()),
+        // A bare number added to a quantity would be assumed to be in its unit
+        (Dimensionless, Dimensionless) => Some(Dimensionless),
+        (l @ (Quantity { .. } | Derived(_)), r) if same_dimension(l, r) => Some(l.clone()),
+        _ => None,
+    }
+}
+
+// `sign` is 1 for multiplication and -1 for division, applied to the right
+// operand's exponent
+fn multiplicative(
+    left: &DimensionSignature,
+    right: &DimensionSignature,
+    sign: i32,
+) -> Option<DimensionSignature> {
+    use DimensionSignature::*;
+
+    match (left, right) {
+        (Unresolved, _) | (_, Unresolved) => Some(Unresolved),
+        (other, Dimensionless) => Some(other.clone()),
+        (Dimensionless, Quantity { dimension, exponent }) => {
+            Some(DimensionSignature::quantity(*dimension, sign * exponent))
+        }
+        (Dimensionless, Derived(dimensions)) => Some(DimensionSignature::product(
+            dimensions.iter().map(|(dimension, exponent)| (*dimension, sign * exponent)).collect(),
+        )),
+        (
+            Quantity {
+                dimension,
+                exponent: l,
+            },
+            Quantity {
+                dimension: other,
+                exponent: r,
+            },
+        ) if dimension == other && *dimension != DimensionType::Unknown => {
+            Some(DimensionSignature::quantity(*dimension, l + sign * r))
+        }
+        // Units of different dimensions make a derived unit, like `km/h`
+        (l, r) => {
+            let mut dimensions = l.dimensions()?;
+            for (dimension, exponent) in r.dimensions()? {
+                // Frequencies and periods cancel, as in `Hz*s`
+                let reciprocal = dimension.reciprocal();
+                if let Some((_, power)) = dimensions.iter_mut().find(|(known, _)| *known == dimension) {
+                    *power += sign * exponent;
+                } else if let Some((_, power)) = dimensions.iter_mut().find(|(known, _)| Some(*known) == reciprocal) {
+                    *power -= sign * exponent;
+                } else {
+                    dimensions.push((dimension, sign * exponent));
+                }
+            }
+            Some(DimensionSignature::product(dimensions))
+        }
+    }
+}
+
+fn unknown(signature: &DimensionSignature) -> bool {
+    matches!(
+        signature,
+        DimensionSignature::Quantity {
+            dimension: DimensionType::Unknown,
+            ..
+        }
+    )
+}
+
+// Units the engine doesn't know have no conversions of their own, so they're
+// only in the same dimension as another once evaluated
+fn same_dimension(left: &DimensionSignature, right: &DimensionSignature) -> bool {
+    match (left, right) {
+        (
+            DimensionSignature::Quantity {
+                dimension: l,
+                exponent: le,
+            },
+            DimensionSignature::Quantity {
+                dimension: r,
+                exponent: re,
+            },
+        ) => l == r && le == re && *l != DimensionType::Unknown,
+        (DimensionSignature::Derived(l), DimensionSignature::Derived(r)) => l == r,
+        _ => false,
+    }
+}
+
+fn unary(
+    operation: &str,
+    operand: DimensionSignature,
+    check: impl Fn(DimensionSignature) -> Option<DimensionSignature> + Copy,
+) -> Result<DimensionSignature, TypeError> {
+    match operand {
+        DimensionSignature::List(items) => {
+            Ok(DimensionSignature::List(Box::new(unary(operation, *items, check)?)))
+        }
+        operand => check(operand.clone()).ok_or_else(|| TypeError::InvalidOperand {
+            operation: operation.to_string(),
+            operand,
+        }),
+    }
+}
+
+fn convert(from: DimensionSignature, target: &Expression) -> Result<DimensionSignature, TypeError> {
+    let Expression::Unit(to_unit) = target else {
+        return Ok(DimensionSignature::Unresolved);
+    };
+    let to = DimensionSignature::of_unit(to_unit);
+
+    match from {
+        DimensionSignature::Unresolved => Ok(to),
+        // Currency codes and units of provided dimensions have no dimension
+        // of their own; whether they convert is only known at evaluation
+        DimensionSignature::Quantity {
+            dimension: DimensionType::Unknown,
+            exponent: 1,
+        } if crate::currency::is_currency(to_unit) || to == from => Ok(to),
+        from if same_dimension(&from, &to) => Ok(to),
+        // Frequencies and periods convert by taking the reciprocal
+        DimensionSignature::Quantity {
+            dimension,
+            exponent: 1,
+        } if matches!(to, DimensionSignature::Quantity { dimension: other, exponent: 1 } if dimension.reciprocal() == Some(other)) => {
+            Ok(to)
+        }
+        from => Err(TypeError::InvalidConversion {
+            from,
+            to_unit: to_unit.clone(),
+        }),
+    }
+}
+
+fn function(
+    name: &str,
+    args: Vec<DimensionSignature>,
+) -> Result<DimensionSignature, TypeError> {
+    match (name, args.as_slice()) {
+        ("sqrt", [arg]) => unary("sqrt", arg.clone(), |s| match s {
+            DimensionSignature::Quantity {
+                dimension,
+                exponent,
+            } if exponent % 2 == 0 => Some(DimensionSignature::quantity(dimension, exponent / 2)),
+            DimensionSignature::Derived(dimensions) if dimensions.iter().all(|(_, exponent)| exponent % 2 == 0) => {
+                Some(DimensionSignature::Derived(
+                    dimensions.into_iter().map(|(dimension, exponent)| (dimension, exponent / 2)).collect(),
+                ))
+            }
+            DimensionSignature::Dimensionless | DimensionSignature::Unresolved => Some(s),
+            _ => None,
+        }),
+        ("sin" | "cos" | "tan" | "exp" | "ln" | "hex" | "bin" | "oct", [arg]) => unary(name, arg.clone(), |s| match s {
+            DimensionSignature::Dimensionless | DimensionSignature::Unresolved => Some(s),
+            _ => None,
+        }),
+        #[cfg(feature = "random")]
+        ("rand" | "randint" | "roll", _) => Ok(DimensionSignature::Dimensionless),
+        ("round" | "floor" | "ceil" | "trunc", [value, digits @ ..]) if digits.len() <= 1 => {
+            if let Some(digits) = digits.first().filter(|d| {
+                !matches!(
+                    d,
+                    DimensionSignature::Dimensionless | DimensionSignature::Unresolved
+                )
+            }) {
+                return Err(TypeError::InvalidOperand {
+                    operation: name.to_string(),
+                    operand: digits.clone(),
+                });
+            }
+            unary(name, value.clone(), |s| match s {
+                DimensionSignature::Boolean => None,
+                other => Some(other),
+            })
+        }
+        ("if", [_, then, otherwise]) => common("choose between", vec![then.clone(), otherwise.clone()]),
+        ("isprime", [arg]) => match arg {
+            DimensionSignature::Dimensionless | DimensionSignature::Unresolved => {
+                Ok(DimensionSignature::Boolean)
+            }
+            other => Err(TypeError::InvalidOperand {
+                operation: name.to_string(),
+                operand: other.clone(),
+            }),
+        },
+        ("sum" | "mean" | "median" | "stdev" | "min" | "max", _) => {
+            // Aggregates take numbers or quantities of a single dimension,
+            // given directly or as lists
+            let items: Vec<_> = args.into_iter().map(list_item).collect();
+            if let Some(operand) = items.iter().find(|item| **item == DimensionSignature::Boolean) {
+                return Err(TypeError::InvalidOperand {
+                    operation: name.to_string(),
+                    operand: operand.clone(),
+                });
+            }
+            common("combine", items)
+        }
+        ("gcd" | "lcm" | "ncr" | "npr", _) => {
+            // Integer functions take plain numbers, given directly or as lists
+            for item in args.into_iter().map(list_item) {
+                if !matches!(
+                    item,
+                    DimensionSignature::Dimensionless | DimensionSignature::Unresolved
+                ) {
+                    return Err(TypeError::InvalidOperand {
+                        operation: name.to_string(),
+                        operand: item,
+                    });
+                }
+            }
+            Ok(DimensionSignature::Dimensionless)
+        }
+        // Unknown functions and wrong argument counts are left to evaluation
+        _ => Ok(DimensionSignature::Unresolved),
+    }
+}
+
+// Functions taking lists look at the signature of their elements
+fn list_item(arg: DimensionSignature) -> DimensionSignature {
+    match arg {
+        DimensionSignature::List(item) => *item,
+        other => other,
+    }
+}
+
+// The signature shared by all of `signatures`, which must agree wherever
+// they're known: the items of a list, or the branches of an `if`
+fn common(operation: &str, signatures: Vec<DimensionSignature>) -> Result<DimensionSignature, TypeError> {
+    let mut shared = DimensionSignature::Unresolved;
+    for signature in signatures {
+        shared = join(&shared, &signature).ok_or_else(|| TypeError::IncompatibleDimensions {
+            operation: operation.to_string(),
+            left: shared.clone(),
+            right: signature,
+        })?;
+    }
+    Ok(shared)
+}
+
+// The signature of values that may be `left` or `right`, if they agree
+fn join(left: &DimensionSignature, right: &DimensionSignature) -> Option<DimensionSignature> {
+    use DimensionSignature::*;
+
+    match (left, right) {
+        (Unresolved, other) | (other, Unresolved) => Some(other.clone()),
+        (List(l), List(r)) => Some(List(Box::new(join(l, r)?))),
+        (l, r) if l == r => Some(l.clone()),
+        _ => None,
+    }
+}
+
+// `n` or `-n` written directly as an exponent
+fn integer_literal(expr: &Expression) -> Option<i32> {
+    match expr {
+        Expression::Integer(i) => i32::try_from(*i).ok(),
+        Expression::Number(n) | Expression::Decimal { value: n, .. } if n.fract() == 0.0 => Some(*n as i32),
+        Expression::Unary {
+            op: Operation::Subtract,
+            operand,
+            ..
+        } => integer_literal(operand).map(|n| -n),
+        _ => None,
+    }
+}
+
+fn operation_name(op: &Operation) -> &'static str {
+    match op {
+        Operation::Add => "add",
+        Operation::Subtract => "subtract",
+        Operation::Multiply => "multiply",
+        Operation::Divide => "divide",
+        Operation::Power => "raise",
+        Operation::Of => "take a percentage of",
+        _ => "compare",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::parse;
+
+    #[test]
+    fn test_check_matches_evaluation() {
+        let length = |exponent| DimensionSignature::Quantity {
+            dimension: DimensionType::Length,
+            exponent,
+        };
+        let per_time = |exponent| {
+            DimensionSignature::Derived(vec![
+                (DimensionType::Length, 1),
+                (DimensionType::Time, exponent),
+            ])
+        };
+
+        let valid = [
+            ("2 + 3 * 4", DimensionSignature::Dimensionless),
+            ("(1m + 50cm) to cm", length(1)),
+            ("sqrt((3m)^2 + (4m)^2)", length(1)),
+            ("5 / 2m", length(-1)),
+            ("10m / 2ft", DimensionSignature::Dimensionless),
+            ("10% of 2m^2", length(2)),
+            ("10m + 10%", length(1)),
+            ("1ft == 12in and true", DimensionSignature::Boolean),
+            ("[1m, 2m] * 2", DimensionSignature::List(Box::new(length(1)))),
+            ("mean([3, 5, 8])", DimensionSignature::Dimensionless),
+            ("sum(1m, 30cm, 2ft)", length(1)),
+            (
+                "max(3kg, 5lb)",
+                DimensionSignature::Quantity {
+                    dimension: DimensionType::Mass,
+                    exponent: 1,
+                },
+            ),
+            ("x * 2m", DimensionSignature::Unresolved),
+            ("(10km / 2h) to mi/h", per_time(-1)),
+            ("9.8m/s^2 * 2s", per_time(-1)),
+            ("50km/h * 30min", length(1)),
+            ("sqrt((3m/s)^2)", per_time(-1)),
+            ("if(1 > 0, 1m, 2ft)", length(1)),
+            ("if(x, y, 2m)", length(1)),
+            ("[x, 1m, 2ft]", DimensionSignature::List(Box::new(length(1)))),
+            ("sum([1m, x], 2ft)", length(1)),
+        ];
+        for (source, expected) in valid {
+            assert_eq!(check(&parse(source)), Ok(expected), "{}", source);
+        }
+
+        // Each of these evaluates without complaint but loses a unit
+        for source in ["5m + 3kg", "1kg + 1s", "5m * 3C", "1m < 1C"] {
+            assert!(check(&parse(source)).is_err(), "{}", source);
+        }
+        // and these assume the bare number is in the other side's unit
+        for source in ["10m + 2", "2 - 10m", "1m > 0"] {
+            assert!(check(&parse(source)).is_err(), "{}", source);
+        }
+        // Branches of an `if` and the items of a list or aggregate share a dimension
+        for source in ["if(1 > 0, 1m, 2s)", "if(x, true, 1)", "sum([1m, 2s])", "[1m, 2s]", "[[1m], [x, 2kg]]", "max([1m], 2s)"] {
+            assert!(check(&parse(source)).is_err(), "{}", source);
+        }
+        for source in ["1m/s to s", "1m/s + 1m", "2m/s to m/s^2", "1m to C", "5 to m", "2^(1m)", "2m^2 to ft", "sqrt(2m)", "max(1m, 2C)", "mean(1m, 2)", "3m!"] {
+            assert!(check(&parse(source)).is_err(), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::cmp::Ordering;
+
+use mathengine_lexer::Operation;
+use mathengine_parser::types::{Number, UnitValue, Value};
+
+use crate::EvalError;
+
+/// Whether an operation is one of the comparison operators.
+pub(crate) fn is_comparison(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::Less
+            | Operation::LessEqual
+            | Operation::Greater
+            | Operation::GreaterEqual
+            | Operation::Equal
+            | Operation::NotEqual
+    )
+}
+
+/// Compare two values, converting unit values to a common unit first.
+pub(crate) fn compare(op: &Operation, left: &Value, right: &Value) -> Result<Value, EvalError> {
+    let ordering = match (left, right) {
+        (Value::Number(l), Value::Number(r)) => compare_numbers(l, r),
+        // A plain number takes the unit of the other side, as in `10m + 5`
+        (Value::UnitValue(l), Value::Number(r)) => l.value().partial_cmp(&r.to_f64()),
+        (Value::Number(l), Value::UnitValue(r)) => l.to_f64().partial_cmp(&r.value()),
+        (Value::UnitValue(l), Value::UnitValue(r)) => compare_units(l, r)?,
+        (Value::Bool(l), Value::Bool(r)) if matches!(op, Operation::Equal | Operation::NotEqual) => {
+            Some(l.cmp(r))
+        }
+        _ => {
+            return Err(EvalError::UnsupportedOperation {
+                operation: format!("{:?}", op),
+                operand_type: format!("'{}' and '{}'", left, right),
+            });
+        }
+    };
+
+    // Incomparable values (NaN) are only ever unequal
+    let result = match ordering {
+        Some(ordering) => match op {
+            Operation::Less => ordering == Ordering::Less,
+            Operation::LessEqual => ordering != Ordering::Greater,
+            Operation::Greater => ordering == Ordering::Greater,
+            Operation::GreaterEqual => ordering != Ordering::Less,
+            Operation::Equal => ordering == Ordering::Equal,
+            _ => ordering != Ordering::Equal,
+        },
+        None => matches!(op, Operation::NotEqual),
+    };
+    Ok(Value::Bool(result))
+}
+
+// Subtracting keeps exact numbers exact, so the sign of the difference gives
+// the ordering without going through a lossy float conversion
+fn compare_numbers(left: &Number, right: &Number) -> Option<Ordering> {
+    (left.clone() - right.clone()).to_f64().partial_cmp(&0.0)
+}
+
+fn compare_units(left: &UnitValue, right: &UnitValue) -> Result<Option<Ordering>, EvalError> {
+    if !left.same_dimension_as(right) {
+        return Err(EvalError::IncompatibleUnits {
+            left_unit: left.canonical_unit_name(),
+            right_unit: right.canonical_unit_name(),
+            operation: "compare".to_string(),
+        });
+    }
+
+    Ok(left.partial_cmp(right))
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mathengine_parser::{
+    types::{Representation, Scalar, Value},
+    Expression,
+};
+
+use crate::{DimensionProvider, EvalLimits, RateProvider, Resolver};
+
+/// How numbers are represented while evaluating.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberMode {
+    /// Integers stay exact; any fractional result becomes a float
+    #[default]
+    Standard,
+    /// Division of exact numbers yields exact fractions (`1/3 + 1/6` is `1/2`),
+    /// and decimal literals are read as the fractions they write (`0.5 + 1/3`
+    /// is `5/6`)
+    Rational,
+    /// Decimal literals and quotients use fixed-precision decimals, so
+    /// `0.1 + 0.2` is exactly `0.3`. Amounts of money are decimals too
+    /// (`0.10 usd + 0.20 usd` is `0.3usd`); quantities in other units keep
+    /// their `f64` values, as they do in every mode
+    #[cfg(feature = "decimal")]
+    Decimal,
+    /// Decimal literals, quotients, powers, roots and the elementary
+    /// functions use binary floats with `precision` bits of mantissa
+    #[cfg(feature = "bigfloat")]
+    BigFloat { precision: usize },
+}
+
+impl NumberMode {
+    /// The mode that evaluates in `T`'s representation, so literals are
+    /// read, arithmetic is done and results come back the way `T` holds
+    /// numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_evaluator::NumberMode;
+    ///
+    /// assert_eq!(NumberMode::for_scalar::<f64>(), NumberMode::Standard);
+    /// assert_eq!(NumberMode::for_scalar::<i64>(), NumberMode::Rational);
+    /// ```
+    pub fn for_scalar<T: Scalar>() -> Self {
+        match T::REPRESENTATION {
+            Representation::Float => NumberMode::Standard,
+            Representation::Exact => NumberMode::Rational,
+            #[cfg(feature = "decimal")]
+            Representation::Decimal => NumberMode::Decimal,
+        }
+    }
+}
+
+/// Options and state that control how an expression is evaluated.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{EvaluationContext, NumberMode};
+///
+/// let context = EvaluationContext {
+///     number_mode: NumberMode::Rational,
+///     ..EvaluationContext::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EvaluationContext {
+    /// Resource limits enforced during evaluation
+    pub limits: EvalLimits,
+    /// Numeric representation used for arithmetic
+    pub number_mode: NumberMode,
+    /// Evaluate `x!` for non-integers as `gamma(x + 1)` instead of returning
+    /// a domain error
+    pub gamma_factorial: bool,
+    /// Values for identifiers used in the expression, keyed by lowercase name
+    pub variables: HashMap<String, Value>,
+    /// Exchange rates for converting between currencies, like `100 usd to eur`
+    pub rates: Option<Arc<dyn RateProvider>>,
+    /// Asked for identifiers that `variables` doesn't bind, like cell
+    /// references or ticker symbols
+    pub resolver: Option<Arc<dyn Resolver>>,
+    /// Functions defined by the user, keyed by lowercase name. These take
+    /// precedence over builtins of the same name
+    pub functions: HashMap<String, Function>,
+    /// Dimensions beyond length, temperature and time, like clothing sizes,
+    /// whose units convert with their provider
+    pub dimensions: Vec<Arc<dyn DimensionProvider>>,
+}
+
+/// A function defined in terms of an expression, like `f(x) = x^2 + 1`.
+///
+/// The body sees its parameters and the context's variables; parameters
+/// shadow variables of the same name.
+#[derive(Debug, Clone)]
+pub struct Function {
+    /// Parameter names, lowercase
+    pub params: Vec<String>,
+    pub body: Expression,
+}
+
+impl EvaluationContext {
+    /// Bind a variable, so identifiers named `name` evaluate to `value`.
+    ///
+    /// Identifiers are case-insensitive, so the name is stored lowercased.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_evaluator::EvaluationContext;
+    ///
+    /// let mut context = EvaluationContext::default();
+    /// context.set_variable("Rate", 0.25);
+    /// assert!(context.variables.contains_key("rate"));
+    /// ```
+    pub fn set_variable(&mut self, name: &str, value: impl Into<Value>) {
+        self.variables.insert(name.to_lowercase(), value.into());
+    }
+
+    /// Define a function, so calls to `name` evaluate `body` with `params`
+    /// bound to the arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_evaluator::{evaluate_with_context, EvaluationContext};
+    /// use mathengine_lexer::Lexer;
+    /// use mathengine_parser::Parser;
+    ///
+    /// let parse = |source: &str| Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+    ///
+    /// let mut context = EvaluationContext::default();
+    /// context.define_function("square", vec!["x".to_string()], parse("x * x"));
+    /// let result = evaluate_with_context(&parse("square(3) + 1"), &context).unwrap();
+    /// assert_eq!(result.to_string(), "10");
+    /// ```
+    pub fn define_function(&mut self, name: &str, params: Vec<String>, body: Expression) {
+        let params = params.iter().map(|param| param.to_lowercase()).collect();
+        self.functions.insert(name.to_lowercase(), Function { params, body });
+    }
+}
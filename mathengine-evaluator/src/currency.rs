@@ -0,0 +1,157 @@
+//! Currency conversion through exchange rates supplied by the host.
+//!
+//! Rates change constantly, so the engine doesn't ship any. Converting between
+//! currency codes, as in `100 usd to eur`, asks the [`RateProvider`] in the
+//! [`EvaluationContext`](crate::EvaluationContext) instead.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use mathengine_parser::types::UnitValue;
+
+use crate::EvalError;
+
+// ISO 4217 codes recognised as currency units, lowercase like identifiers
+const CURRENCIES: &[&str] = &[
+    "aud", "brl", "cad", "chf", "cny", "czk", "dkk", "eur", "gbp", "hkd", "inr", "jpy", "krw",
+    "mxn", "nok", "nzd", "pln", "sek", "sgd", "usd", "zar",
+];
+
+/// A source of exchange rates, such as a cached feed from a rates API.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{evaluate_with_context, EvaluationContext, RateError, RateProvider};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::Parser;
+/// use std::sync::Arc;
+///
+/// #[derive(Debug)]
+/// struct Cached {
+///     age: std::time::Duration,
+/// }
+///
+/// impl RateProvider for Cached {
+///     fn rate(&self, from: &str, to: &str) -> Result<f64, RateError> {
+///         if self.age.as_secs() > 3600 {
+///             return Err(RateError::Stale { age: self.age });
+///         }
+///         match (from, to) {
+///             ("usd", "eur") => Ok(0.5),
+///             _ => Err(RateError::Missing),
+///         }
+///     }
+/// }
+///
+/// let tokens = Lexer::new("10 usd to eur").tokenize().unwrap();
+/// let ast = Parser::new(tokens).parse().unwrap();
+/// let context = EvaluationContext {
+///     rates: Some(Arc::new(Cached { age: std::time::Duration::from_secs(60) })),
+///     ..EvaluationContext::default()
+/// };
+/// assert_eq!(evaluate_with_context(&ast, &context).unwrap().to_string(), "5eur");
+/// ```
+pub trait RateProvider: fmt::Debug + Send + Sync {
+    /// How many units of `to` one unit of `from` buys. Codes are lowercase,
+    /// like `"usd"`.
+    fn rate(&self, from: &str, to: &str) -> Result<f64, RateError>;
+}
+
+/// Why a currency conversion couldn't get a rate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateError {
+    /// No rate provider is configured
+    NoProvider,
+    /// The provider has no rate between the two currencies
+    Missing,
+    /// The provider's rate is too old to use
+    Stale { age: Duration },
+}
+
+impl fmt::Display for RateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateError::NoProvider => write!(f, "no exchange rates are configured"),
+            RateError::Missing => write!(f, "no rate is available"),
+            RateError::Stale { age } => write!(f, "the rate is out of date ({:?} old)", age),
+        }
+    }
+}
+
+impl std::error::Error for RateError {}
+
+/// A fixed table of rates, for tests and offline use.
+///
+/// A rate also answers the reverse conversion, so `usd` to `eur` is enough
+/// for `eur` to `usd` too.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{RateProvider, StaticRates};
+///
+/// let rates = StaticRates::new().with_rate("USD", "EUR", 0.5);
+/// assert_eq!(rates.rate("eur", "usd"), Ok(2.0));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StaticRates {
+    rates: HashMap<(String, String), f64>,
+}
+
+impl StaticRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the rate from one currency to another.
+    pub fn with_rate(mut self, from: &str, to: &str, rate: f64) -> Self {
+        self.rates
+            .insert((from.to_lowercase(), to.to_lowercase()), rate);
+        self
+    }
+}
+
+impl RateProvider for StaticRates {
+    fn rate(&self, from: &str, to: &str) -> Result<f64, RateError> {
+        if from == to {
+            return Ok(1.0);
+        }
+        let key = |from: &str, to: &str| (from.to_string(), to.to_string());
+        if let Some(rate) = self.rates.get(&key(from, to)) {
+            return Ok(*rate);
+        }
+        match self.rates.get(&key(to, from)) {
+            Some(rate) if *rate != 0.0 => Ok(1.0 / rate),
+            _ => Err(RateError::Missing),
+        }
+    }
+}
+
+/// The currency codes the engine converts with exchange rates, lowercase.
+pub fn currencies() -> &'static [&'static str] {
+    CURRENCIES
+}
+
+/// Whether `unit` is a currency code the engine converts with exchange rates.
+pub fn is_currency(unit: &str) -> bool {
+    CURRENCIES.contains(&unit.to_lowercase().as_str())
+}
+
+pub(crate) fn convert(
+    value: &UnitValue,
+    to_unit: &str,
+    rates: Option<&dyn RateProvider>,
+) -> Result<UnitValue, EvalError> {
+    let (from, to) = (value.unit().to_lowercase(), to_unit.to_lowercase());
+    let rate = rates
+        .ok_or(RateError::NoProvider)
+        .and_then(|rates| rates.rate(&from, &to))
+        .map_err(|reason| EvalError::RateUnavailable {
+            from: from.clone(),
+            to: to.clone(),
+            reason,
+        })?;
+    Ok(UnitValue::named(value.value() * rate, to))
+}
@@ -0,0 +1,351 @@
+//! Symbolic differentiation of expression trees.
+//!
+//! [`differentiate`] applies the sum, product, quotient, power and chain rules
+//! to build the derivative as a new [`Expression`], folding away the zeros and
+//! ones the rules leave behind so the result stays readable.
+
+use mathengine_lexer::{Operation, Span};
+use mathengine_parser::Expression;
+
+use crate::{percentage_operands, solve::with_coefficients, EvalError};
+
+// Post-order traversal on an explicit stack, like evaluation: a node's
+// derivative is built from its children's, which are on the stack by then
+enum Task<'a> {
+    Visit(&'a Expression),
+    Apply(&'a Expression),
+}
+
+/// Differentiate an expression with respect to `variable`.
+///
+/// Handles polynomials, quotients, powers, and the `sin`, `cos`, `tan`,
+/// `exp`, `ln` and `sqrt` functions. A coefficient written against the
+/// variable, as in `2x`, multiplies it. Every other identifier is treated as
+/// a constant. Like the lexer, variable names are case-insensitive.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{differentiate, evaluate_with_context, EvaluationContext};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::Parser;
+///
+/// let tokens = Lexer::new("x^3 + 2 * x").tokenize().unwrap();
+/// let derivative = differentiate(&Parser::new(tokens).parse().unwrap(), "x").unwrap();
+///
+/// // 3x^2 + 2 at x = 2
+/// let mut context = EvaluationContext::default();
+/// context.set_variable("x", 2i128);
+/// let slope = evaluate_with_context(&derivative, &context).unwrap();
+/// assert_eq!(slope.to_string(), "14");
+/// ```
+///
+/// # Errors
+///
+/// Returns [`EvalError::UnsupportedOperation`] for expressions without a
+/// derivative here, such as comparisons, factorials, lists and conversions.
+pub fn differentiate(expr: &Expression, variable: &str) -> Result<Expression, EvalError> {
+    let variable = variable.to_lowercase();
+    let expr = &with_coefficients(expr, &variable);
+    let mut tasks = vec![Task::Visit(expr)];
+    let mut derivatives: Vec<Expression> = Vec::new();
+
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Visit(expr) => {
+                tasks.push(Task::Apply(expr));
+                match expr {
+                    Expression::Binary {
+                        op, left, right, ..
+                    } => {
+                        let (left, right) =
+                            percentage_operands(op, left, right).unwrap_or((left, right));
+                        tasks.push(Task::Visit(right));
+                        tasks.push(Task::Visit(left));
+                    }
+                    Expression::Unary { operand, .. } | Expression::Percent(operand) => {
+                        tasks.push(Task::Visit(operand))
+                    }
+                    Expression::FunctionCall { args, .. } => {
+                        tasks.extend(args.iter().rev().map(Task::Visit))
+                    }
+                    _ => {}
+                }
+            }
+            Task::Apply(expr) => {
+                let derivative = apply(expr, &variable, &mut derivatives)
+                    .map_err(|e| e.at(expr.span()))?;
+                derivatives.push(derivative);
+            }
+        }
+    }
+
+    Ok(derivatives.pop().expect("differentiated expression left no derivative"))
+}
+
+fn apply(
+    expr: &Expression,
+    variable: &str,
+    derivatives: &mut Vec<Expression>,
+) -> Result<Expression, EvalError> {
+    let derivative = match expr {
+        Expression::Number(_)
+        | Expression::Decimal { .. }
+        | Expression::Integer(_)
+        | Expression::Bool(_)
+        | Expression::UnitValue { .. } => integer(0),
+        Expression::Unit(name) => integer(if name == variable { 1 } else { 0 }),
+        Expression::Percent(_) => div(pop(derivatives), integer(100)),
+        Expression::Binary {
+            op, left, right, ..
+        } => {
+            let dr = pop(derivatives);
+            let dl = pop(derivatives);
+            match (op, percentage_operands(op, left, right)) {
+                // `p% of v` is `p * v / 100`
+                (Operation::Of, Some((p, v))) => div(product(p, dl, v, dr), integer(100)),
+                // `u + p%` is `u + u * p / 100`
+                (Operation::Add, Some((u, p))) => {
+                    add(dl.clone(), div(product(u, dl, p, dr), integer(100)))
+                }
+                (Operation::Subtract, Some((u, p))) => {
+                    sub(dl.clone(), div(product(u, dl, p, dr), integer(100)))
+                }
+                (Operation::Add, None) => add(dl, dr),
+                (Operation::Subtract, None) => sub(dl, dr),
+                (Operation::Multiply, None) => product(left, dl, right, dr),
+                // (u'v - uv') / v^2
+                (Operation::Divide, None) => div(
+                    sub(mul(dl, (**right).clone()), mul((**left).clone(), dr)),
+                    pow((**right).clone(), integer(2)),
+                ),
+                (Operation::Power, None) => power(left, dl, right, dr),
+                _ => return Err(unsupported(&format!("'{:?}'", op))),
+            }
+        }
+        Expression::Unary {
+            op: Operation::Subtract,
+            ..
+        } => neg(pop(derivatives)),
+        Expression::FunctionCall { name, args, .. } => {
+            let [u] = args.as_slice() else {
+                return Err(unsupported(&format!("function '{}'", name)));
+            };
+            let du = pop(derivatives);
+            let u = u.clone();
+            match name.as_str() {
+                "sin" => mul(call("cos", u), du),
+                "cos" => mul(neg(call("sin", u)), du),
+                "tan" => div(du, pow(call("cos", u), integer(2))),
+                "exp" => mul(call("exp", u), du),
+                "ln" => div(du, u),
+                "sqrt" => div(du, mul(integer(2), call("sqrt", u))),
+                _ => return Err(unsupported(&format!("function '{}'", name))),
+            }
+        }
+        Expression::Unary { op, .. } => return Err(unsupported(&format!("'{:?}'", op))),
+        Expression::Factorial { .. } => return Err(unsupported("factorial")),
+        Expression::List(_) => return Err(unsupported("list")),
+    };
+    Ok(derivative)
+}
+
+// Children are differentiated before their parent, so the stack always holds
+// enough derivatives here
+fn pop(derivatives: &mut Vec<Expression>) -> Expression {
+    derivatives.pop().expect("operand missing from derivative stack")
+}
+
+fn unsupported(operand_type: &str) -> EvalError {
+    EvalError::UnsupportedOperation {
+        operation: "differentiate".to_string(),
+        operand_type: operand_type.to_string(),
+    }
+}
+
+// u'v + uv'
+fn product(u: &Expression, du: Expression, v: &Expression, dv: Expression) -> Expression {
+    add(mul(du, v.clone()), mul(u.clone(), dv))
+}
+
+fn power(u: &Expression, du: Expression, v: &Expression, dv: Expression) -> Expression {
+    let (u, v) = (u.clone(), v.clone());
+    if is_integer(&dv, 0) {
+        // Power rule: v * u^(v - 1) * u'
+        let lowered = pow(u, sub(v.clone(), integer(1)));
+        mul(mul(v, lowered), du)
+    } else if is_integer(&du, 0) {
+        // Exponential: u^v * ln(u) * v'
+        mul(mul(pow(u.clone(), v), call("ln", u)), dv)
+    } else {
+        // u^v * (v' ln(u) + v u' / u)
+        let rate = add(mul(dv, call("ln", u.clone())), div(mul(v.clone(), du), u.clone()));
+        mul(pow(u, v), rate)
+    }
+}
+
+// The constructors below fold the identities the rules produce, like `0 * u`
+// and `u^1`, and do exact integer arithmetic on integer constants
+
+fn integer(value: i128) -> Expression {
+    Expression::Integer(value)
+}
+
+fn is_integer(expr: &Expression, value: i128) -> bool {
+    match expr {
+        Expression::Integer(i) => *i == value,
+        Expression::Number(n) | Expression::Decimal { value: n, .. } => *n == value as f64,
+        _ => false,
+    }
+}
+
+fn binary(op: Operation, left: Expression, right: Expression) -> Expression {
+    Expression::Binary {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+        span: Span::default(),
+    }
+}
+
+// A percent node on the right of `+` or `-` would be read as "plus that
+// share of the left side", so spell it out as a division instead
+fn plain(mut expr: Expression) -> Expression {
+    if let Expression::Percent(value) = &mut expr {
+        return div(std::mem::replace(value, integer(0)), integer(100));
+    }
+    expr
+}
+
+fn add(left: Expression, right: Expression) -> Expression {
+    match (left, plain(right)) {
+        (Expression::Integer(l), Expression::Integer(r)) if l.checked_add(r).is_some() => {
+            integer(l + r)
+        }
+        (l, r) if is_integer(&l, 0) => r,
+        (l, r) if is_integer(&r, 0) => l,
+        (l, r) => binary(Operation::Add, l, r),
+    }
+}
+
+fn sub(left: Expression, right: Expression) -> Expression {
+    match (left, plain(right)) {
+        (Expression::Integer(l), Expression::Integer(r)) if l.checked_sub(r).is_some() => {
+            integer(l - r)
+        }
+        (l, r) if is_integer(&r, 0) => l,
+        (l, r) if is_integer(&l, 0) => neg(r),
+        (l, r) => binary(Operation::Subtract, l, r),
+    }
+}
+
+fn mul(left: Expression, right: Expression) -> Expression {
+    match (left, right) {
+        (Expression::Integer(l), Expression::Integer(r)) if l.checked_mul(r).is_some() => {
+            integer(l * r)
+        }
+        (l, r) if is_integer(&l, 0) || is_integer(&r, 0) => integer(0),
+        (l, r) if is_integer(&l, 1) => r,
+        (l, r) if is_integer(&r, 1) => l,
+        (l, r) => binary(Operation::Multiply, l, r),
+    }
+}
+
+fn div(left: Expression, right: Expression) -> Expression {
+    match (left, right) {
+        (l, _) if is_integer(&l, 0) => integer(0),
+        (l, r) if is_integer(&r, 1) => l,
+        (l, r) => binary(Operation::Divide, l, r),
+    }
+}
+
+fn pow(base: Expression, exponent: Expression) -> Expression {
+    match (base, exponent) {
+        (_, e) if is_integer(&e, 0) => integer(1),
+        (b, e) if is_integer(&e, 1) => b,
+        (b, e) => binary(Operation::Power, b, e),
+    }
+}
+
+fn neg(mut operand: Expression) -> Expression {
+    match &mut operand {
+        Expression::Integer(i) if i.checked_neg().is_some() => integer(-*i),
+        Expression::Unary {
+            op: Operation::Subtract,
+            operand,
+            ..
+        } => std::mem::replace(operand, integer(0)),
+        _ => Expression::Unary {
+            op: Operation::Subtract,
+            operand: Box::new(operand),
+            span: Span::default(),
+        },
+    }
+}
+
+fn call(name: &str, arg: Expression) -> Expression {
+    Expression::FunctionCall {
+        name: name.to_string(),
+        args: vec![arg],
+        span: Span::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::parse;
+    use crate::{evaluate_with_context, EvaluationContext};
+    use mathengine_parser::types::Value;
+
+    #[test]
+    fn test_derivative_matches_finite_difference() {
+        let sources = [
+            "x^3 - 4 * x + 7",
+            "(x + 1) / (x - 3)",
+            "sin(x) * cos(2 * x)",
+            "exp(x^2) + ln(x)",
+            "sqrt(1 + x^2)",
+            "tan(x / 2)",
+            "2^x + x^x",
+            "10% of x^2 + (x + 5%)",
+            "-(a * x)",
+            // Coefficients written against the variable
+            "sin(2x)",
+            "3x^2",
+            "x^3+2x",
+            "0.5x / (2x + 1)",
+        ];
+        let at = |expr: &Expression, x: f64| {
+            let mut context = EvaluationContext::default();
+            context.set_variable("x", x);
+            context.set_variable("a", 3.0);
+            match evaluate_with_context(expr, &context).unwrap() {
+                Value::Number(n) => n.to_f64(),
+                other => panic!("expected a number, got {:?}", other),
+            }
+        };
+
+        for source in sources {
+            let expr = parse(source);
+            let derivative = differentiate(&expr, "x").unwrap();
+            // `2x` only evaluates as a product once it's written as one
+            let expr = with_coefficients(&expr, "x");
+            for x in [0.7, 1.3, 2.1] {
+                let h = 1e-6;
+                let expected = (at(&expr, x + h) - at(&expr, x - h)) / (2.0 * h);
+                let actual = at(&derivative, x);
+                assert!(
+                    (actual - expected).abs() < 1e-5 * expected.abs().max(1.0),
+                    "d/dx {} at {}: {} vs {}",
+                    source,
+                    x,
+                    actual,
+                    expected
+                );
+            }
+        }
+
+        assert!(differentiate(&parse("x!"), "x").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Dimensions defined outside the engine, supplied by the host at runtime.
+//!
+//! The unit tables only cover length, temperature and time. A
+//! [`DimensionProvider`] in the [`EvaluationContext`](crate::EvaluationContext)
+//! adds another dimension, like clothing sizes or time zones, with its own
+//! units and conversion rules, so `42eu to us` converts with the provider
+//! that knows those units.
+
+use std::fmt;
+
+/// A dimension the engine doesn't know, with the units it's measured in and
+/// how to convert between them.
+///
+/// Units of a provided dimension are written like any other, after a number
+/// (`42eu`), and converted with `to`. Unit names are lowercase, like
+/// identifiers. Adding two amounts converts the right one into the left
+/// one's unit, the way it does for currencies.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{evaluate_with_context, DimensionProvider, EvaluationContext};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::Parser;
+/// use std::sync::Arc;
+///
+/// // Men's shoe sizes, which are a fixed offset apart
+/// #[derive(Debug)]
+/// struct ShoeSizes;
+///
+/// impl ShoeSizes {
+///     fn offset(unit: &str) -> Option<f64> {
+///         match unit {
+///             "eu" => Some(0.0),
+///             "uk" => Some(-33.5),
+///             "us" => Some(-33.0),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// impl DimensionProvider for ShoeSizes {
+///     fn name(&self) -> &str {
+///         "shoe size"
+///     }
+///
+///     fn units(&self) -> Vec<String> {
+///         vec!["eu".to_string(), "uk".to_string(), "us".to_string()]
+///     }
+///
+///     fn convert(&self, value: f64, from: &str, to: &str) -> Option<f64> {
+///         Some(value - ShoeSizes::offset(from)? + ShoeSizes::offset(to)?)
+///     }
+/// }
+///
+/// let context = EvaluationContext {
+///     dimensions: vec![Arc::new(ShoeSizes)],
+///     ..EvaluationContext::default()
+/// };
+/// let tokens = Lexer::new("43eu to us").tokenize().unwrap();
+/// let ast = Parser::new(tokens).parse().unwrap();
+/// assert_eq!(evaluate_with_context(&ast, &context).unwrap().to_string(), "10us");
+///
+/// // Without the provider, `eu` isn't a unit at all
+/// assert!(evaluate_with_context(&ast, &EvaluationContext::default()).is_err());
+/// ```
+pub trait DimensionProvider: fmt::Debug + Send + Sync {
+    /// The dimension's name, like `"shoe size"`, for error messages
+    fn name(&self) -> &str;
+
+    /// Every unit of the dimension, lowercase.
+    fn units(&self) -> Vec<String>;
+
+    /// Whether `unit`, lowercase, is a unit of the dimension. Defaults to
+    /// looking it up in [`units`](Self::units).
+    fn has_unit(&self, unit: &str) -> bool {
+        self.units().iter().any(|known| known == unit)
+    }
+
+    /// `value` in `from` as a value in `to`, both units of the dimension, or
+    /// `None` when it has no equivalent there.
+    fn convert(&self, value: f64, from: &str, to: &str) -> Option<f64>;
+}
@@ -1,4 +1,9 @@
 use std::fmt;
+use std::time::Duration;
+
+use mathengine_lexer::Span;
+
+use crate::RateError;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum EvalError {
@@ -22,6 +27,117 @@ pub enum EvalError {
     InvalidUnitExpression {
         message: String,
     },
+    UnknownFunction {
+        name: String,
+    },
+    WrongArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    DomainError {
+        function: String,
+        message: String,
+    },
+    Overflow {
+        operation: String,
+    },
+    /// A result too close to zero to represent, like a power of a big float
+    /// past the exponents it can have
+    Underflow {
+        operation: String,
+    },
+    /// A value with a unit raised to a power its unit's exponent can't hold,
+    /// or that makes its value too large to represent
+    ExponentOutOfRange {
+        exponent: String,
+    },
+    NodeLimitExceeded {
+        limit: usize,
+    },
+    DepthLimitExceeded {
+        limit: usize,
+    },
+    OperationLimitExceeded {
+        limit: usize,
+    },
+    Timeout {
+        limit: Duration,
+    },
+    /// The limits' cancellation token was cancelled before evaluation
+    /// finished
+    Cancelled,
+    /// User-defined functions called each other more than `limit` deep
+    RecursionLimitExceeded {
+        function: String,
+        limit: usize,
+    },
+    RateUnavailable {
+        from: String,
+        to: String,
+        reason: RateError,
+    },
+    /// An error raised by the sub-expression at `span` in the source
+    Spanned {
+        span: Span,
+        error: Box<EvalError>,
+    },
+}
+
+impl EvalError {
+    /// The source span of the sub-expression that caused the error, when the
+    /// expression was parsed with spans.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_evaluator::{evaluate, EvalError};
+    /// use mathengine_lexer::{Lexer, Span};
+    /// use mathengine_parser::Parser;
+    ///
+    /// let tokens = Lexer::new("1 + 10 / (5 - 5)").tokenize_with_spans().unwrap();
+    /// let ast = Parser::with_spans(tokens).parse().unwrap();
+    ///
+    /// let err = evaluate(&ast).unwrap_err();
+    /// assert_eq!(err.span(), Some(Span::new(4, 16)));
+    /// assert_eq!(err.without_span(), &EvalError::DivisionByZero);
+    /// ```
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            EvalError::Spanned { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, without its location.
+    pub fn without_span(&self) -> &EvalError {
+        match self {
+            EvalError::Spanned { error, .. } => error.without_span(),
+            other => other,
+        }
+    }
+
+    // Attaches the span of the node being evaluated. Resource limits aren't
+    // about any one node, and hand-built nodes have no span to report.
+    pub(crate) fn at(self, span: Option<Span>) -> EvalError {
+        match (span, &self) {
+            (
+                _,
+                EvalError::Spanned { .. }
+                | EvalError::NodeLimitExceeded { .. }
+                | EvalError::DepthLimitExceeded { .. }
+                | EvalError::OperationLimitExceeded { .. }
+                | EvalError::Timeout { .. }
+                | EvalError::Cancelled
+                | EvalError::RecursionLimitExceeded { .. },
+            ) => self,
+            (Some(span), _) if !span.is_empty() => EvalError::Spanned {
+                span,
+                error: Box::new(self),
+            },
+            _ => self,
+        }
+    }
 }
 
 impl fmt::Display for EvalError {
@@ -60,6 +176,54 @@ impl fmt::Display for EvalError {
             EvalError::InvalidUnitExpression { message } => {
                 write!(f, "Invalid unit expression: {}", message)
             }
+            EvalError::UnknownFunction { name } => {
+                write!(f, "Unknown function: '{}'", name)
+            }
+            EvalError::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "Function '{}' expects {} argument(s) but got {}",
+                    function, expected, found
+                )
+            }
+            EvalError::DomainError { function, message } => {
+                write!(f, "Domain error in '{}': {}", function, message)
+            }
+            EvalError::Overflow { operation } => {
+                write!(f, "Result of {} is too large to represent", operation)
+            }
+            EvalError::Underflow { operation } => {
+                write!(f, "Result of {} is too small to represent", operation)
+            }
+            EvalError::ExponentOutOfRange { exponent } => {
+                write!(f, "Exponent {} is out of range for a value with a unit", exponent)
+            }
+            EvalError::NodeLimitExceeded { limit } => {
+                write!(f, "Expression exceeds the limit of {} nodes", limit)
+            }
+            EvalError::DepthLimitExceeded { limit } => {
+                write!(f, "Expression exceeds the maximum nesting depth of {}", limit)
+            }
+            EvalError::OperationLimitExceeded { limit } => {
+                write!(f, "Evaluation exceeds the limit of {} operations", limit)
+            }
+            EvalError::Timeout { limit } => {
+                write!(f, "Evaluation timed out after {:?}", limit)
+            }
+            EvalError::Cancelled => write!(f, "Evaluation was cancelled"),
+            EvalError::RecursionLimitExceeded { function, limit } => {
+                write!(f, "Calls to '{}' exceed the recursion limit of {}", function, limit)
+            }
+            EvalError::RateUnavailable { from, to, reason } => {
+                write!(f, "Cannot convert '{}' to '{}': {}", from, to, reason)
+            }
+            EvalError::Spanned { span, error } => {
+                write!(f, "{} at position {}", error, span)
+            }
         }
     }
 }
@@ -77,9 +241,11 @@ impl From<mathengine_parser::types::ConversionError> for EvalError {
                     message: "Cannot convert between different dimensions".to_string(),
                 }
             }
-            mathengine_parser::types::ConversionError::Failed => {
+            mathengine_parser::types::ConversionError::Failed
+            | mathengine_parser::types::ConversionError::NotNumeric(_)
+            | mathengine_parser::types::ConversionError::UnitMismatch { .. } => {
                 EvalError::InvalidUnitExpression {
-                    message: "Conversion failed".to_string(),
+                    message: err.to_string(),
                 }
             }
         }
@@ -0,0 +1,459 @@
+use std::f64::consts::PI;
+
+use mathengine_parser::types::{DimensionType, Number, Radix, Rounding, UnitValue, Value};
+
+#[cfg(feature = "random")]
+use crate::random;
+use crate::{number_theory, statistics, EvalError, NumberMode};
+
+// Builtins `call` handles, apart from the random ones behind a feature
+const BUILTINS: &[&str] = &[
+    "sqrt", "sin", "cos", "tan", "exp", "ln", "round", "floor", "ceil", "trunc", "hex", "bin",
+    "oct", "sum", "mean", "median", "stdev", "min", "max", "gcd", "lcm", "isprime", "ncr", "npr",
+];
+
+/// The names of the builtin functions, including `if`, which the evaluator
+/// handles itself so only the chosen branch runs.
+pub fn builtins() -> Vec<&'static str> {
+    #[cfg_attr(not(feature = "random"), allow(unused_mut))]
+    let mut names = BUILTINS.to_vec();
+    #[cfg(feature = "random")]
+    names.extend(["rand", "randint", "roll"]);
+    names.push("if");
+    names
+}
+
+/// Call a builtin function with already-evaluated arguments.
+///
+/// In big float mode roots, powers and the elementary functions of exact
+/// numbers are worked out at the mode's precision.
+pub fn call(name: &str, args: Vec<Value>, mode: NumberMode) -> Result<Value, EvalError> {
+    // Builtins see integers shown in another base as plain integers
+    let args: Vec<Value> = args.into_iter().map(Value::without_radix).collect();
+    match name {
+        "sqrt" => sqrt(one_arg(name, args)?, mode),
+        "sin" => elementary(name, one_arg(name, args)?, f64::sin, mode),
+        "cos" => elementary(name, one_arg(name, args)?, f64::cos, mode),
+        "tan" => elementary(name, one_arg(name, args)?, f64::tan, mode),
+        "exp" => elementary(name, one_arg(name, args)?, f64::exp, mode),
+        "ln" => elementary(name, one_arg(name, args)?, f64::ln, mode),
+        "round" => round(name, args, Rounding::Nearest),
+        "floor" => round(name, args, Rounding::Floor),
+        "ceil" => round(name, args, Rounding::Ceil),
+        "trunc" => round(name, args, Rounding::Trunc),
+        "hex" => in_radix(name, one_arg(name, args)?, Radix::Hexadecimal),
+        "bin" => in_radix(name, one_arg(name, args)?, Radix::Binary),
+        "oct" => in_radix(name, one_arg(name, args)?, Radix::Octal),
+        #[cfg(feature = "random")]
+        "rand" => random::rand(args),
+        #[cfg(feature = "random")]
+        "randint" => random::randint(args),
+        #[cfg(feature = "random")]
+        "roll" => random::roll(args),
+        "sum" | "mean" | "median" | "stdev" | "min" | "max" => aggregate(name, args),
+        "gcd" => fold_integers(name, args, number_theory::gcd),
+        "lcm" => fold_integers(name, args, number_theory::lcm),
+        "isprime" => {
+            let [n] = exact_integers::<1>(name, args)?;
+            Ok(Value::Bool(number_theory::is_prime(n)))
+        }
+        "ncr" | "npr" => {
+            let [n, k] = exact_integers::<2>(name, args)?;
+            if n < 0 || k < 0 {
+                return Err(domain_error(name, "arguments must not be negative"));
+            }
+            let result = if name == "ncr" {
+                number_theory::choose(n, k)
+            } else {
+                number_theory::permutations(n, k)
+            };
+            integer_result(name, result)
+        }
+        _ => Err(EvalError::UnknownFunction {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// Square root, halving unit exponents so `sqrt(9 m^2)` is `3m`.
+fn sqrt(arg: Value, mode: NumberMode) -> Result<Value, EvalError> {
+    match arg {
+        Value::Number(n) => {
+            if n.to_f64() < 0.0 {
+                return Err(domain_error(
+                    "sqrt",
+                    "cannot take the square root of a negative number",
+                ));
+            }
+            Ok(Value::Number(match mode {
+                #[cfg(feature = "bigfloat")]
+                NumberMode::BigFloat { precision } => n.sqrt_big_float(precision),
+                _ => n.sqrt(),
+            }))
+        }
+        Value::UnitValue(uv) => {
+            if uv.value() < 0.0 {
+                return Err(domain_error(
+                    "sqrt",
+                    "cannot take the square root of a negative number",
+                ));
+            }
+            match uv.sqrt() {
+                Some(root) => Ok(Value::UnitValue(root)),
+                None => Err(domain_error(
+                    "sqrt",
+                    &format!(
+                        "cannot take the square root of '{}'",
+                        uv.canonical_unit_name()
+                    ),
+                )),
+            }
+        }
+        Value::Bool(_) => Err(domain_error(
+            "sqrt",
+            "cannot take the square root of a boolean",
+        )),
+        radix @ Value::Radix { .. } => sqrt(radix.without_radix(), mode),
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| sqrt(item, mode))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+    }
+}
+
+/// Trigonometric and exponential functions of plain numbers, in radians.
+fn elementary(name: &str, arg: Value, f: fn(f64) -> f64, mode: NumberMode) -> Result<Value, EvalError> {
+    match arg {
+        Value::Number(n) => {
+            if name == "ln" && n.to_f64() <= 0.0 {
+                return Err(domain_error(
+                    name,
+                    "logarithm is only defined for positive numbers",
+                ));
+            }
+            let result = match mode {
+                #[cfg(feature = "bigfloat")]
+                NumberMode::BigFloat { precision } => n.elementary_big_float(name, precision),
+                _ => None,
+            };
+            Ok(Value::Number(result.unwrap_or_else(|| Number::from(f(n.to_f64())))))
+        }
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| elementary(name, item, f, mode))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        other => Err(domain_error(
+            name,
+            &format!("expected a plain number, found '{}'", other),
+        )),
+    }
+}
+
+/// Round to a number of decimal places (zero by default), keeping the unit:
+/// `round(1.2345m, 2)` is `1.23m`.
+fn round(name: &str, mut args: Vec<Value>, rounding: Rounding) -> Result<Value, EvalError> {
+    let digits = match args.len() {
+        1 => 0,
+        2 => match args.pop() {
+            Some(Value::Number(n)) => n
+                .as_integer()
+                .and_then(|d| i32::try_from(d).ok())
+                .ok_or_else(|| domain_error(name, "digits must be a whole number"))?,
+            _ => return Err(domain_error(name, "digits must be a plain number")),
+        },
+        found => {
+            return Err(EvalError::WrongArgumentCount {
+                function: name.to_string(),
+                expected: 2,
+                found,
+            });
+        }
+    };
+    round_value(name, args.remove(0), digits, rounding)
+}
+
+fn round_value(name: &str, value: Value, digits: i32, rounding: Rounding) -> Result<Value, EvalError> {
+    match value {
+        Value::Number(n) => Ok(Value::Number(n.round_to(digits, rounding))),
+        Value::UnitValue(uv) => {
+            let rounded = Number::Float(uv.value()).round_to(digits, rounding);
+            Ok(Value::UnitValue(uv.with_value(rounded.to_f64())))
+        }
+        Value::Bool(_) => Err(domain_error(name, "cannot round a boolean")),
+        radix @ Value::Radix { .. } => round_value(name, radix.without_radix(), digits, rounding),
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| round_value(name, item, digits, rounding))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+    }
+}
+
+/// Show a whole number in another base: `hex(255)` is `0xff`.
+fn in_radix(name: &str, arg: Value, radix: Radix) -> Result<Value, EvalError> {
+    match arg {
+        Value::Number(n) => match n.as_integer() {
+            Some(value) => Ok(Value::Radix { value, radix }),
+            None => Err(domain_error(
+                name,
+                &format!("expected a whole number, found '{}'", n),
+            )),
+        },
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| in_radix(name, item, radix))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        other => Err(domain_error(
+            name,
+            &format!("expected a whole number, found '{}'", other),
+        )),
+    }
+}
+
+/// Factorial of a non-negative integer, or `gamma(x + 1)` for other real
+/// numbers when `use_gamma` is set.
+pub fn factorial(arg: Value, use_gamma: bool) -> Result<Value, EvalError> {
+    let n = match arg {
+        Value::Number(n) => n,
+        Value::Radix { value, .. } => Number::from(value),
+        Value::UnitValue(uv) => {
+            return Err(domain_error(
+                "factorial",
+                &format!(
+                    "cannot take the factorial of '{}'",
+                    uv.canonical_unit_name()
+                ),
+            ));
+        }
+        Value::Bool(_) => {
+            return Err(domain_error(
+                "factorial",
+                "cannot take the factorial of a boolean",
+            ));
+        }
+        Value::List(items) => {
+            return items
+                .into_iter()
+                .map(|item| factorial(item, use_gamma))
+                .collect::<Result<_, _>>()
+                .map(Value::List);
+        }
+    };
+
+    let overflow = || EvalError::Overflow {
+        operation: "factorial".to_string(),
+    };
+    match n.as_integer() {
+        Some(i) if i < 0 => Err(domain_error(
+            "factorial",
+            "factorial is not defined for negative integers",
+        )),
+        // Overflows past 33!, so the fold stops long before large inputs get expensive
+        Some(i) => (2..=i)
+            .try_fold(1i128, |acc, k| acc.checked_mul(k))
+            .map(|result| Value::Number(Number::from(result)))
+            .ok_or_else(overflow),
+        None if use_gamma => {
+            let result = gamma(n.to_f64() + 1.0);
+            if result.is_finite() {
+                Ok(Value::Number(Number::from(result)))
+            } else {
+                Err(overflow())
+            }
+        }
+        None => Err(domain_error(
+            "factorial",
+            "factorial requires a non-negative integer",
+        )),
+    }
+}
+
+// Lanczos approximation (g = 7, n = 9), accurate to about 15 significant digits
+fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula for the left half of the real line
+        return PI / ((PI * x).sin() * gamma(1.0 - x));
+    }
+
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+    (2.0 * PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * series
+}
+
+fn one_arg(name: &str, mut args: Vec<Value>) -> Result<Value, EvalError> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongArgumentCount {
+            function: name.to_string(),
+            expected: 1,
+            found: args.len(),
+        });
+    }
+    Ok(args.remove(0))
+}
+
+fn aggregate(name: &str, args: Vec<Value>) -> Result<Value, EvalError> {
+    let (values, unit) = quantities(name, args)?;
+    let result = match name {
+        "sum" => Some(statistics::sum(values)),
+        "mean" => statistics::mean(values),
+        "median" => statistics::median(values),
+        "stdev" => statistics::stdev(values),
+        "min" => statistics::min(values),
+        _ => statistics::max(values),
+    };
+    let result = result.ok_or_else(|| match name {
+        "stdev" => domain_error(name, "standard deviation needs at least two values"),
+        _ => empty_list(name),
+    })?;
+    match (result, unit) {
+        (Value::Number(n), Some(unit)) => Ok(Value::UnitValue(unit.with_exact(n))),
+        (result, _) => Ok(result),
+    }
+}
+
+// Aggregates take either a single list (`mean([3, 5, 8])`) or the values as
+// separate arguments (`max(3, 5, 8)`)
+fn items(args: Vec<Value>) -> Vec<Value> {
+    match <[Value; 1]>::try_from(args) {
+        Ok([Value::List(items)]) => items,
+        Ok([single]) => vec![single],
+        Err(args) => args,
+    }
+}
+
+// Unit values are aggregated in the unit of the first one, so
+// `sum(1m, 30cm)` is `1.3m` and `max(1km, 2m)` is `1km`. Returns that unit
+// alongside the numbers.
+fn quantities(
+    name: &str,
+    args: Vec<Value>,
+) -> Result<(Vec<Number>, Option<UnitValue>), EvalError> {
+    let items = items(args);
+    let first = items.iter().find_map(|item| match item {
+        Value::UnitValue(uv) => Some(uv.clone()),
+        _ => None,
+    });
+    let Some(first) = first else {
+        return Ok((numbers(name, items)?, None));
+    };
+    // Only differences of temperatures add up; `sum(1C, 1C)` isn't 2C any
+    // more than it's 548.3K
+    if name == "sum" && first.dimension() == DimensionType::Temperature {
+        return Err(domain_error(name, "cannot add absolute temperatures"));
+    }
+
+    let unit = first.canonical_unit_name();
+    let values = items
+        .into_iter()
+        .map(|item| match item {
+            // Exact amounts of money stay exact
+            Value::UnitValue(uv) if uv.canonical_unit_name() == unit => Ok(uv.amount()),
+            Value::UnitValue(uv) => match uv.convert_to(&unit) {
+                Ok(converted) if uv.same_dimension_as(&first) => Ok(Number::Float(converted.value())),
+                _ => Err(domain_error(
+                    name,
+                    &format!("cannot combine '{}' and '{}'", unit, uv.canonical_unit_name()),
+                )),
+            },
+            other => Err(domain_error(
+                name,
+                &format!("expected values with units, found '{}'", other),
+            )),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((values, Some(first)))
+}
+
+fn numbers(name: &str, args: Vec<Value>) -> Result<Vec<Number>, EvalError> {
+    items(args)
+        .into_iter()
+        .map(|item| match item {
+            Value::Number(n) => Ok(n),
+            other => Err(domain_error(
+                name,
+                &format!("expected plain numbers, found '{}'", other),
+            )),
+        })
+        .collect()
+}
+
+// `gcd` and `lcm` take any number of integers, like the aggregates
+fn fold_integers(
+    name: &str,
+    args: Vec<Value>,
+    f: fn(i128, i128) -> Option<i128>,
+) -> Result<Value, EvalError> {
+    let values = numbers(name, args)?
+        .iter()
+        .map(|n| whole(name, n))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (first, rest) = values.split_first().ok_or_else(|| empty_list(name))?;
+    let result = rest.iter().try_fold(*first, |acc, n| f(acc, *n));
+    integer_result(name, result)
+}
+
+fn exact_integers<const N: usize>(name: &str, args: Vec<Value>) -> Result<[i128; N], EvalError> {
+    let found = args.len();
+    let args = <[Value; N]>::try_from(args).map_err(|_| EvalError::WrongArgumentCount {
+        function: name.to_string(),
+        expected: N,
+        found,
+    })?;
+    let mut values = [0i128; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = match arg {
+            Value::Number(n) => whole(name, &n)?,
+            other => {
+                return Err(domain_error(
+                    name,
+                    &format!("expected whole numbers, found '{}'", other),
+                ));
+            }
+        };
+    }
+    Ok(values)
+}
+
+fn whole(name: &str, n: &Number) -> Result<i128, EvalError> {
+    n.as_integer()
+        .ok_or_else(|| domain_error(name, &format!("expected whole numbers, found '{}'", n)))
+}
+
+fn integer_result(name: &str, result: Option<i128>) -> Result<Value, EvalError> {
+    result
+        .map(|n| Value::Number(Number::from(n)))
+        .ok_or_else(|| EvalError::Overflow {
+            operation: name.to_string(),
+        })
+}
+
+fn empty_list(function: &str) -> EvalError {
+    domain_error(function, "no values given")
+}
+
+fn domain_error(function: &str, message: &str) -> EvalError {
+    EvalError::DomainError {
+        function: function.to_string(),
+        message: message.to_string(),
+    }
+}
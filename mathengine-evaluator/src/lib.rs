@@ -1,106 +1,1686 @@
 use mathengine_lexer::Operation;
 use mathengine_parser::{
     Expression,
-    types::{Number, UnitValue, Value},
+    types::{ConversionError, DimensionType, Number, UnitValue, Value},
 };
 
+pub mod bytecode;
+pub mod check;
+mod comparison;
+pub mod context;
+pub mod currency;
+pub mod derivative;
+pub mod dimensions;
 pub mod error;
+pub mod functions;
+pub mod limits;
+pub mod number_theory;
+pub mod observer;
+#[cfg(feature = "random")]
+mod random;
+pub mod resolver;
+pub mod solve;
+pub mod statistics;
+pub use bytecode::{compile, Program};
+pub use check::{check, DimensionSignature, TypeError};
+pub use context::{EvaluationContext, Function, NumberMode};
+pub use currency::{RateError, RateProvider, StaticRates};
+pub use derivative::differentiate;
+pub use dimensions::DimensionProvider;
 pub use error::EvalError;
+pub use limits::EvalLimits;
+pub use observer::EvalObserver;
+pub use resolver::{FnResolver, Resolver};
+pub use solve::solve;
 
+use observer::NoObserver;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+// How many tasks run between wall-clock and cancellation checks, to keep
+// timeouts cheap
+const TIMEOUT_CHECK_INTERVAL: usize = 64;
+
+// How deep user-defined functions can call each other. Calls don't use the
+// call stack, but a function that recurses without a base case would
+// otherwise run until memory runs out
+const MAX_CALL_DEPTH: usize = 1_000;
+
+/// Evaluate an expression tree to a value.
+///
+/// Evaluation uses an explicit work stack rather than recursion, so deeply
+/// nested expressions (generated or adversarial) can't overflow the call stack.
 pub fn evaluate(expr: &Expression) -> Result<Value, EvalError> {
-    match expr {
-        Expression::Number(n) => Ok(Value::Number(Number::from(*n))),
-        Expression::UnitValue { value, unit } => {
-            Ok(Value::UnitValue(UnitValue::new(*value, unit.clone())))
+    evaluate_with_limits(expr, &EvalLimits::default())
+}
+
+/// Evaluate an expression, failing once more than `max_nodes` nodes have been visited.
+///
+/// Use this when evaluating untrusted input to bound the work done per expression.
+pub fn evaluate_with_node_limit(expr: &Expression, max_nodes: usize) -> Result<Value, EvalError> {
+    let limits = EvalLimits {
+        max_nodes: Some(max_nodes),
+        ..EvalLimits::default()
+    };
+    evaluate_with_limits(expr, &limits)
+}
+
+/// Evaluate an expression while enforcing the given resource limits.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{evaluate_with_limits, EvalError, EvalLimits};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::Parser;
+///
+/// let tokens = Lexer::new("1 + 2 + 3 + 4").tokenize().unwrap();
+/// let ast = Parser::new(tokens).parse().unwrap();
+///
+/// let limits = EvalLimits {
+///     max_operations: Some(2),
+///     ..EvalLimits::default()
+/// };
+/// assert_eq!(
+///     evaluate_with_limits(&ast, &limits).unwrap_err(),
+///     EvalError::OperationLimitExceeded { limit: 2 }
+/// );
+/// ```
+pub fn evaluate_with_limits(expr: &Expression, limits: &EvalLimits) -> Result<Value, EvalError> {
+    let context = EvaluationContext {
+        limits: limits.clone(),
+        ..EvaluationContext::default()
+    };
+    evaluate_with_context(expr, &context)
+}
+
+/// Evaluate an expression with the options in `context`.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{evaluate_with_context, EvaluationContext, NumberMode};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{types::{Number, Value}, Parser};
+///
+/// let tokens = Lexer::new("1/3 + 1/6").tokenize().unwrap();
+/// let ast = Parser::new(tokens).parse().unwrap();
+///
+/// let context = EvaluationContext {
+///     number_mode: NumberMode::Rational,
+///     ..EvaluationContext::default()
+/// };
+/// let result = evaluate_with_context(&ast, &context).unwrap();
+/// match result {
+///     Value::Number(Number::Rational(r)) => assert_eq!(r.to_string(), "1/2"),
+///     other => panic!("expected a fraction, got {}", other),
+/// }
+/// ```
+pub fn evaluate_with_context(expr: &Expression, context: &EvaluationContext) -> Result<Value, EvalError> {
+    run(expr, context, &mut NoObserver)
+}
+
+/// Evaluate an expression, reporting every evaluated node to `observer`.
+///
+/// Nodes are reported children first, so an observer sees each node's inputs
+/// before the node itself. Branches an `if` doesn't take are never reported.
+pub fn evaluate_with_observer<O: EvalObserver + ?Sized>(
+    expr: &Expression,
+    context: &EvaluationContext,
+    observer: &mut O,
+) -> Result<Value, EvalError> {
+    run(expr, context, observer)
+}
+
+// A unit of pending work: descend into a node (at the given depth), combine
+// the values its children left on the value stack, or pick the branch of an
+// `if` once its condition is on the stack
+enum Task<'a> {
+    Visit(&'a Expression, usize),
+    Apply(&'a Expression),
+    Branch {
+        node: &'a Expression,
+        then: &'a Expression,
+        otherwise: &'a Expression,
+        depth: usize,
+    },
+    // Report an `if` to the observer once its chosen branch has a value
+    Observe {
+        node: &'a Expression,
+        condition: Value,
+    },
+    // Bind the arguments of a call to a user-defined function and evaluate
+    // its body
+    Call {
+        node: &'a Expression,
+        function: &'a Function,
+        depth: usize,
+    },
+    // Leave a user-defined function once its body has a value
+    Return {
+        node: &'a Expression,
+        inputs: Vec<Value>,
+    },
+}
+
+// The parameters of a user-defined function being evaluated
+type Scope<'a> = HashMap<&'a str, Value>;
+
+fn run<O: EvalObserver + ?Sized>(
+    root: &Expression,
+    context: &EvaluationContext,
+    observer: &mut O,
+) -> Result<Value, EvalError> {
+    run_in(root, context, observer, Vec::new())
+}
+
+fn run_in<'a, O: EvalObserver + ?Sized>(
+    root: &'a Expression,
+    context: &'a EvaluationContext,
+    observer: &mut O,
+    mut scopes: Vec<Scope<'a>>,
+) -> Result<Value, EvalError> {
+    let limits = &context.limits;
+    let mut tasks = vec![Task::Visit(root, 1)];
+    let mut values: Vec<Value> = Vec::new();
+    let mut visited = 0usize;
+    let mut operations = 0usize;
+    let mut steps = 0usize;
+    let started = limits.timeout.map(|_| Instant::now());
+
+    while let Some(task) = tasks.pop() {
+        steps += 1;
+        if let (Some(timeout), Some(started)) = (limits.timeout, started) {
+            if steps % TIMEOUT_CHECK_INTERVAL == 0 && started.elapsed() > timeout {
+                return Err(EvalError::Timeout { limit: timeout });
+            }
         }
-        Expression::Unit(_unit) => Err(EvalError::InvalidUnitExpression {
-            message: "Cannot evaluate a unit without a value".to_string(),
-        }),
-        Expression::Binary { op, left, right } => match op {
-            Operation::Convert => {
-                let left_val = evaluate(left)?;
-                let (value, from_unit) = match left_val {
-                    Value::UnitValue(uv) => (uv.value(), uv.unit().to_string()),
-                    _ => {
-                        return Err(EvalError::InvalidUnitExpression {
-                            message: "Left side of conversion must be a unit value".to_string(),
-                        });
+        if let Some(cancel) = &limits.cancel {
+            if steps % TIMEOUT_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
+                return Err(EvalError::Cancelled);
+            }
+        }
+
+        match task {
+            Task::Visit(expr, depth) => {
+                visited += 1;
+                if let Some(limit) = limits.max_nodes {
+                    if visited > limit {
+                        return Err(EvalError::NodeLimitExceeded { limit });
                     }
-                };
+                }
+                if let Some(limit) = limits.max_depth {
+                    if depth > limit {
+                        return Err(EvalError::DepthLimitExceeded { limit });
+                    }
+                }
 
-                let to_unit = match right.as_ref() {
-                    Expression::Unit(u) => u,
-                    _ => {
-                        return Err(EvalError::InvalidUnitExpression {
-                            message: "Right side of conversion must be a unit".to_string(),
+                match expr {
+                    Expression::Number(n) => values.push(Value::Number(number_literal(*n, None, context.number_mode))),
+                    Expression::Decimal { value, digits } => {
+                        values.push(Value::Number(number_literal(*value, Some(digits), context.number_mode)))
+                    }
+                    Expression::Integer(i) => values.push(Value::Number(Number::from(*i))),
+                    Expression::Bool(b) => values.push(Value::Bool(*b)),
+                    Expression::UnitValue { value, unit } => {
+                        values.push(Value::UnitValue(unit_literal(*value, unit, context)?))
+                    }
+                    Expression::Unit(name) => match scopes.last().and_then(|scope| scope.get(name.as_str())) {
+                        Some(value) => values.push(value.clone()),
+                        None => values.push(variable(name, context)?),
+                    },
+                    Expression::Binary { op, left, right, .. } => {
+                        let (left, right) = percentage_operands(op, left, right).unwrap_or((left, right));
+                        tasks.push(Task::Apply(expr));
+                        // The target of a conversion is a bare unit, not a value
+                        if !matches!(op, Operation::Convert) {
+                            tasks.push(Task::Visit(right, depth + 1));
+                        }
+                        tasks.push(Task::Visit(left, depth + 1));
+                    }
+                    Expression::Unary { operand, .. }
+                    | Expression::Percent(operand)
+                    | Expression::Factorial { operand, .. } => {
+                        tasks.push(Task::Apply(expr));
+                        tasks.push(Task::Visit(operand, depth + 1));
+                    }
+                    // Only the branch chosen by the condition is evaluated, so
+                    // the other one can't raise errors
+                    Expression::FunctionCall { name, args, span } if name == "if" => {
+                        let [condition, then, otherwise] = args.as_slice() else {
+                            let error = EvalError::WrongArgumentCount {
+                                function: name.clone(),
+                                expected: 3,
+                                found: args.len(),
+                            };
+                            return Err(error.at(Some(*span)));
+                        };
+                        tasks.push(Task::Branch {
+                            node: expr,
+                            then,
+                            otherwise,
+                            depth: depth + 1,
+                        });
+                        tasks.push(Task::Visit(condition, depth + 1));
+                    }
+                    Expression::FunctionCall { name, args, .. } if context.functions.contains_key(name) => {
+                        tasks.push(Task::Call {
+                            node: expr,
+                            function: &context.functions[name],
+                            depth: depth + 1,
                         });
+                        tasks.extend(args.iter().rev().map(|arg| Task::Visit(arg, depth + 1)));
                     }
+                    Expression::FunctionCall { args, .. } | Expression::List(args) => {
+                        tasks.push(Task::Apply(expr));
+                        tasks.extend(args.iter().rev().map(|arg| Task::Visit(arg, depth + 1)));
+                    }
+                }
+                if observer.is_active() && is_leaf(expr) {
+                    let output = values.last().expect("leaf value missing from value stack");
+                    observer.on_node(expr, &[], output);
+                }
+            }
+            Task::Apply(expr) => {
+                operations += 1;
+                if let Some(limit) = limits.max_operations {
+                    if operations > limit {
+                        return Err(EvalError::OperationLimitExceeded { limit });
+                    }
+                }
+
+                let inputs = match observer.is_active() {
+                    true => values[values.len() - arity(expr)..].to_vec(),
+                    false => Vec::new(),
                 };
+                let result = apply(expr, &mut values, context).map_err(|e| e.at(expr.span()))?;
+                observer.on_node(expr, &inputs, &result);
+                values.push(result);
+            }
+            Task::Branch {
+                node,
+                then,
+                otherwise,
+                depth,
+            } => {
+                let condition = pop(&mut values);
+                let chosen = if condition.is_truthy() { then } else { otherwise };
+                if observer.is_active() {
+                    tasks.push(Task::Observe { node, condition });
+                }
+                tasks.push(Task::Visit(chosen, depth));
+            }
+            Task::Observe { node, condition } => {
+                let output = values.last().expect("branch value missing from value stack");
+                observer.on_node(node, &[condition], output);
+            }
+            Task::Call { node, function, depth } => {
+                operations += 1;
+                if let Some(limit) = limits.max_operations {
+                    if operations > limit {
+                        return Err(EvalError::OperationLimitExceeded { limit });
+                    }
+                }
+
+                let Expression::FunctionCall { name, args, span } = node else {
+                    unreachable!("only function calls are called");
+                };
+                if scopes.len() >= MAX_CALL_DEPTH {
+                    return Err(EvalError::RecursionLimitExceeded {
+                        function: name.clone(),
+                        limit: MAX_CALL_DEPTH,
+                    });
+                }
+                let args = values.split_off(values.len() - args.len());
+                let scope = bind(name, function, args.clone()).map_err(|e| e.at(Some(*span)))?;
+                scopes.push(scope);
+                let inputs = if observer.is_active() { args } else { Vec::new() };
+                tasks.push(Task::Return { node, inputs });
+                tasks.push(Task::Visit(&function.body, depth));
+            }
+            Task::Return { node, inputs } => {
+                scopes.pop();
+                let output = values.last().expect("function value missing from value stack");
+                observer.on_node(node, &inputs, output);
+            }
+        }
+    }
+
+    Ok(pop(&mut values))
+}
+
+// Combines the values a compound node's children left on the stack
+fn apply(expr: &Expression, values: &mut Vec<Value>, context: &EvaluationContext) -> Result<Value, EvalError> {
+    match expr {
+        Expression::Binary {
+            op: Operation::Convert,
+            right,
+            ..
+        } => convert(pop(values), right, context),
+        Expression::Binary {
+            op, left, right, ..
+        } if percentage_operands(op, left, right).is_some() =>
+        {
+            let right = pop(values);
+            let left = pop(values);
+            apply_percentage(op, left, right, context)
+        }
+        Expression::Binary { op, .. } => {
+            let right = pop(values);
+            let left = pop(values);
+            apply_binary(op, left, right, context)
+        }
+        Expression::Unary { op, .. } => apply_unary(op, pop(values)),
+        Expression::Percent(_) => {
+            apply_binary(&Operation::Divide, pop(values), hundred(), context)
+        }
+        Expression::Factorial { .. } => {
+            functions::factorial(pop(values), context.gamma_factorial)
+        }
+        Expression::FunctionCall { name, args, .. } => {
+            let args = values.split_off(values.len() - args.len());
+            functions::call(name, args, context.number_mode)
+        }
+        Expression::List(items) => Ok(Value::List(values.split_off(values.len() - items.len()))),
+        _ => unreachable!("only compound expressions are applied"),
+    }
+}
+
+fn is_leaf(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::Number(_)
+            | Expression::Decimal { .. }
+            | Expression::Integer(_)
+            | Expression::Bool(_)
+            | Expression::UnitValue { .. }
+            | Expression::Unit(_)
+    )
+}
+
+// How many values an applied node takes off the value stack
+fn arity(expr: &Expression) -> usize {
+    match expr {
+        Expression::Binary {
+            op: Operation::Convert,
+            ..
+        } => 1,
+        Expression::Binary { .. } => 2,
+        Expression::FunctionCall { args, .. } | Expression::List(args) => args.len(),
+        _ => 1,
+    }
+}
+
+// Builds the number for a decimal literal in the representation the mode asks for
+fn number_literal(value: f64, digits: Option<&str>, mode: NumberMode) -> Number {
+    // Numbers built by hand go by their shortest round-trip digits, so `0.1`
+    // is still a tenth
+    let shortest;
+    let digits = match digits {
+        Some(digits) => digits,
+        None => {
+            shortest = value.to_string();
+            &shortest
+        }
+    };
+    match mode {
+        NumberMode::Rational => Number::rational_from_digits(digits).unwrap_or(Number::Float(value)),
+        #[cfg(feature = "decimal")]
+        // Decimals keep 28 places, so literals too small for that (like the
+        // 324 places of the smallest float) stay floats rather than rounding
+        // away
+        NumberMode::Decimal => Number::decimal_from_digits(digits)
+            .filter(|decimal| (decimal.to_f64() - value).abs() <= value.abs() * 1e-12)
+            .unwrap_or(Number::Float(value)),
+        #[cfg(feature = "bigfloat")]
+        NumberMode::BigFloat { precision } => {
+            Number::big_float_from_digits(digits, precision).unwrap_or(Number::Float(value))
+        }
+        _ => Number::Float(value),
+    }
+}
+
+// `200 + 10%` adds ten percent of 200 and `10% of 50` takes ten percent of 50.
+// For these the percentage's own operand is evaluated in place of the percent node.
+fn percentage_operands<'a>(
+    op: &Operation,
+    left: &'a Expression,
+    right: &'a Expression,
+) -> Option<(&'a Expression, &'a Expression)> {
+    match (op, left, right) {
+        (Operation::Add | Operation::Subtract, _, Expression::Percent(percent)) => Some((left, percent)),
+        (Operation::Of, Expression::Percent(percent), _) => Some((percent, right)),
+        _ => None,
+    }
+}
+
+// Multiplies before dividing by 100 so whole-number percentages stay exact
+fn apply_percentage(
+    op: &Operation,
+    left_val: Value,
+    right_val: Value,
+    context: &EvaluationContext,
+) -> Result<Value, EvalError> {
+    match op {
+        Operation::Of => {
+            let product = apply_binary(&Operation::Multiply, left_val, right_val, context)?;
+            apply_binary(&Operation::Divide, product, hundred(), context)
+        }
+        _ => {
+            let product = apply_binary(&Operation::Multiply, left_val.clone(), right_val, context)?;
+            let share = apply_binary(&Operation::Divide, product, hundred(), context)?;
+            apply_binary(op, left_val, share, context)
+        }
+    }
+}
+
+fn hundred() -> Value {
+    Value::Number(Number::from(100i128))
+}
+
+// Pairs a user-defined function's parameters with the arguments of a call
+fn bind<'a>(name: &str, function: &'a Function, args: Vec<Value>) -> Result<Scope<'a>, EvalError> {
+    if args.len() != function.params.len() {
+        return Err(EvalError::WrongArgumentCount {
+            function: name.to_string(),
+            expected: function.params.len(),
+            found: args.len(),
+        });
+    }
+    Ok(function.params.iter().map(String::as_str).zip(args).collect())
+}
 
-                // Use the new UnitValue conversion method
-                let unit_value = UnitValue::new(value, from_unit.clone());
-                let converted = unit_value.convert_to(to_unit)?;
+// Calls a user-defined function with already-evaluated arguments, for
+// compiled programs
+fn call_function(
+    name: &str,
+    function: &Function,
+    args: Vec<Value>,
+    context: &EvaluationContext,
+) -> Result<Value, EvalError> {
+    let scope = bind(name, function, args)?;
+    run_in(&function.body, context, &mut NoObserver, vec![scope])
+}
+
+// A value in a unit written in the source. Names outside the unit tables
+// must be a currency, a unit of one of the context's dimensions, or dice
+fn unit_literal(value: f64, unit: &Arc<str>, context: &EvaluationContext) -> Result<UnitValue, EvalError> {
+    match UnitValue::new(value, unit.clone()) {
+        Err(ConversionError::UnknownUnit(name)) if is_registered(&name, context) => {
+            Ok(money_in(UnitValue::named(value, unit.clone()), context.number_mode))
+        }
+        result => Ok(result?),
+    }
+}
+
+// Amounts of money are kept in the number mode, like plain numbers, so
+// `0.1 usd + 0.2 usd` is exactly `0.3 usd` in decimal mode. Other units
+// convert through floats, so their values stay floats
+fn money_in(amount: UnitValue, mode: NumberMode) -> UnitValue {
+    if !currency::is_currency(amount.unit()) {
+        return amount;
+    }
+    amount.with_exact(number_literal(amount.value(), None, mode))
+}
+
+fn is_registered(name: &str, context: &EvaluationContext) -> bool {
+    is_fixed_unit(name) || is_provided(name, context)
+}
+
+// Currencies and dice are units whatever the context
+fn is_fixed_unit(name: &str) -> bool {
+    #[cfg(feature = "random")]
+    if random::is_dice(name) {
+        return true;
+    }
+    currency::is_currency(name)
+}
+
+// Whether a dimension in the context has a unit by this name
+fn is_provided(name: &str, context: &EvaluationContext) -> bool {
+    let lowercase = name.to_lowercase();
+    context.dimensions.iter().any(|provider| provider.has_unit(&lowercase))
+}
+
+// A bare identifier is a variable when the context binds it or its resolver
+// knows it, and otherwise a unit missing its value
+fn variable(name: &str, context: &EvaluationContext) -> Result<Value, EvalError> {
+    if let Some(value) = context.variables.get(name) {
+        return Ok(value.clone());
+    }
+    match context.resolver.as_ref().and_then(|resolver| resolver.resolve(name)) {
+        Some(value) => Ok(value),
+        // `_N` is a session's Nth result, and never a unit
+        None if name.starts_with('_') => Err(EvalError::InvalidUnitExpression {
+            message: format!("There's no earlier result {} to recall", name),
+        }),
+        None => Err(EvalError::InvalidUnitExpression {
+            message: "Cannot evaluate a unit without a value".to_string(),
+        }),
+    }
+}
 
-                Ok(Value::UnitValue(converted))
+// Every Apply task is scheduled after the tasks producing its operands, so the
+// value stack always holds enough values here
+fn pop(values: &mut Vec<Value>) -> Value {
+    values.pop().expect("operand missing from value stack")
+}
+
+fn convert(
+    left_val: Value,
+    right: &Expression,
+    context: &EvaluationContext,
+) -> Result<Value, EvalError> {
+    let unit_value = match left_val {
+        Value::UnitValue(uv) => uv,
+        _ => {
+            return Err(EvalError::InvalidUnitExpression {
+                message: "Left side of conversion must be a unit value".to_string(),
+            });
+        }
+    };
+
+    let to_unit = match right {
+        Expression::Unit(u) => u,
+        _ => {
+            return Err(EvalError::InvalidUnitExpression {
+                message: "Right side of conversion must be a unit".to_string(),
+            });
+        }
+    };
+
+    // Units of dimensions supplied by the host convert with their provider
+    if unit_value.known_unit().is_none() && unit_value.exponent() == 1 {
+        let (from, to) = (unit_value.unit().to_lowercase(), to_unit.to_lowercase());
+        if let Some(provider) = context.dimensions.iter().find(|provider| provider.has_unit(&from)) {
+            if !provider.has_unit(&to) {
+                return Err(EvalError::InvalidUnitExpression {
+                    message: format!("'{}' isn't a unit of {}", to, provider.name()),
+                });
             }
-            _ => {
-                let left_val = evaluate(left)?;
-                let right_val = evaluate(right)?;
+            return match provider.convert(unit_value.value(), &from, &to) {
+                Some(value) => Ok(Value::UnitValue(UnitValue::named(value, to))),
+                None => Err(EvalError::InvalidConversion {
+                    from_unit: from,
+                    to_unit: to,
+                }),
+            };
+        }
+    }
 
-                // Check for division by zero before delegating to operators
-                if let Operation::Divide = op {
-                    match &right_val {
-                        Value::Number(n) if n.0 == 0.0 => return Err(EvalError::DivisionByZero),
-                        _ => {}
-                    }
+    // Exchange rates come from the host, not the unit tables
+    if currency::is_currency(unit_value.unit()) && currency::is_currency(to_unit) {
+        let converted = currency::convert(&unit_value, to_unit, context.rates.as_deref())?;
+        return Ok(Value::UnitValue(converted));
+    }
+
+    let converted = unit_value.convert_to(to_unit)?;
+
+    Ok(Value::UnitValue(converted))
+}
+
+fn apply_binary(
+    op: &Operation,
+    left_val: Value,
+    right_val: Value,
+    context: &EvaluationContext,
+) -> Result<Value, EvalError> {
+    // Lists apply the operation element by element, pairing up equal-length
+    // lists and applying a scalar to every element
+    match (left_val, right_val) {
+        (Value::List(l), Value::List(r)) => {
+            if l.len() != r.len() {
+                return Err(EvalError::UnsupportedOperation {
+                    operation: format!("{:?}", op),
+                    operand_type: format!("lists of different lengths ({} and {})", l.len(), r.len()),
+                });
+            }
+            l.into_iter()
+                .zip(r)
+                .map(|(a, b)| apply_binary(op, a, b, context))
+                .collect::<Result<_, _>>()
+                .map(Value::List)
+        }
+        (Value::List(l), r) => l
+            .into_iter()
+            .map(|a| apply_binary(op, a, r.clone(), context))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        (l, Value::List(r)) => r
+            .into_iter()
+            .map(|b| apply_binary(op, l.clone(), b, context))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        (l, r) => apply_scalar(op, l, r, context),
+    }
+}
+
+fn apply_scalar(
+    op: &Operation,
+    left_val: Value,
+    right_val: Value,
+    context: &EvaluationContext,
+) -> Result<Value, EvalError> {
+    let (left_val, right_val) = (left_val.without_radix(), right_val.without_radix());
+    if comparison::is_comparison(op) {
+        return comparison::compare(op, &left_val, &right_val);
+    }
+
+    // Booleans only combine through logical operators
+    if !matches!(op, Operation::And | Operation::Or)
+        && (matches!(left_val, Value::Bool(_)) || matches!(right_val, Value::Bool(_)))
+    {
+        return Err(EvalError::UnsupportedOperation {
+            operation: format!("{:?}", op),
+            operand_type: "boolean".to_string(),
+        });
+    }
+
+    // Check for division by zero before delegating to operators
+    if let Operation::Divide = op {
+        match &right_val {
+            Value::Number(n) if n.is_zero() => return Err(EvalError::DivisionByZero),
+            Value::UnitValue(uv) if uv.value() == 0.0 => return Err(EvalError::DivisionByZero),
+            _ => {}
+        }
+    }
+
+    let result = match (op, context.number_mode, left_val, right_val) {
+        (Operation::Add | Operation::Subtract, _, Value::UnitValue(l), Value::UnitValue(r))
+            if l.dimension() == DimensionType::Unknown || r.dimension() == DimensionType::Unknown =>
+        {
+            sum_unknown(op, l, r, context)?
+        }
+        // Rational mode keeps exact quotients and negative powers as fractions
+        (Operation::Divide, NumberMode::Rational, Value::Number(l), Value::Number(r)) => {
+            Value::Number(l.div_exact(r))
+        }
+        (Operation::Power, NumberMode::Rational, Value::Number(l), Value::Number(r)) => {
+            Value::Number(l.pow_exact(&r))
+        }
+        #[cfg(feature = "decimal")]
+        (Operation::Divide, NumberMode::Decimal, Value::Number(l), Value::Number(r)) => {
+            Value::Number(l.div_decimal(r))
+        }
+        #[cfg(feature = "bigfloat")]
+        (
+            Operation::Divide,
+            NumberMode::BigFloat { precision },
+            Value::Number(l),
+            Value::Number(r),
+        ) => Value::Number(l.div_big_float(r, precision)),
+        #[cfg(feature = "bigfloat")]
+        (
+            Operation::Power,
+            NumberMode::BigFloat { precision },
+            Value::Number(l),
+            Value::Number(r),
+        ) => match l.pow_big_float(&r, precision) {
+            Some(result) => Value::Number(result),
+            // Away from one when the base and the exponent both grow it, or
+            // both shrink it
+            None if (l.to_f64().abs() > 1.0) == (r.to_f64() > 0.0) => {
+                return Err(EvalError::Overflow { operation: "power".to_string() });
+            }
+            None => return Err(EvalError::Underflow { operation: "power".to_string() }),
+        },
+        (_, _, left_val, right_val) => apply_standard(op, left_val, right_val)?,
+    };
+
+    Ok(result)
+}
+
+// Currencies and units of provided dimensions aren't in the unit tables, so
+// the right amount is converted into the left one's unit with the context's
+// rates or providers before they're added. Amounts that can't be converted
+// are an error rather than the left operand
+fn sum_unknown(
+    op: &Operation,
+    left: UnitValue,
+    right: UnitValue,
+    context: &EvaluationContext,
+) -> Result<Value, EvalError> {
+    let converts = left.exponent() == 1 && right.exponent() == 1 && left.unit() != right.unit();
+    let right = if !converts {
+        right
+    } else if currency::is_currency(left.unit()) && currency::is_currency(right.unit()) {
+        if left.unit().eq_ignore_ascii_case(right.unit()) {
+            // The same currency written another way, so the amount is kept
+            UnitValue::named(right.value(), left.unit().to_string()).with_exact(right.amount())
+        } else {
+            let converted = currency::convert(&right, left.unit(), context.rates.as_deref())?.value();
+            UnitValue::named(converted, left.unit().to_string())
+        }
+    } else {
+        let (from, to) = (right.unit().to_lowercase(), left.unit().to_lowercase());
+        match context.dimensions.iter().find(|provider| provider.has_unit(&from) && provider.has_unit(&to)) {
+            Some(provider) => match provider.convert(right.value(), &from, &to) {
+                Some(value) => UnitValue::named(value, left.unit().to_string()),
+                None => {
+                    return Err(EvalError::InvalidConversion {
+                        from_unit: from,
+                        to_unit: to,
+                    });
                 }
+            },
+            None => right,
+        }
+    };
+    let sum = match op {
+        Operation::Add => left.checked_add(&right)?,
+        _ => left.checked_sub(&right)?,
+    };
+    Ok(Value::UnitValue(sum))
+}
 
-                let result = match op {
-                    Operation::Add => left_val + right_val,
-                    Operation::Subtract => left_val - right_val,
-                    Operation::Multiply => left_val * right_val,
-                    Operation::Divide => left_val / right_val,
-                    Operation::Power => {
-                        // Power is not implemented via operators yet, handle specially
-                        match (left_val, right_val) {
-                            (Value::Number(l), Value::Number(r)) => {
-                                Value::Number(Number::from(l.0.powf(r.0)))
-                            }
-                            _ => {
-                                return Err(EvalError::UnsupportedOperation {
-                                    operation: "power".to_string(),
-                                    operand_type: "non-numeric values".to_string(),
-                                });
-                            }
+fn apply_standard(op: &Operation, left_val: Value, right_val: Value) -> Result<Value, EvalError> {
+    let result = match op {
+        Operation::Add => left_val + right_val,
+        Operation::Subtract => left_val - right_val,
+        Operation::Multiply => left_val * right_val,
+        Operation::Divide => left_val / right_val,
+        Operation::And => Value::Bool(left_val.is_truthy() && right_val.is_truthy()),
+        Operation::Or => Value::Bool(left_val.is_truthy() || right_val.is_truthy()),
+        Operation::Power => {
+            // Power is not implemented via operators yet, handle specially
+            match (left_val, right_val) {
+                (Value::Number(l), Value::Number(r)) => Value::Number(l.pow(&r)),
+                (Value::UnitValue(l), Value::Number(r)) => match integer_exponent(&r) {
+                    Some(power) => {
+                        let out_of_range = || EvalError::ExponentOutOfRange { exponent: r.to_string() };
+                        // Every unit's exponent is multiplied by the power
+                        if l.signature().iter().any(|(_, exponent)| exponent.checked_mul(power).is_none()) {
+                            return Err(out_of_range());
+                        }
+                        if power < 0 && l.value() == 0.0 {
+                            return Err(EvalError::DivisionByZero);
+                        }
+                        if l.value().is_finite() && !l.value().powi(power).is_finite() {
+                            return Err(out_of_range());
+                        }
+                        match l.powi(power) {
+                            Some(uv) => Value::UnitValue(uv),
+                            None => Value::Number(Number::from(l.value().powi(power))),
                         }
                     }
-                    Operation::Convert => {
+                    None if r.as_integer().is_some() => {
+                        return Err(EvalError::ExponentOutOfRange { exponent: r.to_string() });
+                    }
+                    None => {
                         return Err(EvalError::UnsupportedOperation {
-                            operation: "convert".to_string(),
-                            operand_type: "binary operation".to_string(),
+                            operation: "power".to_string(),
+                            operand_type: "unit value with a non-integer exponent".to_string(),
                         });
                     }
-                };
-
-                Ok(result)
-            }
-        },
-        Expression::Unary { op, operand } => {
-            let val = evaluate(operand)?;
-            match op {
-                Operation::Subtract => match val {
-                    Value::Number(n) => Ok(Value::Number(-n)),
-                    Value::UnitValue(_) => Err(EvalError::UnsupportedOperation {
-                        operation: "negate".to_string(),
-                        operand_type: "unit value".to_string(),
-                    }),
                 },
-                _ => Err(EvalError::UnsupportedOperation {
-                    operation: format!("{:?}", op),
-                    operand_type: "unary operand".to_string(),
-                }),
+                _ => {
+                    return Err(EvalError::UnsupportedOperation {
+                        operation: "power".to_string(),
+                        operand_type: "non-numeric values".to_string(),
+                    });
+                }
             }
         }
+        Operation::Convert => {
+            return Err(EvalError::UnsupportedOperation {
+                operation: "convert".to_string(),
+                operand_type: "binary operation".to_string(),
+            });
+        }
+        Operation::Of => {
+            return Err(EvalError::UnsupportedOperation {
+                operation: "of".to_string(),
+                operand_type: "value that is not a percentage".to_string(),
+            });
+        }
+        Operation::Not => {
+            return Err(EvalError::UnsupportedOperation {
+                operation: "not".to_string(),
+                operand_type: "binary operation".to_string(),
+            });
+        }
+        Operation::Less
+        | Operation::LessEqual
+        | Operation::Greater
+        | Operation::GreaterEqual
+        | Operation::Equal
+        | Operation::NotEqual => {
+            return comparison::compare(op, &left_val, &right_val);
+        }
+    };
+
+    Ok(result)
+}
+
+// Unit powers must be small integers (m^2, s^-1)
+fn integer_exponent(n: &Number) -> Option<i32> {
+    n.as_integer().and_then(|i| i32::try_from(i).ok())
+}
+
+fn apply_unary(op: &Operation, val: Value) -> Result<Value, EvalError> {
+    match op {
+        Operation::Subtract => match val {
+            Value::Number(n) => Ok(Value::Number(-n)),
+            Value::Radix { value, .. } => Ok(Value::Number(-Number::from(value))),
+            Value::UnitValue(_) => Err(EvalError::UnsupportedOperation {
+                operation: "negate".to_string(),
+                operand_type: "unit value".to_string(),
+            }),
+            Value::Bool(_) => Err(EvalError::UnsupportedOperation {
+                operation: "negate".to_string(),
+                operand_type: "boolean".to_string(),
+            }),
+            Value::List(items) => items
+                .into_iter()
+                .map(|item| apply_unary(op, item))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+        },
+        Operation::Not => match val {
+            Value::List(items) => items
+                .into_iter()
+                .map(|item| apply_unary(op, item))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            other => Ok(Value::Bool(!other.is_truthy())),
+        },
+        _ => Err(EvalError::UnsupportedOperation {
+            operation: format!("{:?}", op),
+            operand_type: "unary operand".to_string(),
+        }),
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mathengine_lexer::Span;
+
+    // Parse `source`, which must lex and parse
+    pub(crate) fn parse(source: &str) -> Expression {
+        let tokens = mathengine_lexer::Lexer::new(source).tokenize().unwrap();
+        mathengine_parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    // Evaluate `source` with the default context
+    pub(crate) fn eval(source: &str) -> Result<Value, EvalError> {
+        evaluate(&parse(source))
+    }
+
+    // Evaluate `source` in `context`
+    pub(crate) fn eval_in(source: &str, context: &EvaluationContext) -> Result<Value, EvalError> {
+        evaluate_with_context(&parse(source), context)
+    }
+
+    fn nested_sum(depth: usize) -> Expression {
+        let mut expr = Expression::Number(1.0);
+        for _ in 0..depth {
+            expr = Expression::Binary {
+                op: Operation::Add,
+                left: Box::new(expr),
+                right: Box::new(Expression::Number(1.0)),
+                span: Span::default(),
+            };
+        }
+        expr
+    }
+
+    #[test]
+    fn test_deep_expression_does_not_overflow() {
+        let expr = nested_sum(20_000);
+        let result = evaluate(&expr).unwrap();
+        assert_eq!(result.to_string(), "20001");
+    }
+
+    #[test]
+    fn test_deeply_nested_input_is_rejected() {
+        let parse = |source: &str| {
+            let tokens = mathengine_lexer::Lexer::new(source).tokenize().unwrap();
+            mathengine_parser::Parser::new(tokens).parse()
+        };
+        for source in [
+            format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000)),
+            format!("{}1", "-".repeat(100_000)),
+            format!("{}1", "2^".repeat(100_000)),
+            format!("{}1", "1 + ".repeat(100_000)),
+        ] {
+            assert!(matches!(parse(&source), Err(mathengine_parser::ParseError::TooDeep { .. })));
+        }
+    }
+
+    #[test]
+    fn test_long_flat_sums() {
+        use mathengine_parser::{InputLimits, ParseError};
+
+        let parse = |source: &str, limits: InputLimits| {
+            let tokens = mathengine_lexer::Lexer::new(source).tokenize().unwrap();
+            mathengine_parser::Parser::new(tokens).with_limits(limits).parse()
+        };
+        for terms in [1_001, 9_000] {
+            let sum = format!("{}1", "1 + ".repeat(terms - 1));
+            let ast = parse(&sum, InputLimits::default()).unwrap();
+            // Cloning, rendering and dropping don't recurse down the chain
+            let copy = ast.clone();
+            assert_eq!(copy.to_latex().len(), sum.len());
+            assert_eq!(evaluate(&copy).unwrap().to_string(), terms.to_string());
+            assert_eq!(compile(&ast).unwrap().run(&EvaluationContext::default()).unwrap().to_string(), terms.to_string());
+        }
+
+        let limits = InputLimits {
+            max_height: 10,
+            max_nesting: 3,
+            ..InputLimits::default()
+        };
+        assert!(parse(&format!("{}1", "1 + ".repeat(9)), limits).is_ok());
+        assert!(matches!(parse(&format!("{}1", "1 + ".repeat(10)), limits), Err(ParseError::TooDeep { limit: 10, .. })));
+        assert!(parse("((1))", limits).is_ok());
+        assert!(matches!(parse("(((1)))", limits), Err(ParseError::TooDeep { limit: 3, .. })));
+    }
+
+    #[test]
+    fn test_input_limits() {
+        use mathengine_parser::{InputLimit, InputLimits, ParseError};
+
+        let parse = |source: &str, limits: InputLimits| {
+            let lexer = mathengine_lexer::Lexer::new(source);
+            mathengine_parser::Parser::new(lexer.tokens()).with_limits(limits).parse()
+        };
+        let limits = InputLimits {
+            max_length: Some(12),
+            max_tokens: Some(6),
+            max_nodes: Some(4),
+            ..InputLimits::default()
+        };
+        assert_eq!(evaluate(&parse("max(2, 3)", limits).unwrap()).unwrap().to_string(), "3");
+        for (source, exceeded, limit) in [
+            ("1 + 2 + 3", InputLimit::Nodes, 4),
+            ("max(1, 2, 3)", InputLimit::Tokens, 6),
+            ("1234567890 + 1", InputLimit::Length, 12),
+            // Limits win over the errors the rest of the input would give
+            ("(1 + 2) * 3 + )", InputLimit::Tokens, 6),
+        ] {
+            assert_eq!(parse(source, limits).unwrap_err(), ParseError::InputTooLarge { exceeded, limit });
+        }
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let limits = EvalLimits {
+            max_depth: Some(5),
+            ..EvalLimits::default()
+        };
+        assert!(evaluate_with_limits(&nested_sum(4), &limits).is_ok());
+        assert_eq!(
+            evaluate_with_limits(&nested_sum(5), &limits).unwrap_err(),
+            EvalError::DepthLimitExceeded { limit: 5 }
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let limits = EvalLimits {
+            timeout: Some(std::time::Duration::ZERO),
+            ..EvalLimits::default()
+        };
+        assert!(matches!(
+            evaluate_with_limits(&nested_sum(1_000), &limits),
+            Err(EvalError::Timeout { .. })
+        ));
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let token = mathengine_parser::CancellationToken::new();
+        let limits = EvalLimits {
+            cancel: Some(token.clone()),
+            ..EvalLimits::default()
+        };
+        assert!(evaluate_with_limits(&nested_sum(1_000), &limits).is_ok());
+        token.cancel();
+        assert_eq!(evaluate_with_limits(&nested_sum(1_000), &limits).unwrap_err(), EvalError::Cancelled);
+        let context = EvaluationContext { limits, ..EvaluationContext::default() };
+        assert_eq!(compile(&nested_sum(1_000)).unwrap().run(&context).unwrap_err(), EvalError::Cancelled);
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let expr = nested_sum(10);
+        assert!(evaluate_with_node_limit(&expr, 21).is_ok());
+        assert_eq!(
+            evaluate_with_node_limit(&expr, 20).unwrap_err(),
+            EvalError::NodeLimitExceeded { limit: 20 }
+        );
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn test_big_float_division() {
+        let context = EvaluationContext {
+            number_mode: NumberMode::BigFloat { precision: 256 },
+            ..EvaluationContext::default()
+        };
+        let expr = Expression::Binary {
+            op: Operation::Divide,
+            left: Box::new(Expression::Integer(1)),
+            right: Box::new(Expression::Integer(3)),
+            span: Span::default(),
+        };
+        let result = evaluate_with_context(&expr, &context).unwrap().to_string();
+        assert_eq!(result, format!("0.{}", "3".repeat(76)));
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn test_big_float_precision() {
+        let context = EvaluationContext {
+            number_mode: NumberMode::BigFloat { precision: 256 },
+            ..EvaluationContext::default()
+        };
+        let eval = |source: &str| {
+            let expr = parse(source);
+            let compiled = bytecode::compile(&expr).unwrap().run(&context).unwrap();
+            let result = evaluate_with_context(&expr, &context).unwrap();
+            assert_eq!(compiled.key(), result.key(), "{}", source);
+            match result {
+                Value::Number(Number::BigFloat(_)) => result.to_string(),
+                other => panic!("{} gave {:?}, not a big float", source, other),
+            }
+        };
+        // Literals keep the digits an f64 can't hold
+        assert_eq!(eval("3.14159265358979323846264338327950288 * 1"), "3.14159265358979323846264338327950288");
+        let root = "1.41421356237309504880168872420969807856967187537694807317667973799";
+        assert!(eval("sqrt(2)").starts_with(root), "{}", eval("sqrt(2)"));
+        assert!(eval("2^0.5").starts_with(root), "{}", eval("2^0.5"));
+        let e = "2.71828182845904523536028747135266249775724709369995957496696762772";
+        assert!(eval("exp(1)").starts_with(e), "{}", eval("exp(1)"));
+        assert_eq!(eval("ln(exp(2))"), "2");
+        let sin = "0.84147098480789650665250232163029899962256306079837106567275170999";
+        assert!(eval("sin(1)").starts_with(sin), "{}", eval("sin(1)"));
+        let tan = "1.55740772465490223050697480745836017308725077238152003838394660569";
+        assert!(eval("tan(1)").starts_with(tan), "{}", eval("tan(1)"));
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn test_big_float_powers_out_of_range() {
+        let context = EvaluationContext {
+            number_mode: NumberMode::BigFloat { precision: 128 },
+            ..EvaluationContext::default()
+        };
+        let eval = |source: &str| {
+            let expr = parse(source);
+            let compiled = bytecode::compile(&expr).unwrap().run(&context);
+            let result = evaluate_with_context(&expr, &context);
+            assert_eq!(compiled.as_ref().map(Value::key), result.as_ref().map(Value::key), "{}", source);
+            result
+        };
+        let huge = "9999999999999999999999999999999999999999";
+        for source in [format!("2^{}", huge), format!("0.5^-{}", huge), "1.5^(10^30)".to_string(), "0.5^-(10^30)".to_string()] {
+            assert!(matches!(eval(&source), Err(EvalError::Overflow { .. })), "{}", source);
+        }
+        for source in [format!("0.5^{}", huge), format!("2^-{}", huge), "3^-(10^30)".to_string()] {
+            assert!(matches!(eval(&source), Err(EvalError::Underflow { .. })), "{}", source);
+        }
+        // Zero and one have powers of any size
+        assert_eq!(eval("1^(10^30)").unwrap().to_string(), "1");
+        assert_eq!(eval("(-1)^(10^30)").unwrap().to_string(), "1");
+        assert_eq!(eval("0^(10^30)").unwrap().to_string(), "0");
+        assert_eq!(eval("2^-2").unwrap().to_string(), "0.25");
+        // Integer powers past a fraction's range stay big floats
+        assert_eq!(eval("10^40").unwrap().to_string(), format!("1{}", "0".repeat(40)));
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn test_huge_big_floats() {
+        let context = EvaluationContext {
+            number_mode: NumberMode::BigFloat { precision: 128 },
+            ..EvaluationContext::default()
+        };
+        let eval = |source: &str| eval_in(source, &context).unwrap().to_string();
+        // Far past 10^1000 only the leading digits are written out
+        assert!(eval("2.5^1000000000000").starts_with("1.0904595806948112"));
+        assert!(eval("2.5^1000000000000").ends_with("e397940008672"));
+        assert!(eval("2^-4000000000").starts_with("2.2083871892065592"));
+        assert!(eval("2^-4000000000").ends_with("e-1204119983"));
+        assert!(eval("exp(-2000000000)").ends_with("e-868588964"));
+        assert_eq!(eval("10^-1001"), "1e-1001");
+        assert_eq!(eval("-2 * 10^1000"), "-2e1000");
+        assert_eq!(eval("10^999"), format!("1{}", "0".repeat(999)));
+        // Exponentials this large fall back to floats rather than running on
+        let start = std::time::Instant::now();
+        assert_eq!(eval("exp(-100000000000)"), "0");
+        assert_eq!(eval("exp(100000000000)"), "inf");
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal_mode_covers_plain_numbers() {
+        let context = EvaluationContext {
+            number_mode: NumberMode::Decimal,
+            ..EvaluationContext::default()
+        };
+        let eval = |source: &str| eval_in(source, &context).unwrap();
+        assert!(matches!(eval("100 * 0.07"), Value::Number(Number::Decimal(_))));
+        assert_eq!(eval("100 * 0.07").to_string(), "7");
+        // Amounts of money are decimals too, whichever way they're worked out
+        for (source, expected) in [
+            ("0.10 USD + 0.20 USD", "0.3USD"),
+            ("100 usd * 0.07", "7usd"),
+            ("(100 * 0.07) * 1usd", "7usd"),
+            ("0.1 * 3 usd", "0.3usd"),
+            ("1 usd / 3", "0.3333333333333333333333333333usd"),
+            ("0.3 usd - 0.1 USD - 0.2 usd", "0usd"),
+            ("sum([0.1 usd, 0.2 usd]) + 0.7 usd", "1usd"),
+        ] {
+            let value = eval(source);
+            assert!(value.as_unit_value().unwrap().exact().is_some(), "{}", source);
+            assert_eq!(value.to_string(), expected, "{}", source);
+            let compiled = bytecode::compile(&parse(source)).unwrap().run(&context).unwrap();
+            assert_eq!(compiled.to_string(), expected, "{}", source);
+        }
+        // while other quantities hold an f64 whatever the mode
+        assert_eq!(eval("0.1m + 0.2m").to_string(), "0.30000000000000004m");
+    }
+
+    #[test]
+    fn test_rational_decimal_literals() {
+        let context = EvaluationContext {
+            number_mode: NumberMode::Rational,
+            ..EvaluationContext::default()
+        };
+        let eval = |source: &str| {
+            let expr = parse(source);
+            let compiled = bytecode::compile(&expr).unwrap().run(&context).unwrap();
+            let result = evaluate_with_context(&expr, &context).unwrap();
+            assert_eq!(compiled.key(), result.key(), "{}", source);
+            match result {
+                Value::Number(Number::Rational(r)) => r.to_string(),
+                other => other.to_string(),
+            }
+        };
+        assert_eq!(eval("0.5 + 1/3"), "5/6");
+        assert_eq!(eval("0.1 + 0.2"), "3/10");
+        assert_eq!(eval("1.50 * 2"), "3");
+        // Digits past what an f64 holds still count
+        assert_eq!(eval("0.10000000000000000001 - 0.1"), "1/100000000000000000000");
+        // Too many digits for an i128 fraction
+        assert_eq!(eval("0.1000000000000000000000000000000000000001 * 10"), "1");
+    }
+
+    #[test]
+    fn test_gamma_factorial() {
+        let half_factorial = Expression::Factorial {
+            operand: Box::new(Expression::Number(0.5)),
+            span: Span::default(),
+        };
+        assert!(matches!(
+            evaluate(&half_factorial),
+            Err(EvalError::DomainError { .. })
+        ));
+
+        let context = EvaluationContext {
+            gamma_factorial: true,
+            ..EvaluationContext::default()
+        };
+        let result = match evaluate_with_context(&half_factorial, &context).unwrap() {
+            Value::Number(n) => n.to_f64(),
+            other => panic!("expected a number, got {:?}", other),
+        };
+        // 0.5! = gamma(1.5) = sqrt(pi) / 2
+        assert!((result - std::f64::consts::PI.sqrt() / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_streamed_tokens_parse_like_collected_ones() {
+        let sources = [
+            "(1m + 50cm) to cm",
+            "9 m^-2 * 3",
+            "area(w, h) = w * h",
+            "x = 2",
+            "solve 2x + 3 = 7 for x",
+            "f(2) = 4",
+            "1 + (2",
+            "1 + + $",
+            "2 3",
+            "",
+        ];
+        for source in sources {
+            let streamed = mathengine_parser::Parser::new(mathengine_lexer::Lexer::new(source).tokens())
+                .parse_statement();
+            let collected = match mathengine_lexer::Lexer::new(source).tokenize_with_spans() {
+                Ok(tokens) => mathengine_parser::Parser::with_spans(tokens).parse_statement(),
+                Err(e) => Err(e.into()),
+            };
+            assert_eq!(format!("{:?}", streamed), format!("{:?}", collected), "{}", source);
+        }
+    }
+
+    #[cfg(feature = "random")]
+    #[test]
+    fn test_random_builtins() {
+        let call = |source| {
+            match eval(source) {
+                Ok(Value::Number(n)) => Ok(n.to_f64()),
+                Ok(other) => panic!("expected a number, got {:?}", other),
+                Err(e) => Err(e),
+            }
+        };
+
+        for _ in 0..100 {
+            let r = call("rand()").unwrap();
+            assert!((0.0..1.0).contains(&r));
+            let i = call("randint(-2, 2)").unwrap();
+            assert!(i.fract() == 0.0 && (-2.0..=2.0).contains(&i));
+            let total = call("roll(3d6)").unwrap();
+            assert!(total.fract() == 0.0 && (3.0..=18.0).contains(&total));
+        }
+        assert_eq!(call("randint(4, 4)").unwrap(), 4.0);
+
+        for source in ["randint(2, 1)", "randint(0.5, 2)", "roll(3m)", "roll(0d6)", "rand(1)"] {
+            assert!(call(source).is_err(), "{}", source);
+        }
+        assert!(matches!(
+            call("roll(1000d170141183460469231731687303715884105727)"),
+            Err(EvalError::Overflow { .. })
+        ));
+    }
+
+    #[test]
+    fn test_radix_values() {
+        let show = |source| eval(source).map(|value| value.to_string());
+
+        assert_eq!(show("hex(255)").unwrap(), "0xff");
+        assert_eq!(show("bin(-5)").unwrap(), "-0b101");
+        assert_eq!(show("oct(0o17)").unwrap(), "0o17");
+        assert_eq!(show("hex([10, 0b11])").unwrap(), "[0xa, 0x3]");
+        assert_eq!(show("hex(bin(255))").unwrap(), "0xff");
+        // Arithmetic, comparisons and builtins see plain integers
+        assert_eq!(show("0xff + 0b1").unwrap(), "256");
+        assert_eq!(show("-hex(16)").unwrap(), "-16");
+        assert_eq!(show("hex(16) == 16").unwrap(), "true");
+        assert_eq!(show("sqrt(hex(16))").unwrap(), "4");
+        assert_eq!(show("hex(3)!").unwrap(), "6");
+        // A prefix without a digit of its base after it is still a unit
+        let tokens = mathengine_lexer::Lexer::new("0b").tokenize().unwrap();
+        assert!(matches!(&tokens[..], [mathengine_lexer::Token::UnitValue { .. }]));
+
+        for source in ["hex(2.5)", "hex(3m)", "bin(true)", "hex(1, 2)"] {
+            assert!(show(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_aggregates_normalize_units() {
+        // The value of each result in the unit it should be in
+        let value_in = |source, unit| match eval(source) {
+            Ok(Value::UnitValue(uv)) => {
+                assert_eq!(uv.canonical_unit_name(), unit, "{}", source);
+                uv.value()
+            }
+            other => panic!("{}: expected a value in {}, got {:?}", source, unit, other),
+        };
+
+        assert!((value_in("sum(1m, 30cm, 2ft)", "m") - 1.9096).abs() < 1e-9);
+        assert!((value_in("max([1m, 150cm, 4ft])", "m") - 1.5).abs() < 1e-9);
+        assert!((value_in("min(1m, 150cm, 4ft)", "m") - 1.0).abs() < 1e-9);
+        assert!((value_in("mean(50cm, 1.5m)", "cm") - 100.0).abs() < 1e-9);
+        assert!((value_in("median([1in, 1ft, 1m])", "in") - 12.0).abs() < 1e-9);
+        // Results are in the first value's unit, not the base unit
+        assert_eq!(value_in("max(1km, 2m)", "km"), 1.0);
+        assert_eq!(value_in("sum(2m^2, 5000cm^2)", "m^2"), 2.5);
+        assert_eq!(value_in("sum(60mi/h, 1mi/h)", "mi/h"), 61.0);
+        // Temperatures are averaged in their own scale
+        assert!((value_in("mean(10C, 20C)", "C") - 15.0).abs() < 1e-9);
+        assert!((value_in("mean(50F, 10C)", "F") - 50.0).abs() < 1e-9);
+        assert_eq!(value_in("max(10C, 283.15K)", "C"), 10.0);
+        // Masses, and values in the same unit the engine has no table for
+        assert_eq!(value_in("sum(2kg, 3kg)", "kg"), 5.0);
+        assert!((value_in("max(3kg, 5lb)", "kg") - 3.0).abs() < 1e-9);
+        assert!((value_in("max(2kg, 5lb)", "kg") - 2.26796185).abs() < 1e-9);
+        assert_eq!(value_in("sum(3usd, 4usd)", "usd"), 7.0);
+        assert_eq!(value_in("mean([2usd, 4usd])", "usd"), 3.0);
+
+        for source in ["sum(1m, 2C)", "max(1m, 2)", "mean(3, 2ft)", "sum(1kg, 2m)", "sum(1usd, 2eur)", "sum(1C, 1C)", "sum(1m, 1m^2)"] {
+            assert!(eval(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_currency_conversion() {
+        #[derive(Debug)]
+        struct Stale;
+
+        impl RateProvider for Stale {
+            fn rate(&self, _from: &str, _to: &str) -> Result<f64, RateError> {
+                Err(RateError::Stale {
+                    age: std::time::Duration::from_secs(7200),
+                })
+            }
+        }
+
+        let with_rates = |rates: Option<std::sync::Arc<dyn RateProvider>>| EvaluationContext {
+            rates,
+            ..EvaluationContext::default()
+        };
+        let rates = with_rates(Some(std::sync::Arc::new(
+            StaticRates::new().with_rate("usd", "eur", 0.5),
+        )));
+
+        let expr = parse("10 USD to eur");
+        assert_eq!(evaluate_with_context(&expr, &rates).unwrap().to_string(), "5eur");
+        assert_eq!(compile(&expr).unwrap().run(&rates).unwrap().to_string(), "5eur");
+        assert_eq!(
+            evaluate_with_context(&parse("4eur to usd"), &rates).unwrap().to_string(),
+            "8usd"
+        );
+        assert!(check(&expr).is_ok());
+
+        let reason = |source, context: &EvaluationContext| {
+            match evaluate_with_context(&parse(source), context).unwrap_err() {
+                EvalError::RateUnavailable { reason, .. } => reason,
+                other => panic!("{}: expected a rate error, got {:?}", source, other),
+            }
+        };
+        assert_eq!(reason("1 usd to gbp", &rates), RateError::Missing);
+        assert_eq!(reason("1 usd to eur", &with_rates(None)), RateError::NoProvider);
+        assert!(matches!(
+            reason("1 usd to eur", &with_rates(Some(std::sync::Arc::new(Stale)))),
+            RateError::Stale { .. }
+        ));
+        // Units that aren't currencies still can't convert to one
+        assert!(evaluate_with_context(&parse("1m to usd"), &rates).is_err());
+    }
+
+    #[test]
+    fn test_currency_sums() {
+        let rates = EvaluationContext {
+            rates: Some(std::sync::Arc::new(StaticRates::new().with_rate("usd", "eur", 0.5))),
+            ..EvaluationContext::default()
+        };
+        for (source, expected) in [
+            ("3usd + 4usd", "7usd"),
+            ("10 usd - 5 usd", "5usd"),
+            ("10 usd + 4 eur", "18usd"),
+            ("1 eur + 2 USD", "2eur"),
+            ("sum([1usd, 2usd])", "3usd"),
+        ] {
+            let expr = parse(source);
+            // Strict mode checks before evaluating, so it must agree
+            assert!(check(&expr).is_ok(), "{}", source);
+            assert_eq!(evaluate_with_context(&expr, &rates).unwrap().to_string(), expected, "{}", source);
+            assert_eq!(compile(&expr).unwrap().run(&rates).unwrap().to_string(), expected, "{}", source);
+        }
+
+        // Without a rate the sum is an error, never the left operand
+        assert!(matches!(
+            evaluate(&parse("1 usd + 1 eur")),
+            Err(EvalError::RateUnavailable { reason: RateError::NoProvider, .. })
+        ));
+        assert!(matches!(
+            evaluate_with_context(&parse("1 usd + 1 gbp"), &rates),
+            Err(EvalError::RateUnavailable { reason: RateError::Missing, .. })
+        ));
+        assert!(evaluate(&parse("1m + 1usd")).is_err());
+        assert!(check(&parse("1m + 1usd")).is_err());
+    }
+
+    #[test]
+    fn test_unknown_units() {
+        // Only units in the tables, currencies and provided units make values
+        for source in ["5 foo", "2 zz * 3", "1m + 1 parsec"] {
+            let expr = parse(source);
+            assert!(matches!(evaluate(&expr), Err(EvalError::UnknownUnit { .. })), "{}", source);
+            let context = EvaluationContext::default();
+            assert!(matches!(compile(&expr).unwrap().run(&context), Err(EvalError::UnknownUnit { .. })), "{}", source);
+        }
+        assert_eq!(evaluate(&parse("5 USD * 2")).unwrap().to_string(), "10USD");
+        assert_eq!(compile(&parse("5 usd")).unwrap().run(&EvaluationContext::default()).unwrap().to_string(), "5usd");
+    }
+
+    #[test]
+    fn test_unit_powers() {
+        let both = |source| {
+            let expr = parse(source);
+            let compiled = compile(&expr).unwrap().run(&EvaluationContext::default());
+            (evaluate(&expr), compiled)
+        };
+        // Powers of units group to the right, like powers of numbers
+        assert_eq!(evaluate(&parse("5m^2^2")).unwrap().to_string(), "5m^4");
+        assert_eq!(evaluate(&parse("(2m)^2^2")).unwrap().to_string(), "16m^4");
+
+        for source in ["1 m^99999999999", "(2m)^2147483647", "(1m^2)^2000000000", "(1m/s)^99999999999"] {
+            let (tree, compiled) = both(source);
+            assert!(matches!(tree, Err(EvalError::ExponentOutOfRange { .. })), "{}: {:?}", source, tree);
+            assert!(matches!(compiled, Err(EvalError::ExponentOutOfRange { .. })), "{}", source);
+        }
+        for source in ["1 / 0m", "1m/0m", "5 / (2m - 2m)", "(0m)^-1"] {
+            let (tree, compiled) = both(source);
+            assert!(matches!(tree, Err(EvalError::DivisionByZero)), "{}: {:?}", source, tree);
+            assert!(matches!(compiled, Err(EvalError::DivisionByZero)), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_time_units() {
+        assert_eq!(eval("90 minutes to h").unwrap().to_string(), "1.5h");
+        assert_eq!(eval("(2h + 30min) to min").unwrap().to_string(), "150min");
+        assert_eq!(eval("1 week to days").unwrap().to_string(), "7d");
+        assert_eq!(
+            eval("1.5min").unwrap().as_unit_value().unwrap().to_duration(),
+            Ok(std::time::Duration::from_secs(90))
+        );
+        // `min` is still the function when called
+        assert_eq!(eval("min(3, 5)").unwrap().to_string(), "3");
+    }
+
+    #[test]
+    fn test_derived_units() {
+        assert_eq!(eval("10km / 2h").unwrap().to_string(), "5km/h");
+        assert_eq!(eval("36km/h to m/s").unwrap().to_string(), "10m/s");
+        let acceleration = eval("1ft/s^2 to in/s^2").unwrap();
+        let acceleration = acceleration.as_unit_value().unwrap();
+        assert_eq!(acceleration.unit(), "in/s^2");
+        assert!((acceleration.value() - 12.0).abs() < 1e-9);
+        assert_eq!(eval("50km/h * 30min").unwrap().to_string(), "25km");
+        assert_eq!(eval("3m * 2s").unwrap().to_string(), "6m*s");
+        assert_eq!(eval("(3m/s)^2").unwrap().to_string(), "9m^2/s^2");
+        assert_eq!(eval("1m/s + 3.6km/h").unwrap().to_string(), "2m/s");
+        assert_eq!(eval("1m/s == 3.6km/h").unwrap().to_string(), "true");
+        // Areas need no derived unit of their own
+        assert_eq!(eval("1ft^2 to in^2").unwrap().to_string(), "144in^2");
+        // `/` only folds into a unit when it's followed by a unit
+        assert_eq!(eval("6m / 2").unwrap().to_string(), "3m");
+    }
+
+    #[test]
+    fn test_frequency_units() {
+        assert_eq!(eval("2.5Hz to ms").unwrap().to_string(), "400ms");
+        assert_eq!(eval("20ms to Hz").unwrap().to_string(), "50Hz");
+        assert_eq!(eval("1.5MHz to kHz").unwrap().to_string(), "1500kHz");
+        assert_eq!(eval("1 minute to Hz").unwrap().as_unit_value().unwrap().value(), 1.0 / 60.0);
+        assert!(matches!(
+            evaluate(&parse("0Hz to s")),
+            Err(EvalError::InvalidUnitExpression { .. })
+        ));
+        // Only plain frequencies and periods are reciprocals
+        assert!(evaluate(&parse("2Hz to m")).is_err());
+        assert!(evaluate(&parse("2s^2 to Hz")).is_err());
+
+        // A frequency times a period is a plain number, in either order
+        assert_eq!(eval("1 Hz * 1 s").unwrap().to_string(), "1");
+        assert_eq!(eval("1 s * 1 Hz").unwrap().to_string(), "1");
+        assert_eq!(eval("2 kHz * 3 ms").unwrap().to_string(), "6");
+        assert_eq!(eval("2 Hz * 3 min").unwrap().to_string(), "360");
+        assert_eq!(eval("1 min^-1 * 60 s").unwrap().to_string(), "1");
+        assert_eq!(eval("10 Hz / (1 / 2s)").unwrap().to_string(), "20");
+        assert_eq!(eval("5m * 2Hz * 3s").unwrap().to_string(), "30m");
+        assert_eq!(check(&parse("1 Hz * 1 s")), Ok(DimensionSignature::Dimensionless));
+        assert_eq!(check(&parse("1 s * 1 Hz")), Ok(DimensionSignature::Dimensionless));
+        assert_eq!(
+            check(&parse("5m * 2Hz * 3s")),
+            Ok(DimensionSignature::Quantity {
+                dimension: DimensionType::Length,
+                exponent: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let mut context = EvaluationContext::default();
+        context.set_variable("x", 10i128);
+        context.define_function("area", vec!["w".to_string(), "h".to_string()], parse("w * h"));
+        context.define_function("shift", vec!["x".to_string()], parse("x + 1"));
+        context.define_function("fact", vec!["n".to_string()], parse("if(n < 1, 1, n * fact(n - 1))"));
+        context.define_function("forever", vec!["n".to_string()], parse("forever(n)"));
+
+        assert_eq!(eval_in("area(2m, 3m)", &context).unwrap().to_string(), "6m^2");
+        // Parameters shadow variables, and only inside the call
+        assert_eq!(eval_in("shift(1) + x", &context).unwrap().to_string(), "12");
+        assert_eq!(eval_in("fact(20)", &context).unwrap().to_string(), "2432902008176640000");
+        assert_eq!(compile(&parse("fact(5)")).unwrap().run(&context).unwrap().to_string(), "120");
+        assert!(matches!(
+            eval_in("area(1)", &context).unwrap_err().without_span(),
+            EvalError::WrongArgumentCount { expected: 2, found: 1, .. }
+        ));
+        assert!(matches!(
+            eval_in("forever(1)", &context).unwrap_err(),
+            EvalError::RecursionLimitExceeded { limit: MAX_CALL_DEPTH, .. }
+        ));
+    }
+
+    #[test]
+    fn test_resolver() {
+        #[derive(Debug)]
+        struct Cells;
+
+        impl Resolver for Cells {
+            fn resolve(&self, name: &str) -> Option<Value> {
+                match name {
+                    "a1" => Some(Value::from(3i128)),
+                    "b1" => Some(Value::UnitValue(UnitValue::new(2.0, "m".to_string()).unwrap())),
+                    _ => None,
+                }
+            }
+        }
+
+        let mut context = EvaluationContext {
+            resolver: Some(std::sync::Arc::new(Cells)),
+            ..EvaluationContext::default()
+        };
+        context.define_function("double", vec!["a1".to_string()], parse("a1 * 2"));
+
+        assert_eq!(eval_in("A1 * B1", &context).unwrap().to_string(), "6m");
+        // Parameters and bound variables come before the resolver
+        assert_eq!(eval_in("double(5)", &context).unwrap().to_string(), "10");
+        context.set_variable("a1", 10i128);
+        assert_eq!(eval_in("a1 + 1", &context).unwrap().to_string(), "11");
+        assert!(eval_in("c1", &context).is_err());
+    }
+
+    #[test]
+    fn test_recalled_results() {
+        let mut context = EvaluationContext::default();
+        context.set_variable("_1", Value::UnitValue(UnitValue::new(5.0, "m".to_string()).unwrap()));
+        context.set_variable("_12", 3i128);
+
+        assert_eq!(eval_in("_1 * _12", &context).unwrap().to_string(), "15m");
+        assert_eq!(eval_in("_1 to cm", &context).unwrap().to_string(), "500cm");
+        let err = eval_in("_2 + 1", &context).unwrap_err();
+        assert!(err.to_string().contains("no earlier result _2"), "{}", err);
+    }
+
+    #[test]
+    fn test_round_trip_formatting() {
+        let options = mathengine_parser::types::FormatOptions {
+            round_trip: true,
+            ..Default::default()
+        };
+        let modes = [
+            NumberMode::Standard,
+            NumberMode::Rational,
+            #[cfg(feature = "decimal")]
+            NumberMode::Decimal,
+        ];
+
+        let sources = [
+            "0.1 + 0.2",
+            "2.0",
+            "7",
+            "1 / 3",
+            "-2 / 3",
+            "0 - 0.0",
+            "2^100",
+            "0.5^40",
+            // The smallest subnormal, the smallest normal and the largest float
+            "2^-1074",
+            "0 - 2^-1074",
+            "2^-1022",
+            "(2 - 2^-52) * 2^1023",
+            "2^-1074 * 1m",
+            "0 - 170141183460469231731687303715884105727 - 1",
+            "123456789012345678 * 1.5",
+            "1m to ft",
+            "0m - 5.5cm",
+            "(3m)^2",
+            "1 / 4s",
+            "-40 * 1F",
+            "[1 / 3, 2m, [true, hex(-255)]]",
+        ];
+        for mode in modes {
+            let context = EvaluationContext {
+                number_mode: mode,
+                ..EvaluationContext::default()
+            };
+            for source in sources {
+                let value = eval_in(source, &context).unwrap();
+                let formatted = value.format(&options);
+                let again = eval_in(&formatted, &context).unwrap();
+                // The round-trip form keeps the representation, so it's the
+                // same only if that survived too
+                assert_eq!(again.key(), value.key(), "{} as {}", source, formatted);
+                assert_eq!(again.format(&options), formatted, "{}", source);
+            }
+        }
+    }
+
+    #[test]
+    fn test_localized_round_trip() {
+        use mathengine_lexer::LexError;
+        use mathengine_parser::types::{FormatOptions, Locale};
+
+        let sources = ["1234567.89", "1500", "0 - 0.25", "999", "1234.5m", "[1000, 2.5, 1000000]"];
+        for tag in ["de", "de-CH", "fr", "ru", "es"] {
+            let locale = Locale::from_tag(tag).unwrap();
+            let options = FormatOptions {
+                locale,
+                ..FormatOptions::default()
+            };
+            for source in sources {
+                let value = eval(source).unwrap();
+                let formatted = value.format(&options);
+                let read = locale.delocalize(&formatted).unwrap();
+                assert_eq!(read.chars().count(), formatted.chars().count(), "{}", formatted);
+                assert_eq!(eval(&read).unwrap().key(), value.key(), "{} in {} as {}", source, tag, formatted);
+            }
+        }
+
+        let german = Locale::from_tag("de-DE").unwrap();
+        assert_eq!(german.delocalize("1.234,5").unwrap(), " 1234.5");
+        assert_eq!(eval(&german.delocalize("1.500").unwrap()).unwrap().to_string(), "1500");
+        // Errors quote the number as it was typed
+        for (source, number, position) in [("1.5 + 1", "1.5", 0), ("2 * 12.34,5", "12.34,5", 4), ("1,2,3", "1,2,3", 0)] {
+            assert_eq!(
+                german.delocalize(source).unwrap_err(),
+                LexError::InvalidNumber {
+                    input: number.to_string(),
+                    position,
+                },
+            );
+        }
+    }
+}
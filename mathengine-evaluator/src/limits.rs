@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use mathengine_parser::CancellationToken;
+
+/// Resource limits enforced while evaluating an expression.
+///
+/// Every limit is optional and unset by default, so `EvalLimits::default()`
+/// evaluates without restrictions. Services evaluating user-submitted
+/// expressions should set all of them.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use mathengine_evaluator::EvalLimits;
+///
+/// let limits = EvalLimits {
+///     max_depth: Some(64),
+///     max_operations: Some(10_000),
+///     timeout: Some(Duration::from_millis(50)),
+///     ..EvalLimits::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalLimits {
+    /// Maximum nesting depth of the expression tree
+    pub max_depth: Option<usize>,
+    /// Maximum number of operations (operators and function calls) applied
+    pub max_operations: Option<usize>,
+    /// Maximum number of expression nodes visited
+    pub max_nodes: Option<usize>,
+    /// Maximum wall-clock time spent evaluating
+    pub timeout: Option<Duration>,
+    /// Stops evaluation with [`EvalError::Cancelled`](crate::EvalError::Cancelled)
+    /// once cancelled, checked as often as the timeout
+    pub cancel: Option<CancellationToken>,
+}
@@ -0,0 +1,112 @@
+//! Integer functions used by builtins like `gcd(12, 18)` and `nCr(5, 2)`.
+//!
+//! Everything here works on exact integers and returns `None` when the result
+//! doesn't fit in an `i128`.
+
+/// Greatest common divisor, always non-negative; `gcd(0, 0)` is zero.
+pub fn gcd(a: i128, b: i128) -> Option<i128> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    i128::try_from(a).ok()
+}
+
+/// Least common multiple, always non-negative; zero if either input is zero.
+pub fn lcm(a: i128, b: i128) -> Option<i128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)?).checked_mul(b).map(i128::abs)
+}
+
+/// Whether `n` is prime.
+///
+/// Uses a Miller-Rabin test with the first twelve primes as witnesses, which
+/// is exact below 3.3 × 10^24. Beyond that a composite passing every witness
+/// is possible in principle but not known for any number.
+pub fn is_prime(n: i128) -> bool {
+    const WITNESSES: [u128; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+    let n = n as u128;
+    for p in WITNESSES {
+        if n % p == 0 {
+            return n == p;
+        }
+    }
+
+    // n - 1 = d * 2^s with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    WITNESSES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// Number of ways to choose `k` items from `n` ignoring order, or zero when
+/// `k > n`. Both must be non-negative.
+pub fn choose(n: i128, k: i128) -> Option<i128> {
+    if k > n {
+        return Some(0);
+    }
+    // Each partial product is itself a binomial coefficient, so the division is exact
+    let k = k.min(n - k);
+    (0..k).try_fold(1i128, |acc, i| Some(acc.checked_mul(n - i)? / (i + 1)))
+}
+
+/// Number of ordered arrangements of `k` items from `n`, or zero when
+/// `k > n`. Both must be non-negative.
+pub fn permutations(n: i128, k: i128) -> Option<i128> {
+    if k > n {
+        return Some(0);
+    }
+    (0..k).try_fold(1i128, |acc, i| acc.checked_mul(n - i))
+}
+
+// Multiplication modulo `m` without overflowing: double-and-add, reducing as it goes
+fn mul_mod(mut a: u128, mut b: u128, m: u128) -> u128 {
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+    let mut result = 0u128;
+    a %= m;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    result
+}
+
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    // Both are below m, so subtracting first avoids overflow
+    if a >= m - b { a - (m - b) } else { a + b }
+}
+
+fn pow_mod(mut base: u128, mut exponent: u128, m: u128) -> u128 {
+    let mut result = 1u128;
+    base %= m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
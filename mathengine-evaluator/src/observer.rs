@@ -0,0 +1,53 @@
+use mathengine_parser::{Expression, types::Value};
+
+/// Hook invoked as each node of an expression is evaluated, for profiling,
+/// logging, or stepping through an evaluation.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{evaluate_with_observer, EvalObserver, EvaluationContext};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{types::Value, Expression, Parser};
+///
+/// #[derive(Default)]
+/// struct Trace(Vec<String>);
+///
+/// impl EvalObserver for Trace {
+///     fn on_node(&mut self, _node: &Expression, inputs: &[Value], output: &Value) {
+///         let inputs: Vec<String> = inputs.iter().map(|v| v.to_string()).collect();
+///         self.0.push(format!("[{}] -> {}", inputs.join(", "), output));
+///     }
+/// }
+///
+/// let tokens = Lexer::new("2 * 3 + 1").tokenize().unwrap();
+/// let ast = Parser::new(tokens).parse().unwrap();
+///
+/// let mut trace = Trace::default();
+/// evaluate_with_observer(&ast, &EvaluationContext::default(), &mut trace).unwrap();
+/// assert_eq!(trace.0.last().unwrap(), "[6, 1] -> 7");
+/// ```
+pub trait EvalObserver {
+    /// Called once `node` has evaluated to `output`. `inputs` holds the values
+    /// of its children, and is empty for literals and variables.
+    fn on_node(&mut self, node: &Expression, inputs: &[Value], output: &Value);
+
+    /// Whether to report nodes at all. Evaluating without an observer uses an
+    /// inactive one, so the bookkeeping compiles away.
+    fn is_active(&self) -> bool {
+        true
+    }
+}
+
+// Stands in when no observer is registered
+pub(crate) struct NoObserver;
+
+impl EvalObserver for NoObserver {
+    #[inline]
+    fn on_node(&mut self, _node: &Expression, _inputs: &[Value], _output: &Value) {}
+
+    #[inline]
+    fn is_active(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,107 @@
+//! Random-number builtins: `rand()`, `randint(a, b)` and dice rolls like `roll(3d6)`.
+
+use mathengine_parser::types::{Number, Value};
+use rand::Rng;
+
+use crate::EvalError;
+
+// Each die is rolled separately, so cap how many one call can ask for
+const MAX_DICE: i128 = 1_000;
+
+/// A uniformly distributed number in `[0, 1)`.
+pub(crate) fn rand(args: Vec<Value>) -> Result<Value, EvalError> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongArgumentCount {
+            function: "rand".to_string(),
+            expected: 0,
+            found: args.len(),
+        });
+    }
+    Ok(Value::Number(Number::from(rand::rng().random::<f64>())))
+}
+
+/// A uniformly distributed integer between `low` and `high`, inclusive.
+pub(crate) fn randint(args: Vec<Value>) -> Result<Value, EvalError> {
+    let [low, high] = <[Value; 2]>::try_from(args).map_err(|args| {
+        EvalError::WrongArgumentCount {
+            function: "randint".to_string(),
+            expected: 2,
+            found: args.len(),
+        }
+    })?;
+    let (low, high) = (integer("randint", &low)?, integer("randint", &high)?);
+    if low > high {
+        return Err(domain_error(
+            "randint",
+            "the lower bound must not exceed the upper bound",
+        ));
+    }
+    Ok(Value::Number(Number::from(rand::rng().random_range(low..=high))))
+}
+
+// The number of sides of dice like `d6`
+fn sides(unit: &str) -> Option<i128> {
+    unit.strip_prefix('d')
+        .and_then(|sides| sides.parse::<i128>().ok())
+        .filter(|sides| *sides > 0)
+}
+
+/// Whether `unit` is dice notation like `d6`, the unit `3d6` is read with.
+pub(crate) fn is_dice(unit: &str) -> bool {
+    sides(unit).is_some()
+}
+
+/// The total of a dice roll written in dice notation, like `3d6` for three
+/// six-sided dice.
+///
+/// The lexer reads `3d6` as the value 3 with unit "d6", so the unit gives the
+/// number of sides.
+pub(crate) fn roll(args: Vec<Value>) -> Result<Value, EvalError> {
+    let [dice] = <[Value; 1]>::try_from(args).map_err(|args| EvalError::WrongArgumentCount {
+        function: "roll".to_string(),
+        expected: 1,
+        found: args.len(),
+    })?;
+
+    let notation = || domain_error("roll", "expected dice notation such as 3d6");
+    let Value::UnitValue(dice) = dice else {
+        return Err(notation());
+    };
+    let sides = sides(dice.unit()).ok_or_else(notation)?;
+    let count = Some(dice.value())
+        .filter(|count| count.fract() == 0.0 && *count >= 1.0)
+        .ok_or_else(notation)? as i128;
+    if count > MAX_DICE {
+        return Err(domain_error(
+            "roll",
+            &format!("cannot roll more than {} dice at once", MAX_DICE),
+        ));
+    }
+
+    let mut rng = rand::rng();
+    let total = (0..count)
+        .try_fold(0i128, |total, _| total.checked_add(rng.random_range(1..=sides)))
+        .ok_or_else(|| EvalError::Overflow {
+            operation: "roll".to_string(),
+        })?;
+    Ok(Value::Number(Number::from(total)))
+}
+
+fn integer(function: &str, value: &Value) -> Result<i128, EvalError> {
+    match value {
+        Value::Number(n) => n
+            .as_integer()
+            .ok_or_else(|| domain_error(function, "expected whole numbers")),
+        other => Err(domain_error(
+            function,
+            &format!("expected whole numbers, found '{}'", other),
+        )),
+    }
+}
+
+fn domain_error(function: &str, message: &str) -> EvalError {
+    EvalError::DomainError {
+        function: function.to_string(),
+        message: message.to_string(),
+    }
+}
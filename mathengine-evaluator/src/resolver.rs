@@ -0,0 +1,98 @@
+//! Values for identifiers supplied by the host as they're needed.
+//!
+//! Binding every variable up front doesn't suit a spreadsheet with thousands
+//! of cells or a price feed with thousands of symbols. A [`Resolver`] in the
+//! [`EvaluationContext`](crate::EvaluationContext) is asked for identifiers
+//! the context doesn't bind, only when an expression uses them.
+
+use std::fmt;
+
+use mathengine_parser::types::Value;
+
+/// A source of values for identifiers, such as cell references or ticker
+/// symbols.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{evaluate_with_context, EvaluationContext, Resolver};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{types::Value, Parser};
+/// use std::sync::Arc;
+///
+/// #[derive(Debug)]
+/// struct Prices;
+///
+/// impl Resolver for Prices {
+///     fn resolve(&self, name: &str) -> Option<Value> {
+///         match name {
+///             "aapl" => Some(Value::from(190.5)),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let tokens = Lexer::new("10 * AAPL").tokenize().unwrap();
+/// let ast = Parser::new(tokens).parse().unwrap();
+/// let context = EvaluationContext {
+///     resolver: Some(Arc::new(Prices)),
+///     ..EvaluationContext::default()
+/// };
+/// assert_eq!(evaluate_with_context(&ast, &context).unwrap().to_string(), "1905");
+/// ```
+pub trait Resolver: fmt::Debug + Send + Sync {
+    /// The value of the identifier `name`, which is lowercase like all
+    /// identifiers, or `None` if it's unknown here too.
+    fn resolve(&self, name: &str) -> Option<Value>;
+}
+
+/// A [`Resolver`] that calls a closure, for sources that don't need a type of
+/// their own, like a table of cells or `std::env::var`.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{evaluate_with_context, EvaluationContext, FnResolver};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{types::Value, Parser};
+/// use std::collections::HashMap;
+/// use std::sync::Arc;
+///
+/// let cells = HashMap::from([("a1", 1.5), ("a2", 4.0)]);
+/// let sheet = FnResolver::new(move |name: &str| cells.get(name).map(|value| Value::from(*value)));
+///
+/// let tokens = Lexer::new("A1 * A2").tokenize().unwrap();
+/// let ast = Parser::new(tokens).parse().unwrap();
+/// let context = EvaluationContext {
+///     resolver: Some(Arc::new(sheet)),
+///     ..EvaluationContext::default()
+/// };
+/// assert_eq!(evaluate_with_context(&ast, &context).unwrap().to_string(), "6");
+/// ```
+pub struct FnResolver<F> {
+    resolve: F,
+}
+
+impl<F> FnResolver<F>
+where
+    F: Fn(&str) -> Option<Value> + Send + Sync,
+{
+    pub fn new(resolve: F) -> Self {
+        Self { resolve }
+    }
+}
+
+impl<F> fmt::Debug for FnResolver<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnResolver").finish_non_exhaustive()
+    }
+}
+
+impl<F> Resolver for FnResolver<F>
+where
+    F: Fn(&str) -> Option<Value> + Send + Sync,
+{
+    fn resolve(&self, name: &str) -> Option<Value> {
+        (self.resolve)(name)
+    }
+}
@@ -0,0 +1,182 @@
+//! Solving linear equations in one variable.
+//!
+//! An equation `left = right` is rewritten as `f(x) = left - right = 0`. When
+//! `f` is linear its derivative `a` is a constant, so `f(x) = a * x + f(0)`
+//! and the solution is `x = -f(0) / a`.
+
+use mathengine_lexer::{Operation, Span};
+use mathengine_parser::{types::Value, Equation, Expression};
+
+use crate::{
+    apply_binary, differentiate, evaluate_with_context, EvalError,
+    EvaluationContext,
+};
+
+/// Solve a linear equation for `variable`, returning its value.
+///
+/// A coefficient written against the variable, as in `2x`, multiplies it.
+/// Other variables in the equation are taken from `context`.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_evaluator::{solve, EvaluationContext};
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{Parser, Statement};
+///
+/// let tokens = Lexer::new("solve 2x + 3m = 7m for x").tokenize().unwrap();
+/// let Statement::Solve { equation, variable } = Parser::new(tokens).parse_statement().unwrap() else {
+///     unreachable!();
+/// };
+/// let x = solve(&equation, &variable, &EvaluationContext::default()).unwrap();
+/// assert_eq!(x.to_string(), "2m");
+/// ```
+///
+/// # Errors
+///
+/// Returns [`EvalError::DomainError`] when the equation isn't linear in
+/// `variable`, or has no single solution (`x = x + 1`, `x = x`).
+pub fn solve(
+    equation: &Equation,
+    variable: &str,
+    context: &EvaluationContext,
+) -> Result<Value, EvalError> {
+    let variable = variable.to_lowercase();
+    let difference = Expression::Binary {
+        op: Operation::Add,
+        left: Box::new(with_coefficients(&equation.left, &variable)),
+        // Scaled by -1 rather than subtracted, so a percentage on the right
+        // isn't read as "minus that share of the left side"
+        right: Box::new(Expression::Binary {
+            op: Operation::Multiply,
+            left: Box::new(Expression::Integer(-1)),
+            right: Box::new(with_coefficients(&equation.right, &variable)),
+            span: Span::default(),
+        }),
+        span: Span::default(),
+    };
+
+    let slope = differentiate(&difference, &variable)?;
+    if !matches!(differentiate(&slope, &variable)?, Expression::Integer(0)) {
+        return Err(domain_error(&format!(
+            "equation is not linear in '{}'",
+            variable
+        )));
+    }
+
+    let mut at_zero = context.clone();
+    at_zero.set_variable(&variable, 0i128);
+    let slope = evaluate_with_context(&slope, &at_zero)?;
+    let offset = evaluate_with_context(&difference, &at_zero)?;
+
+    let flat = match &slope {
+        Value::Number(n) => n.is_zero(),
+        Value::UnitValue(uv) => uv.value() == 0.0,
+        _ => false,
+    };
+    if flat {
+        return Err(domain_error(&format!(
+            "equation has no single solution for '{}'",
+            variable
+        )));
+    }
+
+    let negated = apply_binary(&Operation::Multiply, Value::from(-1i128), offset, context)?;
+    apply_binary(&Operation::Divide, negated, slope, context)
+}
+
+// Whole coefficients stay exact, as they would if written `2 * x`
+fn coefficient(value: f64) -> Expression {
+    if value.fract() == 0.0 && value.abs() < i128::MAX as f64 {
+        Expression::Integer(value as i128)
+    } else {
+        Expression::Number(value)
+    }
+}
+
+fn domain_error(message: &str) -> EvalError {
+    EvalError::DomainError {
+        function: "solve".to_string(),
+        message: message.to_string(),
+    }
+}
+
+// The lexer reads `2x` as the value 2 with unit "x"; in an equation for `x`,
+// or a derivative with respect to it, that's a coefficient
+pub(crate) fn with_coefficients(expr: &Expression, variable: &str) -> Expression {
+    let rewrite = |expr: &Expression| Box::new(with_coefficients(expr, variable));
+    match expr {
+        Expression::UnitValue { value, unit } if **unit == *variable => Expression::Binary {
+            op: Operation::Multiply,
+            left: Box::new(coefficient(*value)),
+            right: Box::new(Expression::Unit(unit.to_string())),
+            span: Span::default(),
+        },
+        Expression::Percent(operand) => Expression::Percent(rewrite(operand)),
+        Expression::Factorial { operand, span } => Expression::Factorial {
+            operand: rewrite(operand),
+            span: *span,
+        },
+        Expression::Binary {
+            op,
+            left,
+            right,
+            span,
+        } => Expression::Binary {
+            op: op.clone(),
+            left: rewrite(left),
+            right: rewrite(right),
+            span: *span,
+        },
+        Expression::Unary { op, operand, span } => Expression::Unary {
+            op: op.clone(),
+            operand: rewrite(operand),
+            span: *span,
+        },
+        Expression::FunctionCall { name, args, span } => Expression::FunctionCall {
+            name: name.clone(),
+            args: args.iter().map(|arg| with_coefficients(arg, variable)).collect(),
+            span: *span,
+        },
+        Expression::List(items) => Expression::List(
+            items.iter().map(|item| with_coefficients(item, variable)).collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mathengine_parser::{Parser, Statement};
+
+    #[test]
+    fn test_linear_equations() {
+        let mut context = EvaluationContext::default();
+        context.set_variable("y", 4i128);
+        let solve_for = |source| {
+            let tokens = mathengine_lexer::Lexer::new(source).tokenize().unwrap();
+            match Parser::new(tokens).parse_statement().unwrap() {
+                Statement::Solve { equation, variable } => solve(&equation, &variable, &context),
+                other => panic!("{}: expected an equation, got {:?}", source, other),
+            }
+        };
+
+        for (source, expected) in [
+            ("solve 2x + 3 = 7 for x", "2"),
+            ("solve (x - 1) / 4 = 2 for x", "9"),
+            ("solve 3x - 2 = x + 6 for x", "4"),
+            ("solve 2x + 3m = 7m for x", "2m"),
+            ("solve 10% of x = 5 for x", "50"),
+            // Other variables come from the context
+            ("solve x + y = 10 for x", "6"),
+            ("solve X * y = 2 for x", "0.5"),
+        ] {
+            assert_eq!(solve_for(source).unwrap().to_string(), expected, "{}", source);
+        }
+
+        for source in ["solve x * x = 4 for x", "solve x = x + 1 for x", "solve x = x for x", "solve 2 = 3 for x"] {
+            assert!(matches!(solve_for(source), Err(EvalError::DomainError { .. })), "{}", source);
+        }
+    }
+}
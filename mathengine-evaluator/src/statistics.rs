@@ -0,0 +1,69 @@
+//! Aggregates over lists of numbers, used by builtins like `mean([3, 5, 8])`.
+//!
+//! Sums and means stay exact for integers where possible; the spread
+//! measures are computed with floats.
+
+use mathengine_parser::types::{Number, Value};
+
+/// Total of all values; zero for an empty list.
+pub fn sum(values: Vec<Number>) -> Value {
+    Value::Number(
+        values
+            .into_iter()
+            .fold(Number::from(0i128), |total, n| total + n),
+    )
+}
+
+/// Arithmetic mean, or `None` for an empty list.
+pub fn mean(values: Vec<Number>) -> Option<Value> {
+    if values.is_empty() {
+        return None;
+    }
+    let count = Number::from(values.len() as i128);
+    match sum(values) {
+        Value::Number(total) => Some(Value::Number(total / count)),
+        other => Some(other),
+    }
+}
+
+/// Middle value, or the mean of the two middle values for an even count.
+pub fn median(mut values: Vec<Number>) -> Option<Value> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.to_f64().total_cmp(&b.to_f64()));
+    let middle = values.len() / 2;
+    if values.len() % 2 == 1 {
+        return Some(Value::Number(values.swap_remove(middle)));
+    }
+    let upper = values.swap_remove(middle);
+    let lower = values.swap_remove(middle - 1);
+    Some(Value::Number((lower + upper) / Number::from(2i128)))
+}
+
+/// Sample standard deviation, or `None` with fewer than two values.
+pub fn stdev(values: Vec<Number>) -> Option<Value> {
+    if values.len() < 2 {
+        return None;
+    }
+    let floats: Vec<f64> = values.iter().map(Number::to_f64).collect();
+    let mean = floats.iter().sum::<f64>() / floats.len() as f64;
+    let squares: f64 = floats.iter().map(|x| (x - mean).powi(2)).sum();
+    Some(Value::from((squares / (floats.len() - 1) as f64).sqrt()))
+}
+
+/// Smallest value, or `None` for an empty list.
+pub fn min(values: Vec<Number>) -> Option<Value> {
+    values
+        .into_iter()
+        .min_by(|a, b| a.to_f64().total_cmp(&b.to_f64()))
+        .map(Value::Number)
+}
+
+/// Largest value, or `None` for an empty list.
+pub fn max(values: Vec<Number>) -> Option<Value> {
+    values
+        .into_iter()
+        .max_by(|a, b| a.to_f64().total_cmp(&b.to_f64()))
+        .map(Value::Number)
+}
@@ -1,4 +1,5 @@
-use std::fmt;
+use alloc::string::String;
+use core::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LexError {
@@ -27,4 +28,4 @@ impl fmt::Display for LexError {
     }
 }
 
-impl std::error::Error for LexError {}
+impl core::error::Error for LexError {}
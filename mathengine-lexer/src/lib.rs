@@ -1,16 +1,45 @@
-use std::{iter::Peekable, str::Chars};
+// Only needs an allocator, so it builds for targets without std too
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{iter::Peekable, str::Chars};
 
 pub mod error;
+pub mod span;
 pub use error::LexError;
+pub use span::Span;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Operation(Operation),
-    Number(f64),
-    UnitValue { value: f64, unit: String },
+    // Decimal literals, with the digits they were written with for number
+    // types more precise than `f64`
+    Number { value: f64, digits: Arc<str> },
+    // Also written in hexadecimal, binary or octal, like `0xff`
+    Integer(i128),
+    Bool(bool),
+    // The unit is shared with every other use of it in the input, and
+    // cloned cheaply from there on
+    UnitValue { value: f64, unit: Arc<str> },
     Unit(String),
+    Percent,
+    Bang,
     Lparen,
     Rparen,
+    Lbracket,
+    Rbracket,
+    Comma,
+    // `=` in an equation, as opposed to the `==` comparison
+    Equals,
+    Solve,
+    For,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,6 +50,16 @@ pub enum Operation {
     Multiply,
     Power,
     Convert,
+    Of,
+    And,
+    Or,
+    Not,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
 }
 
 pub struct Lexer {
@@ -35,106 +74,62 @@ impl Lexer {
     }
 
     pub fn tokenize(self) -> Result<Vec<Token>, LexError> {
-        if self.source.trim().is_empty() {
-            return Err(LexError::EmptyInput);
-        }
-
-        let mut tokens = Vec::new();
-        let mut chars = self.source.chars().peekable();
-        let mut position = 0;
-
-        while let Some(ch) = chars.next() {
-            match ch {
-                '0'..='9' => {
-                    let start_pos = position;
-                    let num = self.lex_number(ch, &mut chars);
-                    position += num.len();
+        self.tokens().map(|token| token.map(|(token, _)| token)).collect()
+    }
 
-                    // Skip whitespace after number
-                    while let Some(&c) = chars.peek() {
-                        if c.is_whitespace() {
-                            chars.next();
-                            position += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    // Check if there's a unit attached (with or without space)
-                    if let Some(&c) = chars.peek() {
-                        if c.is_alphabetic() {
-                            let unit = self.lex_identifier(chars.next().unwrap(), &mut chars);
-                            position += unit.len();
-                            let value =
-                                num.parse::<f64>().map_err(|_| LexError::InvalidNumber {
-                                    input: num.clone(),
-                                    position: start_pos,
-                                })?;
-                            tokens.push(Token::UnitValue { value, unit });
-                        } else {
-                            let value =
-                                num.parse::<f64>().map_err(|_| LexError::InvalidNumber {
-                                    input: num.clone(),
-                                    position: start_pos,
-                                })?;
-                            tokens.push(Token::Number(value));
-                        }
-                    } else {
-                        let value = num.parse::<f64>().map_err(|_| LexError::InvalidNumber {
-                            input: num.clone(),
-                            position: start_pos,
-                        })?;
-                        tokens.push(Token::Number(value));
-                    }
-                }
-                c if c.is_alphabetic() => {
-                    let ident = self.lex_identifier(c, &mut chars);
-                    position += ident.len();
+    /// Tokenize, pairing each token with the characters of the source it came from.
+    pub fn tokenize_with_spans(self) -> Result<Vec<(Token, Span)>, LexError> {
+        self.tokens().collect()
+    }
 
-                    let tok: Token = match ident.to_lowercase().as_ref() {
-                        "to" => Token::Operation(Operation::Convert),
-                        v => Token::Unit(v.into()),
-                    };
+    /// Lex the source a token at a time, as the tokens are asked for, pairing
+    /// each with the characters of the source it came from.
+    ///
+    /// Collecting the tokens gives what
+    /// [`tokenize_with_spans`](Self::tokenize_with_spans) does, and a parser
+    /// can read them without collecting them at all. The tokens end after the
+    /// first error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_lexer::{LexError, Lexer, Span, Token};
+    ///
+    /// let lexer = Lexer::new("2 + $");
+    /// let mut tokens = lexer.tokens();
+    /// assert_eq!(tokens.next(), Some(Ok((Token::Integer(2), Span::new(0, 1)))));
+    /// assert!(matches!(tokens.next(), Some(Ok((Token::Operation(_), _)))));
+    /// assert!(matches!(tokens.next(), Some(Err(LexError::UnexpectedCharacter { char: '$', .. }))));
+    /// assert_eq!(tokens.next(), None);
+    /// ```
+    pub fn tokens(&self) -> Tokens<'_> {
+        Tokens {
+            lexer: self,
+            chars: self.source.chars().peekable(),
+            position: 0,
+            units: Vec::new(),
+            empty: self.source.trim().is_empty(),
+            failed: false,
+        }
+    }
 
-                    tokens.push(tok);
-                }
-                '+' => {
-                    tokens.push(Token::Operation(Operation::Add));
-                    position += 1;
-                }
-                '-' => {
-                    tokens.push(Token::Operation(Operation::Subtract));
-                    position += 1;
-                }
-                '*' => {
-                    tokens.push(Token::Operation(Operation::Multiply));
-                    position += 1;
-                }
-                '/' => {
-                    tokens.push(Token::Operation(Operation::Divide));
-                    position += 1;
-                }
-                '^' => {
-                    tokens.push(Token::Operation(Operation::Power));
-                    position += 1;
-                }
-                '(' => {
-                    tokens.push(Token::Lparen);
-                    position += 1;
-                }
-                ')' => {
-                    tokens.push(Token::Rparen);
-                    position += 1;
-                }
-                c if c.is_whitespace() => {
-                    position += 1;
-                    continue;
-                }
-                _ => {
-                    return Err(LexError::UnexpectedCharacter { char: ch, position });
-                }
+    // Integer literals stay exact; anything with a decimal point (or too large
+    // for i128) becomes a float
+    fn number_token(&self, num: &str, position: usize) -> Result<Token, LexError> {
+        if !num.contains('.') {
+            if let Ok(value) = num.parse::<i128>() {
+                return Ok(Token::Integer(value));
             }
         }
-        Ok(tokens)
+        num.parse::<f64>()
+            .map(|value| Token::Number {
+                value,
+                digits: num.into(),
+            })
+            .map_err(|_| LexError::InvalidNumber {
+                input: num.to_string(),
+                position,
+            })
     }
 
     fn lex_number(&self, first_digit: char, chars: &mut Peekable<Chars<'_>>) -> String {
@@ -151,6 +146,45 @@ impl Lexer {
         s
     }
 
+    // `0x`, `0b` and `0o` only start a literal when a digit of that base
+    // follows, so `0b` alone is still zero bytes
+    fn next_is_radix_prefix(&self, chars: &Peekable<Chars<'_>>) -> bool {
+        let mut lookahead = chars.clone();
+        match (lookahead.next(), lookahead.next()) {
+            (Some(prefix @ ('x' | 'X' | 'b' | 'B' | 'o' | 'O')), Some(digit)) => {
+                digit.is_digit(radix_base(prefix))
+            }
+            _ => false,
+        }
+    }
+
+    // Lexes the prefix letter and digits after the leading `0`
+    fn lex_radix_literal(&self, chars: &mut Peekable<Chars<'_>>) -> String {
+        let prefix = chars.next().expect("radix prefix checked by lookahead");
+        let base = radix_base(prefix);
+        let mut s = prefix.to_ascii_lowercase().to_string();
+        while let Some(&next) = chars.peek() {
+            if next.is_digit(base) {
+                s.push(next);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        s
+    }
+
+    // Looks ahead (without consuming) to see if the next identifier is a keyword
+    // like "to" or "and", which must not be attached to a preceding number as its unit
+    fn next_is_keyword(&self, chars: &Peekable<Chars<'_>>) -> bool {
+        let mut lookahead = chars.clone();
+        match lookahead.next() {
+            Some(first) => is_keyword(&self.lex_identifier(first, &mut lookahead)),
+            None => false,
+        }
+    }
+
     fn lex_identifier(&self, first_char: char, chars: &mut Peekable<Chars<'_>>) -> String {
         let mut ident = String::new();
         ident.push(first_char);
@@ -167,3 +201,183 @@ impl Lexer {
         ident
     }
 }
+
+/// The tokens of a source, lexed as they're asked for, from [`Lexer::tokens`].
+pub struct Tokens<'a> {
+    lexer: &'a Lexer,
+    chars: Peekable<Chars<'a>>,
+    position: usize,
+    // Each unit named in the source, shared by every token naming it
+    units: Vec<Arc<str>>,
+    // The source is blank, which is an error
+    empty: bool,
+    // An error has been returned, which ends the tokens
+    failed: bool,
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<(Token, Span), LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if self.empty {
+            self.failed = true;
+            return Some(Err(LexError::EmptyInput));
+        }
+        let token = self.next_token().transpose();
+        self.failed = matches!(token, Some(Err(_)));
+        token
+    }
+}
+
+impl Tokens<'_> {
+    fn next_token(&mut self) -> Result<Option<(Token, Span)>, LexError> {
+        let lexer = self.lexer;
+        let chars = &mut self.chars;
+        while let Some(ch) = chars.next() {
+            let start = self.position;
+            let (token, width) = match ch {
+                '0' if lexer.next_is_radix_prefix(chars) => {
+                    let literal = lexer.lex_radix_literal(chars);
+                    let base = radix_base(literal.as_bytes()[0] as char);
+                    let value = i128::from_str_radix(&literal[1..], base).map_err(|_| {
+                        LexError::InvalidNumber {
+                            input: format!("0{}", literal),
+                            position: start,
+                        }
+                    })?;
+                    (Token::Integer(value), literal.len() + 1)
+                }
+                '0'..='9' => {
+                    let num = lexer.lex_number(ch, chars);
+                    let mut end = start + num.len();
+
+                    // Skip whitespace after number
+                    let mut skipped = 0;
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {
+                        skipped += 1;
+                    }
+                    // Check if there's a unit attached (with or without space)
+                    let attached = matches!(chars.peek(), Some(c) if c.is_alphabetic())
+                        && !lexer.next_is_keyword(chars);
+                    let unit = chars
+                        .next_if(|_| attached)
+                        .map(|first| lexer.lex_identifier(first, chars));
+                    self.position = end + skipped;
+                    let token = match unit {
+                        Some(unit) => {
+                            let value = num.parse::<f64>().map_err(|_| LexError::InvalidNumber {
+                                input: num.clone(),
+                                position: start,
+                            })?;
+                            self.position += unit.chars().count();
+                            end = self.position;
+                            let unit = match self.units.iter().find(|known| ***known == *unit) {
+                                Some(known) => known.clone(),
+                                None => {
+                                    self.units.push(unit.into());
+                                    self.units[self.units.len() - 1].clone()
+                                }
+                            };
+                            Token::UnitValue { value, unit }
+                        }
+                        None => lexer.number_token(&num, start)?,
+                    };
+                    // The whitespace looked past for a unit isn't part of the token
+                    return Ok(Some((token, Span::new(start, end))));
+                }
+                c if c.is_alphabetic() => {
+                    let ident = lexer.lex_identifier(c, chars);
+                    let width = ident.chars().count();
+
+                    let tok: Token = match ident.to_lowercase().as_ref() {
+                        "to" => Token::Operation(Operation::Convert),
+                        "of" => Token::Operation(Operation::Of),
+                        "and" => Token::Operation(Operation::And),
+                        "or" => Token::Operation(Operation::Or),
+                        "not" => Token::Operation(Operation::Not),
+                        "true" => Token::Bool(true),
+                        "false" => Token::Bool(false),
+                        "solve" => Token::Solve,
+                        "for" => Token::For,
+                        v => Token::Unit(v.into()),
+                    };
+
+                    (tok, width)
+                }
+                // A session's earlier results, `_1`, `_2` and so on
+                '_' if matches!(chars.peek(), Some(c) if c.is_ascii_digit()) => {
+                    let ident = lexer.lex_identifier(ch, chars);
+                    let width = ident.chars().count();
+                    (Token::Unit(ident.to_lowercase()), width)
+                }
+                '+' => (Token::Operation(Operation::Add), 1),
+                '-' => (Token::Operation(Operation::Subtract), 1),
+                '*' => (Token::Operation(Operation::Multiply), 1),
+                '/' => (Token::Operation(Operation::Divide), 1),
+                '^' => (Token::Operation(Operation::Power), 1),
+                '%' => (Token::Percent, 1),
+                '!' => {
+                    if chars.next_if_eq(&'=').is_some() {
+                        (Token::Operation(Operation::NotEqual), 2)
+                    } else {
+                        (Token::Bang, 1)
+                    }
+                }
+                '<' | '>' => {
+                    let or_equal = chars.next_if_eq(&'=').is_some();
+                    let op = match (ch, or_equal) {
+                        ('<', false) => Operation::Less,
+                        ('<', true) => Operation::LessEqual,
+                        (_, false) => Operation::Greater,
+                        (_, true) => Operation::GreaterEqual,
+                    };
+                    (Token::Operation(op), if or_equal { 2 } else { 1 })
+                }
+                '=' => {
+                    if chars.next_if_eq(&'=').is_some() {
+                        (Token::Operation(Operation::Equal), 2)
+                    } else {
+                        (Token::Equals, 1)
+                    }
+                }
+                '(' => (Token::Lparen, 1),
+                ')' => (Token::Rparen, 1),
+                '[' => (Token::Lbracket, 1),
+                ']' => (Token::Rbracket, 1),
+                ',' => (Token::Comma, 1),
+                c if c.is_whitespace() => {
+                    self.position += 1;
+                    continue;
+                }
+                _ => {
+                    return Err(LexError::UnexpectedCharacter {
+                        char: ch,
+                        position: start,
+                    });
+                }
+            };
+
+            self.position += width;
+            return Ok(Some((token, Span::new(start, self.position))));
+        }
+        Ok(None)
+    }
+}
+
+fn radix_base(prefix: char) -> u32 {
+    match prefix.to_ascii_lowercase() {
+        'x' => 16,
+        'b' => 2,
+        _ => 8,
+    }
+}
+
+fn is_keyword(ident: &str) -> bool {
+    matches!(
+        ident.to_lowercase().as_ref(),
+        "to" | "of" | "and" | "or" | "not" | "true" | "false" | "solve" | "for"
+    )
+}
@@ -0,0 +1,40 @@
+/// A range of character offsets in the source text, `start` inclusive and
+/// `end` exclusive.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_lexer::{Lexer, Span};
+///
+/// let tokens = Lexer::new("10m + 5").tokenize_with_spans().unwrap();
+/// assert_eq!(tokens[0].1, Span::new(0, 3));
+/// assert_eq!(tokens[2].1, Span::new(6, 7));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// Whether the span covers no characters, as for nodes built by hand
+    /// rather than parsed from source.
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+}
+
+impl core::fmt::Display for Span {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
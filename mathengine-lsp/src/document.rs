@@ -0,0 +1,108 @@
+//! What a `.math` file works out to: each line evaluated in turn, in one
+//! session so later lines see what earlier ones define, as `mathengine run`
+//! evaluates it.
+
+use mathengine::{Diagnostic, MathEngine, MathEngineError, Session, Severity, Value};
+use mathengine_lexer::Lexer;
+use mathengine_parser::{Parser, Statement};
+
+/// An open file, evaluated.
+pub struct Document {
+    pub lines: Vec<Line>,
+    /// The session after the last line, with everything the file defines
+    pub session: Session,
+}
+
+/// A line of the file and what it gives.
+pub struct Line {
+    /// The line as written, without its line break
+    pub text: String,
+    /// Where the evaluated code starts and ends in `text`, in characters.
+    /// Empty for lines without any
+    pub code: (usize, usize),
+    /// The line's value, `None` for definitions of functions and lines that
+    /// failed or have no code
+    pub value: Option<Value>,
+    /// Why the line failed, or why its value may not be what was meant
+    pub diagnostic: Option<Diagnostic>,
+}
+
+impl Document {
+    pub fn new(engine: &MathEngine, text: &str) -> Self {
+        let mut session = Session::with_engine(engine.clone());
+        let lines = text
+            .split('\n')
+            .map(|text| evaluate_line(&mut session, text.trim_end_matches('\r')))
+            .collect();
+        Self { lines, session }
+    }
+
+    pub fn engine(&self) -> &MathEngine {
+        self.session.engine()
+    }
+}
+
+fn evaluate_line(session: &mut Session, text: &str) -> Line {
+    let code = strip_comment(text).trim();
+    let code = print_argument(code).unwrap_or(code);
+    // `code` is part of `text`, so where it starts is how far into it it is
+    let offset = code.as_ptr() as usize - text.as_ptr() as usize;
+    let start = text[..offset].chars().count();
+    let mut line = Line {
+        text: text.to_string(),
+        code: (start, start + code.chars().count()),
+        value: None,
+        diagnostic: None,
+    };
+    if code.is_empty() {
+        return line;
+    }
+
+    // Dimensions are checked whether or not the engine is strict, so sums
+    // like `5m + 3C`, which evaluate to `5m`, are pointed out. Definitions
+    // aren't checked, since they can't be validated on their own
+    let mismatch = match is_definition(code) {
+        true => None,
+        false => session.engine().validate(code).err().filter(|err| matches!(err, MathEngineError::Type(_))),
+    };
+    match session.eval(code) {
+        Ok(value) => {
+            line.value = value;
+            line.diagnostic = mismatch.map(|err| Diagnostic {
+                severity: Severity::Warning,
+                ..session.engine().diagnostic(&err, code)
+            });
+        }
+        Err(err) => line.diagnostic = Some(session.engine().diagnostic(&err, code)),
+    }
+    line
+}
+
+fn is_definition(code: &str) -> bool {
+    let lexer = Lexer::new(code);
+    matches!(
+        Parser::new(lexer.tokens()).parse_statement(),
+        Ok(Statement::Assign { .. } | Statement::Define { .. })
+    )
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once('#').map_or(line, |(code, _)| code)
+}
+
+// The expression in a line that's all `print(...)`, which `mathengine run`
+// prints the value of
+fn print_argument(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix("print")?.trim_start().strip_prefix('(')?.strip_suffix(')')?;
+    // The parentheses have to enclose the whole argument, unlike in
+    // `print(1) + (2)`
+    let mut depth = 0usize;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            _ => {}
+        }
+    }
+    Some(inner)
+}
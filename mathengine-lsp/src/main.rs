@@ -0,0 +1,47 @@
+//! A language server for `.math` files, the files of expressions and
+//! definitions `mathengine run` evaluates, speaking the protocol over stdin
+//! and stdout.
+//!
+//! Editors get each line's errors as diagnostics, its value and conversions
+//! on hover, and the functions, units and variables it can use completed.
+
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+
+use serde_json::Value;
+
+mod document;
+mod rpc;
+mod server;
+
+use server::Server;
+
+fn main() -> ExitCode {
+    let mut input = BufReader::new(io::stdin().lock());
+    let mut output = io::stdout().lock();
+    let mut server = Server::new(server::engine());
+
+    loop {
+        let replies = match rpc::read(&mut input) {
+            Ok(Some(message)) => server.handle(&message),
+            // The client went away without saying to exit
+            Ok(None) => return ExitCode::FAILURE,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                vec![server::error(&Value::Null, server::PARSE_ERROR, &err.to_string())]
+            }
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        for reply in replies {
+            if let Err(err) = rpc::write(&mut output, &reply) {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+        if let Some(shut_down) = server.exited() {
+            return if shut_down { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+        }
+    }
+}
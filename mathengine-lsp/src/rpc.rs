@@ -0,0 +1,118 @@
+//! Reading and writing JSON-RPC messages framed the way the language server
+//! protocol frames them: a `Content-Length` header, a blank line and the
+//! JSON body.
+
+use std::io::{self, BufRead, Read, Write};
+
+use serde_json::Value;
+
+/// The longest body a message may have. `Content-Length` comes from the
+/// client, so it isn't allocated for on trust
+pub const MAX_MESSAGE_LENGTH: usize = 64 * 1024 * 1024;
+
+/// Read the next message, `None` once the client has closed the input.
+///
+/// A body that isn't JSON, or is longer than [`MAX_MESSAGE_LENGTH`], fails
+/// with [`io::ErrorKind::InvalidData`], after which the next message can
+/// still be read.
+pub fn read(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        // Other headers, like Content-Type, don't change how the body reads
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| invalid("message without a Content-Length header"))?;
+    if length > MAX_MESSAGE_LENGTH {
+        // Skipped rather than read, so the message after it still frames
+        io::copy(&mut input.by_ref().take(length as u64), &mut io::sink())?;
+        return Err(invalid(format!(
+            "message of {} bytes is longer than the limit of {}",
+            length, MAX_MESSAGE_LENGTH
+        )));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(invalid)
+}
+
+pub fn write(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn framed(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    #[test]
+    fn test_read_messages() {
+        let input = framed(r#"{"id":1}"#) + &framed(r#"{"id":2}"#);
+        let mut input = input.as_bytes();
+        assert_eq!(read(&mut input).unwrap(), Some(json!({ "id": 1 })));
+        assert_eq!(read(&mut input).unwrap(), Some(json!({ "id": 2 })));
+        assert_eq!(read(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_other_headers() {
+        let input = "Content-Type: application/vscode-jsonrpc; charset=utf-8\r\ncontent-length: 2\r\n\r\n{}";
+        assert_eq!(read(&mut input.as_bytes()).unwrap(), Some(json!({})));
+    }
+
+    #[test]
+    fn test_read_invalid_body() {
+        let input = framed("{oops") + &framed("[]");
+        let mut input = input.as_bytes();
+        let err = read(&mut input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(read(&mut input).unwrap(), Some(json!([])));
+    }
+
+    #[test]
+    fn test_read_missing_length() {
+        let err = read(&mut "\r\n{}".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_too_long() {
+        let header = format!("Content-Length: {}\r\n\r\n", MAX_MESSAGE_LENGTH + 1);
+        let err = read(&mut header.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let huge = format!("Content-Length: {}\r\n\r\n", usize::MAX);
+        assert_eq!(read(&mut huge.as_bytes()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_write_frames() {
+        let mut output = Vec::new();
+        write(&mut output, &json!({ "id": "é" })).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        // The length counts bytes, not characters
+        assert_eq!(output, framed(r#"{"id":"é"}"#));
+        assert!(output.starts_with("Content-Length: 11\r\n"));
+    }
+}
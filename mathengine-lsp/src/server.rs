@@ -0,0 +1,354 @@
+//! The language server's side of the protocol: keeping the open documents
+//! evaluated, and answering the editor's requests about them.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use mathengine::{DimensionType, EvalLimits, MathEngine, Severity, Value};
+use serde_json::{json, Value as Json};
+
+use crate::document::{Document, Line};
+
+// JSON-RPC error codes
+pub const PARSE_ERROR: i64 = -32700;
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// Kinds of completion item, as the protocol numbers them
+const FUNCTION: u32 = 3;
+const VARIABLE: u32 = 6;
+const UNIT: u32 = 11;
+const KEYWORD: u32 = 14;
+
+/// The engine documents are evaluated with. Every line is evaluated again on
+/// each edit, so a runaway one, like a function calling itself twice over,
+/// stops with an error rather than hanging the server.
+pub fn engine() -> MathEngine {
+    MathEngine::builder()
+        .limits(EvalLimits {
+            max_depth: Some(256),
+            max_operations: Some(1_000_000),
+            timeout: Some(Duration::from_millis(500)),
+            ..EvalLimits::default()
+        })
+        .build()
+}
+
+pub struct Server {
+    engine: MathEngine,
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+    // Set by the `exit` notification: whether the client asked for a
+    // shutdown first, as it should have
+    exited: Option<bool>,
+}
+
+impl Server {
+    pub fn new(engine: MathEngine) -> Self {
+        Self {
+            engine,
+            documents: HashMap::new(),
+            shut_down: false,
+            exited: None,
+        }
+    }
+
+    /// `Some` once the client has said to exit, with whether it shut the
+    /// server down first.
+    pub fn exited(&self) -> Option<bool> {
+        self.exited
+    }
+
+    /// Handle a message from the client, giving the messages to send back:
+    /// a response to a request, and any diagnostics it changed.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notify(method, params);
+        };
+        let result = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => Ok(self.completion(params)),
+            _ => Err((METHOD_NOT_FOUND, format!("'{}' isn't supported", method))),
+        };
+        vec![match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error(id, code, &message),
+        }]
+    }
+
+    // Notifications get no response, only the diagnostics of documents they
+    // open or change
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // Documents are synced whole, so the last change has all of it
+            "textDocument/didChange" => params["contentChanges"].as_array().and_then(|changes| changes.last()?["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish(&uri, Vec::new())];
+            }
+            "exit" => {
+                self.exited = Some(self.shut_down);
+                return Vec::new();
+            }
+            _ => return Vec::new(),
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+        let document = Document::new(&self.engine, text);
+        let diagnostics = document
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(number, line)| diagnostic(number, line))
+            .collect();
+        self.documents.insert(uri.clone(), document);
+        vec![publish(&uri, diagnostics)]
+    }
+
+    // The value of the line under the cursor, and what it is in other units
+    fn hover(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (document, number, line) = self.line_at(params)?;
+        let (Some(value), Some(character)) = (&line.value, params["position"]["character"].as_u64()) else {
+            return Ok(Json::Null);
+        };
+        let column = char_column(&line.text, character as usize);
+        if column < line.code.0 || column > line.code.1 {
+            return Ok(Json::Null);
+        }
+        let engine = document.engine();
+        let mut contents = format!("= {}", engine.format(value));
+        if let Some(conversions) = conversions(engine, value) {
+            contents.push_str("\n\n");
+            contents.push_str(&conversions);
+        }
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": contents },
+            "range": range(number, line, line.code),
+        }))
+    }
+
+    // Every function, unit, variable and keyword, for the editor to filter
+    // by what's been typed
+    fn completion(&self, params: &Json) -> Json {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let engine = self.documents.get(uri).map_or(&self.engine, Document::engine);
+        let capabilities = engine.capabilities();
+
+        let mut items = Vec::new();
+        for function in &capabilities.functions {
+            items.push(json!({ "label": function, "kind": FUNCTION, "detail": "function" }));
+        }
+        for constant in &capabilities.constants {
+            items.push(json!({ "label": constant, "kind": VARIABLE }));
+        }
+        for unit in &capabilities.units {
+            let detail = match unit.dimension {
+                DimensionType::Unknown => "unit".to_string(),
+                dimension => format!("{} unit", dimension.name()),
+            };
+            for name in std::iter::once(&unit.unit).chain(&unit.aliases) {
+                items.push(json!({ "label": name, "kind": UNIT, "detail": detail }));
+            }
+        }
+        for currency in &capabilities.currencies {
+            items.push(json!({ "label": currency, "kind": UNIT, "detail": "currency" }));
+        }
+        for keyword in capabilities.operators.iter().filter(|op| op.chars().all(char::is_alphabetic)) {
+            items.push(json!({ "label": keyword, "kind": KEYWORD }));
+        }
+        json!({ "isIncomplete": false, "items": items })
+    }
+
+    fn line_at(&self, params: &Json) -> Result<(&Document, usize, &Line), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self.documents.get(uri).ok_or_else(|| (INVALID_PARAMS, format!("'{}' isn't open", uri)))?;
+        let number = params["position"]["line"].as_u64().ok_or_else(|| (INVALID_PARAMS, "missing position".to_string()))?;
+        let line = document
+            .lines
+            .get(number as usize)
+            .ok_or_else(|| (INVALID_PARAMS, format!("there's no line {}", number)))?;
+        Ok((document, number as usize, line))
+    }
+}
+
+pub fn error(id: &Json, code: i64, message: &str) -> Json {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn capabilities() -> Json {
+    json!({
+        "capabilities": {
+            // Whole documents are sent on every change
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "completionProvider": {},
+        },
+        "serverInfo": { "name": "mathengine-lsp", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+// A line's diagnostic in the protocol's terms, on the part of the line its
+// first label is about, or all of its code
+fn diagnostic(number: usize, line: &Line) -> Option<Json> {
+    let diagnostic = line.diagnostic.as_ref()?;
+    let (start, _) = line.code;
+    let span = match diagnostic.labels.first() {
+        Some((span, _)) => ((start + span.start).min(line.code.1), (start + span.end).min(line.code.1)),
+        None => line.code,
+    };
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push_str("\nnote: ");
+        message.push_str(note);
+    }
+    Some(json!({
+        "range": range(number, line, span),
+        "severity": match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "code": diagnostic.code,
+        "source": "mathengine",
+        "message": message,
+    }))
+}
+
+// The results of converting a quantity to the other units of its dimension,
+// like `11.84ft = 142.1in = ...`
+fn conversions(engine: &MathEngine, value: &Value) -> Option<String> {
+    let Value::UnitValue(value) = value else {
+        return None;
+    };
+    let table = engine.units().conversion_table(value.dimension(), std::slice::from_ref(value)).ok()?;
+    let formatted = table.format(engine.format_options());
+    let (_, others) = formatted.split_once(" = ")?;
+    Some(others.to_string())
+}
+
+// Characters of `line` from `span.0` to `span.1` as a protocol range, which
+// counts UTF-16 code units
+fn range(number: usize, line: &Line, (start, end): (usize, usize)) -> Json {
+    json!({
+        "start": { "line": number, "character": utf16_column(&line.text, start) },
+        "end": { "line": number, "character": utf16_column(&line.text, end) },
+    })
+}
+
+fn utf16_column(text: &str, column: usize) -> usize {
+    text.chars().take(column).map(char::len_utf16).sum()
+}
+
+// The character a UTF-16 column falls in
+fn char_column(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (column, c) in text.chars().enumerate() {
+        units += c.len_utf16();
+        if units > utf16 {
+            return column;
+        }
+    }
+    text.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(text: &str) -> Vec<Json> {
+        let document = Document::new(&engine(), text);
+        document
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(number, line)| diagnostic(number, line))
+            .collect()
+    }
+
+    #[test]
+    fn test_columns() {
+        // `𝑥` is one character but two UTF-16 code units
+        let text = "𝑥 + é";
+        assert_eq!(utf16_column(text, 0), 0);
+        assert_eq!(utf16_column(text, 1), 2);
+        assert_eq!(utf16_column(text, 5), 6);
+        assert_eq!(char_column(text, 0), 0);
+        assert_eq!(char_column(text, 1), 0);
+        assert_eq!(char_column(text, 2), 1);
+        assert_eq!(char_column(text, 5), 4);
+        assert_eq!(char_column(text, 100), 5);
+    }
+
+    #[test]
+    fn test_diagnostic_range() {
+        let diagnostics = diagnostics("𝑥 = 2\n  𝑥 + (1 / 0)  # 𝑦");
+        assert_eq!(diagnostics.len(), 1);
+        let range = &diagnostics[0]["range"];
+        // `1 / 0` is characters 7 to 12, after the two code units of `𝑥`
+        assert_eq!(range["start"], json!({ "line": 1, "character": 8 }));
+        assert_eq!(range["end"], json!({ "line": 1, "character": 13 }));
+        assert_eq!(diagnostics[0]["severity"], 1);
+    }
+
+    #[test]
+    fn test_diagnostic_without_label() {
+        let diagnostics = diagnostics("print(𝑥 + )");
+        let range = &diagnostics[0]["range"];
+        // At the end of the argument, not past the closing parenthesis
+        assert_eq!(range["end"], json!({ "line": 0, "character": 11 }));
+    }
+
+    #[test]
+    fn test_mismatch_warning() {
+        let diagnostics = diagnostics("5m + 3C");
+        assert_eq!(diagnostics[0]["severity"], 2);
+    }
+
+    #[test]
+    fn test_runaway_line() {
+        // Twice as many calls for each step down, 2^60 in all
+        let diagnostics = diagnostics("f(n) = if(n < 1, 1, f(n-1) + f(n-1))\nf(60)\n1 + 1");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+        assert_eq!(diagnostics[0]["severity"], 1);
+    }
+
+    #[test]
+    fn test_hover_range() {
+        let mut server = Server::new(engine());
+        let uri = "file:///a.math";
+        server.handle(&json!({
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": "𝑥 = 2 # 𝑦\n𝑥 * 3" } },
+        }));
+        let position = |line, character| {
+            json!({
+                "id": 1,
+                "method": "textDocument/hover",
+                "params": { "textDocument": { "uri": uri }, "position": { "line": line, "character": character } },
+            })
+        };
+        let reply = &server.handle(&position(1, 2))[0];
+        assert_eq!(reply["result"]["contents"]["value"], "= 6");
+        assert_eq!(reply["result"]["range"]["end"]["character"], 6);
+        // Over the comment, not the code
+        assert_eq!(server.handle(&position(0, 9))[0]["result"], Json::Null);
+    }
+}
@@ -0,0 +1,126 @@
+//! The `matheval!` macro, re-exported by `mathengine` behind its `macros`
+//! feature.
+
+use mathengine_evaluator::evaluate;
+use mathengine_lexer::Lexer;
+use mathengine_parser::types::{Unit, UnitValue, Value};
+use mathengine_parser::Parser;
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, LitStr};
+
+/// Evaluate an expression at compile time.
+///
+/// The expression is lexed, parsed and evaluated with the default
+/// configuration while the crate compiles, and the macro expands to the
+/// result: a number literal, `true` or `false`, an array for a list, or a
+/// [`Quantity`] for a length, temperature or time. Each of these is a
+/// constant expression, so the result can be assigned to a `const`. An
+/// expression that fails to evaluate is a compile error.
+///
+/// Whole numbers expand to integer literals and others to float literals,
+/// leaving the type to be inferred.
+///
+/// [`Quantity`]: https://docs.rs/mathengine/latest/mathengine/struct.Quantity.html
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{matheval, Length, LengthUnit, Quantity};
+///
+/// const BUFFER: usize = matheval!("2^10 + 5");
+/// const RATIO: f64 = matheval!("1 / 8");
+/// const TRACK: Quantity<Length> = matheval!("400m to ft");
+///
+/// assert_eq!(BUFFER, 1029);
+/// assert_eq!(RATIO, 0.125);
+/// assert_eq!(TRACK.unit(), LengthUnit::Foot);
+/// assert_eq!(TRACK.to(LengthUnit::Meter).value(), 400.0);
+/// ```
+///
+/// Booleans and lists work too:
+///
+/// ```
+/// use mathengine::matheval;
+///
+/// assert!(matheval!("1m < 200cm"));
+/// assert_eq!(matheval!("[1, 2, 3] * 2"), [2, 4, 6]);
+/// ```
+///
+/// Anything that doesn't evaluate is rejected at compile time:
+///
+/// ```compile_fail
+/// let _: f64 = mathengine::matheval!("1 / 0");
+/// ```
+#[proc_macro]
+pub fn matheval(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let source = parse_macro_input!(input as LitStr);
+    match expand(&source) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(source: &LitStr) -> syn::Result<TokenStream> {
+    let error = |message: String| syn::Error::new(source.span(), message);
+    let expression = source.value();
+    let tokens = Lexer::new(expression.as_str())
+        .tokenize_with_spans()
+        .map_err(|e| error(format!("Lexer error: {}", e)))?;
+    let ast = Parser::with_spans(tokens)
+        .parse()
+        .map_err(|e| error(format!("Parser error: {}", e)))?;
+    let value = evaluate(&ast).map_err(|e| error(format!("Evaluation error: {}", e)))?;
+    constant(&value).map_err(error)
+}
+
+// The value as a constant expression
+fn constant(value: &Value) -> Result<TokenStream, String> {
+    match value {
+        Value::Number(n) => match n.as_integer() {
+            Some(i) => Ok(integer(i)),
+            None => float(n.to_f64()),
+        },
+        Value::Radix { value, .. } => Ok(integer(*value)),
+        Value::Bool(b) => Ok(quote!(#b)),
+        Value::List(items) => {
+            let items = items.iter().map(constant).collect::<Result<Vec<_>, _>>()?;
+            Ok(quote!([#(#items),*]))
+        }
+        Value::UnitValue(uv) => quantity(uv),
+    }
+}
+
+fn integer(i: i128) -> TokenStream {
+    let literal = Literal::i128_unsuffixed(i);
+    quote!(#literal)
+}
+
+fn float(x: f64) -> Result<TokenStream, String> {
+    if !x.is_finite() {
+        return Err(format!("{} has no literal", x));
+    }
+    let literal = Literal::f64_unsuffixed(x);
+    Ok(quote!(#literal))
+}
+
+fn quantity(uv: &UnitValue) -> Result<TokenStream, String> {
+    let (dimension, unit_type, unit) = match uv.known_unit() {
+        Some(_) if uv.exponent() != 1 => {
+            return Err(format!("{} is a power of a unit, which has no Quantity type", uv));
+        }
+        Some(Unit::Length(unit)) => ("Length", "LengthUnit", format!("{:?}", unit)),
+        Some(Unit::Temperature(unit)) => ("Temperature", "TemperatureUnit", format!("{:?}", unit)),
+        Some(Unit::Time(unit)) => ("Time", "TimeUnit", format!("{:?}", unit)),
+        Some(Unit::Frequency(unit)) => ("Frequency", "FrequencyUnit", format!("{:?}", unit)),
+        Some(Unit::Mass(unit)) => ("Mass", "MassUnit", format!("{:?}", unit)),
+        None => return Err(format!("'{}' isn't a unit with a Quantity type", uv.unit())),
+    };
+    let value = float(uv.value())?;
+    let dimension = format_ident!("{}", dimension);
+    let unit_type = format_ident!("{}", unit_type);
+    let unit = format_ident!("{}", unit);
+    Ok(quote! {
+        ::mathengine::Quantity::<::mathengine::#dimension>::new(#value, ::mathengine::#unit_type::#unit)
+    })
+}
@@ -1,20 +1,199 @@
-use mathengine_lexer::Operation;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+use mathengine_lexer::{Operation, Span};
+
+/// A parsed expression.
+///
+/// Nodes that can fail to evaluate record the span of source text they were
+/// parsed from, so errors can point at the sub-expression that caused them.
+/// Nodes built by hand can use `Span::default()`.
+///
+/// Trees are cloned and dropped with a stack on the heap rather than by
+/// recursion, so long chains like `1 + 1 + ...` don't overflow the stack.
+/// `Debug` formatting still recurses, and is for debugging rather than
+/// untrusted input.
+#[derive(Debug)]
 pub enum Expression {
     Number(f64),
+    /// A decimal literal as parsed, keeping the digits it was written with so
+    /// the number modes more precise than `f64` read it exactly
+    Decimal {
+        value: f64,
+        digits: Arc<str>,
+    },
+    Integer(i128),
+    Bool(bool),
     UnitValue {
         value: f64,
-        unit: String,
+        /// Shared with the token it was parsed from, and with every value
+        /// evaluated from it
+        unit: Arc<str>,
     },
     Unit(String),
+    Percent(Box<Expression>),
+    Factorial {
+        operand: Box<Expression>,
+        span: Span,
+    },
     Binary {
         op: Operation,
         left: Box<Expression>,
         right: Box<Expression>,
+        span: Span,
     },
     Unary {
         op: Operation,
         operand: Box<Expression>,
+        span: Span,
+    },
+    FunctionCall {
+        name: String,
+        args: Vec<Expression>,
+        span: Span,
+    },
+    List(Vec<Expression>),
+}
+
+impl Expression {
+    /// The source span of this node, if it records one.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expression::Factorial { span, .. }
+            | Expression::Binary { span, .. }
+            | Expression::Unary { span, .. }
+            | Expression::FunctionCall { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+}
+
+impl Clone for Expression {
+    fn clone(&self) -> Self {
+        // Children are cloned onto `done` before their parents, and each
+        // parent takes its children back off the end
+        enum Step<'a> {
+            Clone(&'a Expression),
+            Build(&'a Expression),
+        }
+        fn next(children: &mut impl Iterator<Item = Expression>) -> Box<Expression> {
+            Box::new(children.next().unwrap())
+        }
+        let mut steps = vec![Step::Clone(self)];
+        let mut done = Vec::new();
+        while let Some(step) = steps.pop() {
+            let expr = match step {
+                Step::Clone(expr) => {
+                    steps.push(Step::Build(expr));
+                    match expr {
+                        Expression::Percent(operand)
+                        | Expression::Factorial { operand, .. }
+                        | Expression::Unary { operand, .. } => steps.push(Step::Clone(operand)),
+                        Expression::Binary { left, right, .. } => {
+                            steps.push(Step::Clone(right));
+                            steps.push(Step::Clone(left));
+                        }
+                        Expression::FunctionCall { args, .. } | Expression::List(args) => {
+                            steps.extend(args.iter().rev().map(Step::Clone))
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                Step::Build(expr) => expr,
+            };
+            let count = match expr {
+                Expression::Percent(_) | Expression::Factorial { .. } | Expression::Unary { .. } => 1,
+                Expression::Binary { .. } => 2,
+                Expression::FunctionCall { args, .. } | Expression::List(args) => args.len(),
+                _ => 0,
+            };
+            let mut children = done.split_off(done.len() - count).into_iter();
+            done.push(match expr {
+                Expression::Number(n) => Expression::Number(*n),
+                Expression::Decimal { value, digits } => Expression::Decimal {
+                    value: *value,
+                    digits: digits.clone(),
+                },
+                Expression::Integer(i) => Expression::Integer(*i),
+                Expression::Bool(b) => Expression::Bool(*b),
+                Expression::UnitValue { value, unit } => Expression::UnitValue {
+                    value: *value,
+                    unit: unit.clone(),
+                },
+                Expression::Unit(name) => Expression::Unit(name.clone()),
+                Expression::Percent(_) => Expression::Percent(next(&mut children)),
+                Expression::Factorial { span, .. } => Expression::Factorial {
+                    operand: next(&mut children),
+                    span: *span,
+                },
+                Expression::Binary { op, span, .. } => Expression::Binary {
+                    op: op.clone(),
+                    left: next(&mut children),
+                    right: next(&mut children),
+                    span: *span,
+                },
+                Expression::Unary { op, span, .. } => Expression::Unary {
+                    op: op.clone(),
+                    operand: next(&mut children),
+                    span: *span,
+                },
+                Expression::FunctionCall { name, span, .. } => Expression::FunctionCall {
+                    name: name.clone(),
+                    args: children.collect(),
+                    span: *span,
+                },
+                Expression::List(_) => Expression::List(children.collect()),
+            });
+        }
+        done.pop().unwrap()
+    }
+}
+
+impl Drop for Expression {
+    fn drop(&mut self) {
+        // Children are moved out onto a stack before each node is dropped,
+        // leaving it nothing to recurse into
+        fn take(expr: &mut Expression, stack: &mut Vec<Expression>) {
+            match expr {
+                Expression::Percent(operand)
+                | Expression::Factorial { operand, .. }
+                | Expression::Unary { operand, .. } => stack.push(std::mem::replace(operand, Expression::Bool(false))),
+                Expression::Binary { left, right, .. } => {
+                    stack.push(std::mem::replace(left, Expression::Bool(false)));
+                    stack.push(std::mem::replace(right, Expression::Bool(false)));
+                }
+                Expression::FunctionCall { args, .. } | Expression::List(args) => stack.append(args),
+                _ => {}
+            }
+        }
+        let mut stack = Vec::new();
+        take(self, &mut stack);
+        while let Some(mut expr) = stack.pop() {
+            take(&mut expr, &mut stack);
+        }
+    }
+}
+
+/// An equation between two expressions, like `2x + 3 = 7`.
+#[derive(Debug, Clone)]
+pub struct Equation {
+    pub left: Expression,
+    pub right: Expression,
+}
+
+/// A complete line of input: an expression to evaluate, or an instruction
+/// acting on one.
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Expression(Expression),
+    /// `solve <equation> for <variable>`
+    Solve { equation: Equation, variable: String },
+    /// `name = <expression>`, binding a variable
+    Assign { name: String, value: Expression },
+    /// `name(params) = <expression>`, defining a function
+    Define {
+        name: String,
+        params: Vec<String>,
+        body: Expression,
     },
 }
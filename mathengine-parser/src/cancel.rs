@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A flag another thread can set to stop parsing or evaluation early.
+///
+/// Clones share the flag, so a host keeps one and hands a clone to the
+/// [`Parser`](crate::Parser) or the evaluator's limits. Work in progress
+/// checks it every so often and stops with a `Cancelled` error, leaving the
+/// thread to carry on. Once cancelled a token stays cancelled; make a new one
+/// for the next evaluation.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+/// assert!(!token.is_cancelled());
+/// std::thread::spawn(move || handle.cancel()).join().unwrap();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask whatever is checking this token, or a clone of it, to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// Tokens are the same when they share a flag
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
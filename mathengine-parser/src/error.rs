@@ -1,4 +1,5 @@
-use mathengine_lexer::Token;
+use crate::limits::InputLimit;
+use mathengine_lexer::{LexError, Token};
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -16,6 +17,23 @@ pub enum ParseError {
         position: usize,
     },
     EmptyTokenStream,
+    /// The expression is nested, or its syntax tree is, more than `limit`
+    /// levels deep
+    TooDeep {
+        limit: usize,
+        position: usize,
+    },
+    /// The input is longer, or has more tokens or nodes, than its
+    /// [`InputLimits`](crate::InputLimits) allow
+    InputTooLarge {
+        exceeded: InputLimit,
+        limit: usize,
+    },
+    /// The parser's [`CancellationToken`](crate::CancellationToken) was
+    /// cancelled before it finished
+    Cancelled,
+    /// The lexer failed on the tokens being parsed
+    Lex(LexError),
 }
 
 impl fmt::Display for ParseError {
@@ -45,8 +63,33 @@ impl fmt::Display for ParseError {
             ParseError::EmptyTokenStream => {
                 write!(f, "Cannot parse empty token stream")
             }
+            ParseError::TooDeep { limit, position } => {
+                write!(
+                    f,
+                    "Expression is nested more than {} levels deep at position {}",
+                    limit, position
+                )
+            }
+            ParseError::InputTooLarge { exceeded, limit } => {
+                write!(f, "Input exceeds the limit of {} {}", limit, exceeded.unit())
+            }
+            ParseError::Cancelled => write!(f, "Parsing was cancelled"),
+            ParseError::Lex(error) => write!(f, "{}", error),
         }
     }
 }
 
-impl std::error::Error for ParseError {}
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Lex(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        ParseError::Lex(error)
+    }
+}
@@ -0,0 +1,310 @@
+//! LaTeX rendering for expressions and values.
+//!
+//! Units are set upright with `\mathrm`, powers as superscripts and
+//! divisions as fractions. Parentheses are only added where the tree needs
+//! them, using the parser's precedence levels.
+
+use mathengine_lexer::Operation;
+
+use crate::types::{Number, Unit, UnitValue, Value};
+use crate::Expression;
+
+// Functions LaTeX has an operator command for
+const OPERATORS: &[&str] = &[
+    "sin", "cos", "tan", "sinh", "cosh", "tanh", "exp", "ln", "log", "min", "max", "gcd",
+];
+
+impl Expression {
+    /// Render this expression as LaTeX math.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_lexer::Lexer;
+    /// use mathengine_parser::Parser;
+    ///
+    /// let latex = |source: &str| {
+    ///     let tokens = Lexer::new(source).tokenize().unwrap();
+    ///     Parser::new(tokens).parse().unwrap().to_latex()
+    /// };
+    /// assert_eq!(latex("(1 + x) / 2"), r"\frac{1 + x}{2}");
+    /// assert_eq!(latex("(a + b)^2"), r"\left(a + b\right)^{2}");
+    /// assert_eq!(latex("sqrt(9 m^2) to ft"), r"\sqrt{9\,\mathrm{m}^{2}} \to \mathrm{ft}");
+    /// ```
+    pub fn to_latex(&self) -> String {
+        match self {
+            Expression::Number(n) => n.to_string(),
+            Expression::Decimal { digits, .. } => digits.to_string(),
+            Expression::Integer(i) => i.to_string(),
+            Expression::Bool(b) => format!(r"\mathrm{{{}}}", b),
+            // `2x` is a coefficient on a variable rather than a unit
+            Expression::UnitValue { value, unit } if is_variable(unit) => format!("{}{}", value, unit),
+            Expression::UnitValue { value, unit } => quantity(&value.to_string(), unit),
+            Expression::Unit(name) => variable(name),
+            Expression::Percent(operand) => format!(r"{}\%", postfix_operand(operand)),
+            Expression::Factorial { operand, .. } => format!("{}!", postfix_operand(operand)),
+            Expression::Binary {
+                op: Operation::Divide,
+                left,
+                right,
+                ..
+            } => format!(r"\frac{{{}}}{{{}}}", left.to_latex(), right.to_latex()),
+            Expression::Binary {
+                op: Operation::Power,
+                left,
+                right,
+                ..
+            } => format!("{}^{{{}}}", postfix_operand(left), right.to_latex()),
+            // The target of a conversion is a unit rather than a variable
+            Expression::Binary {
+                op: Operation::Convert,
+                left,
+                right,
+                ..
+            } if matches!(right.as_ref(), Expression::Unit(_)) => {
+                let Expression::Unit(unit) = right.as_ref() else {
+                    unreachable!();
+                };
+                let left = operand(left, precedence(&Operation::Convert));
+                format!(r"{} \to {}", left, unit_name(unit))
+            }
+            Expression::Binary { .. } => chain(self),
+            Expression::Unary {
+                op: Operation::Not,
+                operand: inner,
+                ..
+            } => format!(r"\lnot {}", operand(inner, precedence(&Operation::Not))),
+            Expression::Unary { operand: inner, .. } => {
+                format!("-{}", operand(inner, precedence(&Operation::Power)))
+            }
+            Expression::FunctionCall { name, args, .. } => function(name, args),
+            Expression::List(items) => list(items.iter().map(Expression::to_latex)),
+        }
+    }
+}
+
+impl Value {
+    /// Render this value as LaTeX math.
+    ///
+    /// Exact fractions are shown as `\frac`, and units by their canonical
+    /// names.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Number, Rational, UnitValue, Value};
+    ///
+    /// let area = Value::UnitValue(UnitValue::new(9.0, "meters^2".to_string()).unwrap());
+    /// assert_eq!(area.to_latex(), r"9\,\mathrm{m}^{2}");
+    ///
+    /// let third = Value::Number(Number::Rational(Rational::new(-1, 3).unwrap()));
+    /// assert_eq!(third.to_latex(), r"-\frac{1}{3}");
+    /// ```
+    pub fn to_latex(&self) -> String {
+        match self {
+            Value::Number(n) => number(n),
+            Value::UnitValue(uv) => unit_value(uv),
+            Value::Bool(b) => format!(r"\mathrm{{{}}}", b),
+            Value::Radix { .. } => format!(r"\mathtt{{{}}}", self),
+            Value::List(items) => list(items.iter().map(Value::to_latex)),
+        }
+    }
+}
+
+fn number(n: &Number) -> String {
+    match n {
+        Number::Rational(r) if !r.is_integer() => {
+            let sign = if r.numer() < 0 { "-" } else { "" };
+            format!(r"{}\frac{{{}}}{{{}}}", sign, r.numer().unsigned_abs(), r.denom())
+        }
+        Number::Float(x) if x.is_infinite() => {
+            let sign = if *x < 0.0 { "-" } else { "" };
+            format!(r"{}\infty", sign)
+        }
+        other => other.to_string(),
+    }
+}
+
+fn unit_value(uv: &UnitValue) -> String {
+    quantity(&uv.value().to_string(), &uv.canonical_unit_name())
+}
+
+// A value followed by its unit, separated by a thin space
+fn quantity(value: &str, unit: &str) -> String {
+    format!(r"{}\,{}", value, unit_name(unit))
+}
+
+// A unit like "m^2", with any exponent as a superscript. Known units are
+// shown by their canonical names
+fn unit_name(unit: &str) -> String {
+    let (base, exponent) = match unit.split_once('^') {
+        Some((base, exponent)) => (base.trim(), Some(exponent.trim())),
+        None => (unit, None),
+    };
+    let base = Unit::parse(base).map(|unit| unit.canonical_string()).unwrap_or(base);
+    let base = match base {
+        "C" | "F" => format!(r"{{}}^{{\circ}}\mathrm{{{}}}", base),
+        _ => format!(r"\mathrm{{{}}}", base),
+    };
+    match exponent {
+        Some(exponent) => format!("{}^{{{}}}", base, exponent),
+        None => base,
+    }
+}
+
+// Whether the unit on a value like `2x` is really a variable it multiplies
+fn is_variable(unit: &str) -> bool {
+    unit.chars().count() == 1 && Unit::parse(unit).is_err()
+}
+
+// Single-letter variables are set in italics, longer names upright
+fn variable(name: &str) -> String {
+    if name.chars().count() == 1 {
+        name.to_string()
+    } else {
+        format!(r"\mathrm{{{}}}", name)
+    }
+}
+
+fn function(name: &str, args: &[Expression]) -> String {
+    let args: Vec<String> = args.iter().map(Expression::to_latex).collect();
+    let args = args.join(", ");
+    match name {
+        "sqrt" => format!(r"\sqrt{{{}}}", args),
+        "abs" => format!(r"\left|{}\right|", args),
+        "floor" => format!(r"\left\lfloor {} \right\rfloor", args),
+        "ceil" => format!(r"\left\lceil {} \right\rceil", args),
+        _ if OPERATORS.contains(&name) => format!(r"\{}\left({}\right)", name, args),
+        _ => format!(r"\operatorname{{{}}}\left({}\right)", name, args),
+    }
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    let items: Vec<String> = items.collect();
+    format!(r"\left[{}\right]", items.join(", "))
+}
+
+// An infix operator and its operands, with the operators down its left side
+// that need no parentheses rendered in a loop rather than by recursion, so
+// long sums like `1 + 1 + ...` don't overflow the stack
+fn chain(expr: &Expression) -> String {
+    let mut rights = Vec::new();
+    let mut node = expr;
+    let left = loop {
+        let Some((op, left, right)) = infix(node) else {
+            unreachable!();
+        };
+        rights.push((op, right));
+        match infix(left) {
+            Some((inner, ..)) if precedence(inner) >= precedence(op) => node = left,
+            _ => break operand(left, precedence(op)),
+        }
+    };
+    let mut latex = left;
+    for (op, right) in rights.into_iter().rev() {
+        // Everything but powers is left-associative, so a right operand at
+        // the same level needs parentheses: a - (b - c)
+        let right = match right {
+            Expression::Unary {
+                op: Operation::Subtract,
+                ..
+            } => format!(r"\left({}\right)", right.to_latex()),
+            right => operand(right, precedence(op) + 1),
+        };
+        latex.push(' ');
+        latex.push_str(binary_symbol(op));
+        latex.push(' ');
+        latex.push_str(&right);
+    }
+    latex
+}
+
+// The operator and operands of a binary node rendered as `a op b`, rather
+// than as a fraction, power or conversion to a unit
+fn infix(expr: &Expression) -> Option<(&Operation, &Expression, &Expression)> {
+    match expr {
+        Expression::Binary {
+            op: Operation::Divide | Operation::Power,
+            ..
+        } => None,
+        Expression::Binary {
+            op: Operation::Convert,
+            right,
+            ..
+        } if matches!(right.as_ref(), Expression::Unit(_)) => None,
+        Expression::Binary { op, left, right, .. } => Some((op, left, right)),
+        _ => None,
+    }
+}
+
+// An operand of a binary or unary operator, parenthesized when it binds more
+// loosely than `min_precedence`
+fn operand(expr: &Expression, min_precedence: u8) -> String {
+    let binds = match expr {
+        Expression::Binary { op, .. } => precedence(op),
+        Expression::Unary { op: Operation::Not, .. } => precedence(&Operation::Not),
+        // Negation binds like multiplication: -a * b is (-a) * b
+        Expression::Unary { .. } => precedence(&Operation::Multiply),
+        _ => u8::MAX,
+    };
+    if binds < min_precedence {
+        format!(r"\left({}\right)", expr.to_latex())
+    } else {
+        expr.to_latex()
+    }
+}
+
+// The operand of a postfix operator or the base of a power, which needs
+// parentheses around anything but a single term
+fn postfix_operand(expr: &Expression) -> String {
+    match expr {
+        Expression::Binary { .. }
+        | Expression::Unary { .. }
+        | Expression::Percent(_)
+        | Expression::Factorial { .. }
+        | Expression::UnitValue { .. } => format!(r"\left({}\right)", expr.to_latex()),
+        Expression::Number(n) | Expression::Decimal { value: n, .. } if *n < 0.0 => format!(r"\left({}\right)", expr.to_latex()),
+        Expression::Integer(i) if *i < 0 => format!(r"\left({}\right)", expr.to_latex()),
+        _ => expr.to_latex(),
+    }
+}
+
+// Matches the parser's precedence levels
+fn precedence(op: &Operation) -> u8 {
+    match op {
+        Operation::Or => 1,
+        Operation::And => 2,
+        Operation::Not => 3,
+        Operation::Less
+        | Operation::LessEqual
+        | Operation::Greater
+        | Operation::GreaterEqual
+        | Operation::Equal
+        | Operation::NotEqual => 4,
+        Operation::Add | Operation::Subtract => 5,
+        Operation::Multiply | Operation::Divide | Operation::Of => 6,
+        Operation::Power => 7,
+        Operation::Convert => 9,
+    }
+}
+
+fn binary_symbol(op: &Operation) -> &'static str {
+    match op {
+        Operation::Add => "+",
+        Operation::Subtract => "-",
+        Operation::Multiply => r"\cdot",
+        Operation::Divide => "/",
+        Operation::Power => "^",
+        Operation::Convert => r"\to",
+        Operation::Of => r"\text{ of }",
+        Operation::And => r"\land",
+        Operation::Or => r"\lor",
+        Operation::Not => r"\lnot",
+        Operation::Less => "<",
+        Operation::LessEqual => r"\le",
+        Operation::Greater => ">",
+        Operation::GreaterEqual => r"\ge",
+        Operation::Equal => "=",
+        Operation::NotEqual => r"\ne",
+    }
+}
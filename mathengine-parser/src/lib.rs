@@ -1,8 +1,17 @@
 pub mod ast;
+mod cancel;
 pub mod error;
+mod latex;
+pub mod limits;
 pub mod parser;
+#[cfg(feature = "serde")]
+mod serialization;
 pub mod types;
 
-pub use ast::Expression;
+pub use ast::{Equation, Expression, Statement};
+pub use cancel::CancellationToken;
 pub use error::ParseError;
-pub use parser::Parser;
+pub use limits::{InputLimit, InputLimits};
+pub use parser::{Parser, TokenItem};
+#[cfg(feature = "serde")]
+pub use serialization::{Versioned, FORMAT_VERSION};
@@ -0,0 +1,76 @@
+/// Caps on the size of the input a [`Parser`](crate::Parser) accepts, for
+/// bounding the memory and time untrusted expressions can take before they're
+/// evaluated.
+///
+/// The size limits are optional and unset by default. Going over one fails
+/// with [`ParseError::InputTooLarge`](crate::ParseError::InputTooLarge) as
+/// soon as the parser gets that far, so the rest of the input is never kept.
+///
+/// Nesting and height are always capped, since parsing recurses through each
+/// level of nesting and syntax trees are cloned, displayed and dropped
+/// recursively. Going over either fails with
+/// [`ParseError::TooDeep`](crate::ParseError::TooDeep). The defaults fit in
+/// the 2 MiB stack a spawned thread gets; raise them for threads with more.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{InputLimit, InputLimits, ParseError, Parser};
+///
+/// let limits = InputLimits {
+///     max_tokens: Some(3),
+///     ..InputLimits::default()
+/// };
+/// let lexer = Lexer::new("1 + 2 + 3");
+/// assert_eq!(
+///     Parser::new(lexer.tokens()).with_limits(limits).parse().unwrap_err(),
+///     ParseError::InputTooLarge { exceeded: InputLimit::Tokens, limit: 3 }
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLimits {
+    /// Maximum length of the source in characters. Only tokens with spans
+    /// are measured
+    pub max_length: Option<usize>,
+    /// Maximum number of tokens read
+    pub max_tokens: Option<usize>,
+    /// Maximum number of nodes in the syntax tree
+    pub max_nodes: Option<usize>,
+    /// Maximum depth of parentheses, calls, lists and prefix operators
+    pub max_nesting: usize,
+    /// Maximum height of the syntax tree, which chains like `1 + 1 + ...`
+    /// add to a level at a time. Items of `sum([...])` don't add height
+    pub max_height: usize,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            max_length: None,
+            max_tokens: None,
+            max_nodes: None,
+            max_nesting: 128,
+            max_height: 10_000,
+        }
+    }
+}
+
+/// Which of the [`InputLimits`] an input went over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputLimit {
+    Length,
+    Tokens,
+    Nodes,
+}
+
+impl InputLimit {
+    /// What the limit counts, like `characters`.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            InputLimit::Length => "characters",
+            InputLimit::Tokens => "tokens",
+            InputLimit::Nodes => "nodes",
+        }
+    }
+}
@@ -1,89 +1,393 @@
-use crate::ast::Expression;
+use crate::ast::{Equation, Expression, Statement};
+use crate::cancel::CancellationToken;
 use crate::error::ParseError;
-use mathengine_lexer::{Operation, Token};
+use crate::limits::{InputLimit, InputLimits};
+use crate::types::{DimensionType, UnitValue};
+use mathengine_lexer::{LexError, Operation, Span, Token};
+use std::collections::VecDeque;
 
-pub struct Parser {
-    tokens: Vec<Token>,
+// How many tokens are read between checks of the cancellation token
+const CANCEL_CHECK_INTERVAL: usize = 64;
+
+/// A token the parser can read: a bare [`Token`], one paired with its
+/// [`Span`], or either as a result straight from the lexer.
+pub trait TokenItem {
+    fn into_spanned(self) -> Result<(Token, Span), LexError>;
+}
+
+impl TokenItem for Token {
+    fn into_spanned(self) -> Result<(Token, Span), LexError> {
+        Ok((self, Span::default()))
+    }
+}
+
+impl TokenItem for (Token, Span) {
+    fn into_spanned(self) -> Result<(Token, Span), LexError> {
+        Ok(self)
+    }
+}
+
+impl<T: TokenItem> TokenItem for Result<T, LexError> {
+    fn into_spanned(self) -> Result<(Token, Span), LexError> {
+        self.and_then(T::into_spanned)
+    }
+}
+
+pub struct Parser<I> {
+    tokens: I,
+    // Tokens read ahead of the one being parsed, oldest first
+    lookahead: VecDeque<(Token, Span)>,
+    // Span of the last consumed token; empty when parsing bare tokens
+    last: Span,
+    // The lexer's error, which ends the tokens and wins over any parse error
+    error: Option<LexError>,
     pos: usize,
+    // How deeply the expression being parsed is nested
+    depth: usize,
+    // Height of the tree the last parse step returned
+    height: usize,
+    cancel: Option<CancellationToken>,
+    limits: InputLimits,
+    // Tokens read from the source so far
+    read: usize,
+    // Nodes of the syntax tree made so far
+    nodes: usize,
+    // Why parsing was cut short, by cancellation or a limit. It ends the
+    // tokens like a lexer error and wins over any other error
+    stopped: Option<ParseError>,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+impl<I> Parser<I>
+where
+    I: Iterator,
+    I::Item: TokenItem,
+{
+    /// Create a parser reading tokens as it needs them.
+    ///
+    /// The tokens can come collected in a `Vec` or straight from
+    /// [`Lexer::tokens`], which never collects them. Tokens with spans make
+    /// the resulting expression record where each node came from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_lexer::{LexError, Lexer};
+    /// use mathengine_parser::{ParseError, Parser};
+    ///
+    /// let lexer = Lexer::new("(1 + 2) * 3");
+    /// assert!(Parser::new(lexer.tokens()).parse().is_ok());
+    ///
+    /// // Errors from the lexer come out of the parser
+    /// let lexer = Lexer::new("1 + $");
+    /// assert!(matches!(
+    ///     Parser::new(lexer.tokens()).parse(),
+    ///     Err(ParseError::Lex(LexError::UnexpectedCharacter { char: '$', .. }))
+    /// ));
+    /// ```
+    ///
+    /// [`Lexer::tokens`]: mathengine_lexer::Lexer::tokens
+    pub fn new<T: IntoIterator<IntoIter = I>>(tokens: T) -> Self {
+        Self {
+            tokens: tokens.into_iter(),
+            lookahead: VecDeque::new(),
+            last: Span::default(),
+            error: None,
+            pos: 0,
+            depth: 0,
+            height: 0,
+            cancel: None,
+            limits: InputLimits::default(),
+            read: 0,
+            nodes: 0,
+            stopped: None,
+        }
+    }
+
+    /// Stop with [`ParseError::Cancelled`] soon after `token` is cancelled,
+    /// checking it every so often as tokens are read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_lexer::Lexer;
+    /// use mathengine_parser::{CancellationToken, ParseError, Parser};
+    ///
+    /// let token = CancellationToken::new();
+    /// token.cancel();
+    /// let lexer = Lexer::new("1 + 2");
+    /// let mut parser = Parser::new(lexer.tokens()).with_cancellation(token);
+    /// assert_eq!(parser.parse().unwrap_err(), ParseError::Cancelled);
+    /// ```
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Fail with [`ParseError::InputTooLarge`] if the input goes over any of
+    /// `limits`.
+    pub fn with_limits(mut self, limits: InputLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Create a parser for tokens from [`Lexer::tokenize_with_spans`], so
+    /// the resulting expression records where each node came from.
+    ///
+    /// [`Lexer::tokenize_with_spans`]: mathengine_lexer::Lexer::tokenize_with_spans
+    pub fn with_spans<T: IntoIterator<IntoIter = I>>(tokens: T) -> Self {
+        Self::new(tokens)
     }
 
     // Entry point for parsing - parses the entire token stream and ensures all tokens are consumed
     pub fn parse(&mut self) -> Result<Expression, ParseError> {
-        if self.tokens.is_empty() {
+        let expr = self.parse_all();
+        self.lexed(expr)
+    }
+
+    fn parse_all(&mut self) -> Result<Expression, ParseError> {
+        if self.peek().is_none() {
             return Err(ParseError::EmptyTokenStream);
         }
 
         let expr = self.parse_expression(0)?;
-        if self.pos < self.tokens.len() {
-            return Err(ParseError::UnexpectedToken {
-                expected: "end of input".to_string(),
-                found: self.tokens[self.pos].clone(),
-                position: self.pos,
+        self.expect_end()?;
+        Ok(expr)
+    }
+
+    /// Parse a statement: a plain expression, an equation to solve such as
+    /// `solve 2x + 3 = 7 for x`, or a definition like `x = 2` or
+    /// `f(x) = x^2 + 1`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_lexer::Lexer;
+    /// use mathengine_parser::{Parser, Statement};
+    ///
+    /// let tokens = Lexer::new("solve 2x + 3 = 7 for x").tokenize().unwrap();
+    /// match Parser::new(tokens).parse_statement().unwrap() {
+    ///     Statement::Solve { variable, .. } => assert_eq!(variable, "x"),
+    ///     other => panic!("expected an equation, got {:?}", other),
+    /// }
+    ///
+    /// let tokens = Lexer::new("area(w, h) = w * h").tokenize().unwrap();
+    /// match Parser::new(tokens).parse_statement().unwrap() {
+    ///     Statement::Define { name, params, .. } => assert_eq!((name.as_str(), params.len()), ("area", 2)),
+    ///     other => panic!("expected a definition, got {:?}", other),
+    /// }
+    /// ```
+    pub fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        let statement = self.parse_any_statement();
+        self.lexed(statement)
+    }
+
+    fn parse_any_statement(&mut self) -> Result<Statement, ParseError> {
+        if let Some((name, params)) = self.definition_head() {
+            let body = self.parse_expression(0)?;
+            self.expect_end()?;
+            return Ok(match params {
+                Some(params) => Statement::Define { name, params, body },
+                None => Statement::Assign { name, value: body },
             });
         }
-        Ok(expr)
+        if !matches!(self.peek(), Some(Token::Solve)) {
+            return self.parse_all().map(Statement::Expression);
+        }
+        self.advance();
+
+        let left = self.parse_expression(0)?;
+        self.expect(&Token::Equals, "'='")?;
+        let right = self.parse_expression(0)?;
+        self.expect(&Token::For, "'for'")?;
+        let variable = match self.advance() {
+            Some(Token::Unit(name)) => name,
+            Some(other) => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "a variable name".to_string(),
+                    found: other,
+                    position: self.pos - 1,
+                });
+            }
+            None => {
+                return Err(ParseError::UnexpectedEndOfInput {
+                    expected: "a variable name".to_string(),
+                });
+            }
+        };
+        self.expect_end()?;
+
+        Ok(Statement::Solve {
+            equation: Equation { left, right },
+            variable,
+        })
+    }
+
+    // An error from the lexer replaces the result, as if the source had been
+    // lexed up front, so after a parse error the rest of the tokens are read
+    // to find one
+    fn lexed<T>(&mut self, result: Result<T, ParseError>) -> Result<T, ParseError> {
+        if result.is_err() {
+            while self.read().is_some() {}
+        }
+        if let Some(error) = self.stopped.take() {
+            return Err(error);
+        }
+        match self.error.take() {
+            Some(error) => Err(ParseError::Lex(error)),
+            None => result,
+        }
+    }
+
+    // Consumes `name =` or `name(a, b) =` at the start of the input, giving
+    // the name and any parameters. Leaves anything else for the expression
+    // parser, so `x == 2` and `f(2) = 4` aren't definitions
+    fn definition_head(&mut self) -> Option<(String, Option<Vec<String>>)> {
+        let Some(Token::Unit(_)) = self.peek() else {
+            return None;
+        };
+        // Index of the token before the `=`
+        let end = match self.peek_nth(1) {
+            Some(Token::Equals) => 0,
+            Some(Token::Lparen) => {
+                let mut at = 2;
+                loop {
+                    match self.peek_nth(at) {
+                        Some(Token::Rparen) if at == 2 => break,
+                        Some(Token::Unit(_)) => {}
+                        _ => return None,
+                    }
+                    match self.peek_nth(at + 1) {
+                        Some(Token::Comma) => at += 2,
+                        Some(Token::Rparen) => {
+                            at += 1;
+                            break;
+                        }
+                        _ => return None,
+                    }
+                }
+                if self.peek_nth(at + 1) != Some(&Token::Equals) {
+                    return None;
+                }
+                at
+            }
+            _ => return None,
+        };
+
+        let mut names = Vec::new();
+        for _ in 0..end + 2 {
+            if let Some(Token::Unit(name)) = self.advance() {
+                names.push(name);
+            }
+        }
+        let name = names.remove(0);
+        Some((name, (end > 0).then_some(names)))
+    }
+
+    fn expect(&mut self, token: &Token, expected: &str) -> Result<(), ParseError> {
+        match self.advance() {
+            Some(found) if found == *token => Ok(()),
+            Some(found) => Err(ParseError::UnexpectedToken {
+                expected: expected.to_string(),
+                found,
+                position: self.pos - 1,
+            }),
+            None => Err(ParseError::UnexpectedEndOfInput {
+                expected: expected.to_string(),
+            }),
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), ParseError> {
+        match self.advance() {
+            Some(found) => Err(ParseError::UnexpectedToken {
+                expected: "end of input".to_string(),
+                found,
+                position: self.pos - 1,
+            }),
+            None => Ok(()),
+        }
     }
 
     // Pratt parsing algorithm - handles binary operators with correct precedence and associativity
     // min_precedence determines the minimum operator precedence this call will handle
     fn parse_expression(&mut self, min_precedence: u8) -> Result<Expression, ParseError> {
-        let mut left = self.parse_primary()?;
-
-        while let Some(token) = self.peek() {
-            match token {
-                Token::Operation(op) => {
-                    let precedence = self.get_precedence(op);
-                    if precedence < min_precedence {
-                        break;
-                    }
+        let start = self.next_span();
+        let mut left = self.parse_postfix()?;
+        let mut height = self.height;
 
-                    let op = match self.advance() {
-                        Some(Token::Operation(o)) => o.clone(),
-                        _ => unreachable!(),
-                    };
-
-                    let right_precedence = if self.is_right_associative(&op) {
-                        precedence
-                    } else {
-                        precedence + 1
-                    };
-
-                    let right = self.parse_expression(right_precedence)?;
-                    left = Expression::Binary {
-                        op,
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    };
-                }
+        loop {
+            let precedence = match self.peek() {
+                // `not` only appears in prefix position
+                Some(Token::Operation(Operation::Not)) => break,
+                Some(Token::Operation(op)) => Self::get_precedence(op),
                 _ => break,
+            };
+            if precedence < min_precedence {
+                break;
             }
+
+            let Some(Token::Operation(op)) = self.advance() else {
+                unreachable!()
+            };
+
+            // Right-associative chains like 2^3^4 recurse once per
+            // operator, so they count towards the nesting limit
+            let right_associative = Self::is_right_associative(&op);
+            let right_precedence = if right_associative {
+                precedence
+            } else {
+                precedence + 1
+            };
+
+            self.depth += usize::from(right_associative);
+            let right = self.parse_expression(right_precedence);
+            self.depth -= usize::from(right_associative);
+            let right = right?;
+            height = self.grow(height.max(self.height))?;
+            left = self.node(Expression::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+                span: self.span_from(start),
+            })?;
         }
 
+        self.height = height;
         Ok(left)
     }
 
     // Parses primary expressions: numbers, parenthesized expressions, and unary operators
     fn parse_primary(&mut self) -> Result<Expression, ParseError> {
         let start_pos = self.pos;
+        let start = self.next_span();
+        self.height = 1;
         match self.advance() {
-            Some(Token::Number(n)) => Ok(Expression::Number(*n)),
-            Some(Token::UnitValue { value, unit }) => Ok(Expression::UnitValue {
-                value: *value,
-                unit: unit.clone(),
-            }),
-            Some(Token::Unit(unit)) => Ok(Expression::Unit(unit.clone())),
+            Some(Token::Number { value, digits }) => self.node(Expression::Decimal { value, digits }),
+            Some(Token::Integer(i)) => self.node(Expression::Integer(i)),
+            Some(Token::Bool(b)) => self.node(Expression::Bool(b)),
+            Some(Token::UnitValue { value, unit }) => {
+                let raised = self.parse_unit_exponent(&unit);
+                let unit = match self.parse_derived_unit(raised.as_deref().unwrap_or(&unit)) {
+                    Some(derived) => derived.into(),
+                    None => raised.map(Into::into).unwrap_or(unit),
+                };
+                self.node(Expression::UnitValue { value, unit })
+            }
+            Some(Token::Unit(name)) => {
+                if let Some(Token::Lparen) = self.peek() {
+                    return self.parse_function_call(name, start);
+                }
+                let unit = self.parse_unit_exponent(&name).unwrap_or(name);
+                let unit = self.parse_derived_unit(&unit).unwrap_or(unit);
+                self.node(Expression::Unit(unit))
+            }
             Some(Token::Lparen) => {
                 let expr = self.parse_expression(0)?;
                 match self.advance() {
                     Some(Token::Rparen) => Ok(expr),
                     Some(other) => Err(ParseError::UnexpectedToken {
                         expected: "')'".to_string(),
-                        found: other.clone(),
+                        found: other,
                         position: self.pos - 1,
                     }),
                     None => Err(ParseError::UnexpectedEndOfInput {
@@ -91,16 +395,34 @@ impl Parser {
                     }),
                 }
             }
+            Some(Token::Lbracket) => {
+                let items = self.parse_comma_separated(Token::Rbracket)?;
+                self.node(Expression::List(items))
+            }
             Some(Token::Operation(Operation::Subtract)) => {
-                let operand = self.parse_primary()?;
-                Ok(Expression::Unary {
+                // Postfix operators bind tighter than negation: -3! is -(3!)
+                let operand = self.parse_postfix()?;
+                self.height = self.grow(self.height)?;
+                self.node(Expression::Unary {
                     op: Operation::Subtract,
                     operand: Box::new(operand),
+                    span: self.span_from(start),
+                })
+            }
+            Some(Token::Operation(Operation::Not)) => {
+                // `not` covers everything that binds tighter than `and`, so
+                // `not a and b` is `(not a) and b`
+                let operand = self.parse_expression(Self::get_precedence(&Operation::Not))?;
+                self.height = self.grow(self.height)?;
+                self.node(Expression::Unary {
+                    op: Operation::Not,
+                    operand: Box::new(operand),
+                    span: self.span_from(start),
                 })
             }
             Some(token) => Err(ParseError::UnexpectedToken {
-                expected: "number, unit value, '(', or unary operator".to_string(),
-                found: token.clone(),
+                expected: "number, unit value, '(', '[', or unary operator".to_string(),
+                found: token,
                 position: start_pos,
             }),
             None => Err(ParseError::UnexpectedEndOfInput {
@@ -109,38 +431,273 @@ impl Parser {
         }
     }
 
+    // Parses a primary expression followed by any postfix operators: percent
+    // signs (10%) and factorials (5!)
+    fn parse_postfix(&mut self) -> Result<Expression, ParseError> {
+        if self.depth == self.limits.max_nesting {
+            return Err(ParseError::TooDeep {
+                limit: self.limits.max_nesting,
+                position: self.pos,
+            });
+        }
+        self.depth += 1;
+        let expr = self.parse_postfix_operators();
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_postfix_operators(&mut self) -> Result<Expression, ParseError> {
+        let start = self.next_span();
+        let mut expr = self.parse_primary()?;
+        loop {
+            let factorial = match self.peek() {
+                Some(Token::Percent) => false,
+                Some(Token::Bang) => true,
+                _ => break,
+            };
+            self.advance();
+            self.height = self.grow(self.height)?;
+            expr = self.node(if factorial {
+                Expression::Factorial {
+                    operand: Box::new(expr),
+                    span: self.span_from(start),
+                }
+            } else {
+                Expression::Percent(Box::new(expr))
+            })?;
+        }
+        Ok(expr)
+    }
+
+    // Parses the parenthesized arguments of a function call like max(3, 5)
+    fn parse_function_call(&mut self, name: String, start: Span) -> Result<Expression, ParseError> {
+        self.advance(); // consume '('
+        let args = self.parse_comma_separated(Token::Rparen)?;
+        self.node(Expression::FunctionCall {
+            name,
+            args,
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses comma-separated expressions up to and including the closing token,
+    // used for function arguments and list literals
+    fn parse_comma_separated(&mut self, close: Token) -> Result<Vec<Expression>, ParseError> {
+        let mut items = Vec::new();
+        self.height = 1;
+        if self.peek() == Some(&close) {
+            self.advance();
+            return Ok(items);
+        }
+
+        let expected = match close {
+            Token::Rbracket => "',' or ']'",
+            _ => "',' or ')'",
+        };
+        let mut height = 0;
+        loop {
+            items.push(self.parse_expression(0)?);
+            height = height.max(self.height);
+            match self.advance() {
+                Some(Token::Comma) => continue,
+                Some(token) if token == close => {
+                    self.height = self.grow(height)?;
+                    return Ok(items);
+                }
+                Some(other) => {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: expected.to_string(),
+                        found: other,
+                        position: self.pos - 1,
+                    });
+                }
+                None => {
+                    return Err(ParseError::UnexpectedEndOfInput {
+                        expected: expected.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Folds an integer power directly following a unit into the unit itself,
+    // so `9 m^2` means nine square meters rather than (9m)^2. A chain of
+    // powers folds whole, right to left like any other, so `m^2^3` is `m^8`.
+    // Identifiers that aren't units (variables like `x^2`), chains that
+    // aren't integers all the way, and exponents too large for a unit keep
+    // the power as an operation, and give `None` like units without a power.
+    fn parse_unit_exponent(&mut self, unit: &str) -> Option<String> {
+        if DimensionType::from_unit(unit) == DimensionType::Unknown {
+            return None;
+        }
+
+        let mut exponents = Vec::new();
+        let mut offset = 0;
+        while let Some(Token::Operation(Operation::Power)) = self.peek_nth(offset) {
+            let negative = matches!(self.peek_nth(offset + 1), Some(Token::Operation(Operation::Subtract)));
+            offset += 1 + usize::from(negative);
+            match self.peek_nth(offset) {
+                Some(Token::Integer(n)) => exponents.push(if negative { -*n } else { *n }),
+                _ => return None,
+            }
+            offset += 1;
+        }
+        let mut exponent = exponents.pop()?;
+        while let Some(base) = exponents.pop() {
+            exponent = u32::try_from(exponent).ok().and_then(|power| base.checked_pow(power))?;
+        }
+        let exponent = i32::try_from(exponent).ok()?;
+        for _ in 0..offset {
+            self.advance();
+        }
+        Some(format!("{}^{}", unit, exponent))
+    }
+
+    // Folds units divided by units of other dimensions into the derived unit
+    // they make, so `60 mi/h` is sixty miles an hour and `to m/s^2` converts
+    // to an acceleration. Each unit after a `/` is folded only if it's a
+    // known unit, and not a call, that the unit so far doesn't already have
+    // the dimension of. Units with nothing to fold give `None`.
+    fn parse_derived_unit(&mut self, unit: &str) -> Option<String> {
+        let mut derived: Option<String> = None;
+        while let Some(Token::Operation(Operation::Divide)) = self.peek() {
+            let Some(Token::Unit(next)) = self.peek_nth(1) else {
+                break;
+            };
+            let next = next.clone();
+            let folded = format!("{}/{}", derived.as_deref().unwrap_or(unit), next);
+            if matches!(self.peek_nth(2), Some(Token::Lparen))
+                || UnitValue::named(1.0, folded.as_str()).dimension() != DimensionType::Derived
+            {
+                break;
+            }
+            self.advance();
+            self.advance();
+            derived = Some(match self.parse_unit_exponent(&next) {
+                Some(raised) => format!("{}/{}", derived.as_deref().unwrap_or(unit), raised),
+                None => folded,
+            });
+        }
+        derived
+    }
+
+    // The height of a node over a subtree `height` tall, if it's allowed
+    fn grow(&self, height: usize) -> Result<usize, ParseError> {
+        if height >= self.limits.max_height {
+            return Err(ParseError::TooDeep {
+                limit: self.limits.max_height,
+                position: self.pos.saturating_sub(1),
+            });
+        }
+        Ok(height + 1)
+    }
+
+    // Span from `start` through the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.last)
+    }
+
+    // Span of the current token, where a node starting at it begins
+    fn next_span(&mut self) -> Span {
+        self.peek();
+        self.lookahead.front().map_or(Span::default(), |(_, span)| *span)
+    }
+
+    // Reads the next token from the input, keeping the lexer's error if
+    // that's what comes instead
+    fn read(&mut self) -> Option<(Token, Span)> {
+        if self.error.is_some() || self.stopped.is_some() {
+            return None;
+        }
+        if self.read % CANCEL_CHECK_INTERVAL == 0 && self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            self.stopped = Some(ParseError::Cancelled);
+            return None;
+        }
+        let (token, span) = match self.tokens.next()?.into_spanned() {
+            Ok(token) => token,
+            Err(error) => {
+                self.error = Some(error);
+                return None;
+            }
+        };
+        self.read += 1;
+        let exceeded = match (self.limits.max_tokens, self.limits.max_length) {
+            (Some(limit), _) if self.read > limit => Some((InputLimit::Tokens, limit)),
+            (_, Some(limit)) if span.end > limit => Some((InputLimit::Length, limit)),
+            _ => None,
+        };
+        if let Some((exceeded, limit)) = exceeded {
+            self.stopped = Some(ParseError::InputTooLarge { exceeded, limit });
+            return None;
+        }
+        Some((token, span))
+    }
+
+    // Counts a node of the syntax tree against the node limit
+    fn node(&mut self, expr: Expression) -> Result<Expression, ParseError> {
+        self.nodes += 1;
+        match self.limits.max_nodes {
+            Some(limit) if self.nodes > limit => {
+                let error = ParseError::InputTooLarge {
+                    exceeded: InputLimit::Nodes,
+                    limit,
+                };
+                self.stopped = Some(error.clone());
+                Err(error)
+            }
+            _ => Ok(expr),
+        }
+    }
+
+    // Returns the token `n` past the current one without consuming anything
+    fn peek_nth(&mut self, n: usize) -> Option<&Token> {
+        while self.lookahead.len() <= n {
+            let token = self.read()?;
+            self.lookahead.push_back(token);
+        }
+        self.lookahead.get(n).map(|(token, _)| token)
+    }
+
     // Returns the current token without consuming it
-    fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.pos)
+    fn peek(&mut self) -> Option<&Token> {
+        self.peek_nth(0)
     }
 
     // Consumes and returns the current token, advancing the position
-    fn advance(&mut self) -> Option<&Token> {
-        if self.pos < self.tokens.len() {
-            let token = &self.tokens[self.pos];
-            self.pos += 1;
-            Some(token)
-        } else {
-            None
-        }
+    fn advance(&mut self) -> Option<Token> {
+        let (token, span) = match self.lookahead.pop_front() {
+            Some(token) => token,
+            None => self.read()?,
+        };
+        self.pos += 1;
+        self.last = span;
+        Some(token)
     }
 
     // Returns the precedence level for each operator (higher number = higher precedence)
-    fn get_precedence(&self, op: &Operation) -> u8 {
+    fn get_precedence(op: &Operation) -> u8 {
         match op {
-            Operation::Add | Operation::Subtract => 1,
-            Operation::Multiply | Operation::Divide => 2,
-            Operation::Power => 3,
-            Operation::Convert => 5,
+            Operation::Or => 1,
+            Operation::And => 2,
+            Operation::Not => 3,
+            Operation::Less
+            | Operation::LessEqual
+            | Operation::Greater
+            | Operation::GreaterEqual
+            | Operation::Equal
+            | Operation::NotEqual => 4,
+            Operation::Add | Operation::Subtract => 5,
+            Operation::Multiply | Operation::Divide | Operation::Of => 6,
+            Operation::Power => 7,
+            Operation::Convert => 9,
         }
     }
 
     // Determines if an operator is right-associative (currently all ops are left-associative)
-    fn is_right_associative(&self, op: &Operation) -> bool {
+    fn is_right_associative(op: &Operation) -> bool {
         match op {
             Operation::Power => true, // Power is right-associative: 2^3^4 = 2^(3^4)
             _ => false,
         }
     }
 }
-
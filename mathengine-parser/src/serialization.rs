@@ -0,0 +1,440 @@
+//! Serde support for expressions, and the versioned documents expressions and
+//! values are exchanged in, behind the `serde` feature.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use mathengine_lexer::{Operation, Span};
+use serde::de::{self, EnumAccess, IgnoredAny, MapAccess, VariantAccess, Visitor};
+use serde::ser::{SerializeStruct, SerializeStructVariant};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::serialization::{Pair, UnitVariant, VariantIndex};
+use crate::Expression;
+
+/// The version of the document format this crate writes, and the newest it
+/// reads.
+pub const FORMAT_VERSION: u32 = 2;
+
+const VERSIONED_FIELDS: &[&str] = &["version", "content"];
+const EXPRESSION_VARIANTS: &[&str] = &[
+    "Number",
+    "Integer",
+    "Bool",
+    "UnitValue",
+    "Unit",
+    "Percent",
+    "Factorial",
+    "Binary",
+    "Unary",
+    "FunctionCall",
+    "List",
+    "Decimal",
+];
+const OPERATION_VARIANTS: &[&str] = &[
+    "Add",
+    "Subtract",
+    "Divide",
+    "Multiply",
+    "Power",
+    "Convert",
+    "Of",
+    "And",
+    "Or",
+    "Not",
+    "Less",
+    "LessEqual",
+    "Greater",
+    "GreaterEqual",
+    "Equal",
+    "NotEqual",
+];
+const SPAN_FIELDS: &[&str] = &["start", "end"];
+const NODE_FIELDS: &[&str] = &["op", "left", "right", "operand", "span", "name", "args", "value", "unit", "digits"];
+
+/// An [`Expression`] or [`Value`](crate::types::Value) in a document tagged
+/// with the format version, for exchanging them with other tools, like web
+/// UIs or programs in other languages.
+///
+/// # Format
+///
+/// A document is an object holding the version and its content:
+///
+/// ```text
+/// {"version": 2, "content": <expression or value>}
+/// ```
+///
+/// Enums are written as serde's derive writes them: variants without data
+/// as their name, and others as an object with the variant's name as its
+/// only key. An expression is one of:
+///
+/// ```text
+/// {"Number": 2.5}
+/// {"Decimal": {"value": 2.5, "digits": "2.5"}}  a literal, as written
+/// {"Integer": 3}
+/// {"Bool": true}
+/// {"UnitValue": {"value": 5.0, "unit": "km"}}
+/// {"Unit": "x"}  a variable, or a unit as the target of a conversion
+/// {"Percent": <expression>}
+/// {"Factorial": {"operand": <expression>, "span": <span>}}
+/// {"Binary": {"op": <operation>, "left": <expression>, "right": <expression>, "span": <span>}}
+/// {"Unary": {"op": <operation>, "operand": <expression>, "span": <span>}}
+/// {"FunctionCall": {"name": "sqrt", "args": [<expression>, ...], "span": <span>}}
+/// {"List": [<expression>, ...]}
+/// ```
+///
+/// An operation is the name of an [`Operation`] variant, like `"Add"` or
+/// `"Convert"`. A span is `{"start": 0, "end": 5}`, counting characters of
+/// the source. Spans are optional, and nodes without one get an empty span,
+/// as nodes built by hand do. Values are written as described in
+/// [`types`](crate::types), with integers as plain numbers, so integers past
+/// 2^53 don't survive readers that parse numbers as doubles.
+///
+/// # Compatibility
+///
+/// - New fields can be added to objects without changing the version.
+///   Readers ignore fields they don't know.
+/// - New variants, and changes to what existing ones mean, change the
+///   version. Readers reject documents with a newer version than they know,
+///   so `version` should be written before `content`.
+/// - Readers keep reading every older version.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_lexer::Lexer;
+/// use mathengine_parser::{Expression, Parser, Versioned};
+///
+/// let tokens = Lexer::new("2 + x").tokenize_with_spans().unwrap();
+/// let expr = Parser::with_spans(tokens).parse().unwrap();
+/// let json = serde_json::to_string(&Versioned(&expr)).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"version":2,"content":{"Binary":{"op":"Add","left":{"Integer":2},"right":{"Unit":"x"},"span":{"start":0,"end":5}}}}"#
+/// );
+///
+/// let Versioned(back): Versioned<Expression> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(format!("{:?}", back), format!("{:?}", expr));
+///
+/// // Other tools can leave out spans, and add fields of their own
+/// let json = r#"{"version":1,"content":{"Unary":{"op":"Subtract","operand":{"Number":0.5},"note":"hi"}}}"#;
+/// let Versioned(expr): Versioned<Expression> = serde_json::from_str(json).unwrap();
+/// assert!(matches!(expr, Expression::Unary { .. }));
+///
+/// // Version 1 documents, from before decimal literals kept their digits
+/// let json = r#"{"version":1,"content":{"Number":2.5}}"#;
+/// assert!(serde_json::from_str::<Versioned<Expression>>(json).is_ok());
+///
+/// let newer = r#"{"version":3,"content":{"Integer":2}}"#;
+/// assert!(serde_json::from_str::<Versioned<Expression>>(newer).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versioned<T>(pub T);
+
+impl<T: Serialize> Serialize for Versioned<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Versioned", 2)?;
+        state.serialize_field("version", &FORMAT_VERSION)?;
+        state.serialize_field("content", &self.0)?;
+        state.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Versioned<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VersionedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for VersionedVisitor<T> {
+            type Value = Versioned<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a versioned mathengine document")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Versioned<T>, M::Error> {
+                let (mut version, mut content) = (None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "version" => {
+                            let v: u32 = map.next_value()?;
+                            if v > FORMAT_VERSION {
+                                return Err(de::Error::custom(format!(
+                                    "format version {} is newer than the newest known, {}",
+                                    v, FORMAT_VERSION
+                                )));
+                            }
+                            version = Some(v);
+                        }
+                        "content" => content = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                required::<_, M::Error>(version, "version")?;
+                required(content, "content").map(Versioned)
+            }
+        }
+
+        deserializer.deserialize_struct("Versioned", VERSIONED_FIELDS, VersionedVisitor(PhantomData))
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Expression::Number(x) => serializer.serialize_newtype_variant("Expression", 0, "Number", x),
+            Expression::Integer(i) => serializer.serialize_newtype_variant("Expression", 1, "Integer", i),
+            Expression::Bool(b) => serializer.serialize_newtype_variant("Expression", 2, "Bool", b),
+            Expression::UnitValue { value, unit } => {
+                let mut variant = serializer.serialize_struct_variant("Expression", 3, "UnitValue", 2)?;
+                variant.serialize_field("value", value)?;
+                variant.serialize_field("unit", &**unit)?;
+                variant.end()
+            }
+            Expression::Unit(name) => serializer.serialize_newtype_variant("Expression", 4, "Unit", name),
+            Expression::Percent(operand) => {
+                serializer.serialize_newtype_variant("Expression", 5, "Percent", operand)
+            }
+            Expression::Factorial { operand, span } => {
+                let mut variant = serializer.serialize_struct_variant("Expression", 6, "Factorial", 2)?;
+                variant.serialize_field("operand", operand)?;
+                variant.serialize_field("span", &SpanField(*span))?;
+                variant.end()
+            }
+            Expression::Binary { op, left, right, span } => {
+                let mut variant = serializer.serialize_struct_variant("Expression", 7, "Binary", 4)?;
+                variant.serialize_field("op", &OperationField(op.clone()))?;
+                variant.serialize_field("left", left)?;
+                variant.serialize_field("right", right)?;
+                variant.serialize_field("span", &SpanField(*span))?;
+                variant.end()
+            }
+            Expression::Unary { op, operand, span } => {
+                let mut variant = serializer.serialize_struct_variant("Expression", 8, "Unary", 3)?;
+                variant.serialize_field("op", &OperationField(op.clone()))?;
+                variant.serialize_field("operand", operand)?;
+                variant.serialize_field("span", &SpanField(*span))?;
+                variant.end()
+            }
+            Expression::FunctionCall { name, args, span } => {
+                let mut variant = serializer.serialize_struct_variant("Expression", 9, "FunctionCall", 3)?;
+                variant.serialize_field("name", name)?;
+                variant.serialize_field("args", args)?;
+                variant.serialize_field("span", &SpanField(*span))?;
+                variant.end()
+            }
+            Expression::List(items) => serializer.serialize_newtype_variant("Expression", 10, "List", items),
+            Expression::Decimal { value, digits } => {
+                let mut variant = serializer.serialize_struct_variant("Expression", 11, "Decimal", 2)?;
+                variant.serialize_field("value", value)?;
+                variant.serialize_field("digits", &**digits)?;
+                variant.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ExpressionVisitor;
+
+        impl<'de> Visitor<'de> for ExpressionVisitor {
+            type Value = Expression;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a mathengine expression")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Expression, A::Error> {
+                let (index, variant) = data.variant_seed(VariantIndex(EXPRESSION_VARIANTS))?;
+                let fields = |variant: A::Variant| variant.struct_variant(NODE_FIELDS, NodeFieldsVisitor);
+                match index {
+                    0 => variant.newtype_variant().map(Expression::Number),
+                    1 => variant.newtype_variant().map(Expression::Integer),
+                    2 => variant.newtype_variant().map(Expression::Bool),
+                    3 => {
+                        let f = fields(variant)?;
+                        Ok(Expression::UnitValue {
+                            value: required(f.value, "value")?,
+                            unit: Arc::from(required(f.unit, "unit")?),
+                        })
+                    }
+                    4 => variant.newtype_variant().map(Expression::Unit),
+                    5 => variant.newtype_variant().map(Expression::Percent),
+                    6 => {
+                        let f = fields(variant)?;
+                        Ok(Expression::Factorial {
+                            operand: required(f.operand, "operand")?,
+                            span: f.span.unwrap_or_default(),
+                        })
+                    }
+                    7 => {
+                        let f = fields(variant)?;
+                        Ok(Expression::Binary {
+                            op: required(f.op, "op")?,
+                            left: required(f.left, "left")?,
+                            right: required(f.right, "right")?,
+                            span: f.span.unwrap_or_default(),
+                        })
+                    }
+                    8 => {
+                        let f = fields(variant)?;
+                        Ok(Expression::Unary {
+                            op: required(f.op, "op")?,
+                            operand: required(f.operand, "operand")?,
+                            span: f.span.unwrap_or_default(),
+                        })
+                    }
+                    9 => {
+                        let f = fields(variant)?;
+                        Ok(Expression::FunctionCall {
+                            name: required(f.name, "name")?,
+                            args: required(f.args, "args")?,
+                            span: f.span.unwrap_or_default(),
+                        })
+                    }
+                    10 => variant.newtype_variant().map(Expression::List),
+                    _ => {
+                        let f = fields(variant)?;
+                        Ok(Expression::Decimal {
+                            value: required(f.value, "value")?,
+                            digits: Arc::from(required(f.digits, "digits")?),
+                        })
+                    }
+                }
+            }
+        }
+
+        deserializer.deserialize_enum("Expression", EXPRESSION_VARIANTS, ExpressionVisitor)
+    }
+}
+
+// The fields of every struct variant of Expression. No two variants use the
+// same name for fields of different types, so one visitor reads them all
+#[derive(Default)]
+struct NodeFields {
+    op: Option<Operation>,
+    left: Option<Box<Expression>>,
+    right: Option<Box<Expression>>,
+    operand: Option<Box<Expression>>,
+    span: Option<Span>,
+    name: Option<String>,
+    args: Option<Vec<Expression>>,
+    value: Option<f64>,
+    unit: Option<String>,
+    digits: Option<String>,
+}
+
+struct NodeFieldsVisitor;
+
+impl<'de> Visitor<'de> for NodeFieldsVisitor {
+    type Value = NodeFields;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the fields of an expression")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<NodeFields, M::Error> {
+        let mut fields = NodeFields::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "op" => fields.op = Some(map.next_value::<OperationField>()?.0),
+                "left" => fields.left = Some(map.next_value()?),
+                "right" => fields.right = Some(map.next_value()?),
+                "operand" => fields.operand = Some(map.next_value()?),
+                "span" => fields.span = Some(map.next_value::<SpanField>()?.0),
+                "name" => fields.name = Some(map.next_value()?),
+                "args" => fields.args = Some(map.next_value()?),
+                "value" => fields.value = Some(map.next_value()?),
+                "unit" => fields.unit = Some(map.next_value()?),
+                "digits" => fields.digits = Some(map.next_value()?),
+                // Fields added in later versions
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(fields)
+    }
+}
+
+fn required<T, E: de::Error>(field: Option<T>, name: &'static str) -> Result<T, E> {
+    field.ok_or_else(|| de::Error::missing_field(name))
+}
+
+// Operations and spans belong to the lexer, which doesn't depend on serde
+struct OperationField(Operation);
+
+impl Serialize for OperationField {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = match self.0 {
+            Operation::Add => 0,
+            Operation::Subtract => 1,
+            Operation::Divide => 2,
+            Operation::Multiply => 3,
+            Operation::Power => 4,
+            Operation::Convert => 5,
+            Operation::Of => 6,
+            Operation::And => 7,
+            Operation::Or => 8,
+            Operation::Not => 9,
+            Operation::Less => 10,
+            Operation::LessEqual => 11,
+            Operation::Greater => 12,
+            Operation::GreaterEqual => 13,
+            Operation::Equal => 14,
+            Operation::NotEqual => 15,
+        };
+        serializer.serialize_unit_variant("Operation", index, OPERATION_VARIANTS[index as usize])
+    }
+}
+
+impl<'de> Deserialize<'de> for OperationField {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = deserializer.deserialize_enum(
+            "Operation",
+            OPERATION_VARIANTS,
+            UnitVariant(OPERATION_VARIANTS),
+        )?;
+        let op = [
+            Operation::Add,
+            Operation::Subtract,
+            Operation::Divide,
+            Operation::Multiply,
+            Operation::Power,
+            Operation::Convert,
+            Operation::Of,
+            Operation::And,
+            Operation::Or,
+            Operation::Not,
+            Operation::Less,
+            Operation::LessEqual,
+            Operation::Greater,
+            Operation::GreaterEqual,
+            Operation::Equal,
+            Operation::NotEqual,
+        ][index]
+            .clone();
+        Ok(OperationField(op))
+    }
+}
+
+struct SpanField(Span);
+
+impl Serialize for SpanField {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Span", 2)?;
+        state.serialize_field("start", &self.0.start)?;
+        state.serialize_field("end", &self.0.end)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SpanField {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (start, end) = deserializer.deserialize_struct("Span", SPAN_FIELDS, Pair::new(SPAN_FIELDS))?;
+        Ok(SpanField(Span::new(start, end)))
+    }
+}
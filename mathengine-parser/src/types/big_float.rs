@@ -0,0 +1,159 @@
+use dashu_float::{ops::SquareRoot, round::mode::HalfAway, DBig, FBig};
+use dashu_int::{ops::EstimatedLog2, IBig, Sign};
+
+use crate::types::Rational;
+
+/// Arbitrary-precision binary floating-point number.
+///
+/// Precision is measured in bits and carried by each value; operations on two
+/// values keep the larger precision.
+pub type BigFloat = FBig<HalfAway>;
+
+/// Parse a decimal string into a big float with `precision` bits.
+pub(crate) fn parse(literal: &str, precision: usize) -> Option<BigFloat> {
+    let decimal: DBig = literal.parse().ok()?;
+    Some(decimal.with_base_and_precision::<2>(precision).value())
+}
+
+/// Convert an integer into a big float with `precision` bits.
+pub(crate) fn from_integer(value: i128, precision: usize) -> BigFloat {
+    BigFloat::from(IBig::from(value)).with_precision(precision).value()
+}
+
+/// Convert a fraction into a big float with `precision` bits.
+pub(crate) fn from_rational(value: &Rational, precision: usize) -> BigFloat {
+    from_integer(value.numer(), precision) / from_integer(value.denom(), precision)
+}
+
+/// Get the value as an integer, if it has no fractional part and fits in an `i128`.
+pub(crate) fn to_integer(value: &BigFloat) -> Option<i128> {
+    if !value.fract().repr().is_zero() {
+        return None;
+    }
+    i128::try_from(value.to_int().value()).ok()
+}
+
+/// The most binary orders of magnitude a power may move away from one.
+/// dashu panics on exponents past its own range, and well before that a
+/// result is too large to be worth working out
+const MAX_POWER_MAGNITUDE: f64 = 4_503_599_627_370_496.0;
+
+/// The most binary orders of magnitude `exp` may move away from one. Its
+/// series takes longer the larger the argument, so the bound is tighter
+const MAX_EXP_MAGNITUDE: f64 = 4_294_967_296.0;
+
+/// Whether `base^exponent` is within [`MAX_POWER_MAGNITUDE`] powers of two
+/// of one, going by estimates of their logarithms.
+pub(crate) fn power_in_range(base: &BigFloat, exponent: f64) -> bool {
+    // Zero and one stay where they are, whatever the exponent
+    if base.repr().is_zero() || base.repr().is_one() || (-base.clone()).repr().is_one() {
+        return true;
+    }
+    (base.log2_est() as f64 * exponent).abs() <= MAX_POWER_MAGNITUDE
+}
+
+/// Raise to an integer power.
+pub(crate) fn powi(value: &BigFloat, exponent: i128) -> BigFloat {
+    value.powi(IBig::from(exponent))
+}
+
+/// Powers of ten past which values are written in scientific notation,
+/// like `2.5e1000`, rather than with every zero
+const SCIENTIFIC_EXPONENT: f64 = 1000.0;
+
+/// Render as a decimal string with the digits the binary precision fully
+/// supports, dropping trailing zeros. Values past 10^1000 or under 10^-1000
+/// are written in scientific notation.
+pub(crate) fn to_decimal_string(value: &BigFloat) -> String {
+    if value.repr().is_zero() {
+        return positional(value);
+    }
+    // The estimate alone is single precision, which is orders of ten out for
+    // exponents in the billions, so only the significand is estimated
+    let repr = value.repr();
+    let log2 = repr.exponent() as f64 + repr.significand().log2_est() as f64;
+    let magnitude = log2 * std::f64::consts::LOG10_2;
+    if magnitude.abs() < SCIENTIFIC_EXPONENT {
+        return positional(value);
+    }
+    // Converting the whole value to base ten works through every digit of
+    // its exponent, so only the mantissa is converted
+    let guarded = value.clone().with_precision(value.precision() + 64).value();
+    let ten = from_integer(10, guarded.precision());
+    let mut exponent = magnitude.floor() as i128;
+    let mut mantissa = guarded / powi(&ten, exponent);
+    // The estimate of the magnitude can still be a power of ten out
+    let size = |mantissa: &BigFloat| match mantissa.sign() {
+        Sign::Negative => -mantissa.clone(),
+        Sign::Positive => mantissa.clone(),
+    };
+    while size(&mantissa) >= ten {
+        mantissa /= &ten;
+        exponent += 1;
+    }
+    while size(&mantissa) < BigFloat::ONE {
+        mantissa *= &ten;
+        exponent -= 1;
+    }
+    let mut digits = positional(&mantissa.with_precision(value.precision()).value());
+    // and the mantissa can round up to ten
+    if let Some(rest) = digits.strip_suffix("10") {
+        if rest.is_empty() || rest == "-" {
+            digits = format!("{}1", rest);
+            exponent += 1;
+        }
+    }
+    format!("{}e{}", digits, exponent)
+}
+
+// Every digit of the value, with a decimal point rather than an exponent
+fn positional(value: &BigFloat) -> String {
+    // Each bit is worth log10(2) decimal digits; holding back one digit hides
+    // the rounding noise from binary conversion (0.1 + 0.2 prints as 0.3)
+    let digits = (value.precision() as f64 * std::f64::consts::LOG10_2).floor() as usize;
+    let digits = digits.saturating_sub(1);
+    // Converting straight to fewer digits than the significand has trips an
+    // assertion in dashu, so convert exactly enough and round after
+    let exact = (value.precision() as f64 * std::f64::consts::LOG10_2).ceil() as usize + 1;
+    let rendered = value
+        .clone()
+        .with_base_and_precision::<10>(exact)
+        .value()
+        .with_precision(digits.max(1))
+        .value()
+        .to_string();
+    if rendered.contains('.') && !rendered.contains(['e', 'E']) {
+        rendered.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        rendered
+    }
+}
+
+/// Square root at the value's precision.
+pub(crate) fn sqrt(value: &BigFloat) -> BigFloat {
+    value.sqrt()
+}
+
+/// `sin`, `cos`, `tan`, `exp` or `ln` at the value's precision. `None` for
+/// other names, for `tan` where the cosine is zero, and for `exp` of values
+/// beyond about ±3 × 10^9.
+pub(crate) fn elementary(name: &str, value: &BigFloat) -> Option<BigFloat> {
+    match name {
+        "sin" => Some(value.sin()),
+        "cos" => Some(value.cos()),
+        "tan" => {
+            let (sin, cos) = value.sin_cos();
+            (!cos.repr().is_zero()).then(|| sin / cos)
+        }
+        "exp" if (value.to_f64().value() * std::f64::consts::LOG2_E).abs() > MAX_EXP_MAGNITUDE => None,
+        "exp" => Some(value.exp()),
+        "ln" => Some(value.ln()),
+        _ => None,
+    }
+}
+
+/// Raise to any power, at the larger of the two precisions. `None` unless
+/// the base is positive.
+pub(crate) fn powf(base: &BigFloat, exponent: &BigFloat) -> Option<BigFloat> {
+    (base.sign() == Sign::Positive && !base.repr().is_zero()).then(|| base.powf(exponent))
+}
@@ -9,6 +9,13 @@ pub enum ConversionError {
     UnknownUnit(String),
     /// Conversion failed for other reasons
     Failed,
+    /// The value isn't numeric, like a boolean or a list
+    NotNumeric(String),
+    /// The value has a unit where none was expected, or lacks one that was
+    UnitMismatch {
+        expected: Option<String>,
+        found: Option<String>,
+    },
 }
 
 impl Display for ConversionError {
@@ -17,6 +24,15 @@ impl Display for ConversionError {
             ConversionError::CrossDimension => write!(f, "Cannot convert between different dimensions"),
             ConversionError::UnknownUnit(unit) => write!(f, "Unknown unit: '{}'", unit),
             ConversionError::Failed => write!(f, "Conversion failed"),
+            ConversionError::NotNumeric(value) => write!(f, "Expected a number, found '{}'", value),
+            ConversionError::UnitMismatch { expected: Some(unit), .. } => {
+                write!(f, "Expected a value in '{}', found a plain number", unit)
+            }
+            ConversionError::UnitMismatch { found, .. } => write!(
+                f,
+                "Expected a plain number, found a value in '{}'",
+                found.as_deref().unwrap_or_default()
+            ),
         }
     }
 }
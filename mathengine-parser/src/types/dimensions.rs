@@ -1,66 +1,215 @@
+#[cfg(feature = "units")]
 use mathengine_units::{
+    frequency::FrequencyUnit,
     length::LengthUnit,
+    mass::MassUnit,
     temperature::TemperatureUnit,
+    time::TimeUnit,
     UnitType, UnitConversion, Dimension
 };
 
+#[cfg(feature = "units")]
+pub use mathengine_units::UnitError;
+
+/// Error for a unit string that doesn't name a known unit. Without the
+/// `units` feature no unit is known.
+#[cfg(not(feature = "units"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitError {
+    UnknownUnit(String),
+}
+
+#[cfg(not(feature = "units"))]
+impl std::fmt::Display for UnitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnitError::UnknownUnit(unit) => write!(f, "Unknown unit: '{}'", unit),
+        }
+    }
+}
+
+#[cfg(not(feature = "units"))]
+impl std::error::Error for UnitError {}
+
 /// Represents the dimension type of a unit
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DimensionType {
     Length,
     Temperature,
+    Time,
+    Frequency,
+    Mass,
+    /// Made of units of other dimensions, like `km/h` for a speed. The
+    /// dimensions are in the value's [`signature`](crate::types::UnitValue::signature)
+    Derived,
     Unknown,
 }
 
 /// Unified enum for any unit type in the system
+///
+/// Without the `units` feature there are no units, and every unit string is
+/// kept by name with an [`Unknown`](DimensionType::Unknown) dimension.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Unit {
+    #[cfg(feature = "units")]
     Length(mathengine_units::length::LengthUnit),
+    #[cfg(feature = "units")]
     Temperature(mathengine_units::temperature::TemperatureUnit),
+    #[cfg(feature = "units")]
+    Time(mathengine_units::time::TimeUnit),
+    #[cfg(feature = "units")]
+    Frequency(mathengine_units::frequency::FrequencyUnit),
+    #[cfg(feature = "units")]
+    Mass(mathengine_units::mass::MassUnit),
 }
 
 impl Unit {
+    /// Parse a unit string in any dimension ("cm", "celsius", ...)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{DimensionType, Unit};
+    ///
+    /// let unit = Unit::parse("inches").unwrap();
+    /// assert_eq!(unit.canonical_string(), "in");
+    /// assert_eq!(unit.dimension_type(), DimensionType::Length);
+    /// assert!(Unit::parse("usd").is_err());
+    /// ```
+    #[cfg(feature = "units")]
+    pub fn parse(unit_str: &str) -> Result<Unit, UnitError> {
+        LengthUnit::parse(unit_str)
+            .map(Unit::Length)
+            .or_else(|_| TemperatureUnit::parse(unit_str).map(Unit::Temperature))
+            .or_else(|_| TimeUnit::parse(unit_str).map(Unit::Time))
+            .or_else(|_| FrequencyUnit::parse(unit_str).map(Unit::Frequency))
+            .or_else(|_| MassUnit::parse(unit_str).map(Unit::Mass))
+    }
+
+    /// Parse a unit string in any dimension, which without the `units`
+    /// feature always fails
+    #[cfg(not(feature = "units"))]
+    pub fn parse(unit_str: &str) -> Result<Unit, UnitError> {
+        Err(UnitError::UnknownUnit(unit_str.to_string()))
+    }
+
+    /// Every known unit, dimension by dimension
+    pub fn all() -> Vec<Unit> {
+        #[cfg_attr(not(feature = "units"), allow(unused_mut))]
+        let mut units = Vec::new();
+        #[cfg(feature = "units")]
+        {
+            units.extend(LengthUnit::all().iter().copied().map(Unit::Length));
+            units.extend(TemperatureUnit::all().iter().copied().map(Unit::Temperature));
+            units.extend(TimeUnit::all().iter().copied().map(Unit::Time));
+            units.extend(FrequencyUnit::all().iter().copied().map(Unit::Frequency));
+            units.extend(MassUnit::all().iter().copied().map(Unit::Mass));
+        }
+        units
+    }
+
+    /// Every name the unit is recognised by, canonical first
+    pub fn names(&self) -> &'static [&'static str] {
+        match *self {
+            #[cfg(feature = "units")]
+            Unit::Length(u) => u.names(),
+            #[cfg(feature = "units")]
+            Unit::Temperature(u) => u.names(),
+            #[cfg(feature = "units")]
+            Unit::Time(u) => u.names(),
+            #[cfg(feature = "units")]
+            Unit::Frequency(u) => u.names(),
+            #[cfg(feature = "units")]
+            Unit::Mass(u) => u.names(),
+        }
+    }
+
     /// Get the canonical string for this unit
     pub fn canonical_string(&self) -> &'static str {
-        match self {
+        match *self {
+            #[cfg(feature = "units")]
             Unit::Length(u) => u.canonical_string(),
+            #[cfg(feature = "units")]
             Unit::Temperature(u) => u.canonical_string(),
+            #[cfg(feature = "units")]
+            Unit::Time(u) => u.canonical_string(),
+            #[cfg(feature = "units")]
+            Unit::Frequency(u) => u.canonical_string(),
+            #[cfg(feature = "units")]
+            Unit::Mass(u) => u.canonical_string(),
         }
     }
 
     /// Get the dimension type for this unit
     pub fn dimension_type(&self) -> DimensionType {
-        match self {
+        match *self {
+            #[cfg(feature = "units")]
             Unit::Length(_) => DimensionType::Length,
+            #[cfg(feature = "units")]
             Unit::Temperature(_) => DimensionType::Temperature,
+            #[cfg(feature = "units")]
+            Unit::Time(_) => DimensionType::Time,
+            #[cfg(feature = "units")]
+            Unit::Frequency(_) => DimensionType::Frequency,
+            #[cfg(feature = "units")]
+            Unit::Mass(_) => DimensionType::Mass,
         }
     }
 }
 
 impl DimensionType {
+    /// A lowercase name for the dimension, like "length"
+    pub fn name(&self) -> &'static str {
+        match self {
+            DimensionType::Length => "length",
+            DimensionType::Temperature => "temperature",
+            DimensionType::Time => "time",
+            DimensionType::Frequency => "frequency",
+            DimensionType::Mass => "mass",
+            DimensionType::Derived => "derived",
+            DimensionType::Unknown => "unknown",
+        }
+    }
+
     /// Determine the dimension type from a unit string
     pub fn from_unit(unit: &str) -> Self {
-        if LengthUnit::parse(unit).is_ok() {
-            DimensionType::Length
-        } else if TemperatureUnit::parse(unit).is_ok() {
-            DimensionType::Temperature
-        } else {
-            DimensionType::Unknown
-        }
+        Unit::parse(unit)
+            .map(|unit| unit.dimension_type())
+            .unwrap_or(DimensionType::Unknown)
     }
 
     /// Parse a unit string into a Unit
-    pub fn parse_unit_str(&self, unit_str: &str) -> Result<Unit, mathengine_units::UnitError> {
+    pub fn parse_unit_str(&self, unit_str: &str) -> Result<Unit, UnitError> {
         match self {
+            #[cfg(feature = "units")]
             DimensionType::Length => {
                 LengthUnit::parse(unit_str)
                     .map(Unit::Length)
             }
+            #[cfg(feature = "units")]
             DimensionType::Temperature => {
                 TemperatureUnit::parse(unit_str)
                     .map(Unit::Temperature)
             }
-            DimensionType::Unknown => Err(mathengine_units::UnitError::UnknownUnit(unit_str.to_string())),
+            #[cfg(feature = "units")]
+            DimensionType::Time => {
+                TimeUnit::parse(unit_str)
+                    .map(Unit::Time)
+            }
+            #[cfg(feature = "units")]
+            DimensionType::Frequency => {
+                FrequencyUnit::parse(unit_str)
+                    .map(Unit::Frequency)
+            }
+            #[cfg(feature = "units")]
+            DimensionType::Mass => {
+                MassUnit::parse(unit_str)
+                    .map(Unit::Mass)
+            }
+            #[cfg(feature = "units")]
+            DimensionType::Derived | DimensionType::Unknown => Err(UnitError::UnknownUnit(unit_str.to_string())),
+            #[cfg(not(feature = "units"))]
+            _ => Err(UnitError::UnknownUnit(unit_str.to_string())),
         }
     }
 
@@ -74,37 +223,140 @@ impl DimensionType {
     }
 
     /// Convert a value to the base unit for this dimension (with validation)
+    #[cfg_attr(not(feature = "units"), allow(unused_variables))]
     pub fn to_base_value(&self, unit: &Unit, value: f64) -> Option<f64> {
         match (self, unit) {
+            #[cfg(feature = "units")]
             (DimensionType::Length, Unit::Length(u)) => {
                 Some(<Dimension<LengthUnit> as UnitConversion<LengthUnit>>::to_base_value(*u, value))
             }
+            #[cfg(feature = "units")]
             (DimensionType::Temperature, Unit::Temperature(u)) => {
                 Some(<Dimension<TemperatureUnit> as UnitConversion<TemperatureUnit>>::to_base_value(*u, value))
             }
+            #[cfg(feature = "units")]
+            (DimensionType::Time, Unit::Time(u)) => {
+                Some(<Dimension<TimeUnit> as UnitConversion<TimeUnit>>::to_base_value(*u, value))
+            }
+            #[cfg(feature = "units")]
+            (DimensionType::Frequency, Unit::Frequency(u)) => {
+                Some(<Dimension<FrequencyUnit> as UnitConversion<FrequencyUnit>>::to_base_value(*u, value))
+            }
+            #[cfg(feature = "units")]
+            (DimensionType::Mass, Unit::Mass(u)) => {
+                Some(<Dimension<MassUnit> as UnitConversion<MassUnit>>::to_base_value(*u, value))
+            }
             _ => None,
         }
     }
 
     /// Convert a value between units within this dimension (with validation)
+    #[cfg_attr(not(feature = "units"), allow(unused_variables))]
     pub fn convert_value(&self, from_unit: &Unit, to_unit: &Unit, value: f64) -> Option<f64> {
         match (self, from_unit, to_unit) {
+            #[cfg(feature = "units")]
             (DimensionType::Length, Unit::Length(from), Unit::Length(to)) => {
                 Some(Dimension::<LengthUnit>::convert_value(*from, *to, value))
             }
+            #[cfg(feature = "units")]
             (DimensionType::Temperature, Unit::Temperature(from), Unit::Temperature(to)) => {
                 Some(Dimension::<TemperatureUnit>::convert_value(*from, *to, value))
             }
+            #[cfg(feature = "units")]
+            (DimensionType::Time, Unit::Time(from), Unit::Time(to)) => {
+                Some(Dimension::<TimeUnit>::convert_value(*from, *to, value))
+            }
+            #[cfg(feature = "units")]
+            (DimensionType::Frequency, Unit::Frequency(from), Unit::Frequency(to)) => {
+                Some(Dimension::<FrequencyUnit>::convert_value(*from, *to, value))
+            }
+            #[cfg(feature = "units")]
+            (DimensionType::Mass, Unit::Mass(from), Unit::Mass(to)) => {
+                Some(Dimension::<MassUnit>::convert_value(*from, *to, value))
+            }
             _ => None, // Cross-dimension conversion rejected
         }
     }
 
+    /// Convert a value in `unit^exponent` to the base unit raised to the same power.
+    ///
+    /// Only linear dimensions scale with the exponent; offset-based dimensions
+    /// like temperature can't be raised to a power other than one.
+    pub fn to_base_value_pow(&self, unit: &Unit, value: f64, exponent: i32) -> Option<f64> {
+        if exponent == 1 {
+            return self.to_base_value(unit, value);
+        }
+        if !self.is_linear() {
+            return None;
+        }
+        self.to_base_value(unit, 1.0).map(|factor| value * factor.powi(exponent))
+    }
+
+    /// Convert a value between `from_unit^exponent` and `to_unit^exponent`.
+    pub fn convert_value_pow(&self, from_unit: &Unit, to_unit: &Unit, value: f64, exponent: i32) -> Option<f64> {
+        if exponent == 1 {
+            return self.convert_value(from_unit, to_unit, value);
+        }
+        if !self.is_linear() {
+            return None;
+        }
+        self.convert_value(from_unit, to_unit, 1.0).map(|factor| value * factor.powi(exponent))
+    }
+
+    /// The dimension whose base unit is one over this one's, like time for
+    /// frequency, which values convert to by taking their reciprocal
+    pub fn reciprocal(&self) -> Option<DimensionType> {
+        match self {
+            DimensionType::Frequency => Some(DimensionType::Time),
+            DimensionType::Time => Some(DimensionType::Frequency),
+            _ => None,
+        }
+    }
+
+    /// Whether conversions in this dimension are a pure scale factor (no offset)
+    pub fn is_linear(&self) -> bool {
+        matches!(self, DimensionType::Length | DimensionType::Time | DimensionType::Frequency | DimensionType::Mass)
+    }
+
+    /// Get the base unit for this dimension, if it has one
+    pub fn base_unit(&self) -> Option<Unit> {
+        match self {
+            #[cfg(feature = "units")]
+            DimensionType::Length => Some(Unit::Length(<Dimension<LengthUnit> as UnitConversion<LengthUnit>>::base_unit())),
+            #[cfg(feature = "units")]
+            DimensionType::Temperature => Some(Unit::Temperature(<Dimension<TemperatureUnit> as UnitConversion<TemperatureUnit>>::base_unit())),
+            #[cfg(feature = "units")]
+            DimensionType::Time => Some(Unit::Time(<Dimension<TimeUnit> as UnitConversion<TimeUnit>>::base_unit())),
+            #[cfg(feature = "units")]
+            DimensionType::Frequency => Some(Unit::Frequency(<Dimension<FrequencyUnit> as UnitConversion<FrequencyUnit>>::base_unit())),
+            #[cfg(feature = "units")]
+            DimensionType::Mass => Some(Unit::Mass(<Dimension<MassUnit> as UnitConversion<MassUnit>>::base_unit())),
+            #[cfg(feature = "units")]
+            DimensionType::Derived | DimensionType::Unknown => None,
+            #[cfg(not(feature = "units"))]
+            _ => None,
+        }
+    }
+
     /// Get the base unit string for this dimension
     pub fn base_unit_string(&self) -> &'static str {
         match self {
+            #[cfg(feature = "units")]
             DimensionType::Length => <Dimension<LengthUnit> as UnitConversion<LengthUnit>>::base_unit().canonical_string(),
+            #[cfg(feature = "units")]
             DimensionType::Temperature => <Dimension<TemperatureUnit> as UnitConversion<TemperatureUnit>>::base_unit().canonical_string(),
+            #[cfg(feature = "units")]
+            DimensionType::Time => <Dimension<TimeUnit> as UnitConversion<TimeUnit>>::base_unit().canonical_string(),
+            #[cfg(feature = "units")]
+            DimensionType::Frequency => <Dimension<FrequencyUnit> as UnitConversion<FrequencyUnit>>::base_unit().canonical_string(),
+            #[cfg(feature = "units")]
+            DimensionType::Mass => <Dimension<MassUnit> as UnitConversion<MassUnit>>::base_unit().canonical_string(),
+            #[cfg(feature = "units")]
+            DimensionType::Derived => "derived",
+            #[cfg(feature = "units")]
             DimensionType::Unknown => "unknown",
+            #[cfg(not(feature = "units"))]
+            _ => "unknown",
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,657 @@
+use std::borrow::Cow;
+
+use mathengine_lexer::LexError;
+
+use crate::types::{Number, Radix, Rational, Rounding, UnitValue, Value};
+
+// Largest denominator tried when showing a float as a fraction
+const MAX_DENOMINATOR: i128 = 10_000;
+
+// Currency codes with the symbol money is written with, and how many
+// decimal places it's shown to unless asked for others
+const CURRENCY_SYMBOLS: &[(&str, &str, usize)] = &[
+    ("aud", "A$", 2), ("brl", "R$", 2), ("cad", "CA$", 2), ("chf", "CHF", 2), ("cny", "CN¥", 2),
+    ("czk", "Kč", 2), ("dkk", "kr", 2), ("eur", "€", 2), ("gbp", "£", 2), ("hkd", "HK$", 2),
+    ("inr", "₹", 2), ("jpy", "¥", 0), ("krw", "₩", 0), ("mxn", "MX$", 2), ("nok", "kr", 2),
+    ("nzd", "NZ$", 2), ("pln", "zł", 2), ("sek", "kr", 2), ("sgd", "S$", 2), ("usd", "$", 2),
+    ("zar", "R", 2),
+];
+
+// Most digits rounding is asked for. Any float is exact well within this
+// many decimal places, and more would overflow the formatting machinery
+const MAX_DIGITS: usize = 1_100;
+
+/// Options for presenting results to people, used by [`Value::format`].
+///
+/// The default options format exactly like `Display`.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::{FormatOptions, UnitValue, Value};
+///
+/// let feet = Value::UnitValue(UnitValue::new(3.2808398950131235, "ft".to_string()).unwrap());
+/// let options = FormatOptions {
+///     decimal_places: Some(2),
+///     unit_spacing: true,
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(feet.format(&options), "3.28 ft");
+///
+/// let exact = FormatOptions {
+///     round_trip: true,
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(Value::from(2.0).format(&exact), "2.0");
+/// assert_eq!(feet.format(&exact), "3.2808398950131235ft");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Round to this many decimal places, up to 1100
+    pub decimal_places: Option<usize>,
+    /// Round to this many significant figures, up to 1100. Takes precedence
+    /// over `decimal_places` when both are set
+    pub significant_figures: Option<usize>,
+    /// Keep the zeros rounding leaves at the end, so `1.5` to two places is
+    /// `1.50` rather than `1.5`
+    pub trailing_zeros: bool,
+    /// Put a space between a number and its unit (`3.28 ft`)
+    pub unit_spacing: bool,
+    /// Plain digits, or a mantissa and power of ten
+    pub notation: Notation,
+    /// Decimal mark and digit grouping
+    pub locale: Locale,
+    /// Write values as expressions that evaluate back to the same value, in
+    /// the number mode that produced them, so results can be fed back into
+    /// other expressions. Floats keep every digit they need and a `.0` when
+    /// they're whole, fractions are written as divisions and negative unit
+    /// values as products. The other options are ignored. Infinities and
+    /// NaN have no such form, and arbitrary-precision floats are read back
+    /// at `f64` precision
+    pub round_trip: bool,
+    /// Write currency values as money, with their symbol and, unless
+    /// `decimal_places` or `significant_figures` say otherwise, the decimal
+    /// places the currency is counted in: `$1,234.56` and `¥1,235`. The
+    /// symbol goes after the number in locales with a decimal comma, like
+    /// `1.234,56 €`, and digits are grouped in thousands even in the default
+    /// locale
+    pub money: bool,
+}
+
+/// The separators numbers are written with.
+///
+/// The default, like `Display`, uses a `.` decimal mark and no grouping.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::{FormatOptions, Locale, Number};
+///
+/// let german = FormatOptions {
+///     locale: Locale::from_tag("de-DE").unwrap(),
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(Number::Float(1234567.89).format(&german), "1.234.567,89");
+///
+/// let english = FormatOptions {
+///     locale: Locale::from_tag("en").unwrap(),
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(Number::Integer(-1234567).format(&english), "-1,234,567");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    /// Separates the whole and fractional parts
+    pub decimal_mark: char,
+    /// Separates groups of three digits in the whole part, if any
+    pub grouping: Option<char>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            decimal_mark: '.',
+            grouping: None,
+        }
+    }
+}
+
+impl Locale {
+    /// The separators for a language tag like `"de-DE"` or `"fr"`, or `None`
+    /// for languages this table doesn't know.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let tag = tag.to_lowercase().replace('_', "-");
+        let language = tag.split('-').next().unwrap_or_default();
+        let (decimal_mark, grouping) = match (language, tag.as_str()) {
+            (_, "de-ch") => ('.', '\u{2019}'),
+            ("en" | "ja" | "ko" | "zh" | "th" | "he", _) => ('.', ','),
+            ("de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" | "ro", _) => (',', '.'),
+            ("fr", _) => (',', '\u{202f}'),
+            ("ru" | "pl" | "cs" | "sk" | "sv" | "nb" | "no" | "fi" | "uk" | "hu", _) => {
+                (',', '\u{a0}')
+            }
+            _ => return None,
+        };
+        Some(Locale {
+            decimal_mark,
+            grouping: Some(grouping),
+        })
+    }
+
+    /// Rewrite an expression typed with this locale's separators into one
+    /// the lexer reads. In locales with a decimal comma, a comma between two
+    /// digits is the decimal mark and `;` separates arguments and list
+    /// items, matching how results are displayed. Digits grouped with the
+    /// locale's separator, as in `1.234,5`, are read without it, unless the
+    /// separator is a comma, which can't be told apart from one between
+    /// arguments.
+    ///
+    /// Every character stays where it was, so error spans still point into
+    /// the input as typed: the separators a number loses are made up with
+    /// spaces before it.
+    ///
+    /// # Errors
+    ///
+    /// [`LexError::InvalidNumber`] for a number with more than one decimal
+    /// mark, or whose digits aren't grouped in threes, like `1.5` in German,
+    /// quoting the number as typed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_lexer::LexError;
+    /// use mathengine_parser::types::Locale;
+    ///
+    /// let german = Locale::from_tag("de").unwrap();
+    /// assert_eq!(german.delocalize("max(1,5; 2) + 0,25").unwrap(), "max(1.5, 2) + 0.25");
+    /// assert_eq!(german.delocalize("max(1, 2)").unwrap(), "max(1, 2)");
+    /// assert_eq!(german.delocalize("1.234,5m + 1.500").unwrap(), " 1234.5m +  1500");
+    /// assert_eq!(
+    ///     german.delocalize("2 + 1.5").unwrap_err(),
+    ///     LexError::InvalidNumber { input: "1.5".to_string(), position: 4 }
+    /// );
+    ///
+    /// // Locales with a decimal point read input as it is
+    /// let english = Locale::from_tag("en").unwrap();
+    /// assert_eq!(english.delocalize("max(1,5)").unwrap(), "max(1,5)");
+    /// ```
+    pub fn delocalize<'a>(&self, input: &'a str) -> Result<Cow<'a, str>, LexError> {
+        let comma = self.decimal_mark == ',';
+        let grouping = self.grouping.filter(|&separator| separator != ',' && separator != self.decimal_mark);
+        let separated = (comma && input.contains([',', ';'])) || grouping.is_some_and(|separator| input.contains(separator));
+        if !separated {
+            return Ok(Cow::Borrowed(input));
+        }
+        let chars: Vec<char> = input.chars().collect();
+        let is_separator = |c: char| c == self.decimal_mark || Some(c) == grouping;
+        let mut rewritten = String::with_capacity(input.len());
+        let mut i = 0;
+        while i < chars.len() {
+            // Digits in a name, like `x2` or `_1`, aren't a number
+            let after_name = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+            if !chars[i].is_ascii_digit() || after_name {
+                rewritten.push(if comma && chars[i] == ';' { ',' } else { chars[i] });
+                i += 1;
+                continue;
+            }
+            let mut end = i + 1;
+            while end < chars.len()
+                && (chars[end].is_ascii_digit()
+                    || (is_separator(chars[end]) && chars.get(end + 1).is_some_and(char::is_ascii_digit)))
+            {
+                end += 1;
+            }
+            let number: String = chars[i..end].iter().collect();
+            let plain = self.read_number(&number).ok_or_else(|| LexError::InvalidNumber {
+                input: number.clone(),
+                position: i,
+            })?;
+            rewritten.extend(std::iter::repeat_n(' ', end - i - plain.len()));
+            rewritten.push_str(&plain);
+            i = end;
+        }
+        Ok(Cow::Owned(rewritten))
+    }
+
+    // A number typed in this locale, as the lexer reads it: with a `.`
+    // decimal mark and no grouping. `None` if it isn't grouped in threes or
+    // has more than one decimal mark
+    fn read_number(&self, number: &str) -> Option<String> {
+        let (whole, fraction) = match number.split_once(self.decimal_mark) {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (number, None),
+        };
+        if fraction.is_some_and(|fraction| !fraction.chars().all(|c| c.is_ascii_digit())) {
+            return None;
+        }
+        let groups: Vec<&str> = whole.split(|c: char| !c.is_ascii_digit()).collect();
+        if groups.len() > 1 && (groups[0].len() > 3 || groups[1..].iter().any(|group| group.len() != 3)) {
+            return None;
+        }
+        let mut plain = groups.concat();
+        if let Some(fraction) = fraction {
+            plain.push('.');
+            plain.push_str(fraction);
+        }
+        Some(plain)
+    }
+
+    // Rewrite a number rendered with `.` and no grouping in this locale
+    fn apply(&self, rendered: String) -> String {
+        if *self == Locale::default() {
+            return rendered;
+        }
+        let (number, exponent) = rendered.split_at(rendered.find('e').unwrap_or(rendered.len()));
+        let (sign, digits) = match number.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", number),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+
+        let mut localized = sign.to_string();
+        for (i, digit) in whole.chars().enumerate() {
+            if let Some(separator) = self.grouping {
+                if i > 0 && (whole.len() - i) % 3 == 0 {
+                    localized.push(separator);
+                }
+            }
+            localized.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            localized.push(self.decimal_mark);
+            localized.push_str(fraction);
+        }
+        localized.push_str(exponent);
+        localized
+    }
+}
+
+/// How numbers are written out by [`Value::format`].
+///
+/// With [`Notation::Scientific`] and [`Notation::Engineering`],
+/// `decimal_places` counts the digits after the mantissa's decimal point.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::{FormatOptions, Notation, Number};
+///
+/// let scientific = FormatOptions {
+///     notation: Notation::Scientific,
+///     significant_figures: Some(3),
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(Number::Float(6.02214076e23).format(&scientific), "6.02e23");
+///
+/// let engineering = FormatOptions {
+///     notation: Notation::Engineering,
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(Number::Float(0.000047).format(&engineering), "47e-6");
+///
+/// let mixed = FormatOptions {
+///     notation: Notation::MixedFraction,
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(Number::Float(2.75).format(&mixed), "2 3/4");
+/// assert_eq!(Number::Float(std::f64::consts::PI).format(&mixed), "3.141592653589793");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Notation {
+    /// Digits with a decimal point, like `Display`
+    #[default]
+    Standard,
+    /// One digit before the point, then a power of ten: `6.02e23`
+    Scientific,
+    /// A power of ten that is a multiple of three, to line up with SI
+    /// prefixes: `602.2e21`
+    Engineering,
+    /// A simplified fraction, like `11/4`. Floats are shown as the fraction
+    /// they're within rounding error of, and in the usual notation when
+    /// there isn't one
+    Fraction,
+    /// A whole part and a proper fraction, like `2 3/4`
+    MixedFraction,
+}
+
+impl FormatOptions {
+    // Decimal places to round to for a number of the given magnitude
+    fn digits_for(&self, value: f64) -> Option<i32> {
+        match (self.significant_figures, self.decimal_places) {
+            (Some(figures), _) => {
+                let magnitude = if value == 0.0 || !value.is_finite() {
+                    0
+                } else {
+                    value.abs().log10().floor() as i32
+                };
+                Some(figures.clamp(1, MAX_DIGITS) as i32 - 1 - magnitude)
+            }
+            (None, Some(places)) => Some(places.min(MAX_DIGITS) as i32),
+            (None, None) => None,
+        }
+    }
+}
+
+impl Number {
+    /// Format for display with the given rounding options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{FormatOptions, Number};
+    ///
+    /// let figures = FormatOptions {
+    ///     significant_figures: Some(3),
+    ///     ..FormatOptions::default()
+    /// };
+    /// assert_eq!(Number::Float(0.000123456).format(&figures), "0.000123");
+    /// assert_eq!(Number::Integer(123456).format(&figures), "123000");
+    ///
+    /// let padded = FormatOptions {
+    ///     decimal_places: Some(2),
+    ///     trailing_zeros: true,
+    ///     ..FormatOptions::default()
+    /// };
+    /// assert_eq!(Number::Float(1.5).format(&padded), "1.50");
+    /// assert_eq!(Number::Float(-0.001).format(&padded), "0.00");
+    /// ```
+    pub fn format(&self, options: &FormatOptions) -> String {
+        if options.round_trip {
+            return round_trip_number(self);
+        }
+        match options.notation {
+            Notation::Scientific | Notation::Engineering if self.to_f64().is_finite() => {
+                return options.locale.apply(exponential(self.to_f64(), options));
+            }
+            Notation::Fraction | Notation::MixedFraction => {
+                if let Some(fraction) = nearby_fraction(self) {
+                    return format_fraction(fraction, options.notation == Notation::MixedFraction);
+                }
+            }
+            _ => {}
+        }
+        let Some(digits) = options.digits_for(self.to_f64()) else {
+            return options.locale.apply(self.to_string());
+        };
+        let rounded = self.round_to(digits, Rounding::Nearest);
+        // Negatives too small for the digits kept round to zero, not `-0`
+        let rendered = if rounded.is_zero() { "0".to_string() } else { rounded.to_string() };
+        if options.trailing_zeros && digits > 0 {
+            options.locale.apply(pad_decimals(rendered, digits as usize))
+        } else {
+            options.locale.apply(rendered)
+        }
+    }
+}
+
+impl UnitValue {
+    /// Format the value with the given options, followed by its unit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{FormatOptions, Locale, UnitValue};
+    ///
+    /// let money = FormatOptions {
+    ///     money: true,
+    ///     ..FormatOptions::default()
+    /// };
+    /// assert_eq!(UnitValue::named(1234.5, "usd").format(&money), "$1,234.50");
+    /// assert_eq!(UnitValue::named(-3.0, "gbp").format(&money), "-£3.00");
+    /// assert_eq!(UnitValue::named(-0.001, "usd").format(&money), "$0.00");
+    ///
+    /// let german = FormatOptions {
+    ///     locale: Locale::from_tag("de").unwrap(),
+    ///     ..money
+    /// };
+    /// assert_eq!(UnitValue::named(1234.5, "eur").format(&german), "1.234,50 €");
+    /// ```
+    pub fn format(&self, options: &FormatOptions) -> String {
+        if options.money && !options.round_trip {
+            if let Some(money) = self.format_money(options) {
+                return money;
+            }
+        }
+        if options.round_trip {
+            // Negating a unit value isn't allowed, but scaling one is
+            let value = self.value();
+            return if value.is_sign_negative() {
+                format!("({} * 1{})", float_literal(value), self.canonical_unit_name())
+            } else {
+                format!("{}{}", value, self.canonical_unit_name())
+            };
+        }
+        let separator = if options.unit_spacing { " " } else { "" };
+        format!(
+            "{}{}{}",
+            self.amount().format(options),
+            separator,
+            self.canonical_unit_name()
+        )
+    }
+}
+
+impl UnitValue {
+    // The value written as money, if its unit is a currency
+    fn format_money(&self, options: &FormatOptions) -> Option<String> {
+        if self.exponent() != 1 {
+            return None;
+        }
+        let unit = self.unit().to_lowercase();
+        let &(_, symbol, places) = CURRENCY_SYMBOLS.iter().find(|(code, _, _)| *code == unit)?;
+        let mut locale = options.locale;
+        if locale.grouping.is_none() && locale.decimal_mark == '.' {
+            locale.grouping = Some(',');
+        }
+        let magnitude = if self.value() < 0.0 { -self.amount() } else { self.amount() };
+        let number = magnitude.format(&FormatOptions {
+            decimal_places: options.decimal_places.or(Some(places)),
+            trailing_zeros: true,
+            notation: Notation::Standard,
+            locale,
+            money: false,
+            ..options.clone()
+        });
+        let rounded_away = !number.bytes().any(|b| (b'1'..=b'9').contains(&b));
+        let sign = if self.value() < 0.0 && !rounded_away { "-" } else { "" };
+        Some(if locale.decimal_mark == ',' {
+            format!("{}{} {}", sign, number, symbol)
+        } else if symbol.chars().all(char::is_alphabetic) {
+            // Symbols made of letters, like `CHF`, would run into the digits
+            format!("{}{} {}", sign, symbol, number)
+        } else {
+            format!("{}{}{}", sign, symbol, number)
+        })
+    }
+}
+
+impl Value {
+    /// Format for end users, rounding numbers (inside lists too) as
+    /// `options` asks. Booleans and integers shown in another base are
+    /// unaffected. In locales with a decimal comma, list items are separated
+    /// by semicolons.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{FormatOptions, Value};
+    ///
+    /// let value = Value::List(vec![Value::from(1.0 / 3.0), Value::from(2i128)]);
+    /// let options = FormatOptions {
+    ///     decimal_places: Some(3),
+    ///     ..FormatOptions::default()
+    /// };
+    /// assert_eq!(value.format(&options), "[0.333, 2]");
+    /// ```
+    pub fn format(&self, options: &FormatOptions) -> String {
+        match self {
+            Value::Number(n) => n.format(options),
+            Value::UnitValue(uv) => uv.format(options),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.format(options)).collect();
+                let separator = if options.locale.decimal_mark == ',' && !options.round_trip {
+                    "; "
+                } else {
+                    ", "
+                };
+                format!("[{}]", items.join(separator))
+            }
+            // Literals like `0xff` read as plain integers, so keep the base
+            // with a conversion
+            Value::Radix { value, radix } if options.round_trip => {
+                let function = match radix {
+                    Radix::Binary => "bin",
+                    Radix::Octal => "oct",
+                    Radix::Hexadecimal => "hex",
+                };
+                format!("{}({})", function, value)
+            }
+            Value::Bool(_) | Value::Radix { .. } => self.to_string(),
+        }
+    }
+}
+
+// An expression evaluating to exactly `number`, for round-trip formatting
+fn round_trip_number(number: &Number) -> String {
+    match number {
+        // The literal for the smallest integer would be one past the largest
+        Number::Integer(i128::MIN) => format!("({} - 1)", i128::MIN + 1),
+        Number::Integer(i) => i.to_string(),
+        Number::Rational(r) if r.denom() == 1 => r.numer().to_string(),
+        Number::Rational(r) => format!("({}/{})", r.numer(), r.denom()),
+        // Literals are read as floats on their way to decimals, which keeps
+        // 15 significant digits. Longer decimals are written as a division
+        #[cfg(feature = "decimal")]
+        Number::Decimal(d) => {
+            let d = d.normalize();
+            let mantissa = d.mantissa();
+            if mantissa.unsigned_abs() < 10u128.pow(15) {
+                float_literal(d.to_string())
+            } else if d.scale() == 0 {
+                format!("({} * 1.0)", mantissa)
+            } else {
+                format!("({}/{})", mantissa, 10i128.pow(d.scale()))
+            }
+        }
+        #[cfg(feature = "bigfloat")]
+        Number::BigFloat(_) => float_literal(number.to_string()),
+        // Literals have no exponent, so the shortest digits are written out
+        // in full, all 324 places of them for the smallest subnormal
+        Number::Float(x) => float_literal(*x),
+    }
+}
+
+// A float as a literal that lexes as a float, not an integer
+fn float_literal(x: impl ToString) -> String {
+    let rendered = x.to_string();
+    let is_number = rendered.bytes().all(|b| b.is_ascii_digit() || b == b'-');
+    if is_number { rendered + ".0" } else { rendered }
+}
+
+// The exact fraction for exact numbers, or the simple fraction a float is
+// within rounding error of
+fn nearby_fraction(number: &Number) -> Option<Rational> {
+    match number {
+        Number::Integer(i) => Some(Rational::from_integer(*i)),
+        Number::Rational(r) => Some(*r),
+        _ => {
+            // Past 2^53 floats no longer hold every integer, so any fraction
+            // would claim digits the float doesn't have
+            let value = number.to_f64();
+            if value.abs() > 2f64.powi(53) {
+                return None;
+            }
+            let fraction = Rational::approximate(value, MAX_DENOMINATOR)?;
+            let tolerance = 1e-9 * value.abs();
+            ((fraction.to_f64() - value).abs() <= tolerance).then_some(fraction)
+        }
+    }
+}
+
+fn format_fraction(fraction: Rational, mixed: bool) -> String {
+    let (numer, denom) = (fraction.numer(), fraction.denom());
+    if denom == 1 {
+        return numer.to_string();
+    }
+    let whole = numer / denom;
+    if !mixed || whole == 0 {
+        return fraction.to_string();
+    }
+    format!("{} {}/{}", whole, (numer % denom).abs(), denom)
+}
+
+// Scientific or engineering notation, built from the digits of Rust's `{:e}`
+// formatting so the mantissa isn't disturbed by dividing by a power of ten
+fn exponential(value: f64, options: &FormatOptions) -> String {
+    let (_, exponent) = split_exponential(&format!("{:e}", value));
+    let shift = match options.notation {
+        Notation::Engineering => exponent.rem_euclid(3) as usize,
+        _ => 0,
+    };
+    let rendered = match (options.significant_figures, options.decimal_places) {
+        (Some(figures), _) => format!("{:.*e}", figures.clamp(1, MAX_DIGITS) - 1, value),
+        (None, Some(places)) => format!("{:.*e}", places.min(MAX_DIGITS) + shift, value),
+        (None, None) => format!("{:e}", value),
+    };
+
+    let (mantissa, exponent) = split_exponential(&rendered);
+    // Rounding can carry into the next power of ten (9.99e2 to 1.0e3)
+    let shift = match options.notation {
+        Notation::Engineering => exponent.rem_euclid(3) as usize,
+        _ => 0,
+    };
+    let mut mantissa = shift_point(mantissa, shift);
+    if !options.trailing_zeros && mantissa.contains('.') {
+        mantissa = mantissa.trim_end_matches('0').trim_end_matches('.').to_string();
+    }
+    format!("{}e{}", mantissa, exponent - shift as i32)
+}
+
+fn split_exponential(rendered: &str) -> (&str, i32) {
+    let (mantissa, exponent) = rendered
+        .split_once('e')
+        .expect("exponential formatting always has an exponent");
+    (mantissa, exponent.parse().expect("exponent is an integer"))
+}
+
+// Move the decimal point of `mantissa` right by `places`, padding with zeros
+fn shift_point(mantissa: &str, places: usize) -> String {
+    if places == 0 {
+        return mantissa.to_string();
+    }
+    let (sign, digits) = match mantissa.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", mantissa),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let mut fraction = fraction.to_string();
+    while fraction.len() < places {
+        fraction.push('0');
+    }
+    let (moved, rest) = fraction.split_at(places);
+    if rest.is_empty() {
+        format!("{}{}{}", sign, whole, moved)
+    } else {
+        format!("{}{}{}.{}", sign, whole, moved, rest)
+    }
+}
+
+// Extend the fractional part of a plain decimal rendering to `places` digits
+fn pad_decimals(rendered: String, places: usize) -> String {
+    if rendered.contains(['e', 'E']) || rendered.contains("inf") || rendered.contains("NaN") {
+        return rendered;
+    }
+    let existing = rendered.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+    if existing >= places {
+        return rendered;
+    }
+    let mut padded = rendered;
+    if existing == 0 {
+        padded.push('.');
+    }
+    padded.extend(std::iter::repeat_n('0', places - existing));
+    padded
+}
@@ -1,12 +1,34 @@
+#[cfg(feature = "bigfloat")]
+pub mod big_float;
 pub mod conversion_error;
 pub mod dimensions;
+pub mod format;
 pub mod number;
+#[cfg(feature = "units")]
+pub mod quantity;
+pub mod radix;
+pub mod rational;
+pub mod scalar;
+#[cfg(feature = "serde")]
+pub(crate) mod serialization;
 pub mod unit_value;
 pub mod value;
+pub mod value_key;
 
 // Re-export all types for easy access
+#[cfg(feature = "bigfloat")]
+pub use big_float::BigFloat;
+#[cfg(feature = "decimal")]
+pub use rust_decimal::Decimal;
 pub use conversion_error::ConversionError;
-pub use dimensions::{DimensionType, Unit};
-pub use number::Number;
-pub use unit_value::UnitValue;
-pub use value::Value;
\ No newline at end of file
+pub use dimensions::{DimensionType, Unit, UnitError};
+pub use format::{FormatOptions, Locale, Notation};
+pub use number::{Number, Rounding};
+#[cfg(feature = "units")]
+pub use quantity::{Frequency, Length, Mass, Quantity, QuantityKind, Temperature, Time};
+pub use radix::Radix;
+pub use rational::Rational;
+pub use scalar::{Representation, Scalar};
+pub use unit_value::{UnitInfo, UnitValue};
+pub use value::Value;
+pub use value_key::ValueKey;
\ No newline at end of file
@@ -1,59 +1,678 @@
 use std::fmt::Display;
+use crate::types::Rational;
+
+#[cfg(feature = "decimal")]
+use rust_decimal::{prelude::ToPrimitive, Decimal, MathematicalOps};
+
+#[cfg(feature = "bigfloat")]
+use crate::types::big_float::{self, BigFloat};
+
+/// How [`Number::round_to`] picks between the two nearest candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// To the nearest, with halves away from zero
+    Nearest,
+    /// Towards negative infinity
+    Floor,
+    /// Towards positive infinity
+    Ceil,
+    /// Towards zero
+    Trunc,
+}
 
 /// Represents a numeric value in mathematical expressions.
+///
+/// Integers stay exact until an operation produces a fractional result (or
+/// overflows `i128`), at which point the value falls back to `f64`. Exact
+/// fractions are produced by [`Number::div_exact`] (used by the evaluator's
+/// rational mode) and stay exact through further arithmetic with integers.
+///
+/// Mixed operations promote to the less exact side: integer → fraction →
+/// decimal (with the `decimal` feature) → big float (with the `bigfloat`
+/// feature) → float.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::Number;
+///
+/// let exact = Number::Integer(10) / Number::Integer(2);
+/// assert_eq!(exact.to_string(), "5");
+/// assert!(exact.is_integer());
+///
+/// let fractional = Number::Integer(1) / Number::Integer(4);
+/// assert_eq!(fractional.to_string(), "0.25");
+/// ```
 #[derive(Debug, Clone)]
-pub struct Number(pub f64);
+pub enum Number {
+    /// An exact integer
+    Integer(i128),
+    /// An exact fraction in lowest terms (never a whole number)
+    Rational(Rational),
+    /// A fixed-precision decimal, exact for base-10 fractions like `0.1`
+    #[cfg(feature = "decimal")]
+    Decimal(Decimal),
+    /// An arbitrary-precision binary float
+    #[cfg(feature = "bigfloat")]
+    BigFloat(BigFloat),
+    /// A floating-point value
+    Float(f64),
+}
+
+impl Number {
+    /// Get the value as an `f64`, rounding large integers if necessary.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Integer(i) => *i as f64,
+            Number::Rational(r) => r.to_f64(),
+            #[cfg(feature = "decimal")]
+            Number::Decimal(d) => d.to_f64().unwrap_or(f64::NAN),
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(b) => b.to_f64().value(),
+            Number::Float(f) => *f,
+        }
+    }
+
+    /// Build a number from a fraction, collapsing whole numbers to integers.
+    pub fn from_rational(r: Rational) -> Number {
+        if r.is_integer() {
+            Number::Integer(r.numer())
+        } else {
+            Number::Rational(r)
+        }
+    }
+
+    /// Get the number as an exact fraction, if it is an integer or fraction.
+    pub fn as_rational(&self) -> Option<Rational> {
+        match self {
+            Number::Integer(i) => Some(Rational::from_integer(*i)),
+            Number::Rational(r) => Some(*r),
+            #[cfg(feature = "decimal")]
+            Number::Decimal(_) => None,
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(_) => None,
+            Number::Float(_) => None,
+        }
+    }
+
+    /// Read the digits of a decimal literal, like `0.25`, as an exact
+    /// fraction, collapsing whole numbers to integers. `None` for digits
+    /// that don't fit in an `i128` fraction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Number, Rational};
+    ///
+    /// let quarter = Number::rational_from_digits("0.25").unwrap();
+    /// assert_eq!(quarter.as_rational(), Rational::new(1, 4));
+    /// let negative = Number::rational_from_digits("-1.50").unwrap();
+    /// assert_eq!(negative.as_rational(), Rational::new(-3, 2));
+    /// assert!(Number::rational_from_digits("1e10").is_none());
+    /// ```
+    pub fn rational_from_digits(digits: &str) -> Option<Number> {
+        let (sign, digits) = match digits.strip_prefix('-') {
+            Some(digits) => (-1, digits),
+            None => (1, digits),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let numer = format!("{}{}", whole, fraction).parse::<i128>().ok()?;
+        let denom = 10i128.checked_pow(u32::try_from(fraction.len()).ok()?)?;
+        Rational::new(sign * numer, denom).map(Number::from_rational)
+    }
+
+    /// Convert an `f64` literal to a decimal through its shortest round-trip
+    /// representation, so `0.1` becomes exactly `0.1`.
+    #[cfg(feature = "decimal")]
+    pub fn decimal_from_f64(value: f64) -> Option<Number> {
+        Number::decimal_from_digits(&value.to_string())
+    }
+
+    /// Read the digits of a decimal literal as a decimal.
+    #[cfg(feature = "decimal")]
+    pub fn decimal_from_digits(digits: &str) -> Option<Number> {
+        digits.parse::<Decimal>().ok().map(Number::Decimal)
+    }
+
+    /// Get the number as a decimal, if it isn't a float.
+    ///
+    /// Fractions are rounded to the decimal's precision (`1/3` becomes
+    /// `0.3333333333333333333333333333`).
+    #[cfg(feature = "decimal")]
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Number::Integer(i) => Decimal::try_from_i128_with_scale(*i, 0).ok(),
+            Number::Rational(r) => {
+                let numer = Decimal::try_from_i128_with_scale(r.numer(), 0).ok()?;
+                let denom = Decimal::try_from_i128_with_scale(r.denom(), 0).ok()?;
+                numer.checked_div(denom)
+            }
+            Number::Decimal(d) => Some(*d),
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(_) => None,
+            Number::Float(_) => None,
+        }
+    }
+
+    /// Convert a decimal literal into a big float with `precision` bits.
+    #[cfg(feature = "bigfloat")]
+    pub fn big_float_from_f64(value: f64, precision: usize) -> Option<Number> {
+        Number::big_float_from_digits(&value.to_string(), precision)
+    }
+
+    /// Read the digits of a decimal literal as a big float with `precision`
+    /// bits, keeping digits past the seventeen an `f64` holds.
+    #[cfg(feature = "bigfloat")]
+    pub fn big_float_from_digits(digits: &str, precision: usize) -> Option<Number> {
+        big_float::parse(digits, precision).map(Number::BigFloat)
+    }
+
+    /// Get the number as a big float with at least `precision` bits, if it
+    /// isn't a float.
+    #[cfg(feature = "bigfloat")]
+    pub fn as_big_float(&self, precision: usize) -> Option<BigFloat> {
+        match self {
+            Number::Integer(i) => Some(big_float::from_integer(*i, precision)),
+            Number::Rational(r) => Some(big_float::from_rational(r, precision)),
+            #[cfg(feature = "decimal")]
+            Number::Decimal(d) => big_float::parse(&d.to_string(), precision),
+            Number::BigFloat(b) => Some(b.clone()),
+            Number::Float(_) => None,
+        }
+    }
+
+    /// Divide, producing a big float with `precision` bits rather than a
+    /// float when the quotient of non-float operands isn't a whole number.
+    #[cfg(feature = "bigfloat")]
+    pub fn div_big_float(self, rhs: Number, precision: usize) -> Number {
+        if let (Number::Integer(l), Number::Integer(r)) = (&self, &rhs) {
+            if let Some(result) = Arith::Div.integer(*l, *r) {
+                return Number::Integer(result);
+            }
+        }
+        if let (Some(l), Some(r)) = (self.as_big_float(precision), rhs.as_big_float(precision)) {
+            if !r.repr().is_zero() {
+                return Number::BigFloat(l / r);
+            }
+        }
+        self / rhs
+    }
+
+    /// Raise to a power, producing a big float with `precision` bits for
+    /// negative integer exponents of non-float bases, for integer powers too
+    /// large for a fraction, and for fractional exponents of positive ones.
+    ///
+    /// `None` when the result would be more than about 2^(2^52) or less than
+    /// its reciprocal, as `2^(10^30)` and `0.5^(10^30)` are.
+    #[cfg(feature = "bigfloat")]
+    pub fn pow_big_float(&self, exponent: &Number, precision: usize) -> Option<Number> {
+        let base = self.as_big_float(precision);
+        if let Some(base) = &base {
+            if !big_float::power_in_range(base, exponent.to_f64()) {
+                return None;
+            }
+        }
+        if let (Some(base), Number::Integer(exp)) = (&base, exponent) {
+            if *exp < 0 && !base.repr().is_zero() {
+                return Some(Number::BigFloat(big_float::powi(base, *exp)));
+            }
+            // Exact while the fraction fits, and a big float once it doesn't
+            return match self.pow(exponent) {
+                Number::Float(_) => Some(Number::BigFloat(big_float::powi(base, *exp))),
+                exact => Some(exact),
+            };
+        }
+        if let (Some(base), Some(exp), None) = (&base, exponent.as_big_float(precision), exponent.as_integer()) {
+            if let Some(result) = big_float::powf(base, &exp) {
+                return Some(Number::BigFloat(result));
+            }
+        }
+        Some(self.pow(exponent))
+    }
+
+    /// Square root as a big float with `precision` bits, for anything but a float.
+    #[cfg(feature = "bigfloat")]
+    pub fn sqrt_big_float(&self, precision: usize) -> Number {
+        match self.as_big_float(precision) {
+            Some(value) => Number::BigFloat(big_float::sqrt(&value)),
+            None => self.sqrt(),
+        }
+    }
+
+    /// `sin`, `cos`, `tan`, `exp` or `ln` as a big float with `precision`
+    /// bits, for anything but a float. `None` for other names, for floats,
+    /// and for `tan` of a right angle.
+    #[cfg(feature = "bigfloat")]
+    pub fn elementary_big_float(&self, name: &str, precision: usize) -> Option<Number> {
+        big_float::elementary(name, &self.as_big_float(precision)?).map(Number::BigFloat)
+    }
+
+    /// Square root. Big floats keep their precision; everything else becomes a float.
+    pub fn sqrt(&self) -> Number {
+        match self {
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(b) => Number::BigFloat(big_float::sqrt(b)),
+            _ => Number::Float(self.to_f64().sqrt()),
+        }
+    }
+
+    /// Divide, producing an exact fraction when both sides are exact.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::Number;
+    ///
+    /// let third = Number::Integer(1).div_exact(Number::Integer(3));
+    /// let sixth = Number::Integer(1).div_exact(Number::Integer(6));
+    /// let half = third + sixth;
+    /// assert_eq!(half.as_rational().unwrap().to_string(), "1/2");
+    /// assert_eq!(half.to_string(), "0.5");
+    /// ```
+    pub fn div_exact(self, rhs: Number) -> Number {
+        if let (Some(l), Some(r)) = (self.as_rational(), rhs.as_rational()) {
+            if let Some(result) = l.checked_div(&r) {
+                return Number::from_rational(result);
+            }
+        }
+        self / rhs
+    }
+
+    /// Divide, producing a decimal rather than a float when the quotient of
+    /// non-float operands isn't a whole number.
+    #[cfg(feature = "decimal")]
+    pub fn div_decimal(self, rhs: Number) -> Number {
+        if let (Number::Integer(l), Number::Integer(r)) = (&self, &rhs) {
+            if let Some(result) = Arith::Div.integer(*l, *r) {
+                return Number::Integer(result);
+            }
+        }
+        if let (Some(l), Some(r)) = (self.as_decimal(), rhs.as_decimal()) {
+            if let Some(result) = l.checked_div(r) {
+                return Number::Decimal(result);
+            }
+        }
+        self / rhs
+    }
+
+    /// Raise to a power, producing an exact fraction for negative integer
+    /// exponents of exact bases (`2^-2` is `1/4`).
+    pub fn pow_exact(&self, exponent: &Number) -> Number {
+        if let (Some(base), Number::Integer(exp)) = (self.as_rational(), exponent) {
+            if let Some(result) = i32::try_from(*exp).ok().and_then(|e| base.checked_pow(e)) {
+                return Number::from_rational(result);
+            }
+        }
+        self.pow(exponent)
+    }
+
+    /// Round to `digits` decimal places, or to a power of ten for negative
+    /// `digits` (`-2` rounds to hundreds).
+    ///
+    /// Exact numbers stay exact; floats round in floating point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Number, Rounding};
+    ///
+    /// assert_eq!(Number::Float(1.2345).round_to(2, Rounding::Nearest).to_string(), "1.23");
+    /// assert_eq!(Number::Integer(1250).round_to(-2, Rounding::Nearest).to_string(), "1300");
+    /// assert_eq!(Number::Float(-2.5).round_to(0, Rounding::Floor).to_string(), "-3");
+    /// ```
+    pub fn round_to(&self, digits: i32, rounding: Rounding) -> Number {
+        if let Some(r) = self.as_rational() {
+            if let Some(result) = round_rational(&r, digits, rounding) {
+                return Number::from_rational(result);
+            }
+        }
+        #[cfg(feature = "decimal")]
+        if let Number::Decimal(d) = self {
+            if let Ok(places) = u32::try_from(digits) {
+                use rust_decimal::RoundingStrategy;
+                let strategy = match rounding {
+                    Rounding::Nearest => RoundingStrategy::MidpointAwayFromZero,
+                    Rounding::Floor => RoundingStrategy::ToNegativeInfinity,
+                    Rounding::Ceil => RoundingStrategy::ToPositiveInfinity,
+                    Rounding::Trunc => RoundingStrategy::ToZero,
+                };
+                return Number::Decimal(d.round_dp_with_strategy(places, strategy));
+            }
+        }
+        #[cfg(feature = "bigfloat")]
+        if let Number::BigFloat(b) = self {
+            let scale = big_float::powi(&big_float::from_integer(10, b.precision()), digits.into());
+            let scaled = b * &scale;
+            let rounded = match rounding {
+                Rounding::Nearest => scaled.round(),
+                Rounding::Floor => scaled.floor(),
+                Rounding::Ceil => scaled.ceil(),
+                Rounding::Trunc => scaled.trunc(),
+            };
+            return Number::BigFloat(rounded / scale);
+        }
+
+        let scale = 10f64.powi(digits);
+        let scaled = self.to_f64() * scale;
+        // Past the digits a float holds, rounding leaves it as it is; to a
+        // power of ten past the largest float, nothing is left
+        if scale.is_infinite() || scaled.is_infinite() {
+            return Number::Float(self.to_f64());
+        }
+        if scale == 0.0 {
+            return Number::Float(0.0);
+        }
+        let rounded = match rounding {
+            Rounding::Nearest => scaled.round(),
+            Rounding::Floor => scaled.floor(),
+            Rounding::Ceil => scaled.ceil(),
+            Rounding::Trunc => scaled.trunc(),
+        };
+        Number::Float(rounded / scale)
+    }
+
+    /// Whether this number is held as an exact integer.
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Number::Integer(_))
+    }
+
+    /// Get the numeric value as an integer, if it is one.
+    ///
+    /// Floats (and decimals) with no fractional part that fit in an `i128`
+    /// are accepted too.
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Number::Integer(i) => Some(*i),
+            Number::Rational(_) => None,
+            #[cfg(feature = "decimal")]
+            Number::Decimal(d) if d.fract().is_zero() => d.to_i128(),
+            #[cfg(feature = "decimal")]
+            Number::Decimal(_) => None,
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(b) => big_float::to_integer(b),
+            Number::Float(f) if f.fract() == 0.0 && f.abs() < i128::MAX as f64 => Some(*f as i128),
+            Number::Float(_) => None,
+        }
+    }
+
+    /// Whether this number is exactly zero.
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Integer(i) => *i == 0,
+            Number::Rational(_) => false,
+            #[cfg(feature = "decimal")]
+            Number::Decimal(d) => d.is_zero(),
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(b) => b.repr().is_zero(),
+            Number::Float(f) => *f == 0.0,
+        }
+    }
+
+    /// Raise this number to a power, staying exact for non-negative integer exponents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::Number;
+    ///
+    /// let big = Number::Integer(3).pow(&Number::Integer(40));
+    /// assert_eq!(big.to_string(), "12157665459056928801");
+    /// ```
+    pub fn pow(&self, exponent: &Number) -> Number {
+        if let (Some(base), Number::Integer(exp)) = (self.as_rational(), exponent) {
+            if let Some(result) = i32::try_from(*exp)
+                .ok()
+                .filter(|e| *e >= 0)
+                .and_then(|e| base.checked_pow(e))
+            {
+                return Number::from_rational(result);
+            }
+        }
+        #[cfg(feature = "decimal")]
+        if let (Number::Decimal(base), Number::Integer(exp)) = (self, exponent) {
+            if let Some(result) = i64::try_from(*exp).ok().and_then(|e| base.checked_powi(e)) {
+                return Number::Decimal(result);
+            }
+        }
+        #[cfg(feature = "bigfloat")]
+        if let (Number::BigFloat(base), Number::Integer(exp)) = (self, exponent) {
+            return Number::BigFloat(big_float::powi(base, *exp));
+        }
+        Number::Float(self.to_f64().powf(exponent.to_f64()))
+    }
+}
+
+// Scale by 10^digits, round to a whole number, and scale back; `None` on overflow
+fn round_rational(value: &Rational, digits: i32, rounding: Rounding) -> Option<Rational> {
+    let scale = Rational::from_integer(10).checked_pow(digits)?;
+    let scaled = value.checked_mul(&scale)?;
+    let (numer, denom) = (scaled.numer(), scaled.denom());
+    let floor = numer.div_euclid(denom);
+    let whole = match rounding {
+        Rounding::Floor => floor,
+        Rounding::Ceil if numer.rem_euclid(denom) == 0 => floor,
+        Rounding::Ceil => floor + 1,
+        Rounding::Trunc => numer / denom,
+        Rounding::Nearest => {
+            // Halves go away from zero: round |x| + 1/2 down, then restore the sign
+            let twice = numer.unsigned_abs().checked_mul(2)?.checked_add(denom.unsigned_abs())?;
+            let magnitude = i128::try_from(twice / (2 * denom.unsigned_abs())).ok()?;
+            if numer < 0 { -magnitude } else { magnitude }
+        }
+    };
+    Rational::from_integer(whole).checked_div(&scale)
+}
 
 impl From<f64> for Number {
     fn from(value: f64) -> Self {
-        Self(value)
+        Number::Float(value)
+    }
+}
+
+impl From<f32> for Number {
+    fn from(value: f32) -> Self {
+        Number::Float(value as f64)
+    }
+}
+
+impl From<i128> for Number {
+    fn from(value: i128) -> Self {
+        Number::Integer(value)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Self {
+        Number::Integer(value as i128)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<Decimal> for Number {
+    fn from(value: Decimal) -> Self {
+        Number::Decimal(value)
     }
 }
 
 impl Display for Number {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            Number::Integer(i) => write!(f, "{}", i),
+            // Fractions are exact internally but display as decimals
+            Number::Rational(r) => write!(f, "{}", r.to_f64()),
+            #[cfg(feature = "decimal")]
+            Number::Decimal(d) => write!(f, "{}", d.normalize()),
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(b) => write!(f, "{}", big_float::to_decimal_string(b)),
+            Number::Float(x) => write!(f, "{}", x),
+        }
     }
 }
 
+// The arithmetic operators, applied at whichever representation both operands
+// can be promoted to
+#[derive(Clone, Copy)]
+enum Arith {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Arith {
+    fn integer(self, l: i128, r: i128) -> Option<i128> {
+        match self {
+            Arith::Add => l.checked_add(r),
+            Arith::Sub => l.checked_sub(r),
+            Arith::Mul => l.checked_mul(r),
+            // Integer division only stays exact when there's no remainder
+            Arith::Div => match l.checked_rem(r) {
+                Some(0) => l.checked_div(r),
+                _ => None,
+            },
+            Arith::Rem => l.checked_rem(r),
+        }
+    }
+
+    fn rational(self, l: &Rational, r: &Rational) -> Option<Rational> {
+        match self {
+            Arith::Add => l.checked_add(r),
+            Arith::Sub => l.checked_sub(r),
+            Arith::Mul => l.checked_mul(r),
+            Arith::Div => l.checked_div(r),
+            Arith::Rem => None,
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    fn decimal(self, l: Decimal, r: Decimal) -> Option<Decimal> {
+        match self {
+            Arith::Add => l.checked_add(r),
+            Arith::Sub => l.checked_sub(r),
+            Arith::Mul => l.checked_mul(r),
+            Arith::Div => l.checked_div(r),
+            Arith::Rem => l.checked_rem(r),
+        }
+    }
+
+    #[cfg(feature = "bigfloat")]
+    fn big_float(self, l: BigFloat, r: BigFloat) -> Option<BigFloat> {
+        match self {
+            Arith::Add => Some(l + r),
+            Arith::Sub => Some(l - r),
+            Arith::Mul => Some(l * r),
+            Arith::Div if r.repr().is_zero() => None,
+            Arith::Div => Some(l / r),
+            Arith::Rem => None,
+        }
+    }
+
+    fn float(self, l: f64, r: f64) -> f64 {
+        match self {
+            Arith::Add => l + r,
+            Arith::Sub => l - r,
+            Arith::Mul => l * r,
+            Arith::Div => l / r,
+            Arith::Rem => l % r,
+        }
+    }
+}
+
+// Applies an operation at the most exact representation both sides share,
+// falling back to floating point if either side is a float or the exact
+// operation overflows
+fn arithmetic(lhs: Number, rhs: Number, op: Arith) -> Number {
+    match (&lhs, &rhs) {
+        (Number::Integer(l), Number::Integer(r)) => {
+            if let Some(result) = op.integer(*l, *r) {
+                return Number::Integer(result);
+            }
+        }
+        #[cfg(feature = "bigfloat")]
+        (Number::BigFloat(b), _) | (_, Number::BigFloat(b)) => {
+            let precision = b.precision();
+            if let (Some(l), Some(r)) = (lhs.as_big_float(precision), rhs.as_big_float(precision)) {
+                if let Some(result) = op.big_float(l, r) {
+                    return Number::BigFloat(result);
+                }
+            }
+        }
+        #[cfg(feature = "decimal")]
+        (Number::Decimal(_), _) | (_, Number::Decimal(_)) => {
+            if let (Some(l), Some(r)) = (lhs.as_decimal(), rhs.as_decimal()) {
+                if let Some(result) = op.decimal(l, r) {
+                    return Number::Decimal(result);
+                }
+            }
+        }
+        (Number::Rational(_), _) | (_, Number::Rational(_)) => {
+            if let (Some(l), Some(r)) = (lhs.as_rational(), rhs.as_rational()) {
+                if let Some(result) = op.rational(&l, &r) {
+                    return Number::from_rational(result);
+                }
+            }
+        }
+        _ => {}
+    }
+    Number::Float(op.float(lhs.to_f64(), rhs.to_f64()))
+}
+
 impl std::ops::Add for Number {
     type Output = Number;
     fn add(self, rhs: Number) -> Self::Output {
-        Number(self.0 + rhs.0)
+        arithmetic(self, rhs, Arith::Add)
     }
 }
 
 impl std::ops::Sub for Number {
     type Output = Number;
     fn sub(self, rhs: Number) -> Self::Output {
-        Number(self.0 - rhs.0)
+        arithmetic(self, rhs, Arith::Sub)
     }
 }
 
 impl std::ops::Mul for Number {
     type Output = Number;
     fn mul(self, rhs: Number) -> Self::Output {
-        Number(self.0 * rhs.0)
+        arithmetic(self, rhs, Arith::Mul)
     }
 }
 
 impl std::ops::Div for Number {
     type Output = Number;
     fn div(self, rhs: Number) -> Self::Output {
-        Number(self.0 / rhs.0)
+        arithmetic(self, rhs, Arith::Div)
     }
 }
 
 impl std::ops::Rem for Number {
     type Output = Number;
     fn rem(self, rhs: Number) -> Self::Output {
-        Number(self.0 % rhs.0)
+        arithmetic(self, rhs, Arith::Rem)
     }
 }
 
 impl std::ops::Neg for Number {
     type Output = Number;
     fn neg(self) -> Self::Output {
-        Number(-self.0)
+        match self {
+            Number::Integer(i) => match i.checked_neg() {
+                Some(negated) => Number::Integer(negated),
+                None => Number::Float(-(i as f64)),
+            },
+            Number::Rational(r) => match r.checked_neg() {
+                Some(negated) => Number::Rational(negated),
+                None => Number::Float(-r.to_f64()),
+            },
+            #[cfg(feature = "decimal")]
+            Number::Decimal(d) => Number::Decimal(-d),
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(b) => Number::BigFloat(-b),
+            Number::Float(f) => Number::Float(-f),
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,260 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use mathengine_units::{
+    frequency::FrequencyUnit,
+    length::LengthUnit,
+    mass::MassUnit,
+    temperature::TemperatureUnit,
+    time::TimeUnit,
+    Dimension, UnitType,
+};
+
+use crate::types::{ConversionError, DimensionType, Unit, UnitValue, Value};
+
+/// A dimension that a [`Quantity`] can be measured in: [`Length`],
+/// [`Temperature`], [`Time`], [`Frequency`] or [`Mass`].
+pub trait QuantityKind {
+    /// The units of this dimension, like [`LengthUnit`]
+    type Unit: UnitType;
+
+    /// The matching runtime dimension
+    const DIMENSION: DimensionType;
+
+    /// The unit as a [`Unit`] of any dimension
+    fn to_unit(unit: Self::Unit) -> Unit;
+
+    /// The unit, if `unit` is in this dimension
+    fn from_unit(unit: Unit) -> Option<Self::Unit>;
+
+    /// Convert a value between two units of this dimension
+    fn convert(from: Self::Unit, to: Self::Unit, value: f64) -> f64;
+}
+
+/// Marker for quantities of length, like `Quantity<Length>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Length;
+
+/// Marker for quantities of temperature, like `Quantity<Temperature>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Temperature;
+
+/// Marker for quantities of time, like `Quantity<Time>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Time;
+
+/// Marker for quantities of frequency, like `Quantity<Frequency>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frequency;
+
+/// Marker for quantities of mass, like `Quantity<Mass>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mass;
+
+impl QuantityKind for Length {
+    type Unit = LengthUnit;
+    const DIMENSION: DimensionType = DimensionType::Length;
+
+    fn to_unit(unit: LengthUnit) -> Unit {
+        Unit::Length(unit)
+    }
+
+    fn from_unit(unit: Unit) -> Option<LengthUnit> {
+        match unit {
+            Unit::Length(unit) => Some(unit),
+            _ => None,
+        }
+    }
+
+    fn convert(from: LengthUnit, to: LengthUnit, value: f64) -> f64 {
+        <Dimension<LengthUnit>>::convert_value(from, to, value)
+    }
+}
+
+impl QuantityKind for Temperature {
+    type Unit = TemperatureUnit;
+    const DIMENSION: DimensionType = DimensionType::Temperature;
+
+    fn to_unit(unit: TemperatureUnit) -> Unit {
+        Unit::Temperature(unit)
+    }
+
+    fn from_unit(unit: Unit) -> Option<TemperatureUnit> {
+        match unit {
+            Unit::Temperature(unit) => Some(unit),
+            _ => None,
+        }
+    }
+
+    fn convert(from: TemperatureUnit, to: TemperatureUnit, value: f64) -> f64 {
+        <Dimension<TemperatureUnit>>::convert_value(from, to, value)
+    }
+}
+
+impl QuantityKind for Time {
+    type Unit = TimeUnit;
+    const DIMENSION: DimensionType = DimensionType::Time;
+
+    fn to_unit(unit: TimeUnit) -> Unit {
+        Unit::Time(unit)
+    }
+
+    fn from_unit(unit: Unit) -> Option<TimeUnit> {
+        match unit {
+            Unit::Time(unit) => Some(unit),
+            _ => None,
+        }
+    }
+
+    fn convert(from: TimeUnit, to: TimeUnit, value: f64) -> f64 {
+        <Dimension<TimeUnit>>::convert_value(from, to, value)
+    }
+}
+
+impl QuantityKind for Frequency {
+    type Unit = FrequencyUnit;
+    const DIMENSION: DimensionType = DimensionType::Frequency;
+
+    fn to_unit(unit: FrequencyUnit) -> Unit {
+        Unit::Frequency(unit)
+    }
+
+    fn from_unit(unit: Unit) -> Option<FrequencyUnit> {
+        match unit {
+            Unit::Frequency(unit) => Some(unit),
+            _ => None,
+        }
+    }
+
+    fn convert(from: FrequencyUnit, to: FrequencyUnit, value: f64) -> f64 {
+        <Dimension<FrequencyUnit>>::convert_value(from, to, value)
+    }
+}
+
+impl QuantityKind for Mass {
+    type Unit = MassUnit;
+    const DIMENSION: DimensionType = DimensionType::Mass;
+
+    fn to_unit(unit: MassUnit) -> Unit {
+        Unit::Mass(unit)
+    }
+
+    fn from_unit(unit: Unit) -> Option<MassUnit> {
+        match unit {
+            Unit::Mass(unit) => Some(unit),
+            _ => None,
+        }
+    }
+
+    fn convert(from: MassUnit, to: MassUnit, value: f64) -> f64 {
+        <Dimension<MassUnit>>::convert_value(from, to, value)
+    }
+}
+
+/// A value whose dimension is known at compile time.
+///
+/// Where a [`UnitValue`] can hold any unit and reports a mismatch at runtime,
+/// a `Quantity<Length>` is always a length, so passing a temperature where a
+/// length is expected is a type error. Converting a result into one checks
+/// the dimension once, at the boundary.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::{Length, Quantity, Temperature, UnitValue, Value};
+/// use mathengine_units::length::LengthUnit;
+///
+/// let result = Value::UnitValue(UnitValue::new(1.5, "km".to_string()).unwrap());
+/// let distance = Quantity::<Length>::try_from(result.clone()).unwrap();
+/// assert_eq!(distance.to(LengthUnit::Meter).value(), 1500.0);
+/// assert!(Quantity::<Temperature>::try_from(result).is_err());
+///
+/// let back: UnitValue = distance.into();
+/// assert_eq!(back.to_string(), "1.5km");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity<D: QuantityKind> {
+    value: f64,
+    unit: D::Unit,
+    dimension: PhantomData<D>,
+}
+
+impl<D: QuantityKind> Quantity<D> {
+    /// Create a quantity of `value` in `unit`.
+    pub const fn new(value: f64, unit: D::Unit) -> Self {
+        Self {
+            value,
+            unit,
+            dimension: PhantomData,
+        }
+    }
+
+    /// Get the numeric value, in [`unit`](Quantity::unit).
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Get the unit the value is in.
+    pub fn unit(&self) -> D::Unit {
+        self.unit
+    }
+
+    /// The same quantity in another unit of this dimension. Unlike
+    /// [`UnitValue::convert_to`], this can't fail.
+    pub fn to(&self, unit: D::Unit) -> Self {
+        Self::new(D::convert(self.unit, unit, self.value), unit)
+    }
+}
+
+impl<D: QuantityKind> Display for Quantity<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.value, self.unit.canonical_string())
+    }
+}
+
+impl<D: QuantityKind> From<Quantity<D>> for UnitValue {
+    fn from(quantity: Quantity<D>) -> Self {
+        UnitValue::from_unit(quantity.value, D::to_unit(quantity.unit), 1)
+    }
+}
+
+impl<D: QuantityKind> From<Quantity<D>> for Value {
+    fn from(quantity: Quantity<D>) -> Self {
+        Value::UnitValue(quantity.into())
+    }
+}
+
+/// Fails with [`ConversionError::CrossDimension`] for a unit value of another
+/// dimension, or one raised to a power (`9m^2` isn't a length).
+impl<D: QuantityKind> TryFrom<UnitValue> for Quantity<D> {
+    type Error = ConversionError;
+
+    fn try_from(value: UnitValue) -> Result<Self, Self::Error> {
+        match value.known_unit().and_then(D::from_unit) {
+            Some(unit) if value.exponent() == 1 => Ok(Quantity::new(value.value(), unit)),
+            Some(_) => Err(ConversionError::CrossDimension),
+            None if value.dimension() == DimensionType::Unknown => {
+                Err(ConversionError::UnknownUnit(value.unit().to_string()))
+            }
+            None => Err(ConversionError::CrossDimension),
+        }
+    }
+}
+
+/// Fails with [`ConversionError::UnitMismatch`] for a plain number and
+/// [`ConversionError::NotNumeric`] for booleans and lists, as well as the
+/// errors converting from a [`UnitValue`] gives.
+impl<D: QuantityKind> TryFrom<Value> for Quantity<D> {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::UnitValue(uv) => uv.try_into(),
+            Value::Number(_) | Value::Radix { .. } => Err(ConversionError::UnitMismatch {
+                expected: D::DIMENSION.base_unit().map(|unit| unit.canonical_string().to_string()),
+                found: None,
+            }),
+            other => Err(ConversionError::NotNumeric(other.to_string())),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt::Display;
+
+/// A base for displaying integers, as produced by `hex()`, `bin()` and `oct()`.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::Radix;
+///
+/// assert_eq!(Radix::Hexadecimal.format(255), "0xff");
+/// assert_eq!(Radix::Binary.format(-5), "-0b101");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Hexadecimal,
+}
+
+impl Radix {
+    /// The numeric base (2, 8 or 16).
+    pub fn base(&self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Hexadecimal => 16,
+        }
+    }
+
+    /// The literal prefix for this base, like `0x`.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Radix::Binary => "0b",
+            Radix::Octal => "0o",
+            Radix::Hexadecimal => "0x",
+        }
+    }
+
+    /// Render an integer in this base with its prefix, putting any sign first.
+    pub fn format(&self, value: i128) -> String {
+        let magnitude = value.unsigned_abs();
+        let digits = match self {
+            Radix::Binary => format!("{:b}", magnitude),
+            Radix::Octal => format!("{:o}", magnitude),
+            Radix::Hexadecimal => format!("{:x}", magnitude),
+        };
+        let sign = if value < 0 { "-" } else { "" };
+        format!("{}{}{}", sign, self.prefix(), digits)
+    }
+}
+
+impl Display for Radix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Radix::Binary => "binary",
+            Radix::Octal => "octal",
+            Radix::Hexadecimal => "hexadecimal",
+        };
+        write!(f, "{}", name)
+    }
+}
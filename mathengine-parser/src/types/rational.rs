@@ -0,0 +1,169 @@
+use std::fmt::Display;
+
+/// An exact fraction, always stored in lowest terms with a positive denominator.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::Rational;
+///
+/// let half = Rational::new(2, 4).unwrap();
+/// assert_eq!(half.numer(), 1);
+/// assert_eq!(half.denom(), 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rational {
+    numer: i128,
+    denom: i128,
+}
+
+impl Rational {
+    /// Create a fraction reduced to lowest terms, or `None` if the denominator is zero.
+    pub fn new(numer: i128, denom: i128) -> Option<Self> {
+        if denom == 0 {
+            return None;
+        }
+
+        let divisor = gcd(numer, denom);
+        let (mut numer, mut denom) = (numer / divisor, denom / divisor);
+        if denom < 0 {
+            numer = numer.checked_neg()?;
+            denom = denom.checked_neg()?;
+        }
+        Some(Self { numer, denom })
+    }
+
+    /// Create a fraction representing a whole number.
+    pub fn from_integer(value: i128) -> Self {
+        Self {
+            numer: value,
+            denom: 1,
+        }
+    }
+
+    /// Get the numerator.
+    pub fn numer(&self) -> i128 {
+        self.numer
+    }
+
+    /// Get the denominator (always positive).
+    pub fn denom(&self) -> i128 {
+        self.denom
+    }
+
+    /// Whether this fraction is a whole number.
+    pub fn is_integer(&self) -> bool {
+        self.denom == 1
+    }
+
+    /// A close fraction to `value` with a denominator of at most `max_denom`:
+    /// the last convergent of its continued fraction that fits. `None` for
+    /// values that aren't finite or don't fit in an `i128`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::Rational;
+    ///
+    /// let third = Rational::approximate(1.0 / 3.0, 1000).unwrap();
+    /// assert_eq!(third.to_string(), "1/3");
+    /// assert_eq!(Rational::approximate(std::f64::consts::PI, 1000).unwrap().to_string(), "355/113");
+    /// ```
+    pub fn approximate(value: f64, max_denom: i128) -> Option<Self> {
+        if !value.is_finite() || value.abs() >= i128::MAX as f64 || max_denom < 1 {
+            return None;
+        }
+        // Convergents h/k of the continued fraction, starting from 0/1 and 1/0
+        let (mut h0, mut h1, mut k0, mut k1) = (0i128, 1i128, 1i128, 0i128);
+        let mut rest = value;
+        loop {
+            let term = rest.floor();
+            let a = term as i128;
+            let next = |p: i128, q: i128| a.checked_mul(p).and_then(|ap| ap.checked_add(q));
+            let (Some(h2), Some(k2)) = (next(h1, h0), next(k1, k0)) else {
+                break;
+            };
+            if k2 > max_denom {
+                break;
+            }
+            (h0, h1, k0, k1) = (h1, h2, k1, k2);
+            let fraction = rest - term;
+            if fraction.abs() < f64::EPSILON {
+                break;
+            }
+            rest = 1.0 / fraction;
+        }
+        Rational::new(h1, k1)
+    }
+
+    /// Get the value as an `f64`.
+    pub fn to_f64(&self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    /// Add two fractions, returning `None` on overflow.
+    pub fn checked_add(&self, rhs: &Rational) -> Option<Rational> {
+        let numer = self
+            .numer
+            .checked_mul(rhs.denom)?
+            .checked_add(rhs.numer.checked_mul(self.denom)?)?;
+        Rational::new(numer, self.denom.checked_mul(rhs.denom)?)
+    }
+
+    /// Subtract two fractions, returning `None` on overflow.
+    pub fn checked_sub(&self, rhs: &Rational) -> Option<Rational> {
+        self.checked_add(&rhs.checked_neg()?)
+    }
+
+    /// Multiply two fractions, returning `None` on overflow.
+    pub fn checked_mul(&self, rhs: &Rational) -> Option<Rational> {
+        Rational::new(
+            self.numer.checked_mul(rhs.numer)?,
+            self.denom.checked_mul(rhs.denom)?,
+        )
+    }
+
+    /// Divide two fractions, returning `None` on overflow or division by zero.
+    pub fn checked_div(&self, rhs: &Rational) -> Option<Rational> {
+        Rational::new(
+            self.numer.checked_mul(rhs.denom)?,
+            self.denom.checked_mul(rhs.numer)?,
+        )
+    }
+
+    /// Negate the fraction, returning `None` on overflow.
+    pub fn checked_neg(&self) -> Option<Rational> {
+        Some(Rational {
+            numer: self.numer.checked_neg()?,
+            denom: self.denom,
+        })
+    }
+
+    /// Raise the fraction to an integer power, returning `None` on overflow
+    /// or when raising zero to a negative power.
+    pub fn checked_pow(&self, exponent: i32) -> Option<Rational> {
+        let magnitude = exponent.unsigned_abs();
+        let numer = self.numer.checked_pow(magnitude)?;
+        let denom = self.denom.checked_pow(magnitude)?;
+        if exponent < 0 {
+            Rational::new(denom, numer)
+        } else {
+            Rational::new(numer, denom)
+        }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numer, self.denom)
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    // gcd(0, 0) is never needed (denominators are non-zero), but keep division safe
+    i128::try_from(a.max(1)).unwrap_or(1)
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
+
+use crate::types::Number;
+
+/// How a [`Scalar`] type's numbers are held while evaluating, which the
+/// evaluator turns into its number mode so literals are read, arithmetic is
+/// done and results come back in that type's representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Representation {
+    /// Binary floating point, with exact integers where results are whole
+    Float,
+    /// Exact integers and fractions
+    Exact,
+    /// Fixed-precision decimals
+    #[cfg(feature = "decimal")]
+    Decimal,
+}
+
+/// A host numeric type that expressions are evaluated in, that results can
+/// be read as, and that inputs are given in.
+///
+/// Each type names the [`Representation`] numbers are held in through the
+/// whole pipeline: integers compute exactly, `Decimal` in decimals, and
+/// `f64` in floats. `f32` computes in `f64`, there being no narrower float
+/// to compute in, and rounds when read. Integers and `Decimal` read exact
+/// results as they are.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::{Number, Representation, Scalar, Value};
+///
+/// let third = Number::Integer(1) / Number::Integer(3);
+/// assert_eq!(f32::from_number(&third), Some(1.0 / 3.0));
+/// assert_eq!(i64::from_number(&Number::Float(4.0)), Some(4));
+/// assert_eq!(i64::from_number(&third), None);
+/// assert_eq!(i64::REPRESENTATION, Representation::Exact);
+///
+/// assert_eq!(Value::from(2.5).to_scalar::<f32>(None), Ok(2.5));
+/// ```
+pub trait Scalar: Into<Number> + Sized {
+    /// How numbers are held while evaluating for this type.
+    const REPRESENTATION: Representation = Representation::Float;
+
+    /// Read a number as this type, or `None` when it can't be represented
+    /// (a fraction as an integer, or an integer out of range). Floating-point
+    /// types round rather than fail.
+    fn from_number(n: &Number) -> Option<Self>;
+}
+
+impl Scalar for f64 {
+    fn from_number(n: &Number) -> Option<Self> {
+        Some(n.to_f64())
+    }
+}
+
+impl Scalar for f32 {
+    fn from_number(n: &Number) -> Option<Self> {
+        Some(n.to_f64() as f32)
+    }
+}
+
+impl Scalar for i128 {
+    const REPRESENTATION: Representation = Representation::Exact;
+
+    fn from_number(n: &Number) -> Option<Self> {
+        n.as_integer()
+    }
+}
+
+impl Scalar for i64 {
+    const REPRESENTATION: Representation = Representation::Exact;
+
+    fn from_number(n: &Number) -> Option<Self> {
+        n.as_integer().and_then(|i| i64::try_from(i).ok())
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl Scalar for Decimal {
+    const REPRESENTATION: Representation = Representation::Decimal;
+
+    /// Floats convert through their shortest round-trip form, so `0.1`
+    /// reads as exactly `0.1`.
+    fn from_number(n: &Number) -> Option<Self> {
+        n.as_decimal()
+            .or_else(|| Number::decimal_from_f64(n.to_f64())?.as_decimal())
+    }
+}
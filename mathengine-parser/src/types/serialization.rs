@@ -0,0 +1,364 @@
+//! Serde support for the result types, behind the `serde` feature.
+//!
+//! Enums are written the way serde's derive would write them: unit variants
+//! as their name, and other variants as a single-key map from the name to the
+//! contents. Unit values store their unit as written, with any exponent, and
+//! re-derive the dimension when read back. To exchange values with other
+//! tools, wrap them in a [`Versioned`](crate::Versioned) document.
+//!
+//! ```
+//! # #[cfg(feature = "serde")] {
+//! use mathengine_parser::types::{UnitValue, Value};
+//!
+//! let value = Value::List(vec![
+//!     Value::from(3i128),
+//!     Value::UnitValue(UnitValue::new(9.0, "m^2".to_string()).unwrap()),
+//! ]);
+//! let json = serde_json::to_string(&value).unwrap();
+//! assert_eq!(
+//!     json,
+//!     r#"{"List":[{"Number":{"Integer":3}},{"UnitValue":{"value":9.0,"unit":"m^2"}}]}"#
+//! );
+//!
+//! let back: Value = serde_json::from_str(&json).unwrap();
+//! assert_eq!(back.to_string(), "[3, 9m^2]");
+//! # }
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::ser::{SerializeStruct, SerializeStructVariant};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "bigfloat")]
+use crate::types::big_float;
+use crate::types::{DimensionType, Number, Radix, Rational, UnitValue, Value};
+
+// Variant names in declaration order. Compact formats store the index, so
+// variants behind features keep their slot whether or not they're enabled.
+const VALUE_VARIANTS: &[&str] = &["Number", "UnitValue", "Bool", "List", "Radix"];
+const NUMBER_VARIANTS: &[&str] = &["Integer", "Rational", "Decimal", "BigFloat", "Float"];
+const RADIX_VARIANTS: &[&str] = &["Binary", "Octal", "Hexadecimal"];
+const DIMENSION_VARIANTS: &[&str] = &["Length", "Temperature", "Time", "Frequency", "Mass", "Derived", "Unknown"];
+
+const UNIT_VALUE_FIELDS: &[&str] = &["value", "unit"];
+const RATIONAL_FIELDS: &[&str] = &["numer", "denom"];
+const RADIX_VALUE_FIELDS: &[&str] = &["value", "radix"];
+#[cfg(feature = "bigfloat")]
+const BIG_FLOAT_FIELDS: &[&str] = &["value", "precision"];
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Number(n) => serializer.serialize_newtype_variant("Value", 0, "Number", n),
+            Value::UnitValue(uv) => {
+                serializer.serialize_newtype_variant("Value", 1, "UnitValue", uv)
+            }
+            Value::Bool(b) => serializer.serialize_newtype_variant("Value", 2, "Bool", b),
+            Value::List(items) => serializer.serialize_newtype_variant("Value", 3, "List", items),
+            Value::Radix { value, radix } => {
+                let mut variant = serializer.serialize_struct_variant("Value", 4, "Radix", 2)?;
+                variant.serialize_field("value", value)?;
+                variant.serialize_field("radix", radix)?;
+                variant.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a mathengine value")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+                let (index, variant) = data.variant_seed(VariantIndex(VALUE_VARIANTS))?;
+                match index {
+                    0 => variant.newtype_variant().map(Value::Number),
+                    1 => variant.newtype_variant().map(Value::UnitValue),
+                    2 => variant.newtype_variant().map(Value::Bool),
+                    3 => variant.newtype_variant().map(Value::List),
+                    _ => {
+                        let (value, radix) =
+                            variant.struct_variant(RADIX_VALUE_FIELDS, Pair::new(RADIX_VALUE_FIELDS))?;
+                        Ok(Value::Radix { value, radix })
+                    }
+                }
+            }
+        }
+
+        deserializer.deserialize_enum("Value", VALUE_VARIANTS, ValueVisitor)
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Number::Integer(i) => serializer.serialize_newtype_variant("Number", 0, "Integer", i),
+            Number::Rational(r) => serializer.serialize_newtype_variant("Number", 1, "Rational", r),
+            // Decimals are written as strings, which every format keeps exactly
+            #[cfg(feature = "decimal")]
+            Number::Decimal(d) => {
+                serializer.serialize_newtype_variant("Number", 2, "Decimal", &d.to_string())
+            }
+            #[cfg(feature = "bigfloat")]
+            Number::BigFloat(b) => {
+                let mut variant = serializer.serialize_struct_variant("Number", 3, "BigFloat", 2)?;
+                variant.serialize_field("value", &big_float::to_decimal_string(b))?;
+                variant.serialize_field("precision", &b.precision())?;
+                variant.end()
+            }
+            Number::Float(x) => serializer.serialize_newtype_variant("Number", 4, "Float", x),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl<'de> Visitor<'de> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a number")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Number, A::Error> {
+                let (index, variant) = data.variant_seed(VariantIndex(NUMBER_VARIANTS))?;
+                match index {
+                    0 => variant.newtype_variant().map(Number::Integer),
+                    1 => variant.newtype_variant().map(Number::Rational),
+                    #[cfg(feature = "decimal")]
+                    2 => {
+                        let literal: String = variant.newtype_variant()?;
+                        literal.parse().map(Number::Decimal).map_err(|_| {
+                            de::Error::invalid_value(de::Unexpected::Str(&literal), &"a decimal")
+                        })
+                    }
+                    #[cfg(feature = "bigfloat")]
+                    3 => {
+                        let (literal, precision): (String, usize) =
+                            variant.struct_variant(BIG_FLOAT_FIELDS, Pair::new(BIG_FLOAT_FIELDS))?;
+                        big_float::parse(&literal, precision).map(Number::BigFloat).ok_or_else(|| {
+                            de::Error::invalid_value(de::Unexpected::Str(&literal), &"a decimal number")
+                        })
+                    }
+                    4 => variant.newtype_variant().map(Number::Float),
+                    _ => Err(de::Error::custom(format!(
+                        "numbers of type `{}` need the `{}` feature",
+                        NUMBER_VARIANTS[index],
+                        NUMBER_VARIANTS[index].to_lowercase()
+                    ))),
+                }
+            }
+        }
+
+        deserializer.deserialize_enum("Number", NUMBER_VARIANTS, NumberVisitor)
+    }
+}
+
+impl Serialize for Rational {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Rational", 2)?;
+        state.serialize_field("numer", &self.numer())?;
+        state.serialize_field("denom", &self.denom())?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Rational {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (numer, denom): (i128, i128) =
+            deserializer.deserialize_struct("Rational", RATIONAL_FIELDS, Pair::new(RATIONAL_FIELDS))?;
+        Rational::new(numer, denom).ok_or_else(|| de::Error::custom("denominator must not be zero"))
+    }
+}
+
+impl Serialize for UnitValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let unit = match self.exponent() {
+            1 => self.unit().to_string(),
+            exponent => format!("{}^{}", self.unit(), exponent),
+        };
+        let mut state = serializer.serialize_struct("UnitValue", 2)?;
+        state.serialize_field("value", &self.value())?;
+        state.serialize_field("unit", &unit)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for UnitValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (value, unit): (f64, String) = deserializer.deserialize_struct(
+            "UnitValue",
+            UNIT_VALUE_FIELDS,
+            Pair::new(UNIT_VALUE_FIELDS),
+        )?;
+        // Units outside the tables were vouched for when the value was made
+        Ok(UnitValue::named(value, unit))
+    }
+}
+
+impl Serialize for Radix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = match self {
+            Radix::Binary => 0,
+            Radix::Octal => 1,
+            Radix::Hexadecimal => 2,
+        };
+        serializer.serialize_unit_variant("Radix", index, RADIX_VARIANTS[index as usize])
+    }
+}
+
+impl<'de> Deserialize<'de> for Radix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = deserializer.deserialize_enum("Radix", RADIX_VARIANTS, UnitVariant(RADIX_VARIANTS))?;
+        Ok([Radix::Binary, Radix::Octal, Radix::Hexadecimal][index])
+    }
+}
+
+impl Serialize for DimensionType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = match self {
+            DimensionType::Length => 0,
+            DimensionType::Temperature => 1,
+            DimensionType::Time => 2,
+            DimensionType::Frequency => 3,
+            DimensionType::Mass => 4,
+            DimensionType::Derived => 5,
+            DimensionType::Unknown => 6,
+        };
+        serializer.serialize_unit_variant("DimensionType", index, DIMENSION_VARIANTS[index as usize])
+    }
+}
+
+impl<'de> Deserialize<'de> for DimensionType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = deserializer.deserialize_enum(
+            "DimensionType",
+            DIMENSION_VARIANTS,
+            UnitVariant(DIMENSION_VARIANTS),
+        )?;
+        Ok([
+            DimensionType::Length,
+            DimensionType::Temperature,
+            DimensionType::Time,
+            DimensionType::Frequency,
+            DimensionType::Mass,
+            DimensionType::Derived,
+            DimensionType::Unknown,
+        ][index])
+    }
+}
+
+// Reads a variant identifier, given by name or (in compact formats) by index
+pub(crate) struct VariantIndex(pub(crate) &'static [&'static str]);
+
+impl<'de> DeserializeSeed<'de> for VariantIndex {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for VariantIndex {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "one of {:?}", self.0)
+    }
+
+    fn visit_u64<E: de::Error>(self, index: u64) -> Result<usize, E> {
+        match usize::try_from(index) {
+            Ok(index) if index < self.0.len() => Ok(index),
+            _ => Err(de::Error::invalid_value(de::Unexpected::Unsigned(index), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<usize, E> {
+        self.0
+            .iter()
+            .position(|variant| *variant == name)
+            .ok_or_else(|| de::Error::unknown_variant(name, self.0))
+    }
+}
+
+// Reads an enum whose variants carry no data, returning the variant's index
+pub(crate) struct UnitVariant(pub(crate) &'static [&'static str]);
+
+impl<'de> Visitor<'de> for UnitVariant {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "one of {:?}", self.0)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<usize, A::Error> {
+        let (index, variant) = data.variant_seed(VariantIndex(self.0))?;
+        variant.unit_variant()?;
+        Ok(index)
+    }
+}
+
+// Reads a struct with two fields, from a map or (in compact formats) a
+// sequence. Fields it doesn't know are skipped, as they may be from a newer
+// version of the format
+pub(crate) struct Pair<A, B> {
+    fields: &'static [&'static str],
+    marker: PhantomData<(A, B)>,
+}
+
+impl<A, B> Pair<A, B> {
+    pub(crate) fn new(fields: &'static [&'static str]) -> Self {
+        Pair {
+            fields,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, A: Deserialize<'de>, B: Deserialize<'de>> Visitor<'de> for Pair<A, B> {
+    type Value = (A, B);
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a struct with fields `{}` and `{}`", self.fields[0], self.fields[1])
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<(A, B), S::Error> {
+        let first = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let second = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok((first, second))
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<(A, B), M::Error> {
+        let (mut first, mut second) = (None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.fields[0] && first.is_none() {
+                first = Some(map.next_value()?);
+            } else if key == self.fields[1] && second.is_none() {
+                second = Some(map.next_value()?);
+            } else if self.fields.contains(&key.as_str()) {
+                return Err(de::Error::custom(format!("duplicate field `{}`", key)));
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        let first = first.ok_or_else(|| de::Error::missing_field(self.fields[0]))?;
+        let second = second.ok_or_else(|| de::Error::missing_field(self.fields[1]))?;
+        Ok((first, second))
+    }
+}
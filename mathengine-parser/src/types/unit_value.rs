@@ -1,34 +1,157 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
-use crate::types::{ConversionError, DimensionType, Number};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "units")]
+use mathengine_units::time::TimeUnit;
+use crate::types::{ConversionError, DimensionType, Number, Unit, Value};
 
 /// Represents a value with an associated unit (e.g., "5 meters", "32 fahrenheit").
 ///
 /// UnitValues automatically track their dimension type (Length, Temperature, etc.)
 /// and support arithmetic operations with automatic unit conversion to base units.
+///
+/// Units this crate knows are stored as a [`Unit`], parsed once when the value
+/// is created. Units from outside the unit tables (a currency code, dice like
+/// `3d6`) can only be made with [`UnitValue::named`], are kept by name and
+/// have an [`Unknown`](DimensionType::Unknown) dimension.
+///
+/// Known units of different dimensions combine into a derived unit, like
+/// `km/h` or `m/s^2`, whose dimension is worked out from theirs. Any two
+/// derived units made of the same dimensions convert to each other.
 #[derive(Debug, Clone)]
 pub struct UnitValue {
     value: f64,
-    unit: String,
-    dimension: DimensionType,
+    // The value as a number kept exact, like an amount of money in decimal
+    // mode, that `value` is the nearest float to
+    exact: Option<Number>,
+    unit: UnitKind,
+    exponent: i32,
+}
+
+/// What a unit value measures, for presenting it without re-parsing its
+/// unit: a UI might pick an icon by `dimension` and print `unit`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitInfo {
+    /// The dimension the unit belongs to, or `Unknown`
+    pub dimension: DimensionType,
+    /// The power the unit is raised to (2 for an area in `m^2`)
+    pub exponent: i32,
+    /// The canonical unit name, with any exponent (`"m^2"`)
+    pub unit: String,
+}
+
+// Names are shared, so copying a value in a unit like `usd` doesn't copy
+// the name
+#[derive(Debug, Clone, PartialEq)]
+enum UnitKind {
+    Known(Unit),
+    // A unit outside the tables, which whoever made the value vouched for
+    Named(Arc<str>),
+    // Known units of different linear dimensions multiplied together, each
+    // raised to its power, with the name they're written as. The value's
+    // exponent is always one
+    Derived { units: Arc<[(Unit, i32)]>, name: Arc<str> },
+}
+
+impl UnitKind {
+    fn derived(units: Vec<(Unit, i32)>) -> UnitKind {
+        let name = derived_name(&units).into();
+        UnitKind::Derived { units: units.into(), name }
+    }
 }
 
 impl UnitValue {
     /// Create a new UnitValue with the given value and unit string.
     ///
+    /// The unit can be a `String`, a `&str` or an `Arc<str>`, and must be one
+    /// this crate knows, optionally raised to a power or combined into a
+    /// derived unit.
+    ///
     /// # Examples
     ///
     /// ```
-    /// use mathengine_parser::types::UnitValue;
+    /// use mathengine_parser::types::{ConversionError, UnitValue};
+    ///
+    /// let length = UnitValue::new(5.0, "meters".to_string()).unwrap();
+    /// let temp = UnitValue::new(32.0, "F".to_string()).unwrap();
+    /// let area = UnitValue::new(9.0, "m^2".to_string()).unwrap();
+    /// assert_eq!(area.exponent(), 2);
+    /// let speed = UnitValue::new(50.0, "kilometers/hour").unwrap();
+    /// assert_eq!(speed.to_string(), "50km/h");
+    /// assert_eq!(UnitValue::new(1.0, "usd").unwrap_err(), ConversionError::UnknownUnit("usd".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConversionError::UnknownUnit`] for a unit outside the unit
+    /// tables. Those can be made with [`named`](UnitValue::named).
+    pub fn new(value: f64, unit: impl Into<Arc<str>>) -> Result<Self, ConversionError> {
+        let value = Self::named(value, unit);
+        match &value.unit {
+            UnitKind::Named(name) => Err(ConversionError::UnknownUnit(name.to_string())),
+            _ => Ok(value),
+        }
+    }
+
+    /// Create a UnitValue in a unit that may be outside the unit tables,
+    /// like a currency code, a unit of a dimension the host provides, or
+    /// dice like `d6`.
+    ///
+    /// Units the tables know are parsed as [`new`](UnitValue::new) would.
+    /// Any other name is kept as given, so checking that it belongs to a
+    /// currency or dimension the caller supports is up to the caller. An
+    /// `Arc<str>` without an exponent is shared rather than copied.
+    ///
+    /// # Examples
     ///
-    /// let length = UnitValue::new(5.0, "meters".to_string());
-    /// let temp = UnitValue::new(32.0, "F".to_string());
     /// ```
-    pub fn new(value: f64, unit: String) -> Self {
-        let dimension = DimensionType::from_unit(&unit);
+    /// use mathengine_parser::types::{DimensionType, UnitValue};
+    ///
+    /// let price = UnitValue::named(3.0, "usd");
+    /// assert_eq!(price.dimension(), DimensionType::Unknown);
+    /// assert_eq!(UnitValue::named(2.0, "km").dimension(), DimensionType::Length);
+    /// ```
+    pub fn named(value: f64, unit: impl Into<Arc<str>>) -> Self {
+        let unit = unit.into();
+        if let Some(units) = parse_derived(&unit) {
+            return Self {
+                value,
+                exact: None,
+                unit: UnitKind::derived(units),
+                exponent: 1,
+            };
+        }
+        let (base, exponent) = split_exponent(&unit);
+        let kind = match Unit::parse(base) {
+            Ok(known) => UnitKind::Known(known),
+            Err(_) if base.len() == unit.len() => UnitKind::Named(unit.clone()),
+            Err(_) => UnitKind::Named(base.into()),
+        };
         Self {
             value,
-            unit,
-            dimension,
+            exact: None,
+            unit: kind,
+            exponent,
+        }
+    }
+
+    /// Create a UnitValue in an already parsed unit, raised to `exponent`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Unit, UnitValue};
+    ///
+    /// let meter = Unit::parse("m").unwrap();
+    /// assert_eq!(UnitValue::from_unit(4.0, meter, 2).to_string(), "4m^2");
+    /// ```
+    pub fn from_unit(value: f64, unit: Unit, exponent: i32) -> Self {
+        Self {
+            value,
+            exact: None,
+            unit: UnitKind::Known(unit),
+            exponent,
         }
     }
 
@@ -37,14 +160,219 @@ impl UnitValue {
         self.value
     }
 
-    /// Get the unit string.
+    /// A value in the same unit (and with the same exponent) as this one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let area = UnitValue::new(9.5, "m^2".to_string()).unwrap();
+    /// assert_eq!(area.with_value(10.0).to_string(), "10m^2");
+    /// ```
+    pub fn with_value(&self, value: f64) -> UnitValue {
+        UnitValue {
+            value,
+            exact: None,
+            ..self.clone()
+        }
+    }
+
+    /// The value as its exact number, if it's kept as one. See
+    /// [`with_exact`](UnitValue::with_exact).
+    pub fn exact(&self) -> Option<&Number> {
+        self.exact.as_ref()
+    }
+
+    /// The value as a [`Number`]: the exact one if it's kept, or else the
+    /// float.
+    pub fn amount(&self) -> Number {
+        self.exact.clone().unwrap_or(Number::Float(self.value))
+    }
+
+    /// A value of exactly `amount`, in the same unit (and with the same
+    /// exponent) as this one.
+    ///
+    /// Adding and subtracting values in the same unit outside the unit
+    /// tables, and scaling by a number, keep the amount exact when every
+    /// operand is. Anything in a float, like converting between units or
+    /// [`map`](UnitValue::map), makes a value without one. A float amount
+    /// isn't kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Number, UnitValue};
+    ///
+    /// let tenth = Number::rational_from_digits("0.1").unwrap();
+    /// let dime = UnitValue::named(0.0, "usd").with_exact(tenth);
+    /// let sum = dime.checked_add(&dime).unwrap().checked_add(&dime).unwrap();
+    /// assert!(matches!(sum.exact(), Some(Number::Rational(_))));
+    /// assert_eq!(sum.to_string(), "0.3usd");
+    ///
+    /// let floats = UnitValue::named(0.1, "usd");
+    /// assert_eq!(floats.checked_add(&floats).unwrap().checked_add(&floats).unwrap().value(), 0.30000000000000004);
+    /// ```
+    pub fn with_exact(&self, amount: Number) -> UnitValue {
+        match amount {
+            Number::Float(value) => self.with_value(value),
+            exact => UnitValue {
+                value: exact.to_f64(),
+                exact: Some(exact),
+                ..self.clone()
+            },
+        }
+    }
+
+    // This value with `exact` as its amount, if there is one
+    fn keeping(self, exact: Option<Number>) -> UnitValue {
+        match exact {
+            Some(exact) => self.with_exact(exact),
+            None => self,
+        }
+    }
+
+    /// Apply `f` to the numeric value, keeping the unit and exponent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let length = UnitValue::new(2.5, "ft".to_string()).unwrap();
+    /// assert_eq!(length.map(|v| v * 4.0).to_string(), "10ft");
+    /// ```
+    pub fn map(&self, f: impl FnOnce(f64) -> f64) -> UnitValue {
+        self.with_value(f(self.value))
+    }
+
+    /// The absolute value, in the same unit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let offset = UnitValue::new(-3.0, "cm".to_string()).unwrap();
+    /// assert_eq!(offset.abs().to_string(), "3cm");
+    /// ```
+    pub fn abs(&self) -> UnitValue {
+        self.map(f64::abs)
+    }
+
+    /// The sign of the value, as `1` or `-1` in the same unit (following
+    /// [`f64::signum`], zero counts as positive).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let offset = UnitValue::new(-3.0, "cm".to_string()).unwrap();
+    /// assert_eq!(offset.signum().to_string(), "-1cm");
+    /// ```
+    pub fn signum(&self) -> UnitValue {
+        self.map(f64::signum)
+    }
+
+    /// Get the unit string, without any exponent.
+    ///
+    /// Known units are given by their canonical name ("m" for a value created
+    /// in "meters"); other units as written.
     pub fn unit(&self) -> &str {
-        &self.unit
+        match &self.unit {
+            UnitKind::Known(unit) => unit.canonical_string(),
+            UnitKind::Named(name) | UnitKind::Derived { name, .. } => name,
+        }
+    }
+
+    /// Get the parsed unit, or `None` for a unit this crate doesn't know.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Unit, UnitValue};
+    ///
+    /// let length = UnitValue::new(5.0, "feet".to_string()).unwrap();
+    /// assert_eq!(length.known_unit(), Unit::parse("ft").ok());
+    /// assert_eq!(UnitValue::named(5.0, "usd").known_unit(), None);
+    /// ```
+    pub fn known_unit(&self) -> Option<Unit> {
+        match self.unit {
+            UnitKind::Known(unit) => Some(unit),
+            UnitKind::Named(_) | UnitKind::Derived { .. } => None,
+        }
+    }
+
+    /// Get the exponent applied to the unit (2 for "m^2", 1 for plain "m").
+    pub fn exponent(&self) -> i32 {
+        self.exponent
     }
 
     /// Get the dimension type of this unit value.
     pub fn dimension(&self) -> DimensionType {
-        self.dimension
+        match self.unit {
+            UnitKind::Known(unit) => unit.dimension_type(),
+            UnitKind::Derived { .. } => DimensionType::Derived,
+            UnitKind::Named(_) => DimensionType::Unknown,
+        }
+    }
+
+    /// The dimensions the unit is made of, with the power of each, in the
+    /// order [`DimensionType`] lists them. Values with the same signature
+    /// measure the same kind of thing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{DimensionType, UnitValue};
+    ///
+    /// let acceleration = UnitValue::new(9.8, "m/s^2".to_string()).unwrap();
+    /// assert_eq!(
+    ///     acceleration.signature(),
+    ///     [(DimensionType::Length, 1), (DimensionType::Time, -2)]
+    /// );
+    /// assert_eq!(UnitValue::new(4.0, "ft^2").unwrap().signature(), [(DimensionType::Length, 2)]);
+    /// ```
+    pub fn signature(&self) -> Vec<(DimensionType, i32)> {
+        match self.units() {
+            Some(units) => {
+                let mut signature: Vec<(DimensionType, i32)> =
+                    units.iter().map(|(unit, exponent)| (unit.dimension_type(), *exponent)).collect();
+                signature.sort_by_key(|(dimension, _)| *dimension as u8);
+                signature
+            }
+            None => vec![(DimensionType::Unknown, self.exponent)],
+        }
+    }
+
+    // The known units the value is in, with their powers
+    fn units(&self) -> Option<Vec<(Unit, i32)>> {
+        match &self.unit {
+            UnitKind::Known(unit) => Some(vec![(*unit, self.exponent)]),
+            UnitKind::Derived { units, .. } => Some(units.to_vec()),
+            UnitKind::Named(_) => None,
+        }
+    }
+
+    /// The dimension, exponent and canonical unit together.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{DimensionType, UnitValue};
+    ///
+    /// let info = UnitValue::new(9.0, "feet^2".to_string()).unwrap().info();
+    /// assert_eq!(info.dimension, DimensionType::Length);
+    /// assert_eq!(info.exponent, 2);
+    /// assert_eq!(info.unit, "ft^2");
+    /// ```
+    pub fn info(&self) -> UnitInfo {
+        UnitInfo {
+            dimension: self.dimension(),
+            exponent: self.exponent,
+            unit: self.canonical_unit_name(),
+        }
     }
 
     /// Get the canonical unit name for display purposes.
@@ -53,33 +381,18 @@ impl UnitValue {
     /// - "meters" → "m"
     /// - "inches" → "in"
     /// - "celsius" → "C"
+    /// - "meters^2" → "m^2"
     ///
     /// # Examples
     ///
     /// ```
     /// use mathengine_parser::types::UnitValue;
     ///
-    /// let length = UnitValue::new(5.0, "meters".to_string());
+    /// let length = UnitValue::new(5.0, "meters".to_string()).unwrap();
     /// assert_eq!(length.canonical_unit_name(), "m");
     /// ```
     pub fn canonical_unit_name(&self) -> String {
-        self.dimension.parse_unit_str(&self.unit)
-            .ok()
-            .and_then(|unit| self.dimension.canonical_string(&unit).map(|s| s.to_string()))
-            .unwrap_or_else(|| self.unit.clone())
-    }
-
-    /// Convert this unit value to base units for its dimension
-    fn to_base_value(&self) -> f64 {
-        self.dimension.parse_unit_str(&self.unit)
-            .ok()
-            .and_then(|unit| self.dimension.to_base_value(&unit, self.value))
-            .unwrap_or(self.value)
-    }
-
-    /// Get the base unit string for this dimension
-    fn base_unit(&self) -> String {
-        self.dimension.base_unit_string().to_string()
+        with_exponent(self.unit(), self.exponent)
     }
 
     /// Convert this unit value to another unit
@@ -89,29 +402,76 @@ impl UnitValue {
     /// ```
     /// use mathengine_parser::types::UnitValue;
     ///
-    /// let length = UnitValue::new(100.0, "cm".to_string());
+    /// let length = UnitValue::new(100.0, "cm".to_string()).unwrap();
     /// let in_meters = length.convert_to("m").unwrap();
     /// assert_eq!(in_meters.value(), 1.0);
     /// assert_eq!(in_meters.unit(), "m");
+    ///
+    /// // Frequencies and periods convert to each other
+    /// let period = UnitValue::new(2.5, "Hz".to_string()).unwrap().convert_to("ms").unwrap();
+    /// assert_eq!(period.value(), 400.0);
     /// ```
     pub fn convert_to(&self, target_unit: &str) -> Result<UnitValue, ConversionError> {
-        // Check if target is same dimension
-        let target_dimension = DimensionType::from_unit(target_unit);
-        if target_dimension != self.dimension || target_dimension == DimensionType::Unknown {
-            return Err(ConversionError::CrossDimension);
+        let target = UnitValue::named(1.0, target_unit);
+        if self.dimension() == DimensionType::Derived || target.dimension() == DimensionType::Derived {
+            return self.convert_derived(&target);
         }
 
-        // Parse both units
-        let from_unit = self.dimension.parse_unit_str(&self.unit)
-            .map_err(|_| ConversionError::UnknownUnit(self.unit.clone()))?;
-        let to_unit = self.dimension.parse_unit_str(target_unit)
-            .map_err(|_| ConversionError::UnknownUnit(target_unit.to_string()))?;
+        // Check if target is same dimension (and raised to the same power)
+        let (target_base, target_exponent) = split_exponent(target_unit);
+        let (Some(from_unit), Ok(to_unit)) = (self.known_unit(), Unit::parse(target_base)) else {
+            return Err(ConversionError::CrossDimension);
+        };
+        if self.exponent == 1
+            && target_exponent == 1
+            && from_unit.dimension_type().reciprocal() == Some(to_unit.dimension_type())
+        {
+            return self.convert_reciprocal(&from_unit, to_unit);
+        }
+        if to_unit.dimension_type() != from_unit.dimension_type() || target_exponent != self.exponent {
+            return Err(ConversionError::CrossDimension);
+        }
 
         // Convert the value
-        let new_value = self.dimension.convert_value(&from_unit, &to_unit, self.value)
+        let new_value = self
+            .dimension()
+            .convert_value_pow(&from_unit, &to_unit, self.value, self.exponent)
             .ok_or(ConversionError::Failed)?;
 
-        Ok(UnitValue::new(new_value, target_unit.to_string()))
+        Ok(UnitValue::from_unit(new_value, to_unit, self.exponent))
+    }
+
+    // Between derived units made of the same dimensions, like `mi/h` and
+    // `m/s`, by way of their base units
+    fn convert_derived(&self, target: &UnitValue) -> Result<UnitValue, ConversionError> {
+        let (Some(from), Some(to)) = (self.units(), target.units()) else {
+            return Err(ConversionError::CrossDimension);
+        };
+        if self.signature() != target.signature() {
+            return Err(ConversionError::CrossDimension);
+        }
+        let (Some(from), Some(to)) = (base_factor(&from), base_factor(&to)) else {
+            return Err(ConversionError::Failed);
+        };
+        Ok(target.with_value(self.value * from / to))
+    }
+
+    // Between dimensions whose base units are reciprocals, like `20ms` and
+    // `50Hz`, by way of the base units
+    fn convert_reciprocal(&self, from_unit: &Unit, to_unit: Unit) -> Result<UnitValue, ConversionError> {
+        let to_dimension = to_unit.dimension_type();
+        let base = self
+            .dimension()
+            .to_base_value(from_unit, self.value)
+            .ok_or(ConversionError::Failed)?;
+        if base == 0.0 {
+            return Err(ConversionError::Failed);
+        }
+        let new_value = to_dimension
+            .base_unit()
+            .and_then(|to_base| to_dimension.convert_value(&to_base, &to_unit, 1.0 / base))
+            .ok_or(ConversionError::Failed)?;
+        Ok(UnitValue::from_unit(new_value, to_unit, 1))
     }
 
     /// Check if this unit value can be converted to another unit
@@ -121,39 +481,127 @@ impl UnitValue {
     /// ```
     /// use mathengine_parser::types::UnitValue;
     ///
-    /// let length = UnitValue::new(5.0, "m".to_string());
+    /// let length = UnitValue::new(5.0, "m".to_string()).unwrap();
     /// assert!(length.can_convert_to("cm"));
     /// assert!(!length.can_convert_to("C"));
+    ///
+    /// let speed = UnitValue::new(60.0, "mi/h".to_string()).unwrap();
+    /// assert!(speed.can_convert_to("m/s"));
+    /// assert!(!speed.can_convert_to("m/s^2"));
     /// ```
     pub fn can_convert_to(&self, target_unit: &str) -> bool {
-        let target_dimension = DimensionType::from_unit(target_unit);
-        target_dimension == self.dimension && target_dimension != DimensionType::Unknown
+        let target = UnitValue::named(1.0, target_unit);
+        if self.dimension() == DimensionType::Derived || target.dimension() == DimensionType::Derived {
+            return self.signature() == target.signature();
+        }
+        let (target_base, target_exponent) = split_exponent(target_unit);
+        let target_dimension = DimensionType::from_unit(target_base);
+        if self.exponent == 1 && target_exponent == 1 && self.dimension().reciprocal() == Some(target_dimension) {
+            return true;
+        }
+        target_dimension == self.dimension()
+            && target_dimension != DimensionType::Unknown
+            && target_exponent == self.exponent
     }
 
     /// Convert this unit value to base units for its dimension
     ///
+    /// Values in a unit this crate doesn't know are returned unchanged.
+    ///
     /// # Examples
     ///
     /// ```
     /// use mathengine_parser::types::UnitValue;
     ///
-    /// let length = UnitValue::new(100.0, "cm".to_string());
+    /// let length = UnitValue::new(100.0, "cm".to_string()).unwrap();
     /// let in_base = length.in_base_units();
     /// assert_eq!(in_base.value(), 1.0);
     /// assert_eq!(in_base.unit(), "m");
     /// ```
     pub fn in_base_units(&self) -> UnitValue {
-        let base_unit_str = self.base_unit();
-        // If we're already in base units, return a copy
-        if self.canonical_unit_name() == base_unit_str {
-            UnitValue::new(self.value, base_unit_str)
-        } else {
-            // Convert to base units
-            self.convert_to(&base_unit_str).unwrap_or_else(|_| {
-                // Fallback: just return with base unit string
-                UnitValue::new(self.to_base_value(), base_unit_str)
-            })
+        if let UnitKind::Derived { units, .. } = &self.unit {
+            let Some(factor) = base_factor(units) else {
+                return self.clone();
+            };
+            let base = units
+                .iter()
+                .map(|(unit, exponent)| Some((unit.dimension_type().base_unit()?, *exponent)))
+                .collect::<Option<Vec<_>>>();
+            return match base {
+                Some(base) => UnitValue {
+                    value: self.value * factor,
+                    exact: None,
+                    unit: UnitKind::derived(base),
+                    exponent: 1,
+                },
+                None => self.clone(),
+            };
+        }
+        let dimension = self.dimension();
+        let (Some(unit), Some(base)) = (self.known_unit(), dimension.base_unit()) else {
+            return self.clone();
+        };
+        if unit == base {
+            return self.clone();
         }
+        let value = dimension
+            .to_base_value_pow(&unit, self.value, self.exponent)
+            .unwrap_or(self.value);
+        UnitValue::from_unit(value, base, self.exponent)
+    }
+
+    /// Whether this and `other` are equal to within `epsilon` once both are
+    /// converted to `unit`. Values that don't convert to `unit` are never
+    /// equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let foot = UnitValue::new(1.0, "ft".to_string()).unwrap();
+    /// let inches = UnitValue::new(12.1, "in".to_string()).unwrap();
+    /// assert!(foot.approx_eq_in(&inches, "cm", 0.5));
+    /// assert!(!foot.approx_eq_in(&inches, "mm", 1.0));
+    /// assert!(!foot.approx_eq_in(&inches, "C", 100.0));
+    /// ```
+    pub fn approx_eq_in(&self, other: &UnitValue, unit: &str, epsilon: f64) -> bool {
+        match (self.value_in(unit), other.value_in(unit)) {
+            (Ok(l), Ok(r)) => (l - r).abs() <= epsilon,
+            _ => false,
+        }
+    }
+
+    // This value's amount in `unit`, which may be an unknown unit it's
+    // already in
+    fn value_in(&self, unit: &str) -> Result<f64, ConversionError> {
+        if self.canonical_unit_name() == unit {
+            return Ok(self.value);
+        }
+        self.convert_to(unit).map(|converted| converted.value)
+    }
+
+    /// Convert a time to a [`Duration`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let time = UnitValue::new(1.5, "min".to_string()).unwrap();
+    /// assert_eq!(time.to_duration(), Ok(Duration::from_secs(90)));
+    /// assert_eq!(UnitValue::from(Duration::from_millis(250)).to_string(), "0.25s");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConversionError::CrossDimension`] for values that aren't
+    /// times, and [`ConversionError::Failed`] for negative times and ones too
+    /// large for a `Duration`.
+    pub fn to_duration(&self) -> Result<Duration, ConversionError> {
+        let seconds = self.value_in("s")?;
+        Duration::try_from_secs_f64(seconds).map_err(|_| ConversionError::Failed)
     }
 
     /// Check if this unit value is in the same dimension as another
@@ -163,39 +611,481 @@ impl UnitValue {
     /// ```
     /// use mathengine_parser::types::UnitValue;
     ///
-    /// let length1 = UnitValue::new(5.0, "m".to_string());
-    /// let length2 = UnitValue::new(100.0, "cm".to_string());
-    /// let temp = UnitValue::new(25.0, "C".to_string());
+    /// let length1 = UnitValue::new(5.0, "m".to_string()).unwrap();
+    /// let length2 = UnitValue::new(100.0, "cm".to_string()).unwrap();
+    /// let temp = UnitValue::new(25.0, "C".to_string()).unwrap();
     ///
     /// assert!(length1.same_dimension_as(&length2));
     /// assert!(!length1.same_dimension_as(&temp));
     /// ```
     pub fn same_dimension_as(&self, other: &UnitValue) -> bool {
-        self.dimension == other.dimension && self.dimension != DimensionType::Unknown
+        self.dimension() != DimensionType::Unknown && self.signature() == other.signature()
+    }
+
+    /// Raise this unit value to an integer power, multiplying the unit exponent.
+    ///
+    /// Returns `None` when the exponents cancel out (e.g. `m^0`), since the
+    /// result is then a plain number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let side = UnitValue::new(3.0, "m".to_string()).unwrap();
+    /// let area = side.powi(2).unwrap();
+    /// assert_eq!(area.to_string(), "9m^2");
+    /// ```
+    pub fn powi(&self, power: i32) -> Option<UnitValue> {
+        if let UnitKind::Derived { units, .. } = &self.unit {
+            if power == 0 {
+                return None;
+            }
+            let units = units.iter().map(|(unit, exponent)| (*unit, exponent * power)).collect();
+            return Some(UnitValue {
+                value: self.value.powi(power),
+                exact: None,
+                unit: UnitKind::derived(units),
+                exponent: 1,
+            });
+        }
+        let exponent = self.exponent * power;
+        if exponent == 0 {
+            return None;
+        }
+
+        Some(UnitValue {
+            value: self.value.powi(power),
+            exact: None,
+            unit: self.unit.clone(),
+            exponent,
+        })
+    }
+
+    /// Take the square root, halving the unit exponent.
+    ///
+    /// Returns `None` when the exponent is odd (e.g. `sqrt(5 m^3)`), since the
+    /// result can't be expressed as an integer power of the unit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let area = UnitValue::new(9.0, "m^2".to_string()).unwrap();
+    /// assert_eq!(area.sqrt().unwrap().to_string(), "3m");
+    /// ```
+    pub fn sqrt(&self) -> Option<UnitValue> {
+        if let UnitKind::Derived { units, .. } = &self.unit {
+            if units.iter().any(|(_, exponent)| exponent % 2 != 0) {
+                return None;
+            }
+            let units = units.iter().map(|(unit, exponent)| (*unit, exponent / 2)).collect();
+            return Some(UnitValue {
+                value: self.value.sqrt(),
+                exact: None,
+                unit: UnitKind::derived(units),
+                exponent: 1,
+            });
+        }
+        if self.exponent % 2 != 0 {
+            return None;
+        }
+
+        Some(UnitValue {
+            value: self.value.sqrt(),
+            exact: None,
+            unit: self.unit.clone(),
+            exponent: self.exponent / 2,
+        })
+    }
+
+    /// Multiply two unit values of the same dimension, adding their exponents.
+    ///
+    /// Both operands are converted to base units first. Returns `None` when the
+    /// dimensions differ, and a plain number when the exponents cancel out.
+    pub(crate) fn mul_same_dimension(&self, other: &UnitValue) -> Option<Value> {
+        self.combine_same_dimension(other, other.exponent, |l, r| l * r)
+    }
+
+    /// Divide two unit values of the same dimension, subtracting their exponents.
+    pub(crate) fn div_same_dimension(&self, other: &UnitValue) -> Option<Value> {
+        self.combine_same_dimension(other, -other.exponent, |l, r| l / r)
+    }
+
+    /// Multiply two unit values across dimensions, making a derived unit
+    /// like `km*h`. Dimensions both have stay in this value's unit.
+    ///
+    /// Returns `None` for dimensions that aren't a pure scale factor, like
+    /// temperature, and for units this crate doesn't know.
+    pub(crate) fn mul_derived(&self, other: &UnitValue) -> Option<Value> {
+        self.combine_derived(other, 1)
+    }
+
+    /// Divide across dimensions, making a derived unit like `km/h`.
+    pub(crate) fn div_derived(&self, other: &UnitValue) -> Option<Value> {
+        self.combine_derived(other, -1)
+    }
+
+    /// Add two unit values, or explain why they can't be added.
+    ///
+    /// Unlike `+`, which keeps the left operand when the units don't match,
+    /// this returns an error. Values in known units are added in base units,
+    /// like `+`; values in the same unknown unit (`5usd + 3usd`) keep it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{ConversionError, UnitValue};
+    ///
+    /// let length = UnitValue::new(1.0, "m".to_string()).unwrap();
+    /// let sum = length.checked_add(&UnitValue::new(50.0, "cm".to_string()).unwrap()).unwrap();
+    /// assert_eq!(sum.to_string(), "1.5m");
+    ///
+    /// let temp = UnitValue::new(20.0, "C".to_string()).unwrap();
+    /// assert_eq!(length.checked_add(&temp).unwrap_err(), ConversionError::CrossDimension);
+    ///
+    /// let price = UnitValue::named(3.0, "usd");
+    /// assert_eq!(
+    ///     length.checked_add(&price).unwrap_err(),
+    ///     ConversionError::UnknownUnit("usd".to_string())
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConversionError::CrossDimension`] when the dimensions or
+    /// exponents differ, and [`ConversionError::UnknownUnit`] when only one
+    /// side's unit is known or the two unknown units differ.
+    pub fn checked_add(&self, other: &UnitValue) -> Result<UnitValue, ConversionError> {
+        self.checked_sum(other, |l, r| l + r, |l, r| l + r)
+    }
+
+    /// Subtract a unit value, or explain why it can't be subtracted.
+    ///
+    /// See [`checked_add`](UnitValue::checked_add) for the rules.
+    pub fn checked_sub(&self, other: &UnitValue) -> Result<UnitValue, ConversionError> {
+        self.checked_sum(other, |l, r| l - r, |l, r| l - r)
+    }
+
+    /// Multiply two unit values of the same dimension, adding their exponents.
+    ///
+    /// The result is a [`Value`] since the exponents can cancel out, leaving
+    /// a plain number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let width = UnitValue::new(3.0, "m".to_string()).unwrap();
+    /// let depth = UnitValue::new(200.0, "cm".to_string()).unwrap();
+    /// assert_eq!(width.checked_mul(&depth).unwrap().to_string(), "6m^2");
+    /// assert!(width.checked_mul(&UnitValue::new(1.0, "F".to_string()).unwrap()).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConversionError::CrossDimension`] when the dimensions differ
+    /// and [`ConversionError::UnknownUnit`] for mismatched unknown units.
+    pub fn checked_mul(&self, other: &UnitValue) -> Result<Value, ConversionError> {
+        self.checked_product(other, other.exponent, |l, r| l * r)
+    }
+
+    /// Divide by a unit value of the same dimension, subtracting its exponent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::UnitValue;
+    ///
+    /// let distance = UnitValue::new(1.0, "km".to_string()).unwrap();
+    /// let step = UnitValue::new(50.0, "cm".to_string()).unwrap();
+    /// assert_eq!(distance.checked_div(&step).unwrap().to_string(), "2000");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The same as [`checked_mul`](UnitValue::checked_mul).
+    pub fn checked_div(&self, other: &UnitValue) -> Result<Value, ConversionError> {
+        self.checked_product(other, -other.exponent, |l, r| l / r)
+    }
+
+    // Whether the two units can be combined at all, whatever their exponents
+    fn check_compatible(&self, other: &UnitValue) -> Result<(), ConversionError> {
+        match (&self.unit, &other.unit) {
+            (UnitKind::Named(left), UnitKind::Named(right)) if left == right => Ok(()),
+            (UnitKind::Named(name), _) | (_, UnitKind::Named(name)) => {
+                Err(ConversionError::UnknownUnit(name.to_string()))
+            }
+            (UnitKind::Known(left), UnitKind::Known(right))
+                if left.dimension_type() == right.dimension_type() =>
+            {
+                Ok(())
+            }
+            _ if self.dimension() == DimensionType::Derived && self.signature() == other.signature() => Ok(()),
+            _ => Err(ConversionError::CrossDimension),
+        }
+    }
+
+    fn checked_sum(
+        &self,
+        other: &UnitValue,
+        op: impl Fn(f64, f64) -> f64,
+        exact_op: impl Fn(Number, Number) -> Number,
+    ) -> Result<UnitValue, ConversionError> {
+        self.check_compatible(other)?;
+        if self.exponent != other.exponent {
+            return Err(ConversionError::CrossDimension);
+        }
+
+        let left_base = self.in_base_units();
+        let right_base = other.in_base_units();
+        let sum = left_base.with_value(op(left_base.value, right_base.value));
+        // Only units outside the tables keep exact amounts, and those are
+        // never converted
+        Ok(match (&self.exact, &other.exact) {
+            (Some(l), Some(r)) if self.unit == other.unit => sum.keeping(Some(exact_op(l.clone(), r.clone()))),
+            _ => sum,
+        })
+    }
+
+    fn checked_product(
+        &self,
+        other: &UnitValue,
+        other_exponent: i32,
+        op: impl Fn(f64, f64) -> f64,
+    ) -> Result<Value, ConversionError> {
+        self.check_compatible(other)?;
+        if let Some(value) = self.combine_same_dimension(other, other_exponent, &op) {
+            return Ok(value);
+        }
+
+        // Both in the same unknown unit, so no conversion is needed
+        let value = op(self.value, other.value);
+        Ok(match self.exponent + other_exponent {
+            0 => Value::Number(Number::Float(value)),
+            exponent => Value::UnitValue(UnitValue {
+                value,
+                exact: None,
+                unit: self.unit.clone(),
+                exponent,
+            }),
+        })
+    }
+
+    fn combine_same_dimension(
+        &self,
+        other: &UnitValue,
+        other_exponent: i32,
+        op: impl Fn(f64, f64) -> f64,
+    ) -> Option<Value> {
+        if self.dimension() != other.dimension()
+            || matches!(self.dimension(), DimensionType::Derived | DimensionType::Unknown)
+        {
+            return None;
+        }
+
+        let left_base = self.in_base_units();
+        let right_base = other.in_base_units();
+        let value = op(left_base.value, right_base.value);
+        let exponent = self.exponent + other_exponent;
+        if exponent == 0 {
+            return Some(Value::Number(Number::Float(value)));
+        }
+
+        Some(Value::UnitValue(UnitValue {
+            value,
+            exact: None,
+            unit: left_base.unit,
+            exponent,
+        }))
+    }
+
+    // `sign` is 1 to multiply and -1 to divide, applied to the powers of the
+    // other value's units
+    fn combine_derived(&self, other: &UnitValue, sign: i32) -> Option<Value> {
+        let mut units = self.units()?;
+        let mut other_value = other.value;
+        for (unit, exponent) in other.units()? {
+            let dimension = unit.dimension_type();
+            if let Some((known, power)) = units.iter_mut().find(|(known, _)| known.dimension_type() == dimension) {
+                other_value = dimension.convert_value_pow(&unit, known, other_value, exponent)?;
+                *power += sign * exponent;
+                continue;
+            }
+            // A frequency cancels a period, as `Hz*s` is a plain number: the
+            // other unit goes to its base, which is one over the base of ours
+            let reciprocal = dimension.reciprocal();
+            match units.iter_mut().find(|(known, _)| Some(known.dimension_type()) == reciprocal) {
+                Some((known, power)) => {
+                    let base = dimension.to_base_value_pow(&unit, other_value, exponent)?;
+                    let known_dimension = known.dimension_type();
+                    other_value = base / known_dimension.to_base_value_pow(known, 1.0, -exponent)?;
+                    *power -= sign * exponent;
+                }
+                None => units.push((unit, sign * exponent)),
+            }
+        }
+        if !units.iter().all(|(unit, _)| unit.dimension_type().is_linear()) {
+            return None;
+        }
+
+        let value = match sign {
+            1 => self.value * other_value,
+            _ => self.value / other_value,
+        };
+        units.retain(|(_, exponent)| *exponent != 0);
+        Some(match units.as_slice() {
+            [] => Value::Number(Number::Float(value)),
+            [(unit, exponent)] => Value::UnitValue(UnitValue::from_unit(value, *unit, *exponent)),
+            _ => Value::UnitValue(UnitValue {
+                value,
+                exact: None,
+                unit: UnitKind::derived(units),
+                exponent: 1,
+            }),
+        })
+    }
+}
+
+// Parse a derived unit like "km/h" or "m/s^2": known units of two or more
+// linear dimensions, each used once, separated by `*` and `/`
+fn parse_derived(unit: &str) -> Option<Vec<(Unit, i32)>> {
+    if !unit.contains(['*', '/']) {
+        return None;
+    }
+    let mut units: Vec<(Unit, i32)> = Vec::new();
+    let (mut start, mut sign) = (0, 1);
+    for (end, separator) in unit.match_indices(['*', '/']).chain([(unit.len(), "")]) {
+        let (base, exponent) = split_exponent(&unit[start..end]);
+        let known = Unit::parse(base).ok()?;
+        let dimension = known.dimension_type();
+        if !dimension.is_linear() || units.iter().any(|(other, _)| other.dimension_type() == dimension) {
+            return None;
+        }
+        units.push((known, sign * exponent));
+        (start, sign) = (end + 1, if separator == "/" { -1 } else { 1 });
+    }
+    (units.len() > 1).then_some(units)
+}
+
+// How a derived unit is written: the units with positive powers, then those
+// with negative ones after a `/` each, like "m/s^2"
+fn derived_name(units: &[(Unit, i32)]) -> String {
+    let above: Vec<String> = units
+        .iter()
+        .filter(|(_, exponent)| *exponent > 0)
+        .map(|(unit, exponent)| with_exponent(unit.canonical_string(), *exponent))
+        .collect();
+    let below = units.iter().filter(|(_, exponent)| *exponent < 0);
+    if above.is_empty() {
+        let below: Vec<String> = below.map(|(unit, exponent)| with_exponent(unit.canonical_string(), *exponent)).collect();
+        return below.join("*");
+    }
+    let mut name = above.join("*");
+    for (unit, exponent) in below {
+        name.push('/');
+        name.push_str(&with_exponent(unit.canonical_string(), -exponent));
+    }
+    name
+}
+
+// How many of their base units one of `units` is
+fn base_factor(units: &[(Unit, i32)]) -> Option<f64> {
+    units
+        .iter()
+        .map(|(unit, exponent)| unit.dimension_type().to_base_value_pow(unit, 1.0, *exponent))
+        .product()
+}
+
+/// Split a unit string like "m^2" into its base unit and exponent.
+fn split_exponent(unit: &str) -> (&str, i32) {
+    match unit.split_once('^') {
+        Some((base, exponent)) => match exponent.trim().parse::<i32>() {
+            Ok(exponent) => (base.trim(), exponent),
+            Err(_) => (unit, 1),
+        },
+        None => (unit, 1),
+    }
+}
+
+/// Append an exponent suffix to a unit name, omitting it for a power of one.
+fn with_exponent(unit: &str, exponent: i32) -> String {
+    if exponent == 1 {
+        unit.to_string()
+    } else {
+        format!("{}^{}", unit, exponent)
     }
 }
 
 impl Display for UnitValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.value, self.canonical_unit_name())
+        match &self.exact {
+            Some(exact) => write!(f, "{}{}", exact, self.canonical_unit_name()),
+            None => write!(f, "{}{}", self.value, self.canonical_unit_name()),
+        }
     }
 }
 
-impl std::ops::Add for UnitValue {
-    type Output = UnitValue;
-    fn add(self, rhs: Self) -> Self::Output {
-        // Only add if dimensions match
-        if !self.same_dimension_as(&rhs) {
-            // For now, just return the left side if dimensions don't match
-            // In the future, this should be an error
-            return self;
+#[cfg(feature = "units")]
+impl From<Duration> for UnitValue {
+    fn from(duration: Duration) -> Self {
+        UnitValue::from_unit(duration.as_secs_f64(), Unit::Time(TimeUnit::Second), 1)
+    }
+}
+
+// Unit conversions go through floating-point factors, so values that are equal
+// on paper (1ft and 12in) can differ in the last few bits once in base units
+const UNIT_TOLERANCE: f64 = 1e-12;
+
+/// Unit values are equal when they measure the same amount, whatever units
+/// they're written in: `1ft == 12in`.
+impl PartialEq for UnitValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+/// Unit values of the same dimension are ordered by the amount they measure.
+/// Values that can't be compared (`5m` and `3C`, or `5m` and `5m^2`) have no
+/// ordering.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine_parser::types::UnitValue;
+///
+/// let length = |value, unit: &str| UnitValue::new(value, unit.to_string()).unwrap();
+/// let mut lengths = vec![length(1.0, "m"), length(2.0, "ft"), length(30.0, "in"), length(150.0, "cm")];
+/// lengths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+///
+/// let sorted: Vec<String> = lengths.iter().map(|l| l.to_string()).collect();
+/// assert_eq!(sorted, ["2ft", "30in", "1m", "150cm"]);
+/// assert_eq!(length(1.0, "ft"), length(12.0, "in"));
+/// assert!(length(5.0, "m").partial_cmp(&length(3.0, "C")).is_none());
+/// ```
+impl PartialOrd for UnitValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.check_compatible(other).is_err() || self.exponent != other.exponent {
+            return None;
         }
 
-        // Convert both to base units and add
-        let left_base = self.in_base_units();
-        let right_base = rhs.in_base_units();
+        let l = self.in_base_units().value;
+        let r = other.in_base_units().value;
+        if (l - r).abs() <= UNIT_TOLERANCE * l.abs().max(r.abs()) {
+            return Some(Ordering::Equal);
+        }
+        l.partial_cmp(&r)
+    }
+}
 
-        UnitValue::new(left_base.value + right_base.value, left_base.unit)
+impl std::ops::Add for UnitValue {
+    type Output = UnitValue;
+    fn add(self, rhs: Self) -> Self::Output {
+        // For now, just return the left side if the units can't be
+        // combined. In the future, this should be an error
+        self.checked_add(&rhs).unwrap_or(self)
     }
 }
 
@@ -203,94 +1093,64 @@ impl std::ops::Add<Number> for UnitValue {
     type Output = UnitValue;
     fn add(self, rhs: Number) -> Self::Output {
         // When adding a number to a unit value, treat the number as having the same unit
-        UnitValue {
-            value: self.value + rhs.0,
-            unit: self.unit,
-            dimension: self.dimension,
-        }
+        let exact = self.exact.clone().map(|exact| exact + rhs.clone());
+        self.map(|value| value + rhs.to_f64()).keeping(exact)
     }
 }
 
 impl std::ops::Add<UnitValue> for Number {
     type Output = UnitValue;
     fn add(self, rhs: UnitValue) -> Self::Output {
-        UnitValue {
-            value: self.0 + rhs.value,
-            unit: rhs.unit,
-            dimension: rhs.dimension,
-        }
+        let exact = rhs.exact.clone().map(|exact| self.clone() + exact);
+        rhs.map(|value| self.to_f64() + value).keeping(exact)
     }
 }
 
 impl std::ops::Sub for UnitValue {
     type Output = UnitValue;
     fn sub(self, rhs: Self) -> Self::Output {
-        // Only subtract if dimensions match
-        if !self.same_dimension_as(&rhs) {
-            // For now, just return the left side if dimensions don't match
-            // In the future, this should be an error
-            return self;
-        }
-
-        // Convert both to base units and subtract
-        let left_base = self.in_base_units();
-        let right_base = rhs.in_base_units();
-
-        UnitValue::new(left_base.value - right_base.value, left_base.unit)
+        // For now, just return the left side if the units can't be
+        // combined. In the future, this should be an error
+        self.checked_sub(&rhs).unwrap_or(self)
     }
 }
 
 impl std::ops::Sub<Number> for UnitValue {
     type Output = UnitValue;
     fn sub(self, rhs: Number) -> Self::Output {
-        UnitValue {
-            value: self.value - rhs.0,
-            unit: self.unit,
-            dimension: self.dimension,
-        }
+        let exact = self.exact.clone().map(|exact| exact - rhs.clone());
+        self.map(|value| value - rhs.to_f64()).keeping(exact)
     }
 }
 
 impl std::ops::Sub<UnitValue> for Number {
     type Output = UnitValue;
     fn sub(self, rhs: UnitValue) -> Self::Output {
-        UnitValue {
-            value: self.0 - rhs.value,
-            unit: rhs.unit,
-            dimension: rhs.dimension,
-        }
+        let exact = rhs.exact.clone().map(|exact| self.clone() - exact);
+        rhs.map(|value| self.to_f64() - value).keeping(exact)
     }
 }
 
 impl std::ops::Mul<Number> for UnitValue {
     type Output = UnitValue;
     fn mul(self, rhs: Number) -> Self::Output {
-        UnitValue {
-            value: self.value * rhs.0,
-            unit: self.unit,
-            dimension: self.dimension,
-        }
+        let exact = self.exact.clone().map(|exact| exact * rhs.clone());
+        self.map(|value| value * rhs.to_f64()).keeping(exact)
     }
 }
 
 impl std::ops::Mul<UnitValue> for Number {
     type Output = UnitValue;
     fn mul(self, rhs: UnitValue) -> Self::Output {
-        UnitValue {
-            value: self.0 * rhs.value,
-            unit: rhs.unit,
-            dimension: rhs.dimension,
-        }
+        let exact = rhs.exact.clone().map(|exact| self.clone() * exact);
+        rhs.map(|value| self.to_f64() * value).keeping(exact)
     }
 }
 
 impl std::ops::Div<Number> for UnitValue {
     type Output = UnitValue;
     fn div(self, rhs: Number) -> Self::Output {
-        UnitValue {
-            value: self.value / rhs.0,
-            unit: self.unit,
-            dimension: self.dimension,
-        }
+        let exact = self.exact.clone().map(|exact| exact / rhs.clone());
+        self.map(|value| value / rhs.to_f64()).keeping(exact)
     }
 }
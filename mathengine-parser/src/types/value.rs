@@ -1,10 +1,11 @@
 use std::fmt::Display;
-use crate::types::{Number, UnitValue};
+use crate::types::{ConversionError, DimensionType, Number, Radix, Scalar, UnitInfo, UnitValue};
 
 /// Unified value type for evaluation results.
 ///
 /// This enum represents the result of evaluating a mathematical expression,
-/// which can be either a plain number or a value with a unit.
+/// which can be a plain number, a value with a unit, a boolean, a list of
+/// values, or an integer to display in another base.
 ///
 /// # Examples
 ///
@@ -12,7 +13,7 @@ use crate::types::{Number, UnitValue};
 /// use mathengine_parser::types::{Value, Number, UnitValue};
 ///
 /// let num_result = Value::Number(Number::from(42.0));
-/// let unit_result = Value::UnitValue(UnitValue::new(5.0, "m".to_string()));
+/// let unit_result = Value::UnitValue(UnitValue::new(5.0, "m".to_string()).unwrap());
 /// ```
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -20,6 +21,298 @@ pub enum Value {
     Number(Number),
     /// A value with an associated unit
     UnitValue(UnitValue),
+    /// A boolean, produced by logical operators
+    Bool(bool),
+    /// A list of values, like `[3, 5, 8]`
+    List(Vec<Value>),
+    /// An integer displayed in another base, like `hex(255)`. Arithmetic
+    /// treats it as the plain integer.
+    Radix { value: i128, radix: Radix },
+}
+
+impl Value {
+    /// Whether the value counts as true in a logical context.
+    ///
+    /// Booleans are themselves, numbers and unit values are true when
+    /// non-zero, and lists are true when non-empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::Value;
+    ///
+    /// assert!(Value::from(2.5).is_truthy());
+    /// assert!(!Value::from(0i128).is_truthy());
+    /// assert!(!Value::List(vec![]).is_truthy());
+    /// ```
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Number(n) => !n.is_zero(),
+            Value::UnitValue(uv) => uv.value() != 0.0,
+            Value::Bool(b) => *b,
+            Value::List(items) => !items.is_empty(),
+            Value::Radix { value, .. } => *value != 0,
+        }
+    }
+
+    /// The plain number this value holds, if it is one. Integers shown in
+    /// another base count as numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Radix, UnitValue, Value};
+    ///
+    /// assert_eq!(Value::from(2.5).as_number().unwrap().to_f64(), 2.5);
+    ///
+    /// let hex = Value::from(255i128).with_radix(Radix::Hexadecimal);
+    /// assert_eq!(hex.as_number().unwrap().as_integer(), Some(255));
+    ///
+    /// let length = Value::UnitValue(UnitValue::new(5.0, "m".to_string()).unwrap());
+    /// assert!(length.as_number().is_none());
+    /// ```
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::Number(n) => Some(n.clone()),
+            Value::Radix { value, .. } => Some(Number::Integer(*value)),
+            _ => None,
+        }
+    }
+
+    /// The unit value this value holds, if it is one.
+    pub fn as_unit_value(&self) -> Option<&UnitValue> {
+        match self {
+            Value::UnitValue(uv) => Some(uv),
+            _ => None,
+        }
+    }
+
+    /// The boolean this value holds, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The items of this value, if it is a list.
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The unit of a unit value (`"cm"` for `5cm`), as [`UnitValue::unit`] gives it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{DimensionType, UnitValue, Value};
+    ///
+    /// let value = Value::UnitValue(UnitValue::new(5.0, "cm".to_string()).unwrap());
+    /// assert_eq!(value.unit(), Some("cm"));
+    /// assert_eq!(value.dimension(), Some(DimensionType::Length));
+    /// assert_eq!(Value::from(5.0).unit(), None);
+    /// ```
+    pub fn unit(&self) -> Option<&str> {
+        self.as_unit_value().map(UnitValue::unit)
+    }
+
+    /// The dimension of a unit value, like [`DimensionType::Length`] for `5cm`.
+    pub fn dimension(&self) -> Option<DimensionType> {
+        self.as_unit_value().map(UnitValue::dimension)
+    }
+
+    /// The dimension and canonical unit of a unit value, like
+    /// [`UnitValue::info`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{DimensionType, UnitValue, Value};
+    ///
+    /// let value = Value::UnitValue(UnitValue::new(90.0, "minutes".to_string()).unwrap());
+    /// let info = value.unit_info().unwrap();
+    /// assert_eq!((info.dimension, info.unit.as_str()), (DimensionType::Time, "min"));
+    /// assert_eq!(info.dimension.name(), "time");
+    /// assert!(Value::from(1.0).unit_info().is_none());
+    /// ```
+    pub fn unit_info(&self) -> Option<UnitInfo> {
+        self.as_unit_value().map(UnitValue::info)
+    }
+
+    /// The value as a float, converted to `expected_unit` when given.
+    ///
+    /// With `None` the value must be a plain number; with a unit it must be a
+    /// unit value that converts to it. Anything else is an error rather than
+    /// a silently misread number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{UnitValue, Value};
+    ///
+    /// let length = Value::UnitValue(UnitValue::new(150.0, "cm".to_string()).unwrap());
+    /// assert_eq!(length.to_f64(Some("m")), Ok(1.5));
+    /// assert!(length.to_f64(None).is_err());
+    /// assert!(length.to_f64(Some("C")).is_err());
+    ///
+    /// assert_eq!(Value::from(2.5).to_f64(None), Ok(2.5));
+    /// assert_eq!(f64::try_from(Value::from(4i128)), Ok(4.0));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConversionError::NotNumeric`] for booleans and lists,
+    /// [`ConversionError::UnitMismatch`] when the value has a unit and
+    /// `expected_unit` is `None` (or the reverse), and the usual conversion
+    /// errors when the units don't convert.
+    pub fn to_f64(&self, expected_unit: Option<&str>) -> Result<f64, ConversionError> {
+        match (self, expected_unit) {
+            (Value::Number(n), None) => Ok(n.to_f64()),
+            (Value::Radix { value, .. }, None) => Ok(*value as f64),
+            (Value::UnitValue(uv), Some(unit)) if uv.unit() == unit => Ok(uv.value()),
+            (Value::UnitValue(uv), Some(unit)) => Ok(uv.convert_to(unit)?.value()),
+            (Value::Number(_) | Value::Radix { .. }, Some(unit)) => {
+                Err(ConversionError::UnitMismatch {
+                    expected: Some(unit.to_string()),
+                    found: None,
+                })
+            }
+            (Value::UnitValue(uv), None) => Err(ConversionError::UnitMismatch {
+                expected: None,
+                found: Some(uv.unit().to_string()),
+            }),
+            (other, _) => Err(ConversionError::NotNumeric(other.to_string())),
+        }
+    }
+
+    /// The value as any [`Scalar`] type, converted to `expected_unit` when
+    /// given, with the same rules as [`to_f64`](Value::to_f64).
+    ///
+    /// Plain numbers, and quantities read in their own unit, are read
+    /// without passing through `f64`, so exact results (like amounts of
+    /// money in decimal mode) stay exact in types that can hold them. Unit
+    /// conversions are done in floating point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{UnitValue, Value};
+    ///
+    /// let length = Value::UnitValue(UnitValue::new(150.0, "cm".to_string()).unwrap());
+    /// assert_eq!(length.to_scalar::<f32>(Some("m")), Ok(1.5));
+    /// assert_eq!(Value::from(7i128).to_scalar::<i64>(None), Ok(7));
+    /// assert!(Value::from(7.5).to_scalar::<i64>(None).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The errors [`to_f64`](Value::to_f64) gives, and
+    /// [`ConversionError::Failed`] when the value can't be represented in `T`.
+    pub fn to_scalar<T: Scalar>(&self, expected_unit: Option<&str>) -> Result<T, ConversionError> {
+        let number = match (self, expected_unit) {
+            (Value::Number(n), None) => n.clone(),
+            (Value::Radix { value, .. }, None) => Number::Integer(*value),
+            (Value::UnitValue(uv), Some(unit)) if uv.unit() == unit => uv.amount(),
+            (value, expected_unit) => Number::Float(value.to_f64(expected_unit)?),
+        };
+        T::from_number(&number).ok_or(ConversionError::Failed)
+    }
+
+    /// Whether this is a plain number, including integers shown in another base.
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Number(_) | Value::Radix { .. })
+    }
+
+    /// Whether this is a value with a unit.
+    pub fn is_unit_value(&self) -> bool {
+        matches!(self, Value::UnitValue(_))
+    }
+
+    /// Whether this is a boolean.
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Value::Bool(_))
+    }
+
+    /// Whether this is a list.
+    pub fn is_list(&self) -> bool {
+        matches!(self, Value::List(_))
+    }
+
+    /// Whether two values are equal to within `epsilon`.
+    ///
+    /// Numbers are compared directly. A unit value is compared with another
+    /// in a compatible unit by converting it to this value's unit, so
+    /// `epsilon` is in this value's unit. Lists are compared item by item;
+    /// booleans must match exactly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{UnitValue, Value};
+    ///
+    /// assert!(Value::from(0.1 + 0.2).approx_eq(&Value::from(0.3), 1e-9));
+    ///
+    /// let meter = Value::UnitValue(UnitValue::new(1.0, "m".to_string()).unwrap());
+    /// let feet = Value::UnitValue(UnitValue::new(3.28, "ft".to_string()).unwrap());
+    /// assert!(meter.approx_eq(&feet, 0.001));
+    /// assert!(!meter.approx_eq(&feet, 0.0001));
+    /// assert!(!meter.approx_eq(&Value::from(1.0), 0.1));
+    /// ```
+    pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+        match (self, other) {
+            (Value::UnitValue(l), Value::UnitValue(r)) => {
+                l.approx_eq_in(r, &l.canonical_unit_name(), epsilon)
+            }
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::List(l), Value::List(r)) => {
+                l.len() == r.len() && l.iter().zip(r).all(|(l, r)| l.approx_eq(r, epsilon))
+            }
+            (l, r) => match (l.as_number(), r.as_number()) {
+                (Some(l), Some(r)) => (l.to_f64() - r.to_f64()).abs() <= epsilon,
+                _ => false,
+            },
+        }
+    }
+
+    /// Display every integer in this value in `radix`, for programmer-style
+    /// output. Values that aren't whole numbers are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine_parser::types::{Radix, Value};
+    ///
+    /// let value = Value::List(vec![Value::from(10i128), Value::from(2.5)]);
+    /// assert_eq!(value.with_radix(Radix::Binary).to_string(), "[0b1010, 2.5]");
+    /// ```
+    pub fn with_radix(self, radix: Radix) -> Value {
+        match self {
+            Value::Number(n) => match n.as_integer() {
+                Some(value) => Value::Radix { value, radix },
+                None => Value::Number(n),
+            },
+            Value::Radix { value, .. } => Value::Radix { value, radix },
+            Value::List(items) => {
+                Value::List(items.into_iter().map(|item| item.with_radix(radix)).collect())
+            }
+            other => other,
+        }
+    }
+
+    /// Drop any display base, turning [`Value::Radix`] back into a plain
+    /// integer (inside lists too).
+    pub fn without_radix(self) -> Value {
+        match self {
+            Value::Radix { value, .. } => Value::Number(Number::Integer(value)),
+            Value::List(items) => {
+                Value::List(items.into_iter().map(Value::without_radix).collect())
+            }
+            other => other,
+        }
+    }
 }
 
 impl Display for Value {
@@ -27,10 +320,31 @@ impl Display for Value {
         match self {
             Value::Number(n) => write!(f, "{}", n),
             Value::UnitValue(uv) => write!(f, "{}", uv),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Radix { value, radix } => write!(f, "{}", radix.format(*value)),
         }
     }
 }
 
+impl TryFrom<Value> for f64 {
+    type Error = ConversionError;
+
+    /// Extract a plain number; see [`Value::to_f64`] for values with units.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.to_f64(None)
+    }
+}
+
 impl From<Number> for Value {
     fn from(n: Number) -> Self {
         Value::Number(n)
@@ -43,9 +357,45 @@ impl From<UnitValue> for Value {
     }
 }
 
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
 impl From<f64> for Value {
     fn from(f: f64) -> Self {
-        Value::Number(Number(f))
+        Value::Number(Number::Float(f))
+    }
+}
+
+impl From<i128> for Value {
+    fn from(i: i128) -> Self {
+        Value::Number(Number::Integer(i))
+    }
+}
+
+// Arithmetic treats booleans as 1 and 0 (the evaluator rejects them before
+// they get this far), and integers in another base as plain integers
+fn as_number(value: Value) -> Value {
+    match value {
+        Value::Bool(b) => Value::Number(Number::from(b as i128)),
+        Value::Radix { value, .. } => Value::Number(Number::from(value)),
+        other => other,
+    }
+}
+
+// Lists combine element-wise with lists and apply scalars to every element.
+// Lists of different lengths pair up only as far as the shorter one goes, so
+// callers that care should compare lengths first.
+fn broadcast(lhs: Value, rhs: Value, op: fn(Value, Value) -> Value) -> Value {
+    match (lhs, rhs) {
+        (Value::List(l), Value::List(r)) => {
+            Value::List(l.into_iter().zip(r).map(|(a, b)| op(a, b)).collect())
+        }
+        (Value::List(l), r) => Value::List(l.into_iter().map(|a| op(a, r.clone())).collect()),
+        (l, Value::List(r)) => Value::List(r.into_iter().map(|b| op(l.clone(), b)).collect()),
+        (l, r) => op(l, r),
     }
 }
 
@@ -53,6 +403,11 @@ impl std::ops::Add for Value {
     type Output = Value;
     fn add(self, rhs: Value) -> Value {
         match (self, rhs) {
+            (l @ Value::List(_), r) | (l, r @ Value::List(_)) => broadcast(l, r, std::ops::Add::add),
+            (l @ (Value::Bool(_) | Value::Radix { .. }), r)
+            | (l, r @ (Value::Bool(_) | Value::Radix { .. })) => {
+                std::ops::Add::add(as_number(l), as_number(r))
+            }
             (Value::Number(l), Value::Number(r)) => Value::Number(l + r),
             (Value::UnitValue(l), Value::Number(r)) => Value::UnitValue(l + r),
             (Value::Number(l), Value::UnitValue(r)) => Value::UnitValue(l + r),
@@ -65,6 +420,11 @@ impl std::ops::Sub for Value {
     type Output = Value;
     fn sub(self, rhs: Value) -> Value {
         match (self, rhs) {
+            (l @ Value::List(_), r) | (l, r @ Value::List(_)) => broadcast(l, r, std::ops::Sub::sub),
+            (l @ (Value::Bool(_) | Value::Radix { .. }), r)
+            | (l, r @ (Value::Bool(_) | Value::Radix { .. })) => {
+                std::ops::Sub::sub(as_number(l), as_number(r))
+            }
             (Value::Number(l), Value::Number(r)) => Value::Number(l - r),
             (Value::UnitValue(l), Value::Number(r)) => Value::UnitValue(l - r),
             (Value::Number(l), Value::UnitValue(r)) => Value::UnitValue(l - r),
@@ -77,14 +437,21 @@ impl std::ops::Mul for Value {
     type Output = Value;
     fn mul(self, rhs: Value) -> Value {
         match (self, rhs) {
+            (l @ Value::List(_), r) | (l, r @ Value::List(_)) => broadcast(l, r, std::ops::Mul::mul),
+            (l @ (Value::Bool(_) | Value::Radix { .. }), r)
+            | (l, r @ (Value::Bool(_) | Value::Radix { .. })) => {
+                std::ops::Mul::mul(as_number(l), as_number(r))
+            }
             (Value::Number(l), Value::Number(r)) => Value::Number(l * r),
             (Value::UnitValue(l), Value::Number(r)) => Value::UnitValue(l * r),
             (Value::Number(l), Value::UnitValue(r)) => Value::UnitValue(l * r),
-            (Value::UnitValue(l), Value::UnitValue(_r)) => {
-                // UnitValue * UnitValue would create area/volume units which we don't support yet
-                // For now, return the left operand (same as current behavior for unsupported ops)
-                // TODO: Implement compound units (area, volume, etc.)
-                Value::UnitValue(l)
+            (Value::UnitValue(l), Value::UnitValue(r)) => {
+                // Same-dimension products raise the unit power (m * m = m^2),
+                // and mixed dimensions make a derived unit (m * s = m*s).
+                // Units that can't be combined return the left operand
+                l.mul_same_dimension(&r)
+                    .or_else(|| l.mul_derived(&r))
+                    .unwrap_or(Value::UnitValue(l))
             }
         }
     }
@@ -94,19 +461,25 @@ impl std::ops::Div for Value {
     type Output = Value;
     fn div(self, rhs: Value) -> Value {
         match (self, rhs) {
+            (l @ Value::List(_), r) | (l, r @ Value::List(_)) => broadcast(l, r, std::ops::Div::div),
+            (l @ (Value::Bool(_) | Value::Radix { .. }), r)
+            | (l, r @ (Value::Bool(_) | Value::Radix { .. })) => {
+                std::ops::Div::div(as_number(l), as_number(r))
+            }
             (Value::Number(l), Value::Number(r)) => Value::Number(l / r),
             (Value::UnitValue(l), Value::Number(r)) => Value::UnitValue(l / r),
-            (Value::Number(l), Value::UnitValue(_r)) => {
-                // Number / UnitValue would create inverse units (like 1/time = frequency)
-                // We don't support this yet, so return the left operand
-                // TODO: Implement inverse units
-                Value::Number(l)
-            }
-            (Value::UnitValue(l), Value::UnitValue(_r)) => {
-                // UnitValue / UnitValue could create ratios or dimensionless quantities
-                // We don't support this yet, so return the left operand
-                // TODO: Implement unit ratios and dimensionless quantities
-                Value::UnitValue(l)
+            (Value::Number(l), Value::UnitValue(r)) => match r.powi(-1) {
+                // Number / UnitValue produces an inverse unit (5 / 2m = 2.5m^-1)
+                Some(inverse) => Value::UnitValue(l * inverse),
+                None => Value::Number(l),
+            },
+            (Value::UnitValue(l), Value::UnitValue(r)) => {
+                // Same-dimension quotients lower the unit power (m^2 / m = m)
+                // or produce a dimensionless ratio (6m / 2m = 3), and ratios
+                // across dimensions make a derived unit (10km / 2h = 5km/h)
+                l.div_same_dimension(&r)
+                    .or_else(|| l.div_derived(&r))
+                    .unwrap_or(Value::UnitValue(l))
             }
         }
     }
@@ -0,0 +1,124 @@
+use std::hash::{Hash, Hasher};
+
+use crate::types::{Number, UnitValue, Value};
+
+/// A hashable stand-in for a [`Value`], for cache keys and deduplication.
+///
+/// Values that mean the same thing get the same key: unit values are
+/// converted to base units, and numbers are compared by what they're worth
+/// rather than how they're stored, so `2`, `2.0` and `hex(2)` share a key.
+/// Unlike comparing values with `==`, there's no tolerance, so keys are a
+/// true equivalence and safe to hash. Unit conversions that round
+/// differently (`1ft` and `12in`) can therefore give different keys.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use mathengine_parser::types::{UnitValue, Value};
+///
+/// let values = [
+///     Value::UnitValue(UnitValue::new(1.0, "m".to_string()).unwrap()),
+///     Value::UnitValue(UnitValue::new(100.0, "cm".to_string()).unwrap()),
+///     Value::from(2i128),
+///     Value::from(2.0),
+/// ];
+/// let unique: HashSet<_> = values.iter().map(Value::key).collect();
+/// assert_eq!(unique.len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValueKey(Key);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Number(NumberKey),
+    Quantity {
+        amount: NumberKey,
+        unit: String,
+        exponent: i32,
+    },
+    Bool(bool),
+    List(Vec<Key>),
+}
+
+// Whole numbers are kept exact; anything else is keyed by its float bits
+#[derive(Debug, Clone, Copy)]
+enum NumberKey {
+    Integer(i128),
+    Float(f64),
+}
+
+impl NumberKey {
+    fn new(n: &Number) -> Self {
+        match n {
+            Number::Rational(r) if r.is_integer() => NumberKey::Integer(r.numer()),
+            n => match n.as_integer() {
+                Some(i) => NumberKey::Integer(i),
+                None => NumberKey::Float(n.to_f64()),
+            },
+        }
+    }
+
+    fn from_f64(value: f64) -> Self {
+        NumberKey::new(&Number::Float(value))
+    }
+
+    // -0.0 and 0.0 are the same number, and every NaN is the same key
+    fn bits(value: f64) -> u64 {
+        if value.is_nan() {
+            f64::NAN.to_bits()
+        } else {
+            (value + 0.0).to_bits()
+        }
+    }
+}
+
+impl PartialEq for NumberKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (NumberKey::Integer(l), NumberKey::Integer(r)) => l == r,
+            (NumberKey::Float(l), NumberKey::Float(r)) => NumberKey::bits(*l) == NumberKey::bits(*r),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for NumberKey {}
+
+impl Hash for NumberKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            NumberKey::Integer(i) => (0u8, *i).hash(state),
+            NumberKey::Float(f) => (1u8, NumberKey::bits(*f)).hash(state),
+        }
+    }
+}
+
+impl Key {
+    fn new(value: &Value) -> Self {
+        match value {
+            Value::Number(n) => Key::Number(NumberKey::new(n)),
+            Value::Radix { value, .. } => Key::Number(NumberKey::Integer(*value)),
+            Value::UnitValue(uv) => Key::quantity(uv),
+            Value::Bool(b) => Key::Bool(*b),
+            Value::List(items) => Key::List(items.iter().map(Key::new).collect()),
+        }
+    }
+
+    fn quantity(uv: &UnitValue) -> Self {
+        let base = uv.in_base_units();
+        Key::Quantity {
+            amount: NumberKey::from_f64(base.value()),
+            unit: base.unit().to_string(),
+            exponent: base.exponent(),
+        }
+    }
+}
+
+impl Value {
+    /// A key that's equal for values meaning the same thing, for hashing.
+    /// See [`ValueKey`].
+    pub fn key(&self) -> ValueKey {
+        ValueKey(Key::new(self))
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{UnitType, UnitConversion, Dimension};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrequencyUnit {
+    Hertz,
+    Kilohertz,
+    Megahertz,
+    Gigahertz,
+}
+
+
+impl UnitType for FrequencyUnit {
+    fn all() -> &'static [Self] {
+        &[
+            FrequencyUnit::Hertz,
+            FrequencyUnit::Kilohertz,
+            FrequencyUnit::Megahertz,
+            FrequencyUnit::Gigahertz,
+        ]
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            FrequencyUnit::Hertz => &["Hz", "hertz"],
+            FrequencyUnit::Kilohertz => &["kHz", "kilohertz"],
+            FrequencyUnit::Megahertz => &["MHz", "megahertz"],
+            FrequencyUnit::Gigahertz => &["GHz", "gigahertz"],
+        }
+    }
+
+    fn dimension_name() -> &'static str {
+        "Frequency"
+    }
+}
+
+
+impl UnitConversion<FrequencyUnit> for Dimension<FrequencyUnit> {
+    fn to_base_value(unit: FrequencyUnit, value: f64) -> f64 {
+        match unit {
+            FrequencyUnit::Hertz => value,
+            FrequencyUnit::Kilohertz => value * 1e3,
+            FrequencyUnit::Megahertz => value * 1e6,
+            FrequencyUnit::Gigahertz => value * 1e9,
+        }
+    }
+
+    fn from_base_value(base_value: f64, unit: FrequencyUnit) -> f64 {
+        match unit {
+            FrequencyUnit::Hertz => base_value,
+            FrequencyUnit::Kilohertz => base_value / 1e3,
+            FrequencyUnit::Megahertz => base_value / 1e6,
+            FrequencyUnit::Gigahertz => base_value / 1e9,
+        }
+    }
+
+    fn base_unit() -> FrequencyUnit {
+        FrequencyUnit::Hertz
+    }
+}
+
+
+/// Type alias for the concrete frequency dimension
+pub type FrequencyDimension = Dimension<FrequencyUnit>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency_creation() {
+        let frequency = FrequencyDimension::from_unit("kHz", 2.5).unwrap();
+        assert_eq!(frequency.value(), 2.5);
+        assert_eq!(frequency.unit(), FrequencyUnit::Kilohertz);
+    }
+
+    #[test]
+    fn test_megahertz_to_kilohertz() {
+        let frequency = FrequencyDimension::new(1.5, FrequencyUnit::Megahertz);
+        let in_kilohertz = frequency.convert_to(FrequencyUnit::Kilohertz);
+        assert!((in_kilohertz.value() - 1500.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_display() {
+        let frequency = FrequencyDimension::from_unit("gigahertz", 3.0).unwrap();
+        assert_eq!(format!("{}", frequency), "3GHz");
+    }
+
+    #[test]
+    fn test_every_name_parses() {
+        for &unit in FrequencyUnit::all() {
+            for name in unit.names() {
+                assert_eq!(FrequencyUnit::parse(&name.to_lowercase()), Ok(unit));
+            }
+        }
+        assert_eq!(FrequencyUnit::Hertz.canonical_string(), "Hz");
+    }
+}
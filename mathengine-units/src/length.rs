@@ -1,4 +1,4 @@
-use crate::{UnitError, UnitType, UnitConversion, Dimension};
+use crate::{UnitType, UnitConversion, Dimension};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LengthUnit {
@@ -14,30 +14,29 @@ pub enum LengthUnit {
 
 
 impl UnitType for LengthUnit {
-    fn canonical_string(&self) -> &'static str {
-        match self {
-            LengthUnit::Meter => "m",
-            LengthUnit::Centimeter => "cm",
-            LengthUnit::Millimeter => "mm",
-            LengthUnit::Kilometer => "km",
-            LengthUnit::Foot => "ft",
-            LengthUnit::Inch => "in",
-            LengthUnit::Yard => "yd",
-            LengthUnit::Mile => "mi",
-        }
+    fn all() -> &'static [Self] {
+        &[
+            LengthUnit::Meter,
+            LengthUnit::Centimeter,
+            LengthUnit::Millimeter,
+            LengthUnit::Kilometer,
+            LengthUnit::Foot,
+            LengthUnit::Inch,
+            LengthUnit::Yard,
+            LengthUnit::Mile,
+        ]
     }
 
-    fn parse(s: &str) -> Result<Self, UnitError> {
-        match s.to_lowercase().as_str() {
-            "m" | "meter" | "meters" => Ok(LengthUnit::Meter),
-            "cm" | "centimeter" | "centimeters" => Ok(LengthUnit::Centimeter),
-            "mm" | "millimeter" | "millimeters" => Ok(LengthUnit::Millimeter),
-            "km" | "kilometer" | "kilometers" => Ok(LengthUnit::Kilometer),
-            "ft" | "foot" | "feet" => Ok(LengthUnit::Foot),
-            "in" | "inch" | "inches" => Ok(LengthUnit::Inch),
-            "yd" | "yard" | "yards" => Ok(LengthUnit::Yard),
-            "mi" | "mile" | "miles" => Ok(LengthUnit::Mile),
-            _ => Err(UnitError::UnknownUnit(s.to_string())),
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            LengthUnit::Meter => &["m", "meter", "meters"],
+            LengthUnit::Centimeter => &["cm", "centimeter", "centimeters"],
+            LengthUnit::Millimeter => &["mm", "millimeter", "millimeters"],
+            LengthUnit::Kilometer => &["km", "kilometer", "kilometers"],
+            LengthUnit::Foot => &["ft", "foot", "feet"],
+            LengthUnit::Inch => &["in", "inch", "inches"],
+            LengthUnit::Yard => &["yd", "yard", "yards"],
+            LengthUnit::Mile => &["mi", "mile", "miles"],
         }
     }
 
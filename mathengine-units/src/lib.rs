@@ -1,15 +1,32 @@
+pub mod frequency;
 pub mod length;
+pub mod mass;
 pub mod temperature;
+pub mod time;
 
 use std::fmt;
 
 /// Common behavior for all unit types (m, cm, F, C, etc.)
 pub trait UnitType: Copy + PartialEq + std::fmt::Debug + 'static {
+    /// Every unit of this type
+    fn all() -> &'static [Self];
+
+    /// Every name the unit can be written as, canonical first
+    fn names(&self) -> &'static [&'static str];
+
     /// Get the canonical string representation (e.g., "m", "cm", "F")
-    fn canonical_string(&self) -> &'static str;
+    fn canonical_string(&self) -> &'static str {
+        self.names()[0]
+    }
 
-    /// Parse a unit string into this unit type
-    fn parse(s: &str) -> Result<Self, UnitError>;
+    /// Parse a unit string into this unit type, by any of its names in any case
+    fn parse(s: &str) -> Result<Self, UnitError> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|unit| unit.names().iter().any(|name| name.eq_ignore_ascii_case(s)))
+            .ok_or_else(|| UnitError::UnknownUnit(s.to_string()))
+    }
 
     /// Get the dimension name for this unit type
     fn dimension_name() -> &'static str;
@@ -0,0 +1,126 @@
+use crate::{UnitType, UnitConversion, Dimension};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MassUnit {
+    Kilogram,
+    Gram,
+    Milligram,
+    Tonne,
+    Pound,
+    Ounce,
+}
+
+
+impl UnitType for MassUnit {
+    fn all() -> &'static [Self] {
+        &[
+            MassUnit::Kilogram,
+            MassUnit::Gram,
+            MassUnit::Milligram,
+            MassUnit::Tonne,
+            MassUnit::Pound,
+            MassUnit::Ounce,
+        ]
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            MassUnit::Kilogram => &["kg", "kilogram", "kilograms"],
+            MassUnit::Gram => &["g", "gram", "grams"],
+            MassUnit::Milligram => &["mg", "milligram", "milligrams"],
+            // Not `t`, which reads as a variable in `f(t) = 2t`
+            MassUnit::Tonne => &["tonne", "tonnes"],
+            MassUnit::Pound => &["lb", "lbs", "pound", "pounds"],
+            MassUnit::Ounce => &["oz", "ounce", "ounces"],
+        }
+    }
+
+    fn dimension_name() -> &'static str {
+        "Mass"
+    }
+}
+
+
+impl UnitConversion<MassUnit> for Dimension<MassUnit> {
+    fn to_base_value(unit: MassUnit, value: f64) -> f64 {
+        match unit {
+            MassUnit::Kilogram => value,
+            MassUnit::Gram => value / 1000.0,
+            MassUnit::Milligram => value / 1e6,
+            MassUnit::Tonne => value * 1000.0,
+            MassUnit::Pound => value * 0.45359237,
+            MassUnit::Ounce => value * 0.028349523125,
+        }
+    }
+
+    fn from_base_value(base_value: f64, unit: MassUnit) -> f64 {
+        match unit {
+            MassUnit::Kilogram => base_value,
+            MassUnit::Gram => base_value * 1000.0,
+            MassUnit::Milligram => base_value * 1e6,
+            MassUnit::Tonne => base_value / 1000.0,
+            MassUnit::Pound => base_value / 0.45359237,
+            MassUnit::Ounce => base_value / 0.028349523125,
+        }
+    }
+
+    fn base_unit() -> MassUnit {
+        MassUnit::Kilogram
+    }
+
+    fn convert_direct(from: MassUnit, to: MassUnit, value: f64) -> Option<f64> {
+        match (from, to) {
+            // Ounce <-> Pound
+            (MassUnit::Ounce, MassUnit::Pound) => Some(value / 16.0),
+            (MassUnit::Pound, MassUnit::Ounce) => Some(value * 16.0),
+
+            // No direct conversion available
+            _ => None,
+        }
+    }
+}
+
+
+/// Type alias for the concrete mass dimension
+pub type MassDimension = Dimension<MassUnit>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mass_creation() {
+        let mass = MassDimension::from_unit("lbs", 5.0).unwrap();
+        assert_eq!(mass.value(), 5.0);
+        assert_eq!(mass.unit(), MassUnit::Pound);
+    }
+
+    #[test]
+    fn test_pound_to_kilograms() {
+        let mass = MassDimension::new(5.0, MassUnit::Pound);
+        let in_kilograms = mass.convert_to(MassUnit::Kilogram);
+        assert!((in_kilograms.value() - 2.26796185).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_pound_to_ounces() {
+        let mass = MassDimension::new(1.5, MassUnit::Pound);
+        assert_eq!(mass.convert_to(MassUnit::Ounce).value(), 24.0);
+    }
+
+    #[test]
+    fn test_display() {
+        let mass = MassDimension::from_unit("grams", 250.0).unwrap();
+        assert_eq!(format!("{}", mass), "250g");
+    }
+
+    #[test]
+    fn test_every_name_parses() {
+        for &unit in MassUnit::all() {
+            for name in unit.names() {
+                assert_eq!(MassUnit::parse(&name.to_lowercase()), Ok(unit));
+            }
+        }
+        assert!(MassUnit::parse("t").is_err());
+    }
+}
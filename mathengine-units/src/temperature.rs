@@ -1,4 +1,4 @@
-use crate::{UnitError, UnitType, UnitConversion, Dimension};
+use crate::{UnitType, UnitConversion, Dimension};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TemperatureUnit {
@@ -9,20 +9,15 @@ pub enum TemperatureUnit {
 
 
 impl UnitType for TemperatureUnit {
-    fn canonical_string(&self) -> &'static str {
-        match self {
-            TemperatureUnit::Kelvin => "K",
-            TemperatureUnit::Celcius => "C",
-            TemperatureUnit::Farenheit => "F",
-        }
+    fn all() -> &'static [Self] {
+        &[TemperatureUnit::Kelvin, TemperatureUnit::Celcius, TemperatureUnit::Farenheit]
     }
 
-    fn parse(s: &str) -> Result<Self, UnitError> {
-        match s.to_lowercase().as_str() {
-            "c" | "celcius" => Ok(TemperatureUnit::Celcius),
-            "f" | "farenheit" => Ok(TemperatureUnit::Farenheit),
-            "k" | "kelvin" => Ok(TemperatureUnit::Kelvin),
-            _ => Err(UnitError::UnknownUnit(s.to_string())),
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            TemperatureUnit::Kelvin => &["K", "kelvin"],
+            TemperatureUnit::Celcius => &["C", "celcius"],
+            TemperatureUnit::Farenheit => &["F", "farenheit"],
         }
     }
 
@@ -0,0 +1,131 @@
+use crate::{UnitType, UnitConversion, Dimension};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeUnit {
+    Nanosecond,
+    Microsecond,
+    Millisecond,
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+}
+
+
+impl UnitType for TimeUnit {
+    fn all() -> &'static [Self] {
+        &[
+            TimeUnit::Nanosecond,
+            TimeUnit::Microsecond,
+            TimeUnit::Millisecond,
+            TimeUnit::Second,
+            TimeUnit::Minute,
+            TimeUnit::Hour,
+            TimeUnit::Day,
+            TimeUnit::Week,
+        ]
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            TimeUnit::Nanosecond => &["ns", "nanosecond", "nanoseconds"],
+            TimeUnit::Microsecond => &["us", "microsecond", "microseconds"],
+            TimeUnit::Millisecond => &["ms", "millisecond", "milliseconds"],
+            TimeUnit::Second => &["s", "sec", "second", "seconds"],
+            TimeUnit::Minute => &["min", "minute", "minutes"],
+            TimeUnit::Hour => &["h", "hr", "hour", "hours"],
+            TimeUnit::Day => &["d", "day", "days"],
+            TimeUnit::Week => &["wk", "week", "weeks"],
+        }
+    }
+
+    fn dimension_name() -> &'static str {
+        "Time"
+    }
+}
+
+
+impl UnitConversion<TimeUnit> for Dimension<TimeUnit> {
+    fn to_base_value(unit: TimeUnit, value: f64) -> f64 {
+        match unit {
+            TimeUnit::Nanosecond => value / 1e9,
+            TimeUnit::Microsecond => value / 1e6,
+            TimeUnit::Millisecond => value / 1000.0,
+            TimeUnit::Second => value,
+            TimeUnit::Minute => value * 60.0,
+            TimeUnit::Hour => value * 3600.0,
+            TimeUnit::Day => value * 86400.0,
+            TimeUnit::Week => value * 604800.0,
+        }
+    }
+
+    fn from_base_value(base_value: f64, unit: TimeUnit) -> f64 {
+        match unit {
+            TimeUnit::Nanosecond => base_value * 1e9,
+            TimeUnit::Microsecond => base_value * 1e6,
+            TimeUnit::Millisecond => base_value * 1000.0,
+            TimeUnit::Second => base_value,
+            TimeUnit::Minute => base_value / 60.0,
+            TimeUnit::Hour => base_value / 3600.0,
+            TimeUnit::Day => base_value / 86400.0,
+            TimeUnit::Week => base_value / 604800.0,
+        }
+    }
+
+    fn base_unit() -> TimeUnit {
+        TimeUnit::Second
+    }
+}
+
+
+/// Type alias for the concrete time dimension
+pub type TimeDimension = Dimension<TimeUnit>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_creation() {
+        let time = TimeDimension::from_unit("min", 90.0).unwrap();
+        assert_eq!(time.value(), 90.0);
+        assert_eq!(time.unit(), TimeUnit::Minute);
+    }
+
+    #[test]
+    fn test_minutes_to_hours() {
+        let time = TimeDimension::new(90.0, TimeUnit::Minute);
+        let in_hours = time.convert_to(TimeUnit::Hour);
+        assert!((in_hours.value() - 1.5).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_milliseconds_to_seconds() {
+        let time = TimeDimension::from_unit("ms", 2500.0).unwrap();
+        let in_seconds = time.convert_to(TimeUnit::Second);
+        assert!((in_seconds.value() - 2.5).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_display() {
+        let time = TimeDimension::from_unit("hours", 2.0).unwrap();
+        assert_eq!(format!("{}", time), "2h");
+    }
+
+    #[test]
+    fn test_every_name_parses() {
+        for &unit in TimeUnit::all() {
+            for name in unit.names() {
+                assert_eq!(TimeUnit::parse(&name.to_uppercase()), Ok(unit));
+            }
+        }
+        assert_eq!(TimeUnit::Minute.canonical_string(), "min");
+    }
+
+    #[test]
+    fn test_unknown_unit() {
+        let result = TimeDimension::from_unit("xyz", 10.0);
+        assert!(result.is_err());
+    }
+}
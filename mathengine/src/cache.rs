@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use mathengine_parser::types::Value;
+use mathengine_parser::{Expression, Statement};
+
+use crate::{MathEngine, MathEngineError};
+
+// Builtins that give a different answer each call, whose results mustn't be
+// remembered
+const RANDOM_FUNCTIONS: &[&str] = &["rand", "randint", "roll"];
+
+// The last number handed out by `next_bindings`. Numbers are unique across
+// every engine, since clones of an engine share its cache
+static LAST_BINDINGS: AtomicU64 = AtomicU64::new(0);
+
+/// A number for a new set of variables and functions, for the engine to key
+/// cached results on rather than the bindings themselves, which a session's
+/// history makes grow with every line.
+pub(crate) fn next_bindings() -> u64 {
+    LAST_BINDINGS.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// The results of recently evaluated expressions, least recently used
+/// dropped first, for [`MathEngineBuilder::cache`](crate::MathEngineBuilder::cache).
+///
+/// Clones of an engine share its cache. Entries are keyed on which variables
+/// and functions the engine had as well as the expression, so a session's
+/// engine never gets a result worked out with other bindings.
+#[derive(Debug, Clone)]
+pub(crate) struct EvalCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+struct Key {
+    // The expression with its whitespace collapsed
+    expression: String,
+    // The variables and functions it was evaluated with, as numbered by
+    // `next_bindings`
+    bindings: u64,
+}
+
+#[derive(Debug)]
+struct Entries {
+    capacity: usize,
+    values: HashMap<Key, (Value, u64)>,
+    // Keys by when they were last used, oldest first
+    used: BTreeMap<u64, Key>,
+    clock: u64,
+}
+
+impl EvalCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries {
+                capacity,
+                values: HashMap::new(),
+                used: BTreeMap::new(),
+                clock: 0,
+            })),
+        }
+    }
+
+    fn get(&self, key: &Key) -> Option<Value> {
+        let mut entries = self.lock();
+        let tick = entries.tick();
+        let (value, used) = entries.values.get_mut(key)?;
+        let (value, last) = (value.clone(), std::mem::replace(used, tick));
+        entries.used.remove(&last);
+        entries.used.insert(tick, key.clone());
+        Some(value)
+    }
+
+    fn insert(&self, key: Key, value: Value) {
+        let mut entries = self.lock();
+        let tick = entries.tick();
+        if let Some((_, last)) = entries.values.insert(key.clone(), (value, tick)) {
+            entries.used.remove(&last);
+        }
+        entries.used.insert(tick, key);
+        while entries.values.len() > entries.capacity {
+            let Some((_, oldest)) = entries.used.pop_first() else {
+                break;
+            };
+            entries.values.remove(&oldest);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.lock().values.len()
+    }
+
+    fn clear(&self) {
+        let mut entries = self.lock();
+        entries.values.clear();
+        entries.used.clear();
+    }
+
+    // A panic mid-update leaves the entries consistent enough to keep using
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Entries {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl MathEngine {
+    /// How many results the engine has cached, which is zero for engines
+    /// built without a [`cache`](crate::MathEngineBuilder::cache).
+    pub fn cached_results(&self) -> usize {
+        self.cache.as_ref().map_or(0, EvalCache::len)
+    }
+
+    /// Forget every cached result, for when something results depend on
+    /// has changed, like the rates a [`RateProvider`](crate::RateProvider)
+    /// gives.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    // Evaluate through the cache, if the engine has one and its results can
+    // be cached: resolvers are asked each time, since what they return can
+    // change
+    pub(crate) fn evaluate_cached(&self, expression: &str) -> Option<Result<Value, MathEngineError>> {
+        let cache = self.cache.as_ref().filter(|_| self.context.resolver.is_none())?;
+        let key = self.key(expression);
+        if let Some(value) = cache.get(&key) {
+            return Some(Ok(value));
+        }
+
+        let statement = match self.parse(expression) {
+            Ok(statement) => statement,
+            Err(err) => return Some(Err(err)),
+        };
+        let repeatable = match &statement {
+            Statement::Expression(expr) => {
+                !calls_random(expr) && !self.context.functions.values().any(|function| calls_random(&function.body))
+            }
+            _ => false,
+        };
+        // Errors aren't cached, since some, like timeouts, might not happen
+        // again
+        let result = self.evaluate_traced(expression, statement);
+        if let (true, Ok(value)) = (repeatable, &result) {
+            cache.insert(key, value.clone());
+        }
+        Some(result)
+    }
+
+    fn key(&self, expression: &str) -> Key {
+        Key {
+            expression: expression.split_whitespace().collect::<Vec<_>>().join(" "),
+            bindings: self.bindings,
+        }
+    }
+}
+
+// Whether `expr` calls a random builtin anywhere. Walks the tree on an
+// explicit stack, like evaluation, so deep expressions can't overflow
+fn calls_random(expr: &Expression) -> bool {
+    let mut nodes = vec![expr];
+    while let Some(node) = nodes.pop() {
+        match node {
+            Expression::FunctionCall { name, args, .. } => {
+                if RANDOM_FUNCTIONS.contains(&name.as_str()) {
+                    return true;
+                }
+                nodes.extend(args);
+            }
+            Expression::List(items) => nodes.extend(items),
+            Expression::Binary { left, right, .. } => nodes.extend([&**left, &**right]),
+            Expression::Percent(operand) | Expression::Factorial { operand, .. } | Expression::Unary { operand, .. } => {
+                nodes.push(operand)
+            }
+            Expression::Number(_)
+            | Expression::Decimal { .. }
+            | Expression::Integer(_)
+            | Expression::Bool(_)
+            | Expression::UnitValue { .. }
+            | Expression::Unit(_) => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MathEngine, Session};
+
+    #[test]
+    fn test_lookups_after_long_history() {
+        let mut session = Session::with_engine(MathEngine::builder().cache(10).build());
+        session.eval("x = 2").unwrap();
+        for n in 0..10_000 {
+            session.eval(format!("{} + 1", n)).unwrap();
+        }
+
+        // The key is the same size however many `_N` the history made
+        let key = session.engine().key("x  *  3");
+        assert_eq!(key.expression, "x * 3");
+        assert_eq!(key, session.engine().key("x * 3"));
+
+        for _ in 0..1_000 {
+            assert_eq!(session.engine().evaluate("x * 3").unwrap().to_string(), "6");
+        }
+        assert_eq!(session.engine().cached_results(), 1);
+    }
+
+    #[test]
+    fn test_rebinding_misses() {
+        let engine = MathEngine::builder().cache(10).build();
+        let mut first = Session::with_engine(engine.clone());
+        let mut second = Session::with_engine(engine);
+        first.eval("x = 1").unwrap();
+        second.eval("x = 2").unwrap();
+        assert_eq!(first.engine().evaluate("x * 3").unwrap().to_string(), "3");
+        assert_eq!(second.engine().evaluate("x * 3").unwrap().to_string(), "6");
+
+        first.eval("x = 5").unwrap();
+        assert_eq!(first.engine().evaluate("x * 3").unwrap().to_string(), "15");
+        first.clear();
+        assert!(first.engine().evaluate("x * 3").is_err());
+        assert_eq!(first.engine().cached_results(), 3);
+    }
+}
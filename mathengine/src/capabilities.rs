@@ -0,0 +1,129 @@
+use mathengine_evaluator::{currency, functions};
+use mathengine_parser::types::{DimensionType, Unit};
+
+use crate::MathEngine;
+
+// Operators in the order the parser binds them, loosest first
+const OPERATORS: &[&str] = &[
+    "or", "and", "not", "==", "!=", "<", "<=", ">", ">=", "+", "-", "*", "/", "of", "^", "to",
+    "!", "%",
+];
+
+/// What an engine understands, from [`MathEngine::capabilities`], for
+/// generating help, autocompletion and documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Operator symbols and keywords, from the loosest binding (`or`) to
+    /// the tightest (the postfix `!` and `%`)
+    pub operators: Vec<&'static str>,
+    /// Builtin functions and any defined on the engine's context, sorted
+    pub functions: Vec<String>,
+    /// `true`, `false` and the variables set on the engine's context, sorted
+    pub constants: Vec<String>,
+    /// The dimensions units are measured in
+    pub dimensions: Vec<DimensionType>,
+    /// Every unit, with the names it can be written as
+    pub units: Vec<UnitNames>,
+    /// Currency codes, when the engine has exchange rates to convert them
+    pub currencies: Vec<&'static str>,
+}
+
+/// A unit and the other names it goes by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitNames {
+    /// The canonical name, used in results
+    pub unit: String,
+    /// [`DimensionType::Unknown`] for units only known from aliases, like
+    /// currencies, and units of dimensions from a
+    /// [`DimensionProvider`](crate::DimensionProvider)
+    pub dimension: DimensionType,
+    /// Built-in spellings and the engine's aliases
+    pub aliases: Vec<String>,
+}
+
+impl MathEngine {
+    /// Describe the operators, functions, constants and units expressions
+    /// can use with this engine's configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{DimensionType, MathEngine, UnitRegistry};
+    ///
+    /// let engine = MathEngine::builder()
+    ///     .units(UnitRegistry::new().with_alias("metre", "m"))
+    ///     .build();
+    /// let capabilities = engine.capabilities();
+    ///
+    /// assert!(capabilities.functions.iter().any(|name| name == "sqrt"));
+    /// assert!(capabilities.dimensions.contains(&DimensionType::Length));
+    /// let meter = capabilities.units.iter().find(|unit| unit.unit == "m").unwrap();
+    /// assert_eq!(meter.aliases, ["meter", "meters", "metre"]);
+    /// ```
+    pub fn capabilities(&self) -> Capabilities {
+        let context = self.context();
+
+        let mut functions: Vec<String> = functions::builtins().into_iter().map(String::from).collect();
+        functions.extend(context.functions.keys().cloned());
+        functions.sort();
+        functions.dedup();
+
+        let mut constants = vec!["true".to_string(), "false".to_string()];
+        constants.extend(context.variables.keys().cloned());
+        constants.sort();
+
+        let mut units: Vec<UnitNames> = Unit::all()
+            .into_iter()
+            .map(|unit| UnitNames {
+                unit: unit.canonical_string().to_string(),
+                dimension: unit.dimension_type(),
+                aliases: unit.names()[1..].iter().map(|name| name.to_string()).collect(),
+            })
+            .collect();
+        let mut dimensions: Vec<DimensionType> = Vec::new();
+        for unit in &units {
+            if !dimensions.contains(&unit.dimension) {
+                dimensions.push(unit.dimension);
+            }
+        }
+
+        let mut aliases: Vec<(&str, &str)> = self.units().aliases().collect();
+        aliases.sort();
+        for (alias, target) in aliases {
+            match units.iter_mut().find(|unit| unit.unit == target) {
+                Some(unit) => unit.aliases.push(alias.to_string()),
+                None => units.push(UnitNames {
+                    unit: target.to_string(),
+                    dimension: DimensionType::Unknown,
+                    aliases: vec![alias.to_string()],
+                }),
+            }
+        }
+
+        for provider in &context.dimensions {
+            for unit in provider.units() {
+                if !units.iter().any(|known| known.unit == unit) {
+                    units.push(UnitNames {
+                        unit,
+                        dimension: DimensionType::Unknown,
+                        aliases: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        let currencies = match context.rates {
+            Some(_) => currency::currencies().to_vec(),
+            None => Vec::new(),
+        };
+
+        Capabilities {
+            operators: OPERATORS.to_vec(),
+            functions,
+            constants,
+            dimensions,
+            units,
+            currencies,
+        }
+    }
+}
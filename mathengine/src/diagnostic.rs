@@ -0,0 +1,139 @@
+use mathengine_evaluator::{EvalError, RateError};
+use mathengine_lexer::{LexError, Span};
+use mathengine_parser::ParseError;
+
+use crate::{MathEngine, MathEngineError, MessageCatalog};
+
+/// An error laid out for display, the way renderers like codespan and
+/// ariadne take them: a message, the parts of the source it's about, each
+/// with a label, and notes on how to fix it.
+///
+/// Label spans count characters of the source, as ariadne does by default.
+/// Renderers that count bytes, like codespan, need them converted.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{evaluate_expression, Severity, Span};
+///
+/// let source = "2 + (3 * 4";
+/// let diagnostic = evaluate_expression(source).unwrap_err().diagnostic(source);
+/// assert_eq!(diagnostic.severity, Severity::Error);
+/// assert_eq!(diagnostic.code, "parser.unexpected_end_of_input");
+/// assert_eq!(diagnostic.message, "Expected ')' but reached end of input");
+/// assert_eq!(diagnostic.labels, [(Span::new(10, 11), "expected ')'".to_string())]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The error's [`code`](MathEngineError::code), like
+    /// `eval.division_by_zero`
+    pub code: &'static str,
+    pub message: String,
+    /// The parts of the source the diagnostic is about, the first being
+    /// where it went wrong. Empty when it isn't about any one part
+    pub labels: Vec<(Span, String)>,
+    /// Hints on how to fix it
+    pub notes: Vec<String>,
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The expression couldn't be evaluated
+    Error,
+    /// The expression evaluated, but likely not as intended
+    Warning,
+}
+
+impl MathEngineError {
+    /// Describe the error as a [`Diagnostic`] about `source`, the expression
+    /// that produced it, with English messages.
+    pub fn diagnostic(&self, source: &str) -> Diagnostic {
+        self.diagnostic_with(&MessageCatalog::default(), source)
+    }
+
+    fn diagnostic_with(&self, messages: &MessageCatalog, source: &str) -> Diagnostic {
+        let message = messages.message(self);
+        let labels = match self.locate(source) {
+            Some(span) => vec![(span, self.label().unwrap_or_else(|| message.clone()))],
+            None => Vec::new(),
+        };
+        Diagnostic {
+            severity: Severity::Error,
+            code: self.code(),
+            message,
+            labels,
+            notes: self.notes(),
+        }
+    }
+
+    // What to say under the part of the source that failed, when there's
+    // something shorter than the message
+    fn label(&self) -> Option<String> {
+        match self {
+            MathEngineError::Lexer(LexError::UnexpectedCharacter { .. }) => {
+                Some("unexpected character".to_string())
+            }
+            MathEngineError::Lexer(LexError::InvalidNumber { .. }) => Some("invalid number".to_string()),
+            MathEngineError::Parser(
+                ParseError::UnexpectedToken { expected, .. } | ParseError::UnexpectedEndOfInput { expected },
+            ) => Some(format!("expected {}", expected)),
+            MathEngineError::Parser(ParseError::InvalidExpression { message, .. }) => Some(message.clone()),
+            MathEngineError::Parser(ParseError::TooDeep { .. }) => Some("nested too deeply".to_string()),
+            _ => None,
+        }
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let note = match self {
+            MathEngineError::Parser(ParseError::InvalidExpression { message, .. })
+                if message.contains("need a Session") =>
+            {
+                "evaluate definitions with a Session, which keeps variables and functions between lines"
+            }
+            MathEngineError::Parser(ParseError::InputTooLarge { .. }) => {
+                "the engine's InputLimits set how large an expression may be"
+            }
+            MathEngineError::Evaluator(e) => match e.without_span() {
+                EvalError::RateUnavailable {
+                    reason: RateError::NoProvider,
+                    ..
+                } => "currencies convert with the exchange rates of a RateProvider set on the engine",
+                EvalError::InvalidUnitExpression { message } if message.contains("without a value") => {
+                    "bind identifiers as variables, or supply them with a Resolver"
+                }
+                EvalError::NodeLimitExceeded { .. }
+                | EvalError::DepthLimitExceeded { .. }
+                | EvalError::OperationLimitExceeded { .. }
+                | EvalError::Timeout { .. } => "the engine's EvalLimits set how much work an expression may take",
+                _ => return Vec::new(),
+            },
+            MathEngineError::Type(_) => "strict mode checks dimensions before evaluating",
+            _ => return Vec::new(),
+        };
+        vec![note.to_string()]
+    }
+}
+
+impl MathEngine {
+    /// Describe `error` as a [`Diagnostic`] about `source`, with messages
+    /// from this engine's catalog.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{MathEngine, Span};
+    ///
+    /// let engine = MathEngine::new();
+    /// let source = "1 + 10 / (5 - 5)";
+    /// let error = engine.evaluate(source).unwrap_err();
+    /// let diagnostic = engine.diagnostic(&error, source);
+    /// assert_eq!(diagnostic.code, "eval.division_by_zero");
+    /// assert_eq!(diagnostic.labels, [(Span::new(4, 16), "Division by zero".to_string())]);
+    /// assert!(diagnostic.notes.is_empty());
+    /// ```
+    pub fn diagnostic(&self, error: &MathEngineError, source: &str) -> Diagnostic {
+        error.diagnostic_with(&self.messages, source)
+    }
+}
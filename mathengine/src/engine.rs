@@ -0,0 +1,680 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mathengine_evaluator::{check, evaluate_with_context, evaluate_with_observer, solve, DimensionProvider, EvalError, EvalObserver, EvalLimits, EvaluationContext, NumberMode, RateProvider, Resolver};
+use mathengine_lexer::{Lexer, Span, Token};
+use mathengine_parser::types::{FormatOptions, Locale, Scalar, Unit, Value};
+use mathengine_parser::{CancellationToken, Expression, InputLimit, InputLimits, ParseError, Parser, Statement};
+
+use crate::cache::{next_bindings, EvalCache};
+use crate::trace::Phase;
+#[cfg(feature = "tracing")]
+use crate::Tracer;
+use crate::{definition_error, MathEngineError, MessageCatalog};
+
+/// Extra names for units, like `metre` for meters.
+///
+/// Aliases are case-insensitive, like the units they stand for. An alias for
+/// a known unit resolves to its canonical name, so results read `m` rather
+/// than `metre`; anything else, like a currency code, is used as written.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{MathEngine, UnitRegistry};
+///
+/// let units = UnitRegistry::new()
+///     .with_alias("metre", "m")
+///     .with_alias("klick", "km");
+/// let engine = MathEngine::builder().units(units).build();
+/// assert_eq!(engine.evaluate("2 klick to metre").unwrap().to_string(), "2000m");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UnitRegistry {
+    // Shared between clones, so every engine and session built from one
+    // registry uses the same table
+    aliases: Arc<HashMap<String, Arc<str>>>,
+}
+
+impl UnitRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `alias` as another name for `unit`. Plurals aren't worked out, so
+    /// add each spelling that should be accepted.
+    pub fn with_alias(mut self, alias: &str, unit: &str) -> Self {
+        let unit = match Unit::parse(unit) {
+            Ok(known) => known.canonical_string().into(),
+            Err(_) => unit.to_lowercase().into(),
+        };
+        Arc::make_mut(&mut self.aliases).insert(alias.to_lowercase(), unit);
+        self
+    }
+
+    /// Also accept the names units go by in `language`, like `Fuß` and
+    /// `Stunden` for `de`. Region subtags are ignored, so `de-AT` is `de`;
+    /// languages without a table add nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{MathEngine, UnitRegistry};
+    ///
+    /// let units = UnitRegistry::new().with_language("fr-FR");
+    /// let engine = MathEngine::builder().units(units).build();
+    /// assert_eq!(engine.evaluate("2 heures to minutes").unwrap().to_string(), "120min");
+    /// ```
+    pub fn with_language(self, language: &str) -> Self {
+        let language = language.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        let names = LOCALIZED_UNITS
+            .iter()
+            .find(|(table, _)| *table == language)
+            .map_or(&[][..], |(_, names)| names);
+        names.iter().fold(self, |units, (alias, unit)| units.with_alias(alias, unit))
+    }
+
+    /// The unit `name` stands for, if it's an alias.
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        self.aliases.get(&name.to_lowercase()).map(|unit| &**unit)
+    }
+
+    /// Each alias with the unit it stands for, in no particular order.
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases.iter().map(|(alias, unit)| (alias.as_str(), &**unit))
+    }
+
+    // Swap aliases in the token stream for the units they name. Identifiers
+    // followed by `(` are function calls and left alone
+    pub(crate) fn apply(&self, tokens: &mut [(Token, Span)]) {
+        if self.aliases.is_empty() {
+            return;
+        }
+        for i in 0..tokens.len() {
+            let calls = matches!(tokens.get(i + 1), Some((Token::Lparen, _)));
+            match &mut tokens[i].0 {
+                Token::UnitValue { unit, .. } => {
+                    if let Some(resolved) = self.aliases.get(&unit.to_lowercase()) {
+                        *unit = resolved.clone();
+                    }
+                }
+                Token::Unit(name) if !calls => {
+                    if let Some(resolved) = self.aliases.get(&name.to_lowercase()) {
+                        *name = resolved.to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Unit names in other languages, for `UnitRegistry::with_language`. Names
+// that are the same as the English ones, like `Meter`, are left out
+const LOCALIZED_UNITS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "de",
+        &[
+            ("Fuß", "ft"), ("Zoll", "in"), ("Meile", "mi"), ("Meilen", "mi"),
+            ("Sekunde", "s"), ("Sekunden", "s"), ("Minuten", "min"), ("Stunde", "h"),
+            ("Stunden", "h"), ("Tag", "d"), ("Tage", "d"), ("Woche", "wk"), ("Wochen", "wk"),
+        ],
+    ),
+    (
+        "es",
+        &[
+            ("metro", "m"), ("metros", "m"), ("kilómetro", "km"), ("kilómetros", "km"),
+            ("centímetro", "cm"), ("centímetros", "cm"), ("pie", "ft"), ("pies", "ft"),
+            ("pulgada", "in"), ("pulgadas", "in"), ("milla", "mi"), ("millas", "mi"),
+            ("segundo", "s"), ("segundos", "s"), ("minuto", "min"), ("minutos", "min"),
+            ("hora", "h"), ("horas", "h"), ("día", "d"), ("días", "d"),
+            ("semana", "wk"), ("semanas", "wk"),
+        ],
+    ),
+    (
+        "fr",
+        &[
+            ("mètre", "m"), ("mètres", "m"), ("kilomètre", "km"), ("kilomètres", "km"),
+            ("centimètre", "cm"), ("centimètres", "cm"), ("pied", "ft"), ("pieds", "ft"),
+            ("pouce", "in"), ("pouces", "in"), ("seconde", "s"), ("secondes", "s"),
+            ("heure", "h"), ("heures", "h"), ("jour", "d"), ("jours", "d"),
+            ("semaine", "wk"), ("semaines", "wk"),
+        ],
+    ),
+];
+
+/// A configured evaluator.
+///
+/// Where [`evaluate_expression`](crate::evaluate_expression) always uses the
+/// defaults, an engine is set up once with [`MathEngine::builder`] and then
+/// evaluates any number of expressions with the same configuration.
+///
+/// Evaluating only reads the engine, and it's `Send + Sync`, so one engine
+/// can serve many threads, like the request handlers of a web server, behind
+/// an [`Arc`] or a `&'static`. Cloning is cheap too: unit tables and rate
+/// providers are shared rather than copied.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{EvalLimits, Locale, MathEngine};
+///
+/// let engine = MathEngine::builder()
+///     .strict(true)
+///     .precision(2)
+///     .locale(Locale::from_tag("de-DE").unwrap())
+///     .limits(EvalLimits {
+///         max_depth: Some(64),
+///         ..EvalLimits::default()
+///     })
+///     .build();
+///
+/// let result = engine.evaluate("1234.5678m + 1cm").unwrap();
+/// assert_eq!(engine.format(&result), "1.234,58m");
+///
+/// // Strict mode rejects mismatched units instead of keeping the left side
+/// assert!(engine.evaluate("5m + 3C").is_err());
+/// ```
+///
+/// Sharing one engine between threads:
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use mathengine::MathEngine;
+///
+/// let engine = Arc::new(MathEngine::builder().precision(1).build());
+/// let handlers: Vec<_> = (1..=4)
+///     .map(|n| {
+///         let engine = Arc::clone(&engine);
+///         thread::spawn(move || engine.format(&engine.evaluate(format!("{n}m / 3")).unwrap()))
+///     })
+///     .collect();
+/// let results: Vec<String> = handlers.into_iter().map(|h| h.join().unwrap()).collect();
+/// assert_eq!(results, ["0.3m", "0.7m", "1m", "1.3m"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MathEngine {
+    #[cfg(feature = "tracing")]
+    pub(crate) tracer: Option<Arc<dyn Tracer>>,
+    pub(crate) context: EvaluationContext,
+    units: UnitRegistry,
+    pub(crate) strict: bool,
+    pub(crate) format: FormatOptions,
+    pub(crate) messages: MessageCatalog,
+    // The separators expressions are typed with
+    pub(crate) input_locale: Locale,
+    pub(crate) cache: Option<EvalCache>,
+    // Which variables and functions `context` holds, for keying cached
+    // results. Renumbered whenever they might change
+    pub(crate) bindings: u64,
+    input_limits: InputLimits,
+}
+
+impl MathEngine {
+    /// An engine with the default configuration, which evaluates like
+    /// [`evaluate_expression`](crate::evaluate_expression).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start configuring an engine.
+    pub fn builder() -> MathEngineBuilder {
+        MathEngineBuilder::default()
+    }
+
+    /// Evaluate an expression with this engine's configuration.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`evaluate_expression`](crate::evaluate_expression), and in
+    /// strict mode also with [`MathEngineError::Type`] for expressions whose
+    /// dimensions don't check.
+    pub fn evaluate<S: AsRef<str>>(&self, expression: S) -> Result<Value, MathEngineError> {
+        let expression = expression.as_ref();
+        match self.evaluate_cached(expression) {
+            Some(result) => result,
+            None => self.evaluate_traced(expression, self.parse(expression)?),
+        }
+    }
+
+    /// Evaluate an expression like [`evaluate`](Self::evaluate), stopping
+    /// with a `Cancelled` error soon after `token` is cancelled, so a host
+    /// can give up on a long evaluation from another thread.
+    ///
+    /// The token is checked every so often while parsing and evaluating, and
+    /// takes the place of any [`EvalLimits::cancel`] the engine was built
+    /// with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{CancellationToken, MathEngine, MathEngineError, ParseError};
+    ///
+    /// let engine = MathEngine::new();
+    /// let token = CancellationToken::new();
+    /// assert_eq!(engine.evaluate_cancellable("2 + 3", &token).unwrap().to_string(), "5");
+    ///
+    /// token.cancel();
+    /// assert!(matches!(
+    ///     engine.evaluate_cancellable("2 + 3", &token),
+    ///     Err(MathEngineError::Parser(ParseError::Cancelled))
+    /// ));
+    /// ```
+    pub fn evaluate_cancellable<S: AsRef<str>>(
+        &self,
+        expression: S,
+        token: &CancellationToken,
+    ) -> Result<Value, MathEngineError> {
+        let mut engine = self.clone();
+        engine.context.limits.cancel = Some(token.clone());
+        engine.evaluate(expression)
+    }
+
+    /// Evaluate an expression in `T`'s representation and read the result
+    /// as a `T`, converted to `unit` when given, like
+    /// [`Value::to_scalar`].
+    ///
+    /// Literals are read and arithmetic is done in the number mode
+    /// [`NumberMode::for_scalar`] picks for `T`, whatever mode the engine
+    /// was built with, so `i64` results are worked out exactly and
+    /// `Decimal` ones in decimals. Engines built with
+    /// [`MathEngineBuilder::scalar`] for `T` already evaluate that way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::MathEngine;
+    ///
+    /// let engine = MathEngine::new();
+    /// assert_eq!(engine.evaluate_as::<i64, _>("(1/3 + 2/3) * 6", None).unwrap(), 6);
+    /// assert!(engine.evaluate_as::<i64, _>("1 / 3", None).is_err());
+    /// assert_eq!(engine.evaluate_as::<f32, _>("150cm", Some("m")).unwrap(), 1.5);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`evaluate`](Self::evaluate), and with an evaluation error
+    /// when the result has another unit or can't be represented in `T`.
+    pub fn evaluate_as<T: Scalar, S: AsRef<str>>(&self, expression: S, unit: Option<&str>) -> Result<T, MathEngineError> {
+        let mode = NumberMode::for_scalar::<T>();
+        let value = if self.context.number_mode == mode {
+            self.evaluate(expression)?
+        } else {
+            // Cached results aren't keyed on the number mode, so they're
+            // neither used nor added to
+            let mut engine = self.clone();
+            engine.context.number_mode = mode;
+            engine.cache = None;
+            engine.evaluate(expression)?
+        };
+        Ok(value.to_scalar(unit).map_err(EvalError::from)?)
+    }
+
+    // Evaluate the statement parsed from `expression`, timing it for the
+    // tracer
+    pub(crate) fn evaluate_traced(&self, expression: &str, statement: Statement) -> Result<Value, MathEngineError> {
+        let stage = self.stage(Phase::Eval);
+        let result = self.evaluate_statement(statement);
+        stage.finish(expression, &result);
+        result
+    }
+
+    pub(crate) fn evaluate_statement(&self, statement: Statement) -> Result<Value, MathEngineError> {
+        match statement {
+            Statement::Expression(expr) => self.evaluate_parsed(&expr),
+            Statement::Solve { equation, variable } => Ok(solve(&equation, &variable, &self.context)?),
+            Statement::Assign { .. } | Statement::Define { .. } => Err(definition_error()),
+        }
+    }
+
+    pub(crate) fn parse(&self, expression: &str) -> Result<Statement, MathEngineError> {
+        let stage = self.stage(Phase::Lex);
+        let tokens = self.lex(expression);
+        stage.finish(expression, &tokens);
+
+        let stage = self.stage(Phase::Parse);
+        let statement = self.parser(tokens?).parse_statement();
+        stage.finish(expression, &statement);
+        Ok(statement?)
+    }
+
+    // A parser for `tokens` that keeps to the engine's input limits and
+    // cancellation token
+    pub(crate) fn parser(&self, tokens: Vec<(Token, Span)>) -> Parser<std::vec::IntoIter<(Token, Span)>> {
+        let parser = Parser::with_spans(tokens).with_limits(self.input_limits);
+        match &self.context.limits.cancel {
+            Some(token) => parser.with_cancellation(token.clone()),
+            None => parser,
+        }
+    }
+
+    // Tokens with unit aliases swapped for the units they name. Input over
+    // the length limit isn't lexed, and lexing stops just past the token
+    // limit, for the parser to report
+    pub(crate) fn lex(&self, expression: &str) -> Result<Vec<(Token, Span)>, MathEngineError> {
+        if let Some(limit) = self.input_limits.max_length {
+            if expression.chars().count() > limit {
+                return Err(ParseError::InputTooLarge { exceeded: InputLimit::Length, limit }.into());
+            }
+        }
+        let expression = self.input_locale.delocalize(expression)?;
+        let lexer = Lexer::new(&*expression);
+        let limit = self.input_limits.max_tokens.map_or(usize::MAX, |limit| limit.saturating_add(1));
+        let mut tokens = lexer.tokens().take(limit).collect::<Result<Vec<_>, _>>()?;
+        self.units.apply(&mut tokens);
+        Ok(tokens)
+    }
+
+    pub(crate) fn evaluate_parsed(&self, expr: &Expression) -> Result<Value, MathEngineError> {
+        if self.strict {
+            check(expr)?;
+        }
+        Ok(evaluate_with_context(expr, &self.context)?)
+    }
+
+    pub(crate) fn evaluate_parsed_with_observer<O: EvalObserver + ?Sized>(
+        &self,
+        expr: &Expression,
+        observer: &mut O,
+    ) -> Result<Value, MathEngineError> {
+        if self.strict {
+            check(expr)?;
+        }
+        Ok(evaluate_with_observer(expr, &self.context, observer)?)
+    }
+
+    pub(crate) fn context_mut(&mut self) -> &mut EvaluationContext {
+        self.bindings = next_bindings();
+        &mut self.context
+    }
+
+    /// The evaluation options expressions are run with.
+    pub fn context(&self) -> &EvaluationContext {
+        &self.context
+    }
+
+    /// The unit aliases expressions can use.
+    pub fn units(&self) -> &UnitRegistry {
+        &self.units
+    }
+
+    /// Whether expressions are dimension checked before they're evaluated.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// The translations errors are displayed with.
+    pub fn message_catalog(&self) -> &MessageCatalog {
+        &self.messages
+    }
+
+    /// How results should be displayed, for [`Value::format`].
+    pub fn format_options(&self) -> &FormatOptions {
+        &self.format
+    }
+
+    /// Display a value with this engine's format options, so every frontend
+    /// sharing the engine shows results the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{MathEngine, Notation, FormatOptions};
+    ///
+    /// let engine = MathEngine::builder()
+    ///     .format_options(FormatOptions {
+    ///         notation: Notation::Scientific,
+    ///         decimal_places: Some(2),
+    ///         ..FormatOptions::default()
+    ///     })
+    ///     .build();
+    /// let distance = engine.evaluate("1500m + 30m").unwrap();
+    /// assert_eq!(engine.format(&distance), "1.53e3m");
+    /// ```
+    pub fn format(&self, value: &Value) -> String {
+        value.format(&self.format)
+    }
+}
+
+// Engines, their configuration and sessions are all meant to cross threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<MathEngine>();
+    assert_send_sync::<MathEngineBuilder>();
+    assert_send_sync::<UnitRegistry>();
+    assert_send_sync::<MessageCatalog>();
+    assert_send_sync::<crate::Session>();
+};
+
+/// Configuration for a [`MathEngine`], from [`MathEngine::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct MathEngineBuilder {
+    engine: MathEngine,
+    cache: usize,
+}
+
+impl MathEngineBuilder {
+    /// Accept the unit aliases in `units` as well as the built-in names.
+    pub fn units(mut self, units: UnitRegistry) -> Self {
+        self.engine.units = units;
+        self
+    }
+
+    /// Dimension check each expression before evaluating it, so adding
+    /// mismatched or unknown units is an error rather than keeping the left
+    /// operand, as is adding a bare number to a quantity rather than
+    /// assuming it's in the quantity's unit.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.engine.strict = strict;
+        self
+    }
+
+    /// Numeric representation used for arithmetic.
+    pub fn number_mode(mut self, mode: NumberMode) -> Self {
+        self.engine.context.number_mode = mode;
+        self
+    }
+
+    /// Evaluate in `T`'s representation, the number mode
+    /// [`NumberMode::for_scalar`] picks for it, so results read with
+    /// [`MathEngine::evaluate_as`] or [`Value::to_scalar`] as a `T` were
+    /// worked out the way `T` holds numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{MathEngine, NumberMode};
+    ///
+    /// let engine = MathEngine::builder().scalar::<i64>().build();
+    /// assert_eq!(engine.context().number_mode, NumberMode::Rational);
+    /// assert_eq!(engine.evaluate("1/3 + 1/6").unwrap().to_string(), "0.5");
+    /// ```
+    pub fn scalar<T: Scalar>(self) -> Self {
+        self.number_mode(NumberMode::for_scalar::<T>())
+    }
+
+    /// Round displayed results to this many decimal places.
+    pub fn precision(mut self, decimal_places: usize) -> Self {
+        self.engine.format.decimal_places = Some(decimal_places);
+        self
+    }
+
+    /// Display results with this locale's separators.
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.engine.format.locale = locale;
+        self
+    }
+
+    /// Read expressions typed with this locale's separators, so `1,5 + 2`
+    /// is 3.5 and `1.500` is 1500 in German. See [`Locale::delocalize`] for
+    /// the rules.
+    pub fn input_locale(mut self, locale: Locale) -> Self {
+        self.engine.input_locale = locale;
+        self
+    }
+
+    /// Tell `tracer` how long lexing, parsing and evaluating each expression
+    /// takes, and whether each stage succeeded. See [`Tracer`].
+    #[cfg(feature = "tracing")]
+    pub fn tracer(mut self, tracer: Arc<dyn Tracer>) -> Self {
+        self.engine.tracer = Some(tracer);
+        self
+    }
+
+    /// Resource limits enforced for each evaluation.
+    pub fn limits(mut self, limits: EvalLimits) -> Self {
+        self.engine.context.limits = limits;
+        self
+    }
+
+    /// Caps on the length, tokens and syntax tree size of each expression,
+    /// which fail with [`ParseError::InputTooLarge`] before any of it is
+    /// evaluated, and on its nesting and height, which fail with
+    /// [`ParseError::TooDeep`]. Along with [`limits`](Self::limits), these bound the
+    /// memory and time a request can take.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{InputLimit, InputLimits, MathEngine, MathEngineError, ParseError};
+    ///
+    /// let engine = MathEngine::builder()
+    ///     .input_limits(InputLimits {
+    ///         max_length: Some(20),
+    ///         max_nodes: Some(5),
+    ///         ..InputLimits::default()
+    ///     })
+    ///     .build();
+    /// assert_eq!(engine.evaluate("1 + 2 * 3").unwrap().to_string(), "7");
+    /// assert!(matches!(
+    ///     engine.evaluate("1 + 2 * 3 - 4"),
+    ///     Err(MathEngineError::Parser(ParseError::InputTooLarge { exceeded: InputLimit::Nodes, limit: 5 }))
+    /// ));
+    /// assert!(matches!(
+    ///     engine.evaluate("1".repeat(21)),
+    ///     Err(MathEngineError::Parser(ParseError::InputTooLarge { exceeded: InputLimit::Length, .. }))
+    /// ));
+    /// ```
+    pub fn input_limits(mut self, limits: InputLimits) -> Self {
+        self.engine.input_limits = limits;
+        self
+    }
+
+    /// Replace the display options as a whole. Options set before this, like
+    /// [`precision`](Self::precision), are overwritten.
+    pub fn format_options(mut self, options: FormatOptions) -> Self {
+        self.engine.format = options;
+        self
+    }
+
+    /// Display errors with the translations in `messages`, for
+    /// [`MathEngine::message`] and [`MathEngine::render_error`].
+    pub fn messages(mut self, messages: MessageCatalog) -> Self {
+        self.engine.messages = messages;
+        self
+    }
+
+    /// Exchange rates for currency conversions.
+    pub fn rates(mut self, rates: Arc<dyn RateProvider>) -> Self {
+        self.engine.context.rates = Some(rates);
+        self
+    }
+
+    /// Add a dimension the unit tables don't have, like clothing sizes, so
+    /// its units convert with `provider`. Providers are asked in the order
+    /// they're added.
+    pub fn dimension(mut self, provider: Arc<dyn DimensionProvider>) -> Self {
+        self.engine.context.dimensions.push(provider);
+        self
+    }
+
+    /// Ask `resolver` for identifiers that aren't otherwise bound, so values
+    /// can come from a spreadsheet, database or feed as they're used. A
+    /// closure can be one, wrapped in a [`FnResolver`](crate::FnResolver).
+    pub fn resolver(mut self, resolver: Arc<dyn Resolver>) -> Self {
+        self.engine.context.resolver = Some(resolver);
+        self
+    }
+
+    /// Remember the results of the last `capacity` expressions evaluated,
+    /// so evaluating one again, like a UI does each time it redraws, skips
+    /// lexing, parsing and evaluating it. Zero, the default, caches nothing.
+    ///
+    /// Expressions are looked up as written, give or take whitespace, along
+    /// with the variables and functions they're evaluated with. Errors,
+    /// calls to random functions and engines with a [`resolver`](Self::resolver)
+    /// aren't cached. Results of currency conversions keep the rate they
+    /// were worked out with until [`MathEngine::clear_cache`] is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use mathengine::{MathEngine, RateError, RateProvider};
+    ///
+    /// #[derive(Debug, Default)]
+    /// struct Counted(AtomicUsize);
+    ///
+    /// impl RateProvider for Counted {
+    ///     fn rate(&self, _from: &str, _to: &str) -> Result<f64, RateError> {
+    ///         self.0.fetch_add(1, Ordering::Relaxed);
+    ///         Ok(0.5)
+    ///     }
+    /// }
+    ///
+    /// let rates = Arc::new(Counted::default());
+    /// let engine = MathEngine::builder().rates(rates.clone()).cache(100).build();
+    /// for _ in 0..3 {
+    ///     assert_eq!(engine.evaluate("10 usd to eur").unwrap().to_string(), "5eur");
+    /// }
+    /// assert_eq!(engine.evaluate("10  usd to eur").unwrap().to_string(), "5eur");
+    /// assert_eq!(rates.0.load(Ordering::Relaxed), 1);
+    /// assert_eq!(engine.cached_results(), 1);
+    ///
+    /// engine.clear_cache();
+    /// engine.evaluate("10 usd to eur").unwrap();
+    /// assert_eq!(rates.0.load(Ordering::Relaxed), 2);
+    /// ```
+    pub fn cache(mut self, capacity: usize) -> Self {
+        self.cache = capacity;
+        self
+    }
+
+    pub fn build(mut self) -> MathEngine {
+        // Made here rather than in `cache`, so engines built from clones of
+        // a builder don't share one
+        self.engine.cache = (self.cache > 0).then(|| EvalCache::new(self.cache));
+        self.engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MathEngine;
+
+    #[test]
+    fn test_evaluate_as_works_in_the_scalars_mode() {
+        let engine = MathEngine::builder().cache(10).build();
+        // In floats the sum isn't three tenths, so it's no whole integer
+        assert_eq!(engine.evaluate("(0.1 + 0.2) * 10").unwrap().to_string(), "3.0000000000000004");
+        assert_eq!(engine.evaluate_as::<i64, _>("(0.1 + 0.2) * 10", None).unwrap(), 3);
+        // and the exact result is neither taken from the cache nor put in it
+        assert_eq!(engine.cached_results(), 1);
+        assert_eq!(engine.evaluate_as::<f64, _>("(0.1 + 0.2) * 10", None).unwrap(), 3.0000000000000004);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_evaluate_as_decimal() {
+        use crate::Decimal;
+
+        let engine = MathEngine::new();
+        let tenths = |n| Decimal::new(n, 1);
+        assert_eq!(engine.evaluate_as::<Decimal, _>("0.1 + 0.2", None).unwrap(), tenths(3));
+        assert_eq!(engine.evaluate_as::<Decimal, _>("0.10 usd + 0.20 usd", Some("usd")).unwrap(), tenths(3));
+        let engine = MathEngine::builder().scalar::<Decimal>().build();
+        assert_eq!(engine.evaluate("0.1 + 0.2").unwrap().to_string(), "0.3");
+    }
+}
@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+
+use mathengine_evaluator::currency;
+use mathengine_lexer::{LexError, Lexer, Operation, Span, Token};
+use mathengine_parser::types::{Unit, Value};
+use mathengine_parser::Expression;
+
+use crate::MathEngine;
+
+// The most tokens a piece of math found in text is made of. Text is tried a
+// window at a time, so this bounds the work each position takes
+const MAX_INLINE_TOKENS: usize = 64;
+
+/// Math found in free text by [`MathEngine::extract_math`], with its result.
+#[derive(Debug, Clone)]
+pub struct InlineMath {
+    /// Where the math is in the text, in characters
+    pub span: Span,
+    /// The math as written
+    pub source: String,
+    pub value: Value,
+}
+
+impl MathEngine {
+    /// Find the calculations in a piece of prose, like the `3m + 2ft` in
+    /// "the fence is 3m + 2ft long", and evaluate them, for chat bots and note
+    /// apps answering the math in what they're given.
+    ///
+    /// A calculation is the longest run of tokens from a position that
+    /// evaluates, as long as it does more than write down a value, so lone
+    /// numbers and quantities aren't picked out. Words only count as math
+    /// when they're units, variables of the engine, or functions being called.
+    /// Anything that doesn't evaluate is left as text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::MathEngine;
+    ///
+    /// let engine = MathEngine::new();
+    /// let text = "The fence is 3m + 2ft long, and 20% of 50 is 10. So is 5 * 2.";
+    /// let found: Vec<_> = engine
+    ///     .extract_math(text)
+    ///     .iter()
+    ///     .map(|math| format!("{} = {}", math.source, math.value))
+    ///     .collect();
+    /// assert_eq!(found, ["3m + 2ft = 3.6096m", "20% of 50 = 10", "5 * 2 = 10"]);
+    ///
+    /// // Spans count characters of the text
+    /// let found = engine.extract_math("Über 2 * 21 Äpfel");
+    /// assert_eq!((found[0].span.start, found[0].span.end), (5, 11));
+    /// ```
+    pub fn extract_math(&self, text: &str) -> Vec<InlineMath> {
+        // Text with numbers the locale can't read, like dates, is searched as written
+        let delocalized = self.input_locale.delocalize(text).unwrap_or(Cow::Borrowed(text));
+        let chars: Vec<char> = delocalized.chars().collect();
+        let original: Vec<char> = text.chars().collect();
+        let mut found = Vec::new();
+        for mut lexed in lexed_runs(&chars) {
+            self.units().apply(&mut lexed);
+            for run in self.split_at_words(lexed, &chars) {
+                let mut start = 0;
+                while start < run.len() {
+                    match self.longest_calculation(&run[start..]) {
+                        Some((length, value)) => {
+                            let mut span = run[start].1.to(run[start + length - 1].1);
+                            // Take in the spaces standing in for the first
+                            // number's digit grouping
+                            while span.start > 0 && chars[span.start - 1] == ' ' && original[span.start - 1] != ' ' {
+                                span.start -= 1;
+                            }
+                            // A number ending a sentence takes its full stop
+                            // with it, as in `2.`, which isn't part of the math
+                            if chars[span.end - 1] == '.' {
+                                span.end -= 1;
+                            }
+                            found.push(InlineMath {
+                                span,
+                                source: original[span.start..span.end].iter().collect(),
+                                value,
+                            });
+                            start += length;
+                        }
+                        None => start += 1,
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    // The number of leading tokens making the longest calculation that
+    // evaluates, with its value
+    fn longest_calculation(&self, tokens: &[(Token, Span)]) -> Option<(usize, Value)> {
+        (1..=tokens.len().min(MAX_INLINE_TOKENS)).rev().find_map(|length| {
+            let expr = self.parser(tokens[..length].to_vec()).parse().ok()?;
+            if !is_calculation(&expr) {
+                return None;
+            }
+            Some((length, self.evaluate_parsed(&expr).ok()?))
+        })
+    }
+
+    // Split lexed tokens into runs of math at the words that aren't. A number
+    // the lexer took a word after as its unit, like `4 apples`, keeps the
+    // number and ends the run
+    fn split_at_words(&self, tokens: Vec<(Token, Span)>, text: &[char]) -> Vec<Vec<(Token, Span)>> {
+        let mut runs = Vec::new();
+        let mut run = Vec::new();
+        for i in 0..tokens.len() {
+            let (token, span) = &tokens[i];
+            let number = match token {
+                Token::UnitValue { unit, .. } if !self.is_unit(unit) => number_before_word(text, *span),
+                _ => None,
+            };
+            if let Some(number) = number {
+                run.push(number);
+                runs.push(std::mem::take(&mut run));
+            } else if self.is_math(&tokens, i) {
+                run.push((token.clone(), *span));
+            } else if !run.is_empty() {
+                runs.push(std::mem::take(&mut run));
+            }
+        }
+        runs.push(run);
+        runs.retain(|run| !run.is_empty());
+        runs
+    }
+
+    fn is_math(&self, tokens: &[(Token, Span)], i: usize) -> bool {
+        match &tokens[i].0 {
+            Token::Number { .. }
+            | Token::Integer(_)
+            | Token::UnitValue { .. }
+            | Token::Percent
+            | Token::Bang
+            | Token::Lparen
+            | Token::Rparen
+            | Token::Lbracket
+            | Token::Rbracket
+            | Token::Comma => true,
+            // `and`, `or` and `not` are more often just words
+            Token::Operation(op) => !matches!(op, Operation::And | Operation::Or | Operation::Not),
+            Token::Unit(name) => {
+                matches!(tokens.get(i + 1), Some((Token::Lparen, _)))
+                    || matches!(i.checked_sub(1).map(|i| &tokens[i].0), Some(Token::Operation(Operation::Convert)))
+                    || self.is_unit(name)
+            }
+            Token::Bool(_) | Token::Equals | Token::Solve | Token::For => false,
+        }
+    }
+
+    // Whether `name` means something to the engine after a number: a unit,
+    // currency or variable
+    fn is_unit(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        Unit::parse(&name).is_ok()
+            || currency::is_currency(&name)
+            || self.context.variables.contains_key(&name)
+            || self.context.dimensions.iter().any(|dimension| dimension.has_unit(&name))
+    }
+}
+
+// The number token at the start of `span`, before the word the lexer took as
+// its unit
+fn number_before_word(text: &[char], span: Span) -> Option<(Token, Span)> {
+    let digits = text[span.start..span.end].iter().take_while(|c| c.is_ascii_digit() || **c == '.').count();
+    let number: String = text[span.start..span.start + digits].iter().collect();
+    let token = Lexer::new(number).tokenize().ok()?.pop()?;
+    Some((token, Span::new(span.start, span.start + digits)))
+}
+
+// The tokens of each stretch of `text` the lexer reads, which end at
+// characters it doesn't, like a full stop after a word
+fn lexed_runs(text: &[char]) -> Vec<Vec<(Token, Span)>> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let lexer = Lexer::new(text[start..].iter().collect::<String>());
+        let mut run = Vec::new();
+        let mut next = text.len();
+        for token in lexer.tokens() {
+            match token {
+                Ok((token, span)) => run.push((token, Span::new(span.start + start, span.end + start))),
+                Err(LexError::UnexpectedCharacter { position, .. }) => {
+                    next = start + position + 1;
+                    break;
+                }
+                Err(LexError::InvalidNumber { input, position }) => {
+                    next = start + position + input.chars().count();
+                    break;
+                }
+                Err(LexError::EmptyInput) => break,
+            }
+        }
+        if !run.is_empty() {
+            runs.push(run);
+        }
+        start = next;
+    }
+    runs
+}
+
+// Whether `expr` works something out, rather than just writing down a value
+// like `3m` or `-2`. The parser caps how deeply this recurses
+fn is_calculation(expr: &Expression) -> bool {
+    match expr {
+        Expression::Number(_)
+        | Expression::Decimal { .. }
+        | Expression::Integer(_)
+        | Expression::Bool(_)
+        | Expression::UnitValue { .. }
+        | Expression::Unit(_) => false,
+        Expression::Unary { operand, .. } | Expression::Percent(operand) => is_calculation(operand),
+        Expression::List(items) => items.iter().any(is_calculation),
+        Expression::Binary { .. } | Expression::Factorial { .. } | Expression::FunctionCall { .. } => true,
+    }
+}
@@ -1,6 +1,38 @@
-use mathengine_evaluator::{evaluate, EvalError};
-use mathengine_lexer::{LexError, Lexer};
-use mathengine_parser::{ParseError, Parser};
+use mathengine_evaluator::{check, compile, evaluate_with_context, evaluate_with_observer, solve};
+use mathengine_lexer::Lexer;
+use mathengine_parser::{Parser, Statement};
+
+mod cache;
+mod capabilities;
+mod diagnostic;
+mod engine;
+mod inline;
+mod messages;
+#[cfg(feature = "serde")]
+mod service;
+mod session;
+mod significance;
+mod stream;
+mod table;
+mod timings;
+mod trace;
+mod validate;
+
+pub use capabilities::{Capabilities, UnitNames};
+pub use diagnostic::{Diagnostic, Severity};
+pub use engine::{MathEngine, MathEngineBuilder, UnitRegistry};
+pub use inline::InlineMath;
+pub use messages::MessageCatalog;
+#[cfg(feature = "serde")]
+pub use service::{EvalRequest, EvalResponse, RequestOptions, ResponseError};
+pub use session::{HistoryEntry, Session};
+pub use significance::Significant;
+pub use stream::{evaluate_stream, EvalStream, StreamLine};
+pub use table::ConversionTable;
+pub use timings::Timings;
+#[cfg(feature = "tracing")]
+pub use trace::{Phase, TraceEvent, Tracer};
+pub use validate::ValidationReport;
 
 /// Error type for expression evaluation
 #[derive(Debug)]
@@ -11,6 +43,8 @@ pub enum MathEngineError {
     Parser(ParseError),
     /// Error during evaluation
     Evaluator(EvalError),
+    /// Error found by static dimension checking
+    Type(TypeError),
 }
 
 impl std::fmt::Display for MathEngineError {
@@ -19,6 +53,90 @@ impl std::fmt::Display for MathEngineError {
             MathEngineError::Lexer(e) => write!(f, "Lexer error: {}", e),
             MathEngineError::Parser(e) => write!(f, "Parser error: {}", e),
             MathEngineError::Evaluator(e) => write!(f, "Evaluation error: {}", e),
+            MathEngineError::Type(e) => write!(f, "Type error: {}", e),
+        }
+    }
+}
+
+impl MathEngineError {
+    /// Show the error under the input it came from, with the part that
+    /// failed underlined.
+    ///
+    /// `source` should be the expression that produced the error. Errors
+    /// that aren't about any one part of it, like resource limits, are shown
+    /// without an underline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::evaluate_expression;
+    ///
+    /// let source = "1 + 10 / (5 - 5)";
+    /// let error = evaluate_expression(source).unwrap_err();
+    /// assert_eq!(
+    ///     error.render(source),
+    ///     "Evaluation error: Division by zero\n  1 + 10 / (5 - 5)\n      ^^^^^^^^^^^^"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let message = match self {
+            MathEngineError::Evaluator(e) => format!("Evaluation error: {}", e.without_span()),
+            MathEngineError::Type(e) => format!("Type error: {}", e.without_span()),
+            other => other.to_string(),
+        };
+        self.underline(message, source)
+    }
+
+    // `message` above `source`, with the part the error is about underlined
+    pub(crate) fn underline(&self, message: String, source: &str) -> String {
+        let Some(span) = self.locate(source) else {
+            return message;
+        };
+
+        // Keep tabs so the underline lines up with the source above it
+        let indent: String = source
+            .chars()
+            .take(span.start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = span.end.saturating_sub(span.start).max(1);
+        format!("{}\n  {}\n  {}{}", message, source, indent, "^".repeat(width))
+    }
+
+    // Where in `source` the error is, in characters. Parse errors count
+    // tokens, so the source is tokenized again to find them
+    pub(crate) fn locate(&self, source: &str) -> Option<Span> {
+        match self {
+            MathEngineError::Lexer(LexError::UnexpectedCharacter { position, .. }) => {
+                Some(Span::new(*position, position + 1))
+            }
+            MathEngineError::Lexer(LexError::InvalidNumber { input, position }) => {
+                Some(Span::new(*position, position + input.chars().count()))
+            }
+            MathEngineError::Lexer(LexError::EmptyInput) => None,
+            MathEngineError::Parser(ParseError::Lex(error)) => {
+                MathEngineError::Lexer(error.clone()).locate(source)
+            }
+            MathEngineError::Parser(error) => {
+                let tokens = Lexer::new(source).tokenize_with_spans().ok()?;
+                match error {
+                    ParseError::UnexpectedToken { position, .. }
+                    | ParseError::InvalidExpression { position, .. }
+                    | ParseError::TooDeep { position, .. } => {
+                        tokens.get(*position).map(|(_, span)| *span)
+                    }
+                    ParseError::UnexpectedEndOfInput { .. } => {
+                        let end = source.trim_end().chars().count();
+                        Some(Span::new(end, end + 1))
+                    }
+                    ParseError::EmptyTokenStream
+                    | ParseError::InputTooLarge { .. }
+                    | ParseError::Cancelled
+                    | ParseError::Lex(_) => None,
+                }
+            }
+            MathEngineError::Evaluator(error) => error.span(),
+            MathEngineError::Type(error) => error.span(),
         }
     }
 }
@@ -29,6 +147,7 @@ impl std::error::Error for MathEngineError {
             MathEngineError::Lexer(e) => Some(e),
             MathEngineError::Parser(e) => Some(e),
             MathEngineError::Evaluator(e) => Some(e),
+            MathEngineError::Type(e) => Some(e),
         }
     }
 }
@@ -41,7 +160,11 @@ impl From<LexError> for MathEngineError {
 
 impl From<ParseError> for MathEngineError {
     fn from(err: ParseError) -> Self {
-        MathEngineError::Parser(err)
+        match err {
+            // Parsing straight from the lexer finds its errors too
+            ParseError::Lex(err) => MathEngineError::Lexer(err),
+            err => MathEngineError::Parser(err),
+        }
     }
 }
 
@@ -51,6 +174,12 @@ impl From<EvalError> for MathEngineError {
     }
 }
 
+impl From<TypeError> for MathEngineError {
+    fn from(err: TypeError) -> Self {
+        MathEngineError::Type(err)
+    }
+}
+
 /// Evaluate a mathematical expression and return the computed value.
 ///
 /// This function supports:
@@ -59,6 +188,23 @@ impl From<EvalError> for MathEngineError {
 /// - Unit arithmetic: `1m + 50cm`, `2ft - 6in`
 /// - Unit conversions: `100cm to meters`, `32F to celsius`
 /// - Mixed expressions: `(1m + 2m) to feet`
+/// - Powers of units and square roots: `sqrt(9 m^2)`, `(3m)^2`
+/// - Trigonometric and exponential functions: `sin`, `cos`, `tan` (in radians),
+///   `exp`, `ln`
+/// - Percentages: `200 + 10%`, `200 - 10%`, `10% of 50`
+/// - Factorials of non-negative integers: `5!`
+/// - Lists and aggregates: `[1, 2, 3] * 2`, `sum`, `mean`, `median`, `stdev`,
+///   `min`, `max`. Aggregates over unit values work in the base unit of their
+///   shared dimension: `sum(1m, 30cm, 2ft)`
+/// - Booleans and logical operators: `true and not false`. Numbers are true
+///   when non-zero and lists when non-empty
+/// - Comparisons that convert units first: `1m < 200cm`, `32F == 0C`
+/// - Conditionals: `if(condition, then, else)`, evaluating only the chosen branch
+/// - Linear equations: `solve 2x + 3 = 7 for x`
+/// - Integer functions: `gcd`, `lcm`, `isprime`, `nCr`, `nPr`
+/// - Rounding that keeps units: `round(1.2345m, 2)`, `floor`, `ceil`, `trunc`
+/// - Hexadecimal, binary and octal literals (`0xff`, `0b1010`, `0o17`) and
+///   output: `hex(255)`, `bin`, `oct`
 ///
 /// # Examples
 ///
@@ -70,6 +216,15 @@ impl From<EvalError> for MathEngineError {
 /// assert_eq!(result.to_string(), "14");
 /// ```
 ///
+/// Integers stay exact until a fractional result appears:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("10 / 2").unwrap().to_string(), "5");
+/// assert_eq!(evaluate_expression("2^64 + 1").unwrap().to_string(), "18446744073709551617");
+/// assert_eq!(evaluate_expression("1 / 4").unwrap().to_string(), "0.25");
+/// ```
+///
 /// Unit arithmetic and conversion:
 /// ```
 /// use mathengine::evaluate_expression;
@@ -87,6 +242,104 @@ impl From<EvalError> for MathEngineError {
 /// // Returns approximately 9.84ft
 /// ```
 ///
+/// Adding or subtracting a percentage scales the left side:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("200 + 10%").unwrap().to_string(), "220");
+/// assert_eq!(evaluate_expression("200 - 10%").unwrap().to_string(), "180");
+/// assert_eq!(evaluate_expression("10% of 50").unwrap().to_string(), "5");
+/// ```
+///
+/// Aggregates over lists:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("sum([3, 5, 8])").unwrap().to_string(), "16");
+/// assert_eq!(evaluate_expression("median([3, 5, 8])").unwrap().to_string(), "5");
+/// assert_eq!(evaluate_expression("max(3, 5, 8)").unwrap().to_string(), "8");
+/// assert_eq!(evaluate_expression("max(1m, 150cm, 4ft)").unwrap().to_string(), "1.5m");
+/// assert!(evaluate_expression("sum(1m, 2C)").is_err());
+/// ```
+///
+/// Comparisons convert to a common unit:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("1m < 200cm").unwrap().to_string(), "true");
+/// assert_eq!(evaluate_expression("32F == 0C").unwrap().to_string(), "true");
+/// assert!(evaluate_expression("1m < 1C").is_err());
+/// ```
+///
+/// `if` skips the branch it doesn't take, so errors there don't surface:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// let result = evaluate_expression("if(2 > 1, 10m, 1 / 0)").unwrap();
+/// assert_eq!(result.to_string(), "10m");
+/// ```
+///
+/// Rounding keeps the unit, and takes an optional number of decimal places:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("round(1.2345m, 2)").unwrap().to_string(), "1.23m");
+/// assert_eq!(evaluate_expression("floor(2.7ft)").unwrap().to_string(), "2ft");
+/// assert_eq!(evaluate_expression("round(1234, -2)").unwrap().to_string(), "1200");
+/// ```
+///
+/// Integer functions stay exact:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("gcd(12, 18)").unwrap().to_string(), "6");
+/// assert_eq!(evaluate_expression("lcm(4, 6, 10)").unwrap().to_string(), "60");
+/// assert_eq!(evaluate_expression("isprime(2^61 - 1)").unwrap().to_string(), "true");
+/// assert_eq!(evaluate_expression("nCr(52, 5)").unwrap().to_string(), "2598960");
+/// ```
+///
+/// Programmer-style bases. Arithmetic treats the results as plain integers:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("hex(255)").unwrap().to_string(), "0xff");
+/// assert_eq!(evaluate_expression("bin(0xa)").unwrap().to_string(), "0b1010");
+/// assert_eq!(evaluate_expression("oct(8)").unwrap().to_string(), "0o10");
+/// assert_eq!(evaluate_expression("hex(255) + 1").unwrap().to_string(), "256");
+/// ```
+///
+/// Solving a linear equation gives the value of its variable:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// assert_eq!(evaluate_expression("solve 2x + 3 = 7 for x").unwrap().to_string(), "2");
+/// assert_eq!(evaluate_expression("solve (x - 1) / 4 = 2 for x").unwrap().to_string(), "9");
+/// assert!(evaluate_expression("solve x * x = 4 for x").is_err());
+/// ```
+///
+/// Units raised to a power:
+/// ```
+/// use mathengine::evaluate_expression;
+///
+/// // `m^2` binds to the unit, so this is nine square meters
+/// let result = evaluate_expression("sqrt(9 m^2)").unwrap();
+/// assert_eq!(result.to_string(), "3m");
+///
+/// // Pythagoras with units
+/// let result = evaluate_expression("sqrt((3m)^2 + (4m)^2)").unwrap();
+/// assert_eq!(result.to_string(), "5m");
+/// ```
+///
+/// Evaluation errors point at the part of the input that failed:
+/// ```
+/// use mathengine::{evaluate_expression, Error};
+///
+/// let Err(Error::Evaluator(error)) = evaluate_expression("1 + 10 / (5 - 5)") else {
+///     panic!("expected an evaluation error");
+/// };
+/// assert_eq!(error.to_string(), "Division by zero at position 4..16");
+/// ```
+///
 /// # Supported Units
 ///
 /// **Length**: m, cm, mm, km, ft, in, yd, mi
@@ -100,19 +353,218 @@ impl From<EvalError> for MathEngineError {
 /// - The expression cannot be evaluated (evaluation error)
 pub fn evaluate_expression<S: AsRef<str>>(expression: S) -> Result<crate::Value, MathEngineError> {
     // Lexical analysis
-    let lexer = Lexer::new(expression.as_ref());
-    let tokens = lexer.tokenize()?;
+    evaluate_expression_with_context(expression, &EvaluationContext::default())
+}
 
-    // Parsing
-    let mut parser = Parser::new(tokens);
-    let expr = parser.parse()?;
+/// Evaluate a mathematical expression while enforcing resource limits.
+///
+/// Use this instead of [`evaluate_expression`] for user-submitted input, so a
+/// single request can't consume unbounded stack, CPU, or time.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use mathengine::{evaluate_expression_with_limits, EvalLimits};
+///
+/// let limits = EvalLimits {
+///     max_depth: Some(32),
+///     max_operations: Some(1_000),
+///     timeout: Some(Duration::from_millis(10)),
+///     ..EvalLimits::default()
+/// };
+/// let result = evaluate_expression_with_limits("2 + 3 * 4", &limits).unwrap();
+/// assert_eq!(result.to_string(), "14");
+/// ```
+pub fn evaluate_expression_with_limits<S: AsRef<str>>(
+    expression: S,
+    limits: &EvalLimits,
+) -> Result<crate::Value, MathEngineError> {
+    let context = EvaluationContext {
+        limits: limits.clone(),
+        ..EvaluationContext::default()
+    };
+    evaluate_expression_with_context(expression, &context)
+}
 
-    // Evaluation
-    let result = evaluate(&expr)?;
+/// Evaluate a mathematical expression with explicit evaluation options.
+///
+/// # Examples
+///
+/// Exact fractions in rational mode:
+/// ```
+/// use mathengine::{evaluate_expression_with_context, EvaluationContext, Number, NumberMode, Value};
+///
+/// let context = EvaluationContext {
+///     number_mode: NumberMode::Rational,
+///     ..EvaluationContext::default()
+/// };
+/// let result = evaluate_expression_with_context("1/3 + 1/6", &context).unwrap();
+/// assert!(matches!(result, Value::Number(Number::Rational(_))));
+///
+/// // Fractions are exact internally, and display as decimals
+/// assert_eq!(result.to_string(), "0.5");
+/// ```
+///
+/// Currency conversions use the exchange rates in the context:
+/// ```
+/// use mathengine::{evaluate_expression_with_context, EvaluationContext, StaticRates};
+/// use std::sync::Arc;
+///
+/// let context = EvaluationContext {
+///     rates: Some(Arc::new(StaticRates::new().with_rate("usd", "eur", 0.5))),
+///     ..EvaluationContext::default()
+/// };
+/// let result = evaluate_expression_with_context("30 eur to usd", &context).unwrap();
+/// assert_eq!(result.to_string(), "60usd");
+///
+/// // Without rates, the conversion is an error rather than a guess
+/// assert!(evaluate_expression_with_context("30 eur to usd", &EvaluationContext::default()).is_err());
+/// ```
+pub fn evaluate_expression_with_context<S: AsRef<str>>(
+    expression: S,
+    context: &EvaluationContext,
+) -> Result<crate::Value, MathEngineError> {
+    let lexer = Lexer::new(expression.as_ref());
+    let result = match Parser::new(lexer.tokens()).parse_statement()? {
+        Statement::Expression(expr) => evaluate_with_context(&expr, context)?,
+        Statement::Solve { equation, variable } => solve(&equation, &variable, context)?,
+        Statement::Assign { .. } | Statement::Define { .. } => return Err(definition_error()),
+    };
 
     Ok(result)
 }
 
+// Definitions like `x = 2` only mean something to a Session, which keeps them
+pub(crate) fn definition_error() -> MathEngineError {
+    MathEngineError::Parser(ParseError::InvalidExpression {
+        message: "definitions need a Session to keep them".to_string(),
+        position: 0,
+    })
+}
+
+/// Evaluate a mathematical expression, reporting each evaluated node to `observer`.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{evaluate_expression_with_observer, EvalObserver, EvaluationContext, Value};
+/// use mathengine_parser::Expression;
+///
+/// struct CountNodes(usize);
+///
+/// impl EvalObserver for CountNodes {
+///     fn on_node(&mut self, _node: &Expression, _inputs: &[Value], _output: &Value) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let mut counter = CountNodes(0);
+/// evaluate_expression_with_observer("1 + 2 * 3", &EvaluationContext::default(), &mut counter).unwrap();
+/// assert_eq!(counter.0, 5);
+/// ```
+pub fn evaluate_expression_with_observer<S: AsRef<str>, O: EvalObserver + ?Sized>(
+    expression: S,
+    context: &EvaluationContext,
+    observer: &mut O,
+) -> Result<crate::Value, MathEngineError> {
+    let lexer = Lexer::new(expression.as_ref());
+    let expr = Parser::new(lexer.tokens()).parse()?;
+    Ok(evaluate_with_observer(&expr, context, observer)?)
+}
+
+/// Compile an expression once for fast repeated evaluation.
+///
+/// The returned [`Program`] can be run many times, typically with different
+/// variable bindings in the context.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{compile_expression, EvaluationContext};
+///
+/// let program = compile_expression("price * (1 + tax)").unwrap();
+///
+/// let mut context = EvaluationContext::default();
+/// context.set_variable("tax", 0.25);
+/// for price in [100.0, 200.0] {
+///     context.set_variable("price", price);
+///     println!("{}", program.run(&context).unwrap());
+/// }
+/// # assert_eq!(program.run(&context).unwrap().to_string(), "250");
+/// ```
+pub fn compile_expression<S: AsRef<str>>(expression: S) -> Result<Program, MathEngineError> {
+    let lexer = Lexer::new(expression.as_ref());
+    let expr = Parser::new(lexer.tokens()).parse()?;
+    Ok(compile(&expr)?)
+}
+
+/// Parse an expression into its syntax tree without evaluating it.
+///
+/// Together with [`evaluate_ast`] this splits [`evaluate_expression`] in two,
+/// so a tree can be inspected, cached or transformed in between. Nodes
+/// record their spans in `expression`, for error positions.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{evaluate_ast, parse_expression, Expression};
+///
+/// let ast = parse_expression("(1m + 50cm) to cm").unwrap();
+/// assert!(matches!(ast, Expression::Binary { .. }));
+///
+/// // Parse once, evaluate as often as needed
+/// assert_eq!(evaluate_ast(&ast).unwrap().to_string(), "150cm");
+/// ```
+pub fn parse_expression<S: AsRef<str>>(expression: S) -> Result<Expression, MathEngineError> {
+    let lexer = Lexer::new(expression.as_ref());
+    Ok(Parser::new(lexer.tokens()).parse()?)
+}
+
+/// Evaluate a syntax tree from [`parse_expression`], or one built by hand.
+pub fn evaluate_ast(expression: &Expression) -> Result<crate::Value, MathEngineError> {
+    Ok(evaluate_with_context(expression, &EvaluationContext::default())?)
+}
+
+/// Check the dimensions in an expression without evaluating it.
+///
+/// Catches unit mismatches and impossible conversions up front, which is
+/// useful for flagging problems as an expression is typed.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{check_expression, DimensionSignature, DimensionType, Error};
+///
+/// assert_eq!(
+///     check_expression("(1m + 50cm) to ft").unwrap(),
+///     DimensionSignature::Quantity { dimension: DimensionType::Length, exponent: 1 }
+/// );
+///
+/// let Err(Error::Type(error)) = check_expression("2 * (5m + 3kg)") else {
+///     panic!("expected a type error");
+/// };
+/// assert_eq!(error.to_string(), "Cannot add length and mass at position 5..13");
+/// ```
+pub fn check_expression<S: AsRef<str>>(expression: S) -> Result<DimensionSignature, MathEngineError> {
+    let lexer = Lexer::new(expression.as_ref());
+    let expr = Parser::new(lexer.tokens()).parse()?;
+    Ok(check(&expr)?)
+}
+
 // Re-export commonly used types for convenience
-pub use mathengine_parser::types::{Value, Number, UnitValue, Unit, DimensionType};
-pub use MathEngineError as Error;
\ No newline at end of file
+pub use mathengine_parser::types::{ConversionError, Value, Number, Rational, Representation, Scalar, UnitValue, UnitInfo, Unit, DimensionType, FormatOptions, Locale, Notation, Radix, ValueKey};
+#[cfg(feature = "units")]
+pub use mathengine_parser::types::{Quantity, QuantityKind, Length, Temperature, Time, Frequency, Mass};
+pub use mathengine_parser::{CancellationToken, Expression, InputLimit, InputLimits, ParseError};
+#[cfg(feature = "serde")]
+pub use mathengine_parser::Versioned;
+#[cfg(feature = "decimal")]
+pub use mathengine_parser::types::Decimal;
+pub use mathengine_lexer::{LexError, Operation, Span};
+#[cfg(feature = "units")]
+pub use mathengine_units::{length::LengthUnit, temperature::TemperatureUnit, time::TimeUnit, frequency::FrequencyUnit, mass::MassUnit};
+pub use MathEngineError as Error;
+#[cfg(feature = "macros")]
+pub use mathengine_macros::matheval;
+pub use mathengine_evaluator::{DimensionProvider, DimensionSignature, EvalError, EvalLimits, EvalObserver, EvaluationContext, Function, NumberMode, Program, RateError, RateProvider, FnResolver, Resolver, StaticRates, TypeError};
\ No newline at end of file
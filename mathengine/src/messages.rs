@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mathengine_evaluator::{EvalError, RateError, TypeError};
+use mathengine_lexer::LexError;
+use mathengine_parser::ParseError;
+
+use crate::{MathEngine, MathEngineError};
+
+/// Translated error messages, for showing errors in a language other than
+/// English.
+///
+/// Messages are keyed by [`MathEngineError::code`], and written as templates
+/// whose `{placeholders}` are filled with the fields of the error, like
+/// `{unit}` for `eval.unknown_unit`. The fields of each error are its
+/// variant's fields, listed with the codes. Errors without a translation fall
+/// back to the English message, so a catalog can be filled in gradually.
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::{MathEngine, MessageCatalog};
+///
+/// let german = MessageCatalog::new()
+///     .with_message("eval.division_by_zero", "Division durch null")
+///     .with_message("eval.unknown_function", "Unbekannte Funktion: '{name}'");
+/// let engine = MathEngine::builder().messages(german).build();
+///
+/// let error = engine.evaluate("1 / 0").unwrap_err();
+/// assert_eq!(engine.message(&error), "Division durch null");
+/// let error = engine.evaluate("frobnicate(2)").unwrap_err();
+/// assert_eq!(engine.message(&error), "Unbekannte Funktion: 'frobnicate'");
+///
+/// // Not translated yet
+/// let error = engine.evaluate("sqrt(1, 2)").unwrap_err();
+/// assert_eq!(engine.message(&error), "Function 'sqrt' expects 1 argument(s) but got 2");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MessageCatalog {
+    // Shared between clones, like unit aliases
+    messages: Arc<HashMap<String, String>>,
+}
+
+impl MessageCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show errors with `code` as `template`.
+    pub fn with_message(mut self, code: &str, template: &str) -> Self {
+        Arc::make_mut(&mut self.messages).insert(code.to_string(), template.to_string());
+        self
+    }
+
+    /// The template for errors with `code`, if it's been translated.
+    pub fn template(&self, code: &str) -> Option<&str> {
+        self.messages.get(code).map(String::as_str)
+    }
+
+    /// Display `error` with its translation. Errors that have no
+    /// translation are displayed in English, as [`MathEngineError::render`]
+    /// shows them.
+    pub fn message(&self, error: &MathEngineError) -> String {
+        let (code, mut fields) = error.fields();
+        if let MathEngineError::Evaluator(e) = error {
+            if let EvalError::RateUnavailable { reason, .. } = e.without_span() {
+                for (name, value) in &mut fields {
+                    if *name == "reason" {
+                        *value = self.rate_reason(reason);
+                    }
+                }
+            }
+        }
+        self.fill(code, &fields).unwrap_or_else(|| match error {
+            MathEngineError::Lexer(e) => e.to_string(),
+            MathEngineError::Parser(e) => e.to_string(),
+            MathEngineError::Evaluator(e) => e.without_span().to_string(),
+            MathEngineError::Type(e) => e.without_span().to_string(),
+        })
+    }
+
+    fn fill(&self, code: &str, fields: &[(&str, String)]) -> Option<String> {
+        let mut message = self.template(code)?.to_string();
+        for (name, value) in fields {
+            message = message.replace(&format!("{{{}}}", name), value);
+        }
+        Some(message)
+    }
+
+    fn rate_reason(&self, reason: &RateError) -> String {
+        let (code, fields) = match reason {
+            RateError::NoProvider => ("rate.no_provider", vec![]),
+            RateError::Missing => ("rate.missing", vec![]),
+            RateError::Stale { age } => ("rate.stale", vec![("age", format!("{:?}", age))]),
+        };
+        self.fill(code, &fields).unwrap_or_else(|| reason.to_string())
+    }
+}
+
+impl MathEngineError {
+    /// A stable identifier for the kind of error, for looking up
+    /// translations in a [`MessageCatalog`] or handling errors by kind.
+    ///
+    /// Codes are the stage followed by the variant, in snake case:
+    /// `lexer.unexpected_character`, `parser.unexpected_token`,
+    /// `eval.division_by_zero`, `type.incompatible_dimensions` and so on.
+    /// Currency conversions without a rate are `eval.rate_unavailable`, with
+    /// their `{reason}` translated from `rate.no_provider`, `rate.missing` or
+    /// `rate.stale`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::evaluate_expression;
+    ///
+    /// let error = evaluate_expression("1 / 0").unwrap_err();
+    /// assert_eq!(error.code(), "eval.division_by_zero");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        self.fields().0
+    }
+
+    // The error's code, with the values its message template can use
+    fn fields(&self) -> (&'static str, Vec<(&'static str, String)>) {
+        match self {
+            MathEngineError::Lexer(e) => lexer_fields(e),
+            MathEngineError::Parser(e) => parser_fields(e),
+            MathEngineError::Evaluator(e) => eval_fields(e.without_span()),
+            MathEngineError::Type(e) => type_fields(e.without_span()),
+        }
+    }
+}
+
+fn lexer_fields(error: &LexError) -> (&'static str, Vec<(&'static str, String)>) {
+    match error {
+        LexError::UnexpectedCharacter { char, position } => (
+            "lexer.unexpected_character",
+            vec![("char", char.to_string()), ("position", position.to_string())],
+        ),
+        LexError::InvalidNumber { input, position } => (
+            "lexer.invalid_number",
+            vec![("input", input.clone()), ("position", position.to_string())],
+        ),
+        LexError::EmptyInput => ("lexer.empty_input", vec![]),
+    }
+}
+
+fn parser_fields(error: &ParseError) -> (&'static str, Vec<(&'static str, String)>) {
+    match error {
+        ParseError::UnexpectedToken {
+            expected,
+            found,
+            position,
+        } => (
+            "parser.unexpected_token",
+            vec![
+                ("expected", expected.clone()),
+                ("found", format!("{:?}", found)),
+                ("position", position.to_string()),
+            ],
+        ),
+        ParseError::UnexpectedEndOfInput { expected } => {
+            ("parser.unexpected_end_of_input", vec![("expected", expected.clone())])
+        }
+        ParseError::InvalidExpression { message, position } => (
+            "parser.invalid_expression",
+            vec![("message", message.clone()), ("position", position.to_string())],
+        ),
+        ParseError::EmptyTokenStream => ("parser.empty_token_stream", vec![]),
+        ParseError::TooDeep { limit, position } => (
+            "parser.too_deep",
+            vec![("limit", limit.to_string()), ("position", position.to_string())],
+        ),
+        ParseError::InputTooLarge { exceeded, limit } => (
+            "parser.input_too_large",
+            vec![("limit", limit.to_string()), ("unit", exceeded.unit().to_string())],
+        ),
+        ParseError::Cancelled => ("parser.cancelled", vec![]),
+        ParseError::Lex(e) => lexer_fields(e),
+    }
+}
+
+fn eval_fields(error: &EvalError) -> (&'static str, Vec<(&'static str, String)>) {
+    match error {
+        EvalError::DivisionByZero => ("eval.division_by_zero", vec![]),
+        EvalError::IncompatibleUnits {
+            left_unit,
+            right_unit,
+            operation,
+        } => (
+            "eval.incompatible_units",
+            vec![
+                ("left_unit", left_unit.clone()),
+                ("right_unit", right_unit.clone()),
+                ("operation", operation.clone()),
+            ],
+        ),
+        EvalError::UnknownUnit { unit } => ("eval.unknown_unit", vec![("unit", unit.clone())]),
+        EvalError::InvalidConversion { from_unit, to_unit } => (
+            "eval.invalid_conversion",
+            vec![("from_unit", from_unit.clone()), ("to_unit", to_unit.clone())],
+        ),
+        EvalError::UnsupportedOperation {
+            operation,
+            operand_type,
+        } => (
+            "eval.unsupported_operation",
+            vec![("operation", operation.clone()), ("operand_type", operand_type.clone())],
+        ),
+        EvalError::InvalidUnitExpression { message } => {
+            ("eval.invalid_unit_expression", vec![("message", message.clone())])
+        }
+        EvalError::UnknownFunction { name } => ("eval.unknown_function", vec![("name", name.clone())]),
+        EvalError::WrongArgumentCount {
+            function,
+            expected,
+            found,
+        } => (
+            "eval.wrong_argument_count",
+            vec![
+                ("function", function.clone()),
+                ("expected", expected.to_string()),
+                ("found", found.to_string()),
+            ],
+        ),
+        EvalError::DomainError { function, message } => (
+            "eval.domain_error",
+            vec![("function", function.clone()), ("message", message.clone())],
+        ),
+        EvalError::Overflow { operation } => ("eval.overflow", vec![("operation", operation.clone())]),
+        EvalError::Underflow { operation } => ("eval.underflow", vec![("operation", operation.clone())]),
+        EvalError::ExponentOutOfRange { exponent } => ("eval.exponent_out_of_range", vec![("exponent", exponent.clone())]),
+        EvalError::NodeLimitExceeded { limit } => ("eval.node_limit_exceeded", vec![("limit", limit.to_string())]),
+        EvalError::DepthLimitExceeded { limit } => ("eval.depth_limit_exceeded", vec![("limit", limit.to_string())]),
+        EvalError::OperationLimitExceeded { limit } => {
+            ("eval.operation_limit_exceeded", vec![("limit", limit.to_string())])
+        }
+        EvalError::Timeout { limit } => ("eval.timeout", vec![("limit", format!("{:?}", limit))]),
+        EvalError::Cancelled => ("eval.cancelled", vec![]),
+        EvalError::RecursionLimitExceeded { function, limit } => (
+            "eval.recursion_limit_exceeded",
+            vec![("function", function.clone()), ("limit", limit.to_string())],
+        ),
+        EvalError::RateUnavailable { from, to, reason } => (
+            "eval.rate_unavailable",
+            vec![("from", from.clone()), ("to", to.clone()), ("reason", reason.to_string())],
+        ),
+        EvalError::Spanned { error, .. } => eval_fields(error),
+    }
+}
+
+fn type_fields(error: &TypeError) -> (&'static str, Vec<(&'static str, String)>) {
+    match error {
+        TypeError::IncompatibleDimensions {
+            operation,
+            left,
+            right,
+        } => (
+            "type.incompatible_dimensions",
+            vec![
+                ("operation", operation.clone()),
+                ("left", left.to_string()),
+                ("right", right.to_string()),
+            ],
+        ),
+        TypeError::InvalidConversion { from, to_unit } => (
+            "type.invalid_conversion",
+            vec![("from", from.to_string()), ("to_unit", to_unit.clone())],
+        ),
+        TypeError::InvalidOperand { operation, operand } => (
+            "type.invalid_operand",
+            vec![("operation", operation.clone()), ("operand", operand.to_string())],
+        ),
+        TypeError::Spanned { error, .. } => type_fields(error),
+    }
+}
+
+impl MathEngine {
+    /// Display `error` with this engine's message catalog.
+    pub fn message(&self, error: &MathEngineError) -> String {
+        self.messages.message(error)
+    }
+
+    /// Show `error` under the input it came from, like
+    /// [`MathEngineError::render`], with this engine's message catalog.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{MathEngine, MessageCatalog};
+    ///
+    /// let spanish = MessageCatalog::new().with_message("eval.division_by_zero", "División por cero");
+    /// let engine = MathEngine::builder().messages(spanish).build();
+    /// let error = engine.evaluate("1 + 2 / 0").unwrap_err();
+    /// assert_eq!(engine.render_error(&error, "1 + 2 / 0"), "División por cero\n  1 + 2 / 0\n      ^^^^^");
+    /// ```
+    pub fn render_error(&self, error: &MathEngineError, source: &str) -> String {
+        error.underline(self.message(error), source)
+    }
+}
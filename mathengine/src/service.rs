@@ -0,0 +1,316 @@
+//! Request and response types for running an engine as a service, behind
+//! the `serde` feature.
+//!
+//! A request names the expression, optional display options and variable
+//! bindings; [`MathEngine::handle`] answers it with the value, its unit and
+//! dimension, the formatted result, or what went wrong. Neither side needs
+//! any glue beyond deserializing the request and serializing the response.
+//!
+//! ```
+//! use mathengine::{EvalRequest, MathEngine};
+//!
+//! let request: EvalRequest = serde_json::from_str(
+//!     r#"{"expression": "width * 3", "options": {"precision": 1}, "variables": {"width": 1.25}}"#,
+//! )
+//! .unwrap();
+//! let response = MathEngine::new().handle(&request);
+//! assert_eq!(
+//!     serde_json::to_string(&response).unwrap(),
+//!     r#"{"value":{"Number":{"Float":3.75}},"unit":null,"dimension":null,"formatted":"3.8","error":null}"#
+//! );
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::{self, value::MapAccessDeserializer, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+use mathengine_lexer::Span;
+use mathengine_parser::types::{DimensionType, Locale, Value};
+
+use crate::{MathEngine, MathEngineError};
+
+const REQUEST_FIELDS: &[&str] = &["expression", "options", "variables"];
+const OPTIONS_FIELDS: &[&str] = &["precision", "strict", "locale"];
+
+/// An expression to evaluate, with the options and variables to use.
+///
+/// Only `expression` is required. Fields a request doesn't know are ignored,
+/// so clients can send newer requests to older services. Variables can be
+/// plain JSON numbers and booleans, or any value in its serialized form.
+#[derive(Debug, Clone, Default)]
+pub struct EvalRequest {
+    pub expression: String,
+    pub options: RequestOptions,
+    pub variables: HashMap<String, Value>,
+}
+
+/// Per-request overrides of the engine's configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestOptions {
+    /// Round the formatted result to this many decimal places
+    pub precision: Option<usize>,
+    /// Dimension check the expression before evaluating it
+    pub strict: Option<bool>,
+    /// Separators for the formatted result, as a tag like `"de-DE"`
+    pub locale: Option<String>,
+}
+
+/// The outcome of an [`EvalRequest`]: either a value, described several
+/// ways, or an error.
+///
+/// Every field is always written, as `null` when it doesn't apply.
+#[derive(Debug, Clone)]
+pub struct EvalResponse {
+    pub value: Option<Value>,
+    /// The canonical unit of a unit value, with any exponent (`"m^2"`)
+    pub unit: Option<String>,
+    pub dimension: Option<DimensionType>,
+    /// The value displayed with the engine's and request's format options
+    pub formatted: Option<String>,
+    pub error: Option<ResponseError>,
+}
+
+/// Why a request failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseError {
+    /// The stage that failed: `"request"`, `"lexer"`, `"parser"`,
+    /// `"evaluator"` or `"type"`
+    pub kind: &'static str,
+    /// Translated with the engine's message catalog
+    pub message: String,
+    /// The characters of the expression the error is about, when it's about
+    /// one part of it
+    pub span: Option<Span>,
+}
+
+impl MathEngine {
+    /// Answer a request, with its options applied over this engine's
+    /// configuration. Failures are reported in the response rather than as
+    /// an `Err`, so they can be sent back like any other result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{EvalRequest, MathEngine};
+    ///
+    /// let request = EvalRequest {
+    ///     expression: "2 + * 3".to_string(),
+    ///     ..EvalRequest::default()
+    /// };
+    /// let error = MathEngine::new().handle(&request).error.unwrap();
+    /// assert_eq!(error.kind, "parser");
+    /// assert_eq!(error.span.map(|span| span.start), Some(4));
+    /// ```
+    pub fn handle(&self, request: &EvalRequest) -> EvalResponse {
+        let engine = match self.for_request(request) {
+            Ok(engine) => engine,
+            Err(error) => return EvalResponse::failed(error),
+        };
+        match engine.evaluate(&request.expression) {
+            Ok(value) => {
+                let info = value.unit_info();
+                EvalResponse {
+                    formatted: Some(engine.format(&value)),
+                    unit: info.as_ref().map(|info| info.unit.clone()),
+                    dimension: info.map(|info| info.dimension),
+                    value: Some(value),
+                    error: None,
+                }
+            }
+            Err(error) => EvalResponse::failed(ResponseError::new(&engine, &error, &request.expression)),
+        }
+    }
+
+    fn for_request(&self, request: &EvalRequest) -> Result<MathEngine, ResponseError> {
+        let mut engine = self.clone();
+        let options = &request.options;
+        if let Some(places) = options.precision {
+            engine.format.decimal_places = Some(places);
+        }
+        if let Some(strict) = options.strict {
+            engine.strict = strict;
+        }
+        if let Some(tag) = &options.locale {
+            engine.format.locale = Locale::from_tag(tag).ok_or_else(|| ResponseError {
+                kind: "request",
+                message: format!("Unknown locale '{}'", tag),
+                span: None,
+            })?;
+        }
+        for (name, value) in &request.variables {
+            engine.context_mut().set_variable(name, value.clone());
+        }
+        Ok(engine)
+    }
+}
+
+impl EvalResponse {
+    fn failed(error: ResponseError) -> Self {
+        EvalResponse {
+            value: None,
+            unit: None,
+            dimension: None,
+            formatted: None,
+            error: Some(error),
+        }
+    }
+}
+
+impl ResponseError {
+    fn new(engine: &MathEngine, error: &MathEngineError, source: &str) -> Self {
+        let kind = match error {
+            MathEngineError::Lexer(_) => "lexer",
+            MathEngineError::Parser(_) => "parser",
+            MathEngineError::Evaluator(_) => "evaluator",
+            MathEngineError::Type(_) => "type",
+        };
+        ResponseError {
+            kind,
+            message: engine.message(error),
+            span: error.locate(source),
+        }
+    }
+}
+
+impl Serialize for EvalResponse {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("EvalResponse", 5)?;
+        state.serialize_field("value", &self.value)?;
+        state.serialize_field("unit", &self.unit)?;
+        state.serialize_field("dimension", &self.dimension)?;
+        state.serialize_field("formatted", &self.formatted)?;
+        state.serialize_field("error", &self.error)?;
+        state.end()
+    }
+}
+
+impl Serialize for ResponseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ResponseError", 3)?;
+        state.serialize_field("kind", self.kind)?;
+        state.serialize_field("message", &self.message)?;
+        state.serialize_field("span", &self.span.map(SpanFields))?;
+        state.end()
+    }
+}
+
+// Spans are written as `{"start": 0, "end": 3}`
+struct SpanFields(Span);
+
+impl Serialize for SpanFields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Span", 2)?;
+        state.serialize_field("start", &self.0.start)?;
+        state.serialize_field("end", &self.0.end)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for EvalRequest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RequestVisitor;
+
+        impl<'de> Visitor<'de> for RequestVisitor {
+            type Value = EvalRequest;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "an evaluation request")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<EvalRequest, M::Error> {
+                let mut expression = None;
+                let mut request = EvalRequest::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "expression" => expression = Some(map.next_value()?),
+                        "options" => request.options = map.next_value()?,
+                        "variables" => {
+                            let variables: HashMap<String, Variable> = map.next_value()?;
+                            request.variables = variables.into_iter().map(|(name, v)| (name, v.0)).collect();
+                        }
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                request.expression = expression.ok_or_else(|| de::Error::missing_field("expression"))?;
+                Ok(request)
+            }
+        }
+
+        deserializer.deserialize_struct("EvalRequest", REQUEST_FIELDS, RequestVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for RequestOptions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OptionsVisitor;
+
+        impl<'de> Visitor<'de> for OptionsVisitor {
+            type Value = RequestOptions;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "request options")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<RequestOptions, M::Error> {
+                let mut options = RequestOptions::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "precision" => options.precision = map.next_value()?,
+                        "strict" => options.strict = map.next_value()?,
+                        "locale" => options.locale = map.next_value()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(options)
+            }
+        }
+
+        deserializer.deserialize_struct("RequestOptions", OPTIONS_FIELDS, OptionsVisitor)
+    }
+}
+
+// A variable binding: a bare number or boolean, or a value in full
+struct Variable(Value);
+
+impl<'de> Deserialize<'de> for Variable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VariableVisitor;
+
+        impl<'de> Visitor<'de> for VariableVisitor {
+            type Value = Variable;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a number, boolean or mathengine value")
+            }
+
+            fn visit_bool<E: de::Error>(self, b: bool) -> Result<Variable, E> {
+                Ok(Variable(Value::Bool(b)))
+            }
+
+            fn visit_i64<E: de::Error>(self, i: i64) -> Result<Variable, E> {
+                Ok(Variable(Value::from(i128::from(i))))
+            }
+
+            fn visit_u64<E: de::Error>(self, i: u64) -> Result<Variable, E> {
+                Ok(Variable(Value::from(i128::from(i))))
+            }
+
+            fn visit_f64<E: de::Error>(self, x: f64) -> Result<Variable, E> {
+                Ok(Variable(Value::from(x)))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Variable, M::Error> {
+                Value::deserialize(MapAccessDeserializer::new(map)).map(Variable)
+            }
+        }
+
+        deserializer.deserialize_any(VariableVisitor)
+    }
+}
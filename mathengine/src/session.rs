@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use mathengine_evaluator::{solve, EvalObserver, Function};
+use mathengine_parser::types::{FormatOptions, Value};
+use mathengine_parser::Statement;
+
+use crate::significance::Figures;
+use crate::trace::Phase;
+use crate::{MathEngine, MathEngineError, Significant, Timings};
+
+/// An evaluator that remembers, for notebooks and REPLs.
+///
+/// Each line can define a variable (`x = 2`) or a function
+/// (`f(x) = x^2 + 1`) for later lines to use. `ans` holds the last result,
+/// and `_1`, `_2` and so on the result of each line in the
+/// [`history`](Self::history).
+///
+/// # Examples
+///
+/// ```
+/// use mathengine::Session;
+///
+/// let mut session = Session::new();
+/// session.eval("x = 2").unwrap();
+/// session.eval("f(t) = t^2 + 1").unwrap();
+///
+/// let result = session.eval("f(x) * 3").unwrap().unwrap();
+/// assert_eq!(result.to_string(), "15");
+/// assert_eq!(session.eval("ans + 1").unwrap().unwrap().to_string(), "16");
+/// assert_eq!(session.eval("_3 * _1").unwrap().unwrap().to_string(), "30");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    engine: MathEngine,
+    history: Vec<HistoryEntry>,
+    // What the engine's context held to begin with, for `clear`
+    initial_variables: HashMap<String, Value>,
+    initial_functions: HashMap<String, Function>,
+}
+
+/// A line a [`Session`] evaluated, and what it gave.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub input: String,
+    /// `None` for a line defining a function
+    pub result: Option<Value>,
+}
+
+impl Session {
+    /// A session using the default engine configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A session evaluating with `engine`'s configuration. Variables already
+    /// in its context stay visible to the session.
+    pub fn with_engine(engine: MathEngine) -> Self {
+        Self {
+            initial_variables: engine.context().variables.clone(),
+            initial_functions: engine.context().functions.clone(),
+            engine,
+            history: Vec::new(),
+        }
+    }
+
+    /// Evaluate a line, keeping any definition it makes.
+    ///
+    /// Returns the line's value, which also becomes `ans` and the next `_N`.
+    /// Assigning a variable gives the assigned value; defining a function
+    /// gives `None`.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`MathEngine::evaluate`]. A line that fails leaves the
+    /// session as it was, and isn't added to the history.
+    pub fn eval<S: AsRef<str>>(&mut self, line: S) -> Result<Option<Value>, MathEngineError> {
+        let line = line.as_ref();
+        let statement = self.engine.parse(line)?;
+        let stage = self.engine.stage(Phase::Eval);
+        let result = self.eval_statement(statement);
+        stage.finish(line, &result);
+        Ok(self.record(line, result?))
+    }
+
+    /// Evaluate a line like [`eval`](Self::eval), reporting each node of the
+    /// expression to `observer` as it's evaluated, for showing the steps a
+    /// result was worked out in. Nodes of user-defined functions' bodies are
+    /// reported too. Solving isn't observed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{EvalObserver, Expression, Session, Value};
+    ///
+    /// #[derive(Default)]
+    /// struct Results(Vec<String>);
+    ///
+    /// impl EvalObserver for Results {
+    ///     fn on_node(&mut self, _node: &Expression, inputs: &[Value], output: &Value) {
+    ///         if !inputs.is_empty() {
+    ///             self.0.push(output.to_string());
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut session = Session::new();
+    /// let mut results = Results::default();
+    /// session.eval_with_observer("x = (2 + 3) * 4", &mut results).unwrap();
+    /// assert_eq!(results.0, ["5", "20"]);
+    /// ```
+    pub fn eval_with_observer<S: AsRef<str>, O: EvalObserver + ?Sized>(
+        &mut self,
+        line: S,
+        observer: &mut O,
+    ) -> Result<Option<Value>, MathEngineError> {
+        let statement = self.engine.parse(line.as_ref())?;
+        self.eval_observed(line.as_ref(), &statement, observer)
+    }
+
+    /// Evaluate a line like [`eval`](Self::eval), tracking the significant
+    /// figures of its numbers like [`MathEngine::evaluate_significant`].
+    /// Variables are exact, whatever they were assigned from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{FormatOptions, Session};
+    ///
+    /// let mut session = Session::new();
+    /// let side = session.eval_significant("side = 12.5m / 4.0").unwrap().unwrap();
+    /// assert_eq!(side.format(&FormatOptions::default()), "3.1m");
+    /// assert_eq!(session.eval_significant("side * 2.0").unwrap().unwrap().figures, Some(2));
+    /// ```
+    pub fn eval_significant<S: AsRef<str>>(&mut self, line: S) -> Result<Option<Significant>, MathEngineError> {
+        let line = line.as_ref();
+        let statement = self.engine.parse(line)?;
+        let root = match &statement {
+            Statement::Expression(expr) | Statement::Assign { value: expr, .. } => Some(expr),
+            _ => None,
+        };
+        let mut figures = match root {
+            Some(expr) => Figures::new(&self.engine, line, expr),
+            None => Figures::default(),
+        };
+        let value = self.eval_observed(line, &statement, &mut figures)?;
+        let figures = root.and_then(|expr| figures.of(expr));
+        Ok(value.map(|value| Significant { value, figures }))
+    }
+
+    // The statement is borrowed so observers can tell its nodes apart by
+    // address
+    fn eval_observed<O: EvalObserver + ?Sized>(
+        &mut self,
+        line: &str,
+        statement: &Statement,
+        observer: &mut O,
+    ) -> Result<Option<Value>, MathEngineError> {
+        let result = match statement {
+            Statement::Expression(expr) => {
+                let value = self.engine.evaluate_parsed_with_observer(expr, observer)?;
+                self.engine.context_mut().set_variable("ans", value.clone());
+                Some(value)
+            }
+            Statement::Assign { name, value } => {
+                let value = self.engine.evaluate_parsed_with_observer(value, observer)?;
+                self.engine.context_mut().set_variable(name, value.clone());
+                self.engine.context_mut().set_variable("ans", value.clone());
+                Some(value)
+            }
+            statement => self.eval_statement(statement.clone())?,
+        };
+        Ok(self.record(line, result))
+    }
+
+    /// Evaluate a line like [`eval`](Self::eval), timing each stage.
+    pub fn eval_timed<S: AsRef<str>>(&mut self, line: S) -> (Result<Option<Value>, MathEngineError>, Timings) {
+        let mut timings = Timings::default();
+        let result = self
+            .engine
+            .parse_timed(line.as_ref(), &mut timings)
+            .and_then(|statement| timings.time_eval(|| self.eval_statement(statement)));
+        (result.map(|result| self.record(line.as_ref(), result)), timings)
+    }
+
+    // Add a line that evaluated to the history, its result becoming the
+    // next `_N`
+    fn record(&mut self, input: &str, result: Option<Value>) -> Option<Value> {
+        self.history.push(HistoryEntry {
+            input: input.to_string(),
+            result: result.clone(),
+        });
+        if let Some(value) = &result {
+            let name = format!("_{}", self.history.len());
+            self.engine.context_mut().set_variable(&name, value.clone());
+        }
+        result
+    }
+
+    fn eval_statement(&mut self, statement: Statement) -> Result<Option<Value>, MathEngineError> {
+        let value = match statement {
+            Statement::Expression(expr) => self.engine.evaluate_parsed(&expr)?,
+            Statement::Solve { equation, variable } => {
+                solve(&equation, &variable, self.engine.context())?
+            }
+            Statement::Assign { name, value } => {
+                let value = self.engine.evaluate_parsed(&value)?;
+                self.engine.context_mut().set_variable(&name, value.clone());
+                value
+            }
+            Statement::Define { name, params, body } => {
+                self.engine.context_mut().define_function(&name, params, body);
+                return Ok(None);
+            }
+        };
+        self.engine.context_mut().set_variable("ans", value.clone());
+        Ok(Some(value))
+    }
+
+    /// The value of a variable, including `ans`.
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.engine.context().variables.get(&name.to_lowercase())
+    }
+
+    /// The result of the last line that had one.
+    pub fn ans(&self) -> Option<&Value> {
+        self.variable("ans")
+    }
+
+    /// Each line evaluated so far, oldest first. The result of entry `i` is
+    /// `_{i + 1}` in later lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::Session;
+    ///
+    /// let mut session = Session::new();
+    /// session.eval("2 + 3").unwrap();
+    /// session.eval("f(x) = x * 2").unwrap();
+    /// assert!(session.eval("2 +").is_err());
+    ///
+    /// let inputs: Vec<&str> = session.history().iter().map(|entry| entry.input.as_str()).collect();
+    /// assert_eq!(inputs, ["2 + 3", "f(x) = x * 2"]);
+    /// assert!(session.history()[1].result.is_none());
+    /// assert_eq!(session.eval("f(_1)").unwrap().unwrap().to_string(), "10");
+    /// ```
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    /// The engine evaluating each line, whose context holds the session's
+    /// variables and functions.
+    pub fn engine(&self) -> &MathEngine {
+        &self.engine
+    }
+
+    /// Display later results with `options`, like a REPL's `:set precision 4`.
+    pub fn set_format_options(&mut self, options: FormatOptions) {
+        self.engine.format = options;
+    }
+
+    /// Forget the variables and functions lines have defined, `ans` and the
+    /// history. Those the engine's context started with are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::Session;
+    ///
+    /// let mut session = Session::new();
+    /// session.eval("x = 2").unwrap();
+    /// session.clear();
+    /// assert!(session.variable("x").is_none());
+    /// assert!(session.eval("x + 1").is_err());
+    /// ```
+    pub fn clear(&mut self) {
+        let context = self.engine.context_mut();
+        context.variables = self.initial_variables.clone();
+        context.functions = self.initial_functions.clone();
+        self.history.clear();
+    }
+}
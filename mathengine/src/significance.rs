@@ -0,0 +1,255 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use mathengine_evaluator::EvalObserver;
+use mathengine_lexer::{Lexer, Operation, Token};
+use mathengine_parser::types::{FormatOptions, Value};
+use mathengine_parser::{Expression, Statement};
+
+use crate::{MathEngine, MathEngineError};
+
+/// A result with as many significant figures as the numbers it was worked
+/// out from justify, from [`MathEngine::evaluate_significant`] and
+/// [`Session::eval_significant`](crate::Session::eval_significant).
+///
+/// Decimals and quantities count the figures they're written with, so `2.50`
+/// has three and `1200m` two. Products, quotients and functions keep the
+/// fewest figures of their operands; sums and differences keep the last
+/// decimal place all their operands have. Integers like the `3` in
+/// `1.20m * 3` are exact counts, as are variables, radix literals and
+/// conversion factors.
+#[derive(Debug, Clone)]
+pub struct Significant {
+    pub value: Value,
+    /// `None` when every number the value was worked out from is exact
+    pub figures: Option<usize>,
+}
+
+impl Significant {
+    /// The value formatted with `options`, rounded to its significant
+    /// figures, trailing zeros included, when it has a count of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{FormatOptions, MathEngine};
+    ///
+    /// let engine = MathEngine::new();
+    /// let area = engine.evaluate_significant("2.50m * 4.0").unwrap();
+    /// assert_eq!(area.figures, Some(2));
+    /// assert_eq!(area.format(&FormatOptions::default()), "10m");
+    ///
+    /// let length = engine.evaluate_significant("12.0m + 1.25m").unwrap();
+    /// assert_eq!(length.format(&FormatOptions::default()), "13.3m");
+    ///
+    /// let sum = engine.evaluate_significant("1.20 + 2.30").unwrap();
+    /// assert_eq!(sum.format(&FormatOptions::default()), "3.50");
+    /// ```
+    pub fn format(&self, options: &FormatOptions) -> String {
+        match self.figures {
+            Some(figures) => self.value.format(&FormatOptions {
+                significant_figures: Some(figures),
+                decimal_places: None,
+                trailing_zeros: true,
+                ..options.clone()
+            }),
+            None => self.value.format(options),
+        }
+    }
+}
+
+impl MathEngine {
+    /// Evaluate an expression like [`evaluate`](Self::evaluate), tracking the
+    /// significant figures of its numbers through each operation so the
+    /// result can be shown with no more precision than they have.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::MathEngine;
+    ///
+    /// let engine = MathEngine::new();
+    /// let result = engine.evaluate_significant("3.0 / 7.00").unwrap();
+    /// assert_eq!(result.figures, Some(2));
+    /// assert_eq!(engine.evaluate_significant("0x10 * 2.0").unwrap().figures, Some(2));
+    /// assert_eq!(engine.evaluate_significant("1.20m * 3").unwrap().figures, Some(3));
+    /// assert_eq!(engine.evaluate_significant("0x10 * 2").unwrap().figures, None);
+    /// ```
+    pub fn evaluate_significant<S: AsRef<str>>(&self, expression: S) -> Result<Significant, MathEngineError> {
+        let expression = expression.as_ref();
+        match self.parse(expression)? {
+            Statement::Expression(expr) => {
+                let mut figures = Figures::new(self, expression, &expr);
+                let value = self.evaluate_parsed_with_observer(&expr, &mut figures)?;
+                Ok(Significant { figures: figures.of(&expr), value })
+            }
+            statement => Ok(Significant { value: self.evaluate_statement(statement)?, figures: None }),
+        }
+    }
+}
+
+/// Works out the significant figures of each node as it's evaluated.
+///
+/// Nodes are told apart by address, so the tree has to stay where it is
+/// between [`new`](Self::new) and evaluating it.
+#[derive(Default)]
+pub(crate) struct Figures {
+    // `None` for nodes known to be exact
+    figures: HashMap<*const Expression, Option<usize>>,
+}
+
+impl Figures {
+    /// Start with the figures of the literals of `expr`, which was parsed
+    /// from `source`.
+    pub(crate) fn new(engine: &MathEngine, source: &str, expr: &Expression) -> Self {
+        let mut nodes = Vec::new();
+        literals(expr, &mut nodes);
+        // Spans count characters of the source as the lexer read it
+        let source = engine.input_locale.delocalize(source).unwrap_or(Cow::Borrowed(source));
+        let source: Vec<char> = source.chars().collect();
+        let counted: Vec<Option<usize>> = Lexer::new(source.iter().collect::<String>())
+            .tokenize_with_spans()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(token, span)| match token {
+                // Integers count things, exactly
+                Token::Integer(_) => Some(None),
+                Token::Number { .. } | Token::UnitValue { .. } => {
+                    let text: String = source[span.start..span.end.min(source.len())].iter().collect();
+                    Some(literal_figures(&text))
+                }
+                _ => None,
+            })
+            .collect();
+        // Each literal node comes from one number token, in the same order.
+        // Should they somehow not line up, every literal is taken as exact
+        let mut figures = HashMap::new();
+        if counted.len() == nodes.len() {
+            for (node, literal) in nodes.into_iter().zip(counted) {
+                figures.insert(node as *const Expression, literal);
+            }
+        }
+        Self { figures }
+    }
+
+    /// The figures `node` was found to have, `None` if it's exact or hasn't
+    /// been evaluated.
+    pub(crate) fn of(&self, node: &Expression) -> Option<usize> {
+        self.figures.get(&(node as *const Expression)).copied().flatten()
+    }
+
+    // The decimal place sums are good to is the last one all their inexact
+    // operands have, in the units of the result
+    fn sum(&self, operands: [&Expression; 2], inputs: &[Value], output: &Value) -> Option<usize> {
+        let place = operands
+            .iter()
+            .zip(inputs)
+            .filter_map(|(node, value)| last_place(magnitude(value, output)?, self.of(node)?))
+            .max()?;
+        let total = magnitude(output, output)?;
+        if total == 0.0 || !total.is_finite() {
+            return Some(1);
+        }
+        Some((total.abs().log10().floor() as i32 - place + 1).max(1) as usize)
+    }
+}
+
+impl EvalObserver for Figures {
+    fn on_node(&mut self, node: &Expression, inputs: &[Value], output: &Value) {
+        let figures = match node {
+            // Literals were counted up front, and variables are exact
+            Expression::Number(_)
+            | Expression::Decimal { .. }
+            | Expression::Integer(_)
+            | Expression::UnitValue { .. }
+            | Expression::Bool(_)
+            | Expression::Unit(_) => return,
+            Expression::Percent(operand)
+            | Expression::Factorial { operand, .. }
+            | Expression::Unary { operand, .. } => self.of(operand),
+            Expression::Binary { op, left, right, .. } => match op {
+                Operation::Add | Operation::Subtract
+                    if !matches!(**left, Expression::Percent(_)) && !matches!(**right, Expression::Percent(_)) =>
+                {
+                    self.sum([left, right], inputs, output)
+                }
+                Operation::Add | Operation::Subtract | Operation::Multiply | Operation::Divide | Operation::Of => {
+                    fewest([self.of(left), self.of(right)])
+                }
+                Operation::Power | Operation::Convert => self.of(left),
+                _ => None,
+            },
+            // Only the branch the condition chose counts
+            Expression::FunctionCall { name, args, .. } if name == "if" && args.len() == 3 => {
+                match inputs.first().is_some_and(Value::is_truthy) {
+                    true => self.of(&args[1]),
+                    false => self.of(&args[2]),
+                }
+            }
+            Expression::FunctionCall { args: items, .. } | Expression::List(items) => {
+                fewest(items.iter().map(|item| self.of(item)))
+            }
+        };
+        self.figures.insert(node as *const Expression, figures);
+    }
+}
+
+// The literal nodes of `expr`, in the order they're written
+fn literals<'a>(expr: &'a Expression, found: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::Number(_) | Expression::Decimal { .. } | Expression::Integer(_) | Expression::UnitValue { .. } => found.push(expr),
+        Expression::Bool(_) | Expression::Unit(_) => {}
+        Expression::Percent(operand) | Expression::Factorial { operand, .. } | Expression::Unary { operand, .. } => {
+            literals(operand, found)
+        }
+        Expression::Binary { left, right, .. } => {
+            literals(left, found);
+            literals(right, found);
+        }
+        Expression::FunctionCall { args: items, .. } | Expression::List(items) => {
+            for item in items {
+                literals(item, found);
+            }
+        }
+    }
+}
+
+// The significant figures a number is written with: every digit from the
+// first nonzero one, except trailing zeros of a number without a point.
+// Radix literals are exact
+fn literal_figures(text: &str) -> Option<usize> {
+    if text.len() > 1 && text.starts_with('0') && text[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mantissa: String = text.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let digits = digits.trim_start_matches('0');
+    let digits = match mantissa.contains('.') {
+        true => digits,
+        false => digits.trim_end_matches('0'),
+    };
+    Some(digits.len().max(1))
+}
+
+// The fewest figures of those that aren't exact
+fn fewest(figures: impl IntoIterator<Item = Option<usize>>) -> Option<usize> {
+    figures.into_iter().flatten().min()
+}
+
+// The power of ten of the last significant digit of `magnitude`, which zero
+// hasn't got
+fn last_place(magnitude: f64, figures: usize) -> Option<i32> {
+    (magnitude != 0.0 && magnitude.is_finite()).then(|| magnitude.abs().log10().floor() as i32 - figures as i32 + 1)
+}
+
+// The size of `value` in the unit of `result`, for lining up decimal places
+fn magnitude(value: &Value, result: &Value) -> Option<f64> {
+    match (value, result) {
+        (Value::UnitValue(value), Value::UnitValue(result)) if value.unit() != result.unit() => {
+            Some(value.convert_to(result.unit()).ok()?.value())
+        }
+        (Value::UnitValue(value), _) => Some(value.value()),
+        (Value::Number(number), _) => Some(number.to_f64()),
+        _ => None,
+    }
+}
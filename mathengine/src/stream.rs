@@ -0,0 +1,106 @@
+use std::io::{self, BufRead};
+
+use mathengine_parser::types::Value;
+
+use crate::{MathEngine, MathEngineError, Timings};
+
+/// Evaluate each line read from `reader` with the default configuration.
+///
+/// Lines are read, evaluated and handed back one at a time, so a file of any
+/// size is never held in memory at once. Blank lines are skipped. See
+/// [`MathEngine::evaluate_stream`] for other configurations.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use mathengine::evaluate_stream;
+///
+/// let input = Cursor::new("2 + 3\n\n10m to cm\n1 / 0\n");
+/// let results: Vec<String> = evaluate_stream(input)
+///     .map(|line| {
+///         let line = line.unwrap();
+///         match line.result {
+///             Ok(value) => format!("{}: {}", line.number, value),
+///             Err(error) => format!("{}: {}", line.number, error),
+///         }
+///     })
+///     .collect();
+/// assert_eq!(
+///     results,
+///     ["1: 5", "3: 1000cm", "4: Evaluation error: Division by zero at position 0..5"]
+/// );
+/// ```
+pub fn evaluate_stream<R: BufRead>(reader: R) -> EvalStream<R> {
+    MathEngine::new().evaluate_stream(reader)
+}
+
+impl MathEngine {
+    /// Evaluate each line read from `reader` with this engine's
+    /// configuration, like [`evaluate_stream`].
+    pub fn evaluate_stream<R: BufRead>(&self, reader: R) -> EvalStream<R> {
+        EvalStream {
+            engine: self.clone(),
+            reader,
+            buffer: String::new(),
+            number: 0,
+            done: false,
+        }
+    }
+}
+
+/// An iterator over the results of evaluating a reader line by line, from
+/// [`evaluate_stream`].
+///
+/// A line that fails to evaluate doesn't stop the stream; reading does, after
+/// yielding the I/O error.
+#[derive(Debug)]
+pub struct EvalStream<R> {
+    engine: MathEngine,
+    reader: R,
+    buffer: String,
+    number: usize,
+    done: bool,
+}
+
+/// One evaluated line from an [`EvalStream`].
+#[derive(Debug)]
+pub struct StreamLine {
+    /// The line's number in the input, counting from 1
+    pub number: usize,
+    /// The line as read, without its line ending
+    pub source: String,
+    pub result: Result<Value, MathEngineError>,
+    /// How long each stage of evaluating the line took
+    pub timings: Timings,
+}
+
+impl<R: BufRead> Iterator for EvalStream<R> {
+    type Item = io::Result<StreamLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => self.number += 1,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+            let source = self.buffer.trim_end_matches(['\n', '\r']);
+            if source.trim().is_empty() {
+                continue;
+            }
+            let (result, timings) = self.engine.evaluate_timed(source);
+            return Some(Ok(StreamLine {
+                number: self.number,
+                source: source.to_string(),
+                result,
+                timings,
+            }));
+        }
+        None
+    }
+}
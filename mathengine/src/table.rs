@@ -0,0 +1,110 @@
+use std::fmt;
+
+use mathengine_parser::types::{ConversionError, DimensionType, FormatOptions, Unit, UnitValue};
+
+use crate::UnitRegistry;
+
+/// Values converted to every unit of a dimension, from
+/// [`UnitRegistry::conversion_table`], for showing tables like
+/// `1mi = 1.609km = 5280ft`.
+///
+/// Displaying a table writes a line per value, starting with the value as it
+/// was given. A precision, like `{:.3}`, rounds to that many decimal places.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionTable {
+    pub dimension: DimensionType,
+    /// The canonical names of the dimension's units, one per column
+    pub units: Vec<String>,
+    /// The values the table was made from, one per row, with aliases
+    /// resolved
+    pub values: Vec<UnitValue>,
+    /// Each value in every one of `units`, in the same order
+    pub rows: Vec<Vec<UnitValue>>,
+}
+
+impl ConversionTable {
+    /// Each row as a line like `1mi = 1.609344km = ...`, formatted with
+    /// `options`.
+    pub fn format(&self, options: &FormatOptions) -> String {
+        let lines: Vec<String> = self
+            .values
+            .iter()
+            .zip(&self.rows)
+            .map(|(value, row)| {
+                let mut cells = vec![value.format(options)];
+                cells.extend(
+                    self.units
+                        .iter()
+                        .zip(row)
+                        .filter(|(unit, _)| unit.as_str() != value.canonical_unit_name())
+                        .map(|(_, converted)| converted.format(options)),
+                );
+                cells.join(" = ")
+            })
+            .collect();
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for ConversionTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = FormatOptions {
+            decimal_places: f.precision(),
+            ..FormatOptions::default()
+        };
+        f.write_str(&self.format(&options))
+    }
+}
+
+impl UnitRegistry {
+    /// Convert each of `values` to every built-in unit of `dimension`, a row
+    /// per value.
+    ///
+    /// Values have to be in units of `dimension`, or their row couldn't be
+    /// filled in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{DimensionType, UnitRegistry, UnitValue};
+    ///
+    /// let values = [UnitValue::new(1.0, "mi").unwrap(), UnitValue::new(2.0, "m").unwrap()];
+    /// let table = UnitRegistry::new()
+    ///     .conversion_table(DimensionType::Length, &values)
+    ///     .unwrap();
+    ///
+    /// let feet = table.units.iter().position(|unit| unit == "ft").unwrap();
+    /// assert_eq!(table.rows[0][feet].value().round(), 5280.0);
+    /// assert_eq!(
+    ///     format!("{:.2}", table),
+    ///     "1mi = 1609.34m = 160934.4cm = 1609344mm = 1.61km = 5280ft = 63360in = 1760yd\n\
+    ///      2m = 200cm = 2000mm = 0km = 6.56ft = 78.74in = 2.19yd = 0mi"
+    /// );
+    ///
+    /// let hour = [UnitValue::new(1.0, "h").unwrap()];
+    /// assert!(UnitRegistry::new().conversion_table(DimensionType::Length, &hour).is_err());
+    /// ```
+    pub fn conversion_table(&self, dimension: DimensionType, values: &[UnitValue]) -> Result<ConversionTable, ConversionError> {
+        let units: Vec<String> = Unit::all()
+            .into_iter()
+            .filter(|unit| unit.dimension_type() == dimension)
+            .map(|unit| unit.canonical_string().to_string())
+            .collect();
+        // An alias, like `metre`, stands for the unit it names
+        let values: Vec<UnitValue> = values
+            .iter()
+            .map(|value| match self.resolve(value.unit()) {
+                Some(unit) => UnitValue::new(value.value(), unit),
+                None => Ok(value.clone()),
+            })
+            .collect::<Result<_, _>>()?;
+        let mut rows = Vec::with_capacity(values.len());
+        for value in &values {
+            if value.dimension() != dimension {
+                return Err(ConversionError::CrossDimension);
+            }
+            rows.push(units.iter().map(|unit| value.convert_to(unit)).collect::<Result<Vec<_>, _>>()?);
+        }
+        Ok(ConversionTable { dimension, units, values, rows })
+    }
+}
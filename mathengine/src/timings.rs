@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use mathengine_parser::types::Value;
+use mathengine_parser::Statement;
+
+use crate::{MathEngine, MathEngineError};
+
+/// How long each stage of evaluating an expression took, from
+/// [`MathEngine::evaluate_timed`] and [`Session::eval_timed`](crate::Session::eval_timed).
+///
+/// Stages after one that fails didn't run, and are zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Splitting the input into tokens, and resolving unit aliases
+    pub lex: Duration,
+    /// Building the syntax tree
+    pub parse: Duration,
+    /// Evaluating the tree, including dimension checks in strict mode
+    pub eval: Duration,
+}
+
+impl Timings {
+    /// The time taken by every stage together.
+    pub fn total(&self) -> Duration {
+        self.lex + self.parse + self.eval
+    }
+
+    pub(crate) fn time_eval<T>(&mut self, eval: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = eval();
+        self.eval = start.elapsed();
+        result
+    }
+}
+
+impl MathEngine {
+    /// Evaluate an expression like [`evaluate`](Self::evaluate), timing each
+    /// stage, for finding out where a slow expression spends its time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::MathEngine;
+    ///
+    /// let (result, timings) = MathEngine::new().evaluate_timed("2 + 3 * 4");
+    /// assert_eq!(result.unwrap().to_string(), "14");
+    /// assert_eq!(timings.total(), timings.lex + timings.parse + timings.eval);
+    ///
+    /// // Nothing is evaluated when parsing fails
+    /// let (result, timings) = MathEngine::new().evaluate_timed("2 + * 3");
+    /// assert!(result.is_err());
+    /// assert!(timings.eval.is_zero());
+    /// ```
+    pub fn evaluate_timed<S: AsRef<str>>(&self, expression: S) -> (Result<Value, MathEngineError>, Timings) {
+        let mut timings = Timings::default();
+        let result = self
+            .parse_timed(expression.as_ref(), &mut timings)
+            .and_then(|statement| timings.time_eval(|| self.evaluate_statement(statement)));
+        (result, timings)
+    }
+
+    pub(crate) fn parse_timed(&self, expression: &str, timings: &mut Timings) -> Result<Statement, MathEngineError> {
+        let start = Instant::now();
+        let tokens = self.lex(expression);
+        timings.lex = start.elapsed();
+
+        let start = Instant::now();
+        let statement = self.parser(tokens?).parse_statement();
+        timings.parse = start.elapsed();
+        Ok(statement?)
+    }
+}
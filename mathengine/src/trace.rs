@@ -0,0 +1,107 @@
+#[cfg(feature = "tracing")]
+use std::fmt;
+#[cfg(feature = "tracing")]
+use std::sync::Arc;
+#[cfg(feature = "tracing")]
+use std::time::{Duration, Instant};
+
+use crate::MathEngine;
+
+/// A stage of evaluating an expression, as reported to a `Tracer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Splitting the input into tokens, and resolving unit aliases
+    Lex,
+    /// Building the syntax tree
+    Parse,
+    /// Evaluating the tree, including dimension checks in strict mode
+    Eval,
+}
+
+/// One finished stage of evaluating an expression, from a [`Tracer`].
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEvent<'a> {
+    pub phase: Phase,
+    /// The expression as it was given to the engine
+    pub expression: &'a str,
+    /// How long the stage took
+    pub elapsed: Duration,
+    /// Whether the stage succeeded. A stage that fails is the last one
+    /// reported for its expression
+    pub succeeded: bool,
+}
+
+/// Told about each stage of every expression an engine evaluates, for
+/// profiling slow inputs in production, from
+/// [`MathEngineBuilder::tracer`](crate::MathEngineBuilder::tracer).
+///
+/// Stages are reported as they finish, from the thread evaluating them. A
+/// tracer can forward them to whatever a service logs or traces with.
+/// Results taken from the engine's [cache](crate::MathEngineBuilder::cache)
+/// run no stages, so aren't reported.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use mathengine::{MathEngine, Phase, TraceEvent, Tracer};
+///
+/// #[derive(Debug, Default)]
+/// struct Log(Mutex<Vec<(Phase, String, bool)>>);
+///
+/// impl Tracer for Log {
+///     fn phase(&self, event: &TraceEvent<'_>) {
+///         self.0.lock().unwrap().push((event.phase, event.expression.to_string(), event.succeeded));
+///     }
+/// }
+///
+/// let log = Arc::new(Log::default());
+/// let engine = MathEngine::builder().tracer(log.clone()).build();
+/// engine.evaluate("2 + 3").unwrap();
+/// assert!(engine.evaluate("2 + * 3").is_err());
+///
+/// let phases: Vec<(Phase, bool)> = log.0.lock().unwrap().iter().map(|(phase, _, ok)| (*phase, *ok)).collect();
+/// assert_eq!(phases, [
+///     (Phase::Lex, true), (Phase::Parse, true), (Phase::Eval, true),
+///     (Phase::Lex, true), (Phase::Parse, false),
+/// ]);
+/// assert_eq!(log.0.lock().unwrap()[4].1, "2 + * 3");
+/// ```
+#[cfg(feature = "tracing")]
+pub trait Tracer: fmt::Debug + Send + Sync {
+    /// Called once a stage of evaluating an expression has finished.
+    fn phase(&self, event: &TraceEvent<'_>);
+}
+
+/// A stage being timed for the engine's tracer, from [`MathEngine::stage`].
+/// Without the `tracing` feature it's empty, and finishing it does nothing.
+pub(crate) struct Stage {
+    #[cfg(feature = "tracing")]
+    started: Option<(Arc<dyn Tracer>, Phase, Instant)>,
+}
+
+impl Stage {
+    /// Report the stage to the tracer, with how it went.
+    #[inline]
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish<T, E>(self, expression: &str, result: &Result<T, E>) {
+        #[cfg(feature = "tracing")]
+        if let Some((tracer, phase, start)) = self.started {
+            tracer.phase(&TraceEvent { phase, expression, elapsed: start.elapsed(), succeeded: result.is_ok() });
+        }
+    }
+}
+
+impl MathEngine {
+    // Start timing `phase`, if there's a tracer to tell. The stage holds its
+    // own handle on the tracer, so whatever runs in it can borrow the engine
+    #[inline]
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn stage(&self, phase: Phase) -> Stage {
+        Stage {
+            #[cfg(feature = "tracing")]
+            started: self.tracer.clone().map(|tracer| (tracer, phase, Instant::now())),
+        }
+    }
+}
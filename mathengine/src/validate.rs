@@ -0,0 +1,95 @@
+use mathengine_evaluator::{check, DimensionSignature};
+use mathengine_lexer::Operation;
+use mathengine_parser::{Expression, Statement};
+
+use crate::{definition_error, MathEngine, MathEngineError};
+
+/// What validating an expression found out about it, from
+/// [`MathEngine::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    /// The dimension the expression will evaluate to, as far as it can be
+    /// told without evaluating it
+    pub signature: DimensionSignature,
+    /// Identifiers the expression reads that the engine's context doesn't
+    /// bind, sorted. Evaluating asks the resolver for these, and fails on
+    /// any it can't supply
+    pub unbound: Vec<String>,
+}
+
+impl MathEngine {
+    /// Lex, parse and dimension check an expression without evaluating it,
+    /// for validating input as it's typed into an editor or form.
+    ///
+    /// Dimensions are checked whether or not the engine is strict, so a
+    /// mismatch that evaluating would let through is still reported.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the lexer and parser errors evaluating would, and with
+    /// [`MathEngineError::Type`] for dimensions that don't check.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathengine::{DimensionSignature, DimensionType, MathEngine};
+    ///
+    /// let engine = MathEngine::new();
+    /// let report = engine.validate("(width + 50cm) to ft").unwrap();
+    /// assert_eq!(
+    ///     report.signature,
+    ///     DimensionSignature::Quantity { dimension: DimensionType::Length, exponent: 1 }
+    /// );
+    /// assert_eq!(report.unbound, ["width"]);
+    ///
+    /// let report = engine.validate("2m * height").unwrap();
+    /// assert_eq!(report.signature, DimensionSignature::Unresolved);
+    ///
+    /// assert!(engine.validate("5m + 3kg").is_err());
+    /// assert!(engine.validate("2 + * 3").is_err());
+    /// ```
+    pub fn validate<S: AsRef<str>>(&self, expression: S) -> Result<ValidationReport, MathEngineError> {
+        let (signature, mut unbound) = match self.parse(expression.as_ref())? {
+            Statement::Expression(expr) => (check(&expr)?, self.unbound(&[&expr])),
+            Statement::Solve { equation, variable } => {
+                check(&equation.left)?;
+                check(&equation.right)?;
+                let mut unbound = self.unbound(&[&equation.left, &equation.right]);
+                unbound.retain(|name| *name != variable);
+                (DimensionSignature::Unresolved, unbound)
+            }
+            Statement::Assign { .. } | Statement::Define { .. } => return Err(definition_error()),
+        };
+        unbound.sort();
+        unbound.dedup();
+        Ok(ValidationReport { signature, unbound })
+    }
+
+    // Names read as variables in `exprs` that the context has no value for
+    fn unbound(&self, exprs: &[&Expression]) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut pending = exprs.to_vec();
+        while let Some(expr) = pending.pop() {
+            match expr {
+                Expression::Unit(name) => {
+                    if !self.context.variables.contains_key(name) {
+                        names.push(name.clone());
+                    }
+                }
+                Expression::Binary { op, left, right, .. } => {
+                    pending.push(left);
+                    // The target of a conversion is a unit, not a variable
+                    if !matches!(op, Operation::Convert) {
+                        pending.push(right);
+                    }
+                }
+                Expression::Unary { operand, .. }
+                | Expression::Percent(operand)
+                | Expression::Factorial { operand, .. } => pending.push(operand),
+                Expression::FunctionCall { args, .. } | Expression::List(args) => pending.extend(args),
+                Expression::Number(_) | Expression::Decimal { .. } | Expression::Integer(_) | Expression::Bool(_) | Expression::UnitValue { .. } => {}
+            }
+        }
+        names
+    }
+}